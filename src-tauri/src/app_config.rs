@@ -156,6 +156,15 @@ pub struct InstalledSkill {
     pub apps: SkillApps,
     /// 安装时间（Unix 时间戳）
     pub installed_at: i64,
+    /// 安装内容对应的上游提交
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_ref: Option<String>,
+    /// 安装内容哈希（SHA-256）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// 固定的 tag / commit（为空时跟随分支更新）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pinned_ref: Option<String>,
 }
 
 /// 未管理的 Skill（在应用目录中发现但未被 CC Switch 管理）
@@ -673,6 +682,7 @@ impl MultiAppConfig {
             enabled: true, // 自动启用
             created_at: Some(timestamp),
             updated_at: Some(timestamp),
+            project_id: None,
         };
 
         // 插入到对应的应用配置中
@@ -1,12 +1,13 @@
 #![allow(non_snake_case)]
 
-use tauri::AppHandle;
+use tauri::{AppHandle, Emitter};
 use tauri_plugin_dialog::DialogExt;
 use tauri_plugin_opener::OpenerExt;
 
 use crate::app_config::AppType;
 use crate::codex_config;
 use crate::config::{self, get_claude_settings_path, ConfigStatus};
+use crate::services::config_drift::{ConfigDrift, ConfigDriftService, DriftResolution};
 use crate::settings;
 
 /// 获取 Claude Code 配置状态
@@ -278,3 +279,34 @@ pub async fn extract_common_config_snippet(
     crate::services::provider::ProviderService::extract_common_config_snippet(&state, app)
         .map_err(|e| e.to_string())
 }
+
+/// 检测应用 Live 配置与当前供应商存储配置之间的漂移
+#[tauri::command]
+pub async fn get_config_drift(
+    app: String,
+    state: tauri::State<'_, crate::store::AppState>,
+) -> Result<Option<ConfigDrift>, String> {
+    let app_type = AppType::from_str(&app).map_err(|e| e.to_string())?;
+    ConfigDriftService::detect(&state, &app_type)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 处理 Live 配置漂移：采纳到供应商、还原为存储配置或忽略
+#[tauri::command]
+pub async fn resolve_config_drift(
+    handle: AppHandle,
+    app: String,
+    resolution: DriftResolution,
+    state: tauri::State<'_, crate::store::AppState>,
+) -> Result<(), String> {
+    let app_type = AppType::from_str(&app).map_err(|e| e.to_string())?;
+    ConfigDriftService::resolve(&state, &app_type, resolution)
+        .await
+        .map_err(|e| e.to_string())?;
+    let _ = handle.emit(
+        "config-drift-cleared",
+        serde_json::json!({ "appType": app }),
+    );
+    Ok(())
+}
@@ -1,9 +1,12 @@
 //! 故障转移队列命令
 //!
-//! 管理代理模式下的故障转移队列（基于 providers 表的 in_failover_queue 字段）
+//! 管理代理模式下的故障转移队列（基于 providers 表的 in_failover_queue 字段），
+//! 以及自动切回配置、请求对冲配置、路由规则和切换历史
 
 use crate::database::FailoverQueueItem;
 use crate::provider::Provider;
+use crate::proxy::routing_rules::RoutingRule;
+use crate::proxy::types::{FailbackConfig, FailoverTransition, HedgingConfig, StreamResumeConfig};
 use crate::store::AppState;
 use std::str::FromStr;
 use tauri::Emitter;
@@ -189,3 +192,150 @@ pub async fn set_auto_failover_enabled(
 
     Ok(())
 }
+
+/// 获取指定应用的自动切回配置
+#[tauri::command]
+pub async fn get_failback_config(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+) -> Result<FailbackConfig, String> {
+    state
+        .db
+        .get_failback_config(&app_type)
+        .map_err(|e| e.to_string())
+}
+
+/// 设置指定应用的自动切回配置
+///
+/// 关闭后正在进行的切回监控会在下一次探测时自行停止
+#[tauri::command]
+pub async fn set_failback_config(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+    config: FailbackConfig,
+) -> Result<(), String> {
+    config.validate()?;
+    state
+        .db
+        .set_failback_config(&app_type, &config)
+        .map_err(|e| e.to_string())
+}
+
+/// 获取指定应用的请求对冲配置
+#[tauri::command]
+pub async fn get_hedging_config(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+) -> Result<HedgingConfig, String> {
+    state
+        .db
+        .get_hedging_config(&app_type)
+        .map_err(|e| e.to_string())
+}
+
+/// 设置指定应用的请求对冲配置（对之后的请求生效）
+#[tauri::command]
+pub async fn set_hedging_config(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+    config: HedgingConfig,
+) -> Result<(), String> {
+    if !(100..=60_000).contains(&config.delay_ms) {
+        return Err("对冲等待时间必须在 100-60000 毫秒之间".to_string());
+    }
+    state
+        .db
+        .set_hedging_config(&app_type, &config)
+        .map_err(|e| e.to_string())
+}
+
+/// 获取指定应用的断流续传配置
+#[tauri::command]
+pub async fn get_stream_resume_config(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+) -> Result<StreamResumeConfig, String> {
+    state
+        .db
+        .get_stream_resume_config(&app_type)
+        .map_err(|e| e.to_string())
+}
+
+/// 设置指定应用的断流续传配置（对之后的请求生效）
+#[tauri::command]
+pub async fn set_stream_resume_config(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+    config: StreamResumeConfig,
+) -> Result<(), String> {
+    if !(1..=5).contains(&config.max_resumes) {
+        return Err("最大续传次数必须在 1-5 之间".to_string());
+    }
+    state
+        .db
+        .set_stream_resume_config(&app_type, &config)
+        .map_err(|e| e.to_string())
+}
+
+/// 获取指定应用的路由规则
+#[tauri::command]
+pub async fn get_routing_rules(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+) -> Result<Vec<RoutingRule>, String> {
+    state
+        .db
+        .get_routing_rules(&app_type)
+        .map_err(|e| e.to_string())
+}
+
+/// 整体替换指定应用的路由规则（数组顺序即评估顺序）
+#[tauri::command]
+pub async fn set_routing_rules(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+    rules: Vec<RoutingRule>,
+) -> Result<(), String> {
+    let providers = state
+        .db
+        .get_all_providers(&app_type)
+        .map_err(|e| e.to_string())?;
+    for rule in &rules {
+        rule.validate()?;
+        if let Some(missing) = rule
+            .provider_ids
+            .iter()
+            .find(|id| !providers.contains_key(id.as_str()))
+        {
+            return Err(format!(
+                "路由规则 {} 引用了不存在的供应商: {missing}",
+                rule.name
+            ));
+        }
+    }
+
+    state
+        .db
+        .set_routing_rules(&app_type, &rules)
+        .map_err(|e| e.to_string())?;
+
+    state
+        .proxy_service
+        .invalidate_provider_cache(&app_type)
+        .await;
+
+    Ok(())
+}
+
+/// 获取故障转移 / 自动切回历史（最新在前）
+#[tauri::command]
+pub async fn get_failover_history(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+    limit: Option<u32>,
+) -> Result<Vec<FailoverTransition>, String> {
+    state
+        .db
+        .get_failover_history(&app_type, limit.unwrap_or(100))
+        .map_err(|e| e.to_string())
+}
@@ -9,6 +9,8 @@ mod import_export;
 mod mcp;
 mod misc;
 mod plugin;
+mod profile;
+mod project;
 mod prompt;
 mod provider;
 mod proxy;
@@ -16,6 +18,7 @@ mod settings;
 mod session_manager;
 pub mod skill;
 mod stream_check;
+mod switch_rule;
 mod usage;
 
 pub use config::*;
@@ -27,6 +30,8 @@ pub use import_export::*;
 pub use mcp::*;
 pub use misc::*;
 pub use plugin::*;
+pub use profile::*;
+pub use project::*;
 pub use prompt::*;
 pub use provider::*;
 pub use proxy::*;
@@ -34,4 +39,5 @@ pub use settings::*;
 pub use session_manager::*;
 pub use skill::*;
 pub use stream_check::*;
+pub use switch_rule::*;
 pub use usage::*;
@@ -0,0 +1,70 @@
+use tauri::{AppHandle, State};
+
+use crate::profile::{Profile, ProfileApps};
+use crate::services::ProfileService;
+use crate::store::AppState;
+
+#[tauri::command]
+pub async fn get_profiles(state: State<'_, AppState>) -> Result<Vec<Profile>, String> {
+    ProfileService::list(&state).map_err(|e| e.to_string())
+}
+
+/// 获取最近一次成功应用的档案 ID
+#[tauri::command]
+pub async fn get_active_profile_id(state: State<'_, AppState>) -> Result<Option<String>, String> {
+    ProfileService::active_id(&state).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn save_profile(
+    app: AppHandle,
+    profile: Profile,
+    state: State<'_, AppState>,
+) -> Result<Profile, String> {
+    let saved = ProfileService::upsert(&state, profile).map_err(|e| e.to_string())?;
+    crate::tray::refresh_tray_menu(&app, &state);
+    Ok(saved)
+}
+
+#[tauri::command]
+pub async fn delete_profile(
+    app: AppHandle,
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let deleted = ProfileService::delete(&state, &id).map_err(|e| e.to_string())?;
+    crate::tray::refresh_tray_menu(&app, &state);
+    Ok(deleted)
+}
+
+/// 读取当前各应用的配置状态（用于编辑档案时预填）
+#[tauri::command]
+pub async fn get_current_profile_snapshot(
+    state: State<'_, AppState>,
+) -> Result<ProfileApps, String> {
+    ProfileService::snapshot(&state).map_err(|e| e.to_string())
+}
+
+/// 将当前配置保存为新档案
+#[tauri::command]
+pub async fn capture_current_profile(
+    app: AppHandle,
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<Profile, String> {
+    let profile = ProfileService::capture_current(&state, &name).map_err(|e| e.to_string())?;
+    crate::tray::refresh_tray_menu(&app, &state);
+    Ok(profile)
+}
+
+/// 应用配置档案（失败时自动回滚）
+#[tauri::command]
+pub async fn apply_profile(
+    app: AppHandle,
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<Profile, String> {
+    let profile = ProfileService::apply(&state, &id).map_err(|e| e.to_string())?;
+    crate::tray::notify_profile_applied(&app, &state, &profile);
+    Ok(profile)
+}
@@ -0,0 +1,50 @@
+use std::str::FromStr;
+
+use tauri::State;
+
+use crate::app_config::AppType;
+use crate::project::{Project, ProjectProviderOverride};
+use crate::services::ProjectService;
+use crate::store::AppState;
+
+#[tauri::command]
+pub async fn get_projects(state: State<'_, AppState>) -> Result<Vec<Project>, String> {
+    ProjectService::list(&state).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn add_project(
+    path: String,
+    name: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Project, String> {
+    ProjectService::add(&state, &path, name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn remove_project(id: String, state: State<'_, AppState>) -> Result<(), String> {
+    ProjectService::remove(&state, &id).map_err(|e| e.to_string())
+}
+
+/// 列出项目覆盖的供应商（`project_id` 为空时列出所有项目）
+#[tauri::command]
+pub async fn get_project_providers(
+    project_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ProjectProviderOverride>, String> {
+    ProjectService::list_provider_overrides(&state, project_id.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+/// 为项目指定某个应用的供应商（`provider_id` 为空时取消覆盖）
+#[tauri::command]
+pub async fn set_project_provider(
+    project_id: String,
+    app: String,
+    provider_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<ProjectProviderOverride>, String> {
+    let app_type = AppType::from_str(&app).map_err(|e| e.to_string())?;
+    ProjectService::set_provider(&state, &project_id, app_type, provider_id.as_deref())
+        .map_err(|e| e.to_string())
+}
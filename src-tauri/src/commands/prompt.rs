@@ -4,17 +4,19 @@ use std::str::FromStr;
 use tauri::State;
 
 use crate::app_config::AppType;
-use crate::prompt::Prompt;
+use crate::prompt::{Prompt, PromptRevision, PromptRevisionDiff};
+use crate::prompt_template::RenderedPrompt;
 use crate::services::PromptService;
 use crate::store::AppState;
 
 #[tauri::command]
 pub async fn get_prompts(
     app: String,
+    project_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<IndexMap<String, Prompt>, String> {
     let app_type = AppType::from_str(&app).map_err(|e| e.to_string())?;
-    PromptService::get_prompts(&state, app_type).map_err(|e| e.to_string())
+    PromptService::get_prompts(&state, app_type, project_id.as_deref()).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -51,14 +53,66 @@ pub async fn enable_prompt(
 #[tauri::command]
 pub async fn import_prompt_from_file(
     app: String,
+    project_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<String, String> {
     let app_type = AppType::from_str(&app).map_err(|e| e.to_string())?;
-    PromptService::import_from_file(&state, app_type).map_err(|e| e.to_string())
+    PromptService::import_from_file(&state, app_type, project_id.as_deref())
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn get_current_prompt_file_content(app: String) -> Result<Option<String>, String> {
+pub async fn get_current_prompt_file_content(
+    app: String,
+    project_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, String> {
+    let app_type = AppType::from_str(&app).map_err(|e| e.to_string())?;
+    PromptService::get_current_file_content(&state, app_type, project_id.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_prompt_revisions(
+    app: String,
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<PromptRevision>, String> {
+    let app_type = AppType::from_str(&app).map_err(|e| e.to_string())?;
+    PromptService::get_revisions(&state, app_type, &id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn diff_prompt_revisions(
+    app: String,
+    id: String,
+    from_revision: i64,
+    to_revision: i64,
+    state: State<'_, AppState>,
+) -> Result<PromptRevisionDiff, String> {
+    let app_type = AppType::from_str(&app).map_err(|e| e.to_string())?;
+    PromptService::diff_revisions(&state, app_type, &id, from_revision, to_revision)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn restore_prompt_revision(
+    app: String,
+    id: String,
+    revision_id: i64,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let app_type = AppType::from_str(&app).map_err(|e| e.to_string())?;
+    PromptService::restore_revision(&state, app_type, &id, revision_id).map_err(|e| e.to_string())
+}
+
+/// 预览提示词模板渲染结果（变量替换与引入），不写入文件
+#[tauri::command]
+pub async fn preview_prompt(
+    app: String,
+    prompt: Prompt,
+    state: State<'_, AppState>,
+) -> Result<RenderedPrompt, String> {
     let app_type = AppType::from_str(&app).map_err(|e| e.to_string())?;
-    PromptService::get_current_file_content(app_type).map_err(|e| e.to_string())
+    PromptService::preview_prompt(&state, app_type, &prompt).map_err(|e| e.to_string())
 }
@@ -137,6 +137,24 @@ pub async fn queryProviderUsage(
         .map_err(|e| e.to_string())
 }
 
+/// 获取用量脚本历史快照与耗尽预测
+#[tauri::command]
+pub fn get_usage_script_trend(
+    state: State<'_, AppState>,
+    provider_id: String,
+    app: String,
+    days: Option<u32>,
+) -> Result<crate::services::usage_history::UsageTrend, String> {
+    let app_type = AppType::from_str(&app).map_err(|e| e.to_string())?;
+    crate::services::usage_history::UsageHistoryService::trend(
+        state.inner(),
+        &app_type,
+        &provider_id,
+        days.unwrap_or(30),
+    )
+    .map_err(|e| e.to_string())
+}
+
 /// 测试用量脚本（使用当前编辑器中的脚本，不保存）
 #[allow(non_snake_case)]
 #[allow(clippy::too_many_arguments)]
@@ -175,10 +193,13 @@ pub struct RequestHookScriptTestResult {
     pub url: String,
     pub headers: HashMap<String, String>,
     pub body: Value,
+    /// 脚本 console 输出
+    pub logs: Vec<String>,
 }
 
 /// 测试请求重写脚本（onRequest）（使用当前编辑器中的脚本，不保存）
 #[allow(non_snake_case)]
+#[allow(clippy::too_many_arguments)]
 #[tauri::command]
 pub fn testRequestHookScript(
     state: State<'_, AppState>,
@@ -188,10 +209,11 @@ pub fn testRequestHookScript(
     headers: HashMap<String, String>,
     body: Value,
     endpoint: Option<String>,
+    #[allow(non_snake_case)] timeoutMs: Option<u64>,
 ) -> Result<RequestHookScriptTestResult, String> {
     let app_type = AppType::from_str(&app).map_err(|e| e.to_string())?;
-    if app_type != AppType::Codex {
-        return Err("testRequestHookScript 目前仅支持 Codex".to_string());
+    if app_type == AppType::OpenCode {
+        return Err("testRequestHookScript 不支持 OpenCode".to_string());
     }
 
     let provider = state
@@ -205,7 +227,14 @@ pub fn testRequestHookScript(
     let base_url = adapter
         .extract_base_url(&provider)
         .map_err(|e| e.to_string())?;
-    let endpoint = endpoint.unwrap_or_else(|| "/v1/responses".to_string());
+    let endpoint = endpoint.unwrap_or_else(|| {
+        match app_type {
+            AppType::Codex => "/v1/responses",
+            AppType::Gemini => "/v1beta/models/gemini-2.5-pro:generateContent",
+            _ => "/v1/messages",
+        }
+        .to_string()
+    });
     let url = adapter.build_url(&base_url, &endpoint);
 
     let incoming_headers: HashMap<String, String> = headers
@@ -243,9 +272,13 @@ pub fn testRequestHookScript(
         body,
     };
 
-    let output_request = execute_on_request_script(&scriptCode, &context, &original_request)
-        .map_err(|e| e.to_string())?
-        .unwrap_or(original_request);
+    let run = execute_on_request_script(&scriptCode, timeoutMs, &context, &original_request);
+    let logs = run.logs;
+    let output_request = match run.result {
+        Ok(output) => output.unwrap_or(original_request),
+        Err(e) if logs.is_empty() => return Err(e),
+        Err(e) => return Err(format!("{e}\n\nconsole:\n{}", logs.join("\n"))),
+    };
 
     let filtered_headers: HashMap<String, String> = output_request
         .headers
@@ -266,6 +299,7 @@ pub fn testRequestHookScript(
         url: output_url,
         headers: filtered_headers,
         body: output_request.body,
+        logs,
     })
 }
 
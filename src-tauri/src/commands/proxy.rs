@@ -2,10 +2,13 @@
 //!
 //! 提供前端调用的 API 接口
 
+use crate::app_config::AppType;
 use crate::error::AppError;
+use crate::proxy::client_auth::{self, ClientToken, CreatedClientToken};
 use crate::proxy::types::*;
 use crate::proxy::{CircuitBreakerConfig, CircuitBreakerStats};
 use crate::store::AppState;
+use std::str::FromStr;
 
 /// 启动代理服务器（仅启动服务，不接管 Live 配置）
 #[tauri::command]
@@ -358,7 +361,14 @@ pub async fn reset_circuit_breaker(
                     let switch_manager =
                         crate::proxy::failover_switch::FailoverSwitchManager::new(db.clone());
                     if let Err(e) = switch_manager
-                        .try_switch(Some(&app_handle), &app_type, &provider_id, &provider_name)
+                        .try_switch_with_kind(
+                            Some(&app_handle),
+                            &app_type,
+                            &provider_id,
+                            &provider_name,
+                            crate::proxy::types::FailoverTransitionKind::Recovery,
+                            Some("熔断器已手动重置"),
+                        )
                         .await
                     {
                         log::error!("[Recovery] 自动切换失败: {e}");
@@ -416,3 +426,104 @@ pub async fn get_circuit_breaker_stats(
     let _ = (state, provider_id, app_type);
     Ok(None)
 }
+
+/// 获取 Key 池中指定 Key 的熔断器状态（仅当代理服务器运行时）
+#[tauri::command]
+pub async fn get_key_circuit_breaker_stats(
+    state: tauri::State<'_, AppState>,
+    provider_id: String,
+    app_type: String,
+    key_id: String,
+) -> Result<Option<CircuitBreakerStats>, String> {
+    Ok(state
+        .proxy_service
+        .get_key_circuit_breaker_stats(&provider_id, &app_type, &key_id)
+        .await)
+}
+
+// ==================== Client Access Tokens ====================
+
+/// 校验令牌的名称与访问限制
+fn validate_client_token(
+    name: &str,
+    allowed_apps: &Option<Vec<String>>,
+    allowed_providers: &Option<Vec<String>>,
+) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("令牌名称不能为空".to_string());
+    }
+    if let Some(apps) = allowed_apps {
+        if apps.is_empty() {
+            return Err("至少需要允许一个应用".to_string());
+        }
+        for app in apps {
+            AppType::from_str(app).map_err(|e| e.to_string())?;
+        }
+    }
+    if allowed_providers.as_ref().is_some_and(|p| p.is_empty()) {
+        return Err("至少需要允许一个供应商".to_string());
+    }
+    Ok(())
+}
+
+/// 获取代理客户端访问令牌列表（不含明文）
+#[tauri::command]
+pub async fn get_proxy_client_tokens(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<ClientToken>, String> {
+    state.db.get_client_tokens().map_err(|e| e.to_string())
+}
+
+/// 创建代理客户端访问令牌（明文令牌只在此返回一次）
+#[tauri::command]
+pub async fn create_proxy_client_token(
+    state: tauri::State<'_, AppState>,
+    name: String,
+    allowed_apps: Option<Vec<String>>,
+    allowed_providers: Option<Vec<String>>,
+) -> Result<CreatedClientToken, String> {
+    validate_client_token(&name, &allowed_apps, &allowed_providers)?;
+
+    let (token, token_prefix) = client_auth::generate_token();
+    let info = ClientToken {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name.trim().to_string(),
+        token_prefix,
+        allowed_apps,
+        allowed_providers,
+        enabled: true,
+        created_at: chrono::Utc::now().timestamp(),
+        last_used_at: None,
+    };
+    state
+        .db
+        .insert_client_token(&info, &client_auth::hash_token(&token))
+        .map_err(|e| e.to_string())?;
+
+    Ok(CreatedClientToken { token, info })
+}
+
+/// 更新代理客户端访问令牌的名称、访问限制与启用状态
+#[tauri::command]
+pub async fn update_proxy_client_token(
+    state: tauri::State<'_, AppState>,
+    token: ClientToken,
+) -> Result<bool, String> {
+    validate_client_token(&token.name, &token.allowed_apps, &token.allowed_providers)?;
+    state
+        .db
+        .update_client_token(&ClientToken {
+            name: token.name.trim().to_string(),
+            ..token
+        })
+        .map_err(|e| e.to_string())
+}
+
+/// 删除代理客户端访问令牌
+#[tauri::command]
+pub async fn delete_proxy_client_token(
+    state: tauri::State<'_, AppState>,
+    id: String,
+) -> Result<bool, String> {
+    state.db.delete_client_token(&id).map_err(|e| e.to_string())
+}
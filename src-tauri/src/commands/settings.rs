@@ -1,5 +1,6 @@
 #![allow(non_snake_case)]
 
+use std::str::FromStr;
 use tauri::AppHandle;
 
 /// 获取设置
@@ -81,6 +82,63 @@ pub async fn set_rectifier_config(
     Ok(true)
 }
 
+/// 获取链路追踪配置
+#[tauri::command]
+pub async fn get_tracing_config(
+    state: tauri::State<'_, crate::AppState>,
+) -> Result<crate::proxy::types::TracingConfig, String> {
+    state.db.get_tracing_config().map_err(|e| e.to_string())
+}
+
+/// 设置链路追踪配置
+#[tauri::command]
+pub async fn set_tracing_config(
+    state: tauri::State<'_, crate::AppState>,
+    config: crate::proxy::types::TracingConfig,
+) -> Result<bool, String> {
+    let endpoint = config.endpoint.trim();
+    if config.enabled && !(endpoint.starts_with("http://") || endpoint.starts_with("https://")) {
+        return Err("OTLP 端点必须以 http:// 或 https:// 开头".to_string());
+    }
+    state
+        .db
+        .set_tracing_config(&config)
+        .map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+/// 获取全局（`app` 为空）或应用级请求重写脚本
+#[tauri::command]
+pub async fn get_request_hook_script(
+    state: tauri::State<'_, crate::AppState>,
+    app: Option<String>,
+) -> Result<Option<crate::provider::RequestHookScript>, String> {
+    let app_type = app
+        .map(|app| crate::app_config::AppType::from_str(&app).map_err(|e| e.to_string()))
+        .transpose()?;
+    state
+        .db
+        .get_request_hook_script(app_type.as_ref().map(|a| a.as_str()))
+        .map_err(|e| e.to_string())
+}
+
+/// 设置全局（`app` 为空）或应用级请求重写脚本，`script` 为空时删除
+#[tauri::command]
+pub async fn set_request_hook_script(
+    state: tauri::State<'_, crate::AppState>,
+    app: Option<String>,
+    script: Option<crate::provider::RequestHookScript>,
+) -> Result<bool, String> {
+    let app_type = app
+        .map(|app| crate::app_config::AppType::from_str(&app).map_err(|e| e.to_string()))
+        .transpose()?;
+    state
+        .db
+        .set_request_hook_script(app_type.as_ref().map(|a| a.as_str()), script.as_ref())
+        .map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
 /// 获取日志配置
 #[tauri::command]
 pub async fn get_log_config(
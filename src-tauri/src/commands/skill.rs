@@ -7,6 +7,8 @@
 use crate::app_config::{AppType, InstalledSkill, UnmanagedSkill};
 use crate::error::format_skill_error;
 use crate::services::skill::{DiscoverableSkill, Skill, SkillRepo, SkillService};
+use crate::services::skill_source;
+use crate::services::skill_update::SkillUpdateInfo;
 use crate::store::AppState;
 use std::sync::Arc;
 use tauri::State;
@@ -91,6 +93,50 @@ pub fn import_skills_from_apps(
     SkillService::import_from_apps(&app_state.db, directories).map_err(|e| e.to_string())
 }
 
+// ========== 更新命令 ==========
+
+/// 检查已安装 Skills 的可用更新
+#[tauri::command]
+pub async fn check_skill_updates(
+    service: State<'_, SkillServiceState>,
+    app_state: State<'_, AppState>,
+) -> Result<Vec<SkillUpdateInfo>, String> {
+    service
+        .0
+        .check_updates(&app_state.db)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 更新 Skill 到上游最新内容（或固定的版本）
+#[tauri::command]
+pub async fn update_skill(
+    id: String,
+    service: State<'_, SkillServiceState>,
+    app_state: State<'_, AppState>,
+) -> Result<InstalledSkill, String> {
+    service
+        .0
+        .update_skill(&app_state.db, &id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 固定 Skill 到指定 tag / commit（git_ref 为空时取消固定）
+#[tauri::command]
+pub async fn pin_skill(
+    id: String,
+    git_ref: Option<String>,
+    service: State<'_, SkillServiceState>,
+    app_state: State<'_, AppState>,
+) -> Result<InstalledSkill, String> {
+    service
+        .0
+        .pin_skill(&app_state.db, &id, git_ref)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 // ========== 发现功能命令 ==========
 
 /// 发现可安装的 Skills（从仓库获取）
@@ -228,7 +274,18 @@ pub fn get_skill_repos(app_state: State<'_, AppState>) -> Result<Vec<SkillRepo>,
 
 /// 添加技能仓库
 #[tauri::command]
-pub fn add_skill_repo(repo: SkillRepo, app_state: State<'_, AppState>) -> Result<bool, String> {
+pub fn add_skill_repo(mut repo: SkillRepo, app_state: State<'_, AppState>) -> Result<bool, String> {
+    // 前端拿不到已保存的令牌，更新同一仓库且未提供新令牌时沿用原令牌
+    if repo.token.is_none() {
+        repo.token = app_state
+            .db
+            .get_skill_repos()
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .find(|r| r.owner == repo.owner && r.name == repo.name)
+            .and_then(|r| r.token);
+    }
+    skill_source::validate_repo(&repo).map_err(|e| e.to_string())?;
     app_state
         .db
         .save_skill_repo(&repo)
@@ -0,0 +1,38 @@
+use tauri::State;
+
+use crate::services::switch_rule::SwitchRuleService;
+use crate::store::AppState;
+use crate::switch_rule::{SwitchRule, SwitchRuleFiring};
+
+/// 获取自动切换规则（`app` 为空时返回所有应用）
+#[tauri::command]
+pub async fn get_switch_rules(
+    app: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<SwitchRule>, String> {
+    SwitchRuleService::list(&state, app.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn save_switch_rule(
+    rule: SwitchRule,
+    state: State<'_, AppState>,
+) -> Result<SwitchRule, String> {
+    SwitchRuleService::upsert(&state, rule).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_switch_rule(id: String, state: State<'_, AppState>) -> Result<bool, String> {
+    SwitchRuleService::delete(&state, &id).map_err(|e| e.to_string())
+}
+
+/// 获取规则触发记录（最新在前）
+#[tauri::command]
+pub async fn get_switch_rule_log(
+    app: Option<String>,
+    limit: Option<u32>,
+    state: State<'_, AppState>,
+) -> Result<Vec<SwitchRuleFiring>, String> {
+    SwitchRuleService::get_log(&state, app.as_deref(), limit.unwrap_or(100))
+        .map_err(|e| e.to_string())
+}
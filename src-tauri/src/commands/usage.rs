@@ -31,6 +31,16 @@ pub fn get_provider_stats(state: State<'_, AppState>) -> Result<Vec<ProviderStat
     state.db.get_provider_stats()
 }
 
+/// 获取供应商 Key 池中各 Key 的用量统计
+#[tauri::command]
+pub fn get_api_key_stats(
+    state: State<'_, AppState>,
+    provider_id: String,
+    app_type: String,
+) -> Result<Vec<ApiKeyStats>, AppError> {
+    state.db.get_api_key_stats(&provider_id, &app_type)
+}
+
 /// 获取模型统计
 #[tauri::command]
 pub fn get_model_stats(state: State<'_, AppState>) -> Result<Vec<ModelStats>, AppError> {
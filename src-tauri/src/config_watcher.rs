@@ -0,0 +1,148 @@
+//! Live 配置文件监听
+//!
+//! 监听各应用 Live 配置所在目录，文件变化（防抖）后检测配置漂移，
+//! 通过 `config-drift-detected` / `config-drift-cleared` 事件通知前端。
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+use notify::{EventKind, RecursiveMode, Watcher};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::app_config::AppType;
+use crate::services::config_drift::ConfigDriftService;
+use crate::store::AppState;
+
+/// 防抖间隔：CLI 与编辑器保存文件时通常会触发多次事件
+const DEBOUNCE: Duration = Duration::from_millis(800);
+
+/// 需要监听的 Live 配置文件
+fn watch_targets() -> Vec<(AppType, PathBuf)> {
+    vec![
+        (AppType::Claude, crate::config::get_claude_settings_path()),
+        (AppType::Codex, crate::codex_config::get_codex_auth_path()),
+        (AppType::Codex, crate::codex_config::get_codex_config_path()),
+        (AppType::Gemini, crate::gemini_config::get_gemini_env_path()),
+        (
+            AppType::Gemini,
+            crate::gemini_config::get_gemini_settings_path(),
+        ),
+        (
+            AppType::OpenCode,
+            crate::opencode_config::get_opencode_config_path(),
+        ),
+    ]
+}
+
+/// 启动 Live 配置监听线程
+///
+/// 监听文件所在目录而非文件本身，以兼容原子写入（先写临时文件再重命名）。
+/// 启动时不存在的目录不会被监听。
+pub fn start(app: AppHandle) {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = match notify::recommended_watcher(tx) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            log::warn!("[ConfigWatcher] 创建文件监听失败: {e}");
+            return;
+        }
+    };
+
+    // 统一使用规范化后的目录，避免 macOS 上 /var 与 /private/var 等符号链接导致匹配失败
+    let mut targets: Vec<(AppType, PathBuf)> = Vec::new();
+    let mut watched_dirs: Vec<PathBuf> = Vec::new();
+    for (app_type, path) in watch_targets() {
+        let (Some(dir), Some(file_name)) = (path.parent(), path.file_name()) else {
+            continue;
+        };
+        let Ok(dir) = dir.canonicalize() else {
+            log::debug!("[ConfigWatcher] 目录不存在，跳过监听: {}", dir.display());
+            continue;
+        };
+        if !watched_dirs.contains(&dir) {
+            if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+                log::warn!("[ConfigWatcher] 监听目录失败 {}: {e}", dir.display());
+                continue;
+            }
+            watched_dirs.push(dir.clone());
+        }
+        targets.push((app_type, dir.join(file_name)));
+    }
+
+    if targets.is_empty() {
+        return;
+    }
+    log::info!("[ConfigWatcher] 已监听 {} 个 Live 配置文件", targets.len());
+
+    let spawned = std::thread::Builder::new()
+        .name("config-watcher".to_string())
+        .spawn(move || {
+            // 线程持有 watcher，保证监听在应用生命周期内持续有效
+            let _watcher = watcher;
+            let mut pending: Vec<AppType> = Vec::new();
+            // 已通知前端的漂移指纹，避免重复发送相同事件
+            let mut notified: HashMap<String, String> = HashMap::new();
+
+            loop {
+                match rx.recv_timeout(DEBOUNCE) {
+                    Ok(Ok(event)) => {
+                        if matches!(event.kind, EventKind::Access(_)) {
+                            continue;
+                        }
+                        for path in &event.paths {
+                            for (app_type, target) in &targets {
+                                if path == target && !pending.contains(app_type) {
+                                    pending.push(app_type.clone());
+                                }
+                            }
+                        }
+                    }
+                    Ok(Err(e)) => log::warn!("[ConfigWatcher] 文件监听错误: {e}"),
+                    Err(RecvTimeoutError::Timeout) => {
+                        for app_type in pending.drain(..) {
+                            check_drift(&app, &app_type, &mut notified);
+                        }
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        });
+
+    if let Err(e) = spawned {
+        log::warn!("[ConfigWatcher] 启动监听线程失败: {e}");
+    }
+}
+
+fn check_drift(app: &AppHandle, app_type: &AppType, notified: &mut HashMap<String, String>) {
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    let key = app_type.as_str().to_string();
+
+    match tauri::async_runtime::block_on(ConfigDriftService::detect(state.inner(), app_type)) {
+        Ok(Some(drift)) => {
+            if notified.get(&key) == Some(&drift.fingerprint) {
+                return;
+            }
+            log::info!(
+                "[ConfigWatcher] 检测到 {key} Live 配置漂移（{} 处差异）",
+                drift.changes.len()
+            );
+            notified.insert(key, drift.fingerprint.clone());
+            if let Err(e) = app.emit("config-drift-detected", &drift) {
+                log::error!("发射 config-drift-detected 事件失败: {e}");
+            }
+        }
+        Ok(None) => {
+            if notified.remove(&key).is_some() {
+                let payload = serde_json::json!({ "appType": key });
+                if let Err(e) = app.emit("config-drift-cleared", payload) {
+                    log::error!("发射 config-drift-cleared 事件失败: {e}");
+                }
+            }
+        }
+        Err(e) => log::warn!("[ConfigWatcher] 检测 {key} 配置漂移失败: {e}"),
+    }
+}
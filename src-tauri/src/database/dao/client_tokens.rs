@@ -0,0 +1,152 @@
+//! 代理客户端访问令牌数据访问对象
+//!
+//! 令牌明文不落库，只保存哈希与展示前缀。
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::proxy::client_auth::ClientToken;
+use rusqlite::{params, OptionalExtension, Row};
+
+/// `last_used_at` 的最小更新间隔（秒），避免每个请求都写库
+const TOUCH_INTERVAL_SECS: i64 = 60;
+
+const TOKEN_COLUMNS: &str =
+    "id, name, token_prefix, allowed_apps, allowed_providers, enabled, created_at, last_used_at";
+
+fn parse_list(value: Option<String>) -> Option<Vec<String>> {
+    value.and_then(|v| serde_json::from_str(&v).ok())
+}
+
+fn to_json(list: &Option<Vec<String>>) -> Option<String> {
+    list.as_ref()
+        .and_then(|items| serde_json::to_string(items).ok())
+}
+
+fn row_to_token(row: &Row<'_>) -> rusqlite::Result<ClientToken> {
+    Ok(ClientToken {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        token_prefix: row.get(2)?,
+        allowed_apps: parse_list(row.get(3)?),
+        allowed_providers: parse_list(row.get(4)?),
+        enabled: row.get(5)?,
+        created_at: row.get(6)?,
+        last_used_at: row.get(7)?,
+    })
+}
+
+impl Database {
+    /// 获取所有客户端访问令牌
+    pub fn get_client_tokens(&self) -> Result<Vec<ClientToken>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {TOKEN_COLUMNS} FROM proxy_client_tokens ORDER BY created_at ASC, id ASC"
+            ))
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        let rows = stmt
+            .query_map([], row_to_token)
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let mut tokens = Vec::new();
+        for row in rows {
+            tokens.push(row.map_err(|e| AppError::Database(e.to_string()))?);
+        }
+        Ok(tokens)
+    }
+
+    /// 按令牌哈希查找
+    pub fn find_client_token_by_hash(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<ClientToken>, AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.query_row(
+            &format!("SELECT {TOKEN_COLUMNS} FROM proxy_client_tokens WHERE token_hash = ?1"),
+            params![token_hash],
+            row_to_token,
+        )
+        .optional()
+        .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// 已启用的令牌数量
+    pub fn count_enabled_client_tokens(&self) -> Result<u32, AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.query_row(
+            "SELECT COUNT(*) FROM proxy_client_tokens WHERE enabled = 1",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// 新增令牌（仅保存哈希）
+    pub fn insert_client_token(
+        &self,
+        token: &ClientToken,
+        token_hash: &str,
+    ) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "INSERT INTO proxy_client_tokens
+             (id, name, token_hash, token_prefix, allowed_apps, allowed_providers, enabled, created_at, last_used_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                token.id,
+                token.name,
+                token_hash,
+                token.token_prefix,
+                to_json(&token.allowed_apps),
+                to_json(&token.allowed_providers),
+                token.enabled,
+                token.created_at,
+                token.last_used_at,
+            ],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 更新令牌的名称、访问限制与启用状态，返回是否存在
+    pub fn update_client_token(&self, token: &ClientToken) -> Result<bool, AppError> {
+        let conn = lock_conn!(self.conn);
+        let affected = conn
+            .execute(
+                "UPDATE proxy_client_tokens
+                 SET name = ?2, allowed_apps = ?3, allowed_providers = ?4, enabled = ?5
+                 WHERE id = ?1",
+                params![
+                    token.id,
+                    token.name,
+                    to_json(&token.allowed_apps),
+                    to_json(&token.allowed_providers),
+                    token.enabled,
+                ],
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(affected > 0)
+    }
+
+    /// 删除令牌，返回是否存在
+    pub fn delete_client_token(&self, id: &str) -> Result<bool, AppError> {
+        let conn = lock_conn!(self.conn);
+        let affected = conn
+            .execute("DELETE FROM proxy_client_tokens WHERE id = ?1", params![id])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(affected > 0)
+    }
+
+    /// 记录令牌使用时间（间隔不足 `TOUCH_INTERVAL_SECS` 时跳过）
+    pub fn touch_client_token(&self, id: &str) -> Result<(), AppError> {
+        let now = chrono::Utc::now().timestamp();
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "UPDATE proxy_client_tokens SET last_used_at = ?2
+             WHERE id = ?1 AND (last_used_at IS NULL OR last_used_at <= ?3)",
+            params![id, now, now - TOUCH_INTERVAL_SECS],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+}
@@ -1,12 +1,21 @@
 //! 故障转移队列 DAO
 //!
-//! 管理代理模式下的故障转移队列（基于 providers 表的 in_failover_queue 字段）
+//! 管理代理模式下的故障转移队列（基于 providers 表的 in_failover_queue 字段），
+//! 以及自动切回配置、请求对冲配置、路由规则和故障转移 / 切回历史
 
 use crate::database::{lock_conn, Database};
 use crate::error::AppError;
 use crate::provider::Provider;
+use crate::proxy::routing_rules::RoutingRule;
+use crate::proxy::types::{
+    FailbackConfig, FailoverTransition, FailoverTransitionKind, HedgingConfig, StreamResumeConfig,
+};
+use rusqlite::params;
 use serde::{Deserialize, Serialize};
 
+/// 每个应用保留的切换历史数量
+const FAILOVER_HISTORY_RETAIN: i64 = 500;
+
 /// 故障转移队列条目（简化版，用于前端展示）
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -143,4 +152,170 @@ impl Database {
 
         Ok(available)
     }
+
+    // --- 自动切回配置 ---
+
+    /// 获取指定应用的自动切回配置（不存在时返回默认值：关闭）
+    pub fn get_failback_config(&self, app_type: &str) -> Result<FailbackConfig, AppError> {
+        match self.get_setting(&format!("failback_config:{app_type}"))? {
+            Some(json) => serde_json::from_str(&json)
+                .map_err(|e| AppError::Database(format!("解析自动切回配置失败: {e}"))),
+            None => Ok(FailbackConfig::default()),
+        }
+    }
+
+    /// 更新指定应用的自动切回配置
+    pub fn set_failback_config(
+        &self,
+        app_type: &str,
+        config: &FailbackConfig,
+    ) -> Result<(), AppError> {
+        let json = serde_json::to_string(config)
+            .map_err(|e| AppError::Database(format!("序列化自动切回配置失败: {e}")))?;
+        self.set_setting(&format!("failback_config:{app_type}"), &json)
+    }
+
+    // --- 请求对冲配置 ---
+
+    /// 获取指定应用的请求对冲配置（不存在时返回默认值：关闭）
+    pub fn get_hedging_config(&self, app_type: &str) -> Result<HedgingConfig, AppError> {
+        match self.get_setting(&format!("hedging_config:{app_type}"))? {
+            Some(json) => serde_json::from_str(&json)
+                .map_err(|e| AppError::Database(format!("解析请求对冲配置失败: {e}"))),
+            None => Ok(HedgingConfig::default()),
+        }
+    }
+
+    /// 更新指定应用的请求对冲配置
+    pub fn set_hedging_config(
+        &self,
+        app_type: &str,
+        config: &HedgingConfig,
+    ) -> Result<(), AppError> {
+        let json = serde_json::to_string(config)
+            .map_err(|e| AppError::Database(format!("序列化请求对冲配置失败: {e}")))?;
+        self.set_setting(&format!("hedging_config:{app_type}"), &json)
+    }
+
+    // --- 断流续传配置 ---
+
+    /// 获取指定应用的断流续传配置（不存在时返回默认值：关闭）
+    pub fn get_stream_resume_config(&self, app_type: &str) -> Result<StreamResumeConfig, AppError> {
+        match self.get_setting(&format!("stream_resume_config:{app_type}"))? {
+            Some(json) => serde_json::from_str(&json)
+                .map_err(|e| AppError::Database(format!("解析断流续传配置失败: {e}"))),
+            None => Ok(StreamResumeConfig::default()),
+        }
+    }
+
+    /// 更新指定应用的断流续传配置
+    pub fn set_stream_resume_config(
+        &self,
+        app_type: &str,
+        config: &StreamResumeConfig,
+    ) -> Result<(), AppError> {
+        let json = serde_json::to_string(config)
+            .map_err(|e| AppError::Database(format!("序列化断流续传配置失败: {e}")))?;
+        self.set_setting(&format!("stream_resume_config:{app_type}"), &json)
+    }
+
+    // --- 路由规则 ---
+
+    /// 获取指定应用的路由规则（按评估顺序，不存在时为空）
+    pub fn get_routing_rules(&self, app_type: &str) -> Result<Vec<RoutingRule>, AppError> {
+        match self.get_setting(&format!("routing_rules:{app_type}"))? {
+            Some(json) => serde_json::from_str(&json)
+                .map_err(|e| AppError::Database(format!("解析路由规则失败: {e}"))),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// 整体替换指定应用的路由规则
+    pub fn set_routing_rules(&self, app_type: &str, rules: &[RoutingRule]) -> Result<(), AppError> {
+        let json = serde_json::to_string(rules)
+            .map_err(|e| AppError::Database(format!("序列化路由规则失败: {e}")))?;
+        self.set_setting(&format!("routing_rules:{app_type}"), &json)
+    }
+
+    // --- 切换历史 ---
+
+    /// 记录一次故障转移 / 切回
+    pub fn insert_failover_transition(
+        &self,
+        app_type: &str,
+        kind: FailoverTransitionKind,
+        from_provider: Option<(&str, &str)>,
+        to_provider_id: &str,
+        to_provider_name: &str,
+        detail: Option<&str>,
+    ) -> Result<i64, AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "INSERT INTO failover_history
+             (app_type, kind, from_provider_id, from_provider_name, to_provider_id, to_provider_name, detail, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                app_type,
+                kind.as_str(),
+                from_provider.map(|(id, _)| id),
+                from_provider.map(|(_, name)| name),
+                to_provider_id,
+                to_provider_name,
+                detail,
+                chrono::Utc::now().timestamp(),
+            ],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        let id = conn.last_insert_rowid();
+
+        conn.execute(
+            "DELETE FROM failover_history
+             WHERE app_type = ?1 AND id NOT IN (
+                 SELECT id FROM failover_history WHERE app_type = ?1
+                 ORDER BY id DESC LIMIT ?2
+             )",
+            params![app_type, FAILOVER_HISTORY_RETAIN],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(id)
+    }
+
+    /// 获取切换历史（最新在前）
+    pub fn get_failover_history(
+        &self,
+        app_type: &str,
+        limit: u32,
+    ) -> Result<Vec<FailoverTransition>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, app_type, kind, from_provider_id, from_provider_name,
+                        to_provider_id, to_provider_name, detail, created_at
+                 FROM failover_history WHERE app_type = ?1
+                 ORDER BY id DESC LIMIT ?2",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let items = stmt
+            .query_map(params![app_type, limit], |row| {
+                let kind: String = row.get(2)?;
+                Ok(FailoverTransition {
+                    id: row.get(0)?,
+                    app_type: row.get(1)?,
+                    kind: FailoverTransitionKind::from_db_str(&kind),
+                    from_provider_id: row.get(3)?,
+                    from_provider_name: row.get(4)?,
+                    to_provider_id: row.get(5)?,
+                    to_provider_name: row.get(6)?,
+                    detail: row.get(7)?,
+                    created_at: row.get(8)?,
+                })
+            })
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(items)
+    }
 }
@@ -2,15 +2,20 @@
 //!
 //! Database access operations for each domain
 
+pub mod client_tokens;
 pub mod failover;
 pub mod mcp;
+pub mod profiles;
+pub mod projects;
 pub mod prompts;
 pub mod providers;
 pub mod proxy;
 pub mod settings;
 pub mod skills;
 pub mod stream_check;
+pub mod switch_rules;
 pub mod universal_providers;
+pub mod usage_snapshots;
 
 // 所有 DAO 方法都通过 Database impl 提供，无需单独导出
 // 导出 FailoverQueueItem 供外部使用
@@ -0,0 +1,101 @@
+//! 配置档案数据访问对象
+//!
+//! 提供配置档案的 CRUD 操作及当前档案记录。
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::profile::{Profile, ProfileApps};
+use rusqlite::{params, OptionalExtension};
+
+/// 最近一次成功应用的档案 ID（settings 表 key）
+const ACTIVE_PROFILE_KEY: &str = "active_profile_id";
+
+impl Database {
+    /// 获取所有配置档案
+    pub fn get_profiles(&self) -> Result<Vec<Profile>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, name, description, apps, sort_index, created_at FROM profiles
+                 ORDER BY COALESCE(sort_index, 999999), created_at ASC, id ASC",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rows = stmt
+            .query_map([], Self::row_to_profile)
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// 获取单个配置档案
+    pub fn get_profile(&self, id: &str) -> Result<Option<Profile>, AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.query_row(
+            "SELECT id, name, description, apps, sort_index, created_at FROM profiles WHERE id = ?1",
+            params![id],
+            Self::row_to_profile,
+        )
+        .optional()
+        .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    fn row_to_profile(row: &rusqlite::Row<'_>) -> rusqlite::Result<Profile> {
+        let apps_json: String = row.get(3)?;
+        Ok(Profile {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            apps: serde_json::from_str::<ProfileApps>(&apps_json).unwrap_or_default(),
+            sort_index: row.get(4)?,
+            created_at: row.get(5)?,
+        })
+    }
+
+    /// 保存配置档案（新增或更新）
+    pub fn save_profile(&self, profile: &Profile) -> Result<(), AppError> {
+        let apps_json = serde_json::to_string(&profile.apps)
+            .map_err(|e| AppError::Database(format!("序列化档案配置失败: {e}")))?;
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "INSERT OR REPLACE INTO profiles (id, name, description, apps, sort_index, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                profile.id,
+                profile.name,
+                profile.description,
+                apps_json,
+                profile.sort_index,
+                profile.created_at
+            ],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 删除配置档案
+    pub fn delete_profile(&self, id: &str) -> Result<bool, AppError> {
+        let affected = {
+            let conn = lock_conn!(self.conn);
+            conn.execute("DELETE FROM profiles WHERE id = ?1", params![id])
+                .map_err(|e| AppError::Database(e.to_string()))?
+        };
+        if self.get_active_profile_id()?.as_deref() == Some(id) {
+            self.set_active_profile_id(None)?;
+        }
+        Ok(affected > 0)
+    }
+
+    /// 获取最近一次成功应用的档案 ID
+    pub fn get_active_profile_id(&self) -> Result<Option<String>, AppError> {
+        Ok(self
+            .get_setting(ACTIVE_PROFILE_KEY)?
+            .filter(|id| !id.is_empty()))
+    }
+
+    /// 记录当前档案（None 表示清除）
+    pub fn set_active_profile_id(&self, id: Option<&str>) -> Result<(), AppError> {
+        self.set_setting(ACTIVE_PROFILE_KEY, id.unwrap_or_default())
+    }
+}
@@ -0,0 +1,183 @@
+//! 项目数据访问对象
+//!
+//! 提供项目目录登记及项目级供应商绑定的 CRUD 操作。
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::project::{Project, ProjectProvider};
+use rusqlite::{params, OptionalExtension};
+
+impl Database {
+    /// 获取所有已登记的项目
+    pub fn get_projects(&self) -> Result<Vec<Project>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, name, path, created_at FROM projects
+                 ORDER BY created_at ASC, id ASC",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(Project {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    path: row.get(2)?,
+                    created_at: row.get(3)?,
+                })
+            })
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// 获取单个项目
+    pub fn get_project(&self, id: &str) -> Result<Option<Project>, AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.query_row(
+            "SELECT id, name, path, created_at FROM projects WHERE id = ?1",
+            params![id],
+            |row| {
+                Ok(Project {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    path: row.get(2)?,
+                    created_at: row.get(3)?,
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// 保存项目（新增或更新）
+    pub fn save_project(&self, project: &Project) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "INSERT OR REPLACE INTO projects (id, name, path, created_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![project.id, project.name, project.path, project.created_at],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 删除项目，并清理该项目下的提示词及其修订、供应商绑定
+    pub fn delete_project(&self, id: &str) -> Result<(), AppError> {
+        let mut conn = lock_conn!(self.conn);
+        let tx = conn
+            .transaction()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        tx.execute(
+            "DELETE FROM prompt_revisions WHERE (prompt_id, app_type) IN (
+                SELECT id, app_type FROM prompts WHERE project_id = ?1
+            )",
+            params![id],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        tx.execute("DELETE FROM prompts WHERE project_id = ?1", params![id])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        tx.execute(
+            "DELETE FROM project_providers WHERE project_id = ?1",
+            params![id],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        tx.execute("DELETE FROM projects WHERE id = ?1", params![id])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 获取项目级供应商绑定
+    ///
+    /// `project_id` 为 `None` 时返回所有项目的绑定。
+    pub fn get_project_providers(
+        &self,
+        project_id: Option<&str>,
+    ) -> Result<Vec<ProjectProvider>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(
+                "SELECT project_id, app_type, provider_id, managed_keys, updated_at
+                 FROM project_providers
+                 WHERE ?1 IS NULL OR project_id = ?1
+                 ORDER BY project_id ASC, app_type ASC",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rows = stmt
+            .query_map(params![project_id], Self::row_to_project_provider)
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// 获取项目在指定应用下的供应商绑定
+    pub fn get_project_provider(
+        &self,
+        project_id: &str,
+        app_type: &str,
+    ) -> Result<Option<ProjectProvider>, AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.query_row(
+            "SELECT project_id, app_type, provider_id, managed_keys, updated_at
+             FROM project_providers WHERE project_id = ?1 AND app_type = ?2",
+            params![project_id, app_type],
+            Self::row_to_project_provider,
+        )
+        .optional()
+        .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    fn row_to_project_provider(row: &rusqlite::Row<'_>) -> rusqlite::Result<ProjectProvider> {
+        let managed_keys: String = row.get(3)?;
+        Ok(ProjectProvider {
+            project_id: row.get(0)?,
+            app_type: row.get(1)?,
+            provider_id: row.get(2)?,
+            managed_keys: serde_json::from_str(&managed_keys).unwrap_or_default(),
+            updated_at: row.get(4)?,
+        })
+    }
+
+    /// 保存项目级供应商绑定（新增或更新）
+    pub fn save_project_provider(&self, binding: &ProjectProvider) -> Result<(), AppError> {
+        let managed_keys = serde_json::to_string(&binding.managed_keys)
+            .map_err(|e| AppError::Database(format!("序列化管理键失败: {e}")))?;
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "INSERT OR REPLACE INTO project_providers
+             (project_id, app_type, provider_id, managed_keys, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                binding.project_id,
+                binding.app_type,
+                binding.provider_id,
+                managed_keys,
+                binding.updated_at
+            ],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 删除项目级供应商绑定
+    pub fn delete_project_provider(
+        &self,
+        project_id: &str,
+        app_type: &str,
+    ) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "DELETE FROM project_providers WHERE project_id = ?1 AND app_type = ?2",
+            params![project_id, app_type],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+}
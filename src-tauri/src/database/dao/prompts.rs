@@ -1,47 +1,46 @@
 //! 提示词数据访问对象
 //!
-//! 提供提示词（Prompt）的 CRUD 操作。
+//! 提供提示词（Prompt）的 CRUD 操作，以及内容变更时的历史修订记录。
 
 use crate::database::{lock_conn, Database};
 use crate::error::AppError;
-use crate::prompt::Prompt;
+use crate::prompt::{Prompt, PromptRevision, PromptRevisionSource};
 use indexmap::IndexMap;
-use rusqlite::params;
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// 每个提示词保留的历史修订数量
+const PROMPT_REVISION_RETAIN: i64 = 50;
+
+/// 最近一次写入 live 文件内容的 Settings Key 前缀
+const PROMPT_LIVE_SNAPSHOT_PREFIX: &str = "prompt_live_snapshot";
 
 impl Database {
-    /// 获取指定应用类型的所有提示词
+    /// 获取指定应用类型的所有全局提示词
     pub fn get_prompts(&self, app_type: &str) -> Result<IndexMap<String, Prompt>, AppError> {
+        self.get_prompts_in_scope(app_type, None)
+    }
+
+    /// 获取指定作用域下的提示词
+    ///
+    /// `project_id` 为 `None` 时返回全局提示词，否则返回该项目的提示词。
+    pub fn get_prompts_in_scope(
+        &self,
+        app_type: &str,
+        project_id: Option<&str>,
+    ) -> Result<IndexMap<String, Prompt>, AppError> {
         let conn = lock_conn!(self.conn);
         let mut stmt = conn
             .prepare(
-                "SELECT id, name, content, description, enabled, created_at, updated_at
-             FROM prompts WHERE app_type = ?1
+                "SELECT id, name, content, description, enabled, created_at, updated_at, project_id
+             FROM prompts WHERE app_type = ?1 AND project_id IS ?2
              ORDER BY created_at ASC, id ASC",
             )
             .map_err(|e| AppError::Database(e.to_string()))?;
 
         let prompt_iter = stmt
-            .query_map(params![app_type], |row| {
-                let id: String = row.get(0)?;
-                let name: String = row.get(1)?;
-                let content: String = row.get(2)?;
-                let description: Option<String> = row.get(3)?;
-                let enabled: bool = row.get(4)?;
-                let created_at: Option<i64> = row.get(5)?;
-                let updated_at: Option<i64> = row.get(6)?;
-
-                Ok((
-                    id.clone(),
-                    Prompt {
-                        id,
-                        name,
-                        content,
-                        description,
-                        enabled,
-                        created_at,
-                        updated_at,
-                    },
-                ))
+            .query_map(params![app_type, project_id], |row| {
+                let prompt = Self::row_to_prompt(row)?;
+                Ok((prompt.id.clone(), prompt))
             })
             .map_err(|e| AppError::Database(e.to_string()))?;
 
@@ -53,13 +52,94 @@ impl Database {
         Ok(prompts)
     }
 
+    /// 按 ID 获取单个提示词（不区分作用域）
+    pub fn get_prompt(&self, app_type: &str, id: &str) -> Result<Option<Prompt>, AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.query_row(
+            "SELECT id, name, content, description, enabled, created_at, updated_at, project_id
+             FROM prompts WHERE app_type = ?1 AND id = ?2",
+            params![app_type, id],
+            Self::row_to_prompt,
+        )
+        .optional()
+        .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    fn row_to_prompt(row: &rusqlite::Row<'_>) -> rusqlite::Result<Prompt> {
+        Ok(Prompt {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            content: row.get(2)?,
+            description: row.get(3)?,
+            enabled: row.get(4)?,
+            created_at: row.get(5)?,
+            updated_at: row.get(6)?,
+            project_id: row.get(7)?,
+        })
+    }
+
     /// 保存提示词
+    ///
+    /// 内容发生变化时会记录一条 `edit` 修订。
     pub fn save_prompt(&self, app_type: &str, prompt: &Prompt) -> Result<(), AppError> {
-        let conn = lock_conn!(self.conn);
-        conn.execute(
+        self.save_prompt_with_source(app_type, prompt, PromptRevisionSource::Edit)
+    }
+
+    /// 保存提示词，并以指定来源记录修订
+    ///
+    /// - 内容与最近一条修订相同时不重复记录（仅切换启用状态等不会产生修订）
+    /// - 首次记录修订时，若库中已有旧内容，先将其记为 `baseline`，保证第一次覆盖也可恢复
+    pub fn save_prompt_with_source(
+        &self,
+        app_type: &str,
+        prompt: &Prompt,
+        source: PromptRevisionSource,
+    ) -> Result<(), AppError> {
+        let mut conn = lock_conn!(self.conn);
+        let tx = conn
+            .transaction()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let latest_revision: Option<String> = tx
+            .query_row(
+                "SELECT content FROM prompt_revisions
+                 WHERE app_type = ?1 AND prompt_id = ?2
+                 ORDER BY id DESC LIMIT 1",
+                params![app_type, prompt.id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let previous_content = match latest_revision {
+            Some(content) => Some(content),
+            None => {
+                // 尚无修订：先把库中已有内容记为 baseline
+                let stored: Option<String> = tx
+                    .query_row(
+                        "SELECT content FROM prompts WHERE id = ?1 AND app_type = ?2",
+                        params![prompt.id, app_type],
+                        |row| row.get(0),
+                    )
+                    .optional()
+                    .map_err(|e| AppError::Database(e.to_string()))?;
+                if let Some(stored) = stored.as_deref().filter(|c| !c.trim().is_empty()) {
+                    Self::insert_prompt_revision(
+                        &tx,
+                        app_type,
+                        &prompt.id,
+                        stored,
+                        PromptRevisionSource::Baseline,
+                    )?;
+                }
+                stored
+            }
+        };
+
+        tx.execute(
             "INSERT OR REPLACE INTO prompts (
-                id, app_type, name, content, description, enabled, created_at, updated_at
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                id, app_type, name, content, description, enabled, created_at, updated_at, project_id
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
             params![
                 prompt.id,
                 app_type,
@@ -69,12 +149,103 @@ impl Database {
                 prompt.enabled,
                 prompt.created_at,
                 prompt.updated_at,
+                prompt.project_id,
+            ],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        if previous_content.as_deref() != Some(prompt.content.as_str()) {
+            Self::insert_prompt_revision(&tx, app_type, &prompt.id, &prompt.content, source)?;
+            tx.execute(
+                "DELETE FROM prompt_revisions
+                 WHERE app_type = ?1 AND prompt_id = ?2 AND id NOT IN (
+                     SELECT id FROM prompt_revisions
+                     WHERE app_type = ?1 AND prompt_id = ?2
+                     ORDER BY id DESC LIMIT ?3
+                 )",
+                params![app_type, prompt.id, PROMPT_REVISION_RETAIN],
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        }
+
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    fn insert_prompt_revision(
+        conn: &Connection,
+        app_type: &str,
+        prompt_id: &str,
+        content: &str,
+        source: PromptRevisionSource,
+    ) -> Result<(), AppError> {
+        conn.execute(
+            "INSERT INTO prompt_revisions (prompt_id, app_type, content, source, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                prompt_id,
+                app_type,
+                content,
+                source.as_str(),
+                chrono::Utc::now().timestamp()
             ],
         )
         .map_err(|e| AppError::Database(e.to_string()))?;
         Ok(())
     }
 
+    /// 获取提示词的历史修订（最新在前）
+    pub fn get_prompt_revisions(
+        &self,
+        app_type: &str,
+        prompt_id: &str,
+    ) -> Result<Vec<PromptRevision>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, prompt_id, content, source, created_at
+                 FROM prompt_revisions WHERE app_type = ?1 AND prompt_id = ?2
+                 ORDER BY id DESC",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rows = stmt
+            .query_map(params![app_type, prompt_id], Self::row_to_prompt_revision)
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// 获取单条修订
+    pub fn get_prompt_revision(
+        &self,
+        app_type: &str,
+        prompt_id: &str,
+        revision_id: i64,
+    ) -> Result<Option<PromptRevision>, AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.query_row(
+            "SELECT id, prompt_id, content, source, created_at
+             FROM prompt_revisions WHERE app_type = ?1 AND prompt_id = ?2 AND id = ?3",
+            params![app_type, prompt_id, revision_id],
+            Self::row_to_prompt_revision,
+        )
+        .optional()
+        .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    fn row_to_prompt_revision(row: &rusqlite::Row<'_>) -> rusqlite::Result<PromptRevision> {
+        let source: String = row.get(3)?;
+        Ok(PromptRevision {
+            id: row.get(0)?,
+            prompt_id: row.get(1)?,
+            content: row.get(2)?,
+            source: PromptRevisionSource::from_db_str(&source),
+            created_at: row.get(4)?,
+        })
+    }
+
     /// 删除提示词
     pub fn delete_prompt(&self, app_type: &str, id: &str) -> Result<(), AppError> {
         let conn = lock_conn!(self.conn);
@@ -83,6 +254,42 @@ impl Database {
             params![id, app_type],
         )
         .map_err(|e| AppError::Database(e.to_string()))?;
+        conn.execute(
+            "DELETE FROM prompt_revisions WHERE prompt_id = ?1 AND app_type = ?2",
+            params![id, app_type],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
         Ok(())
     }
+
+    /// 获取最近一次写入提示词 live 文件的内容
+    ///
+    /// 用于判断 live 文件是否在 cc-switch 之外被修改过。
+    pub fn get_prompt_live_snapshot(
+        &self,
+        app_type: &str,
+        project_id: Option<&str>,
+    ) -> Result<Option<String>, AppError> {
+        self.get_setting(&Self::prompt_live_snapshot_key(app_type, project_id))
+    }
+
+    /// 记录最近一次写入提示词 live 文件的内容
+    pub fn set_prompt_live_snapshot(
+        &self,
+        app_type: &str,
+        project_id: Option<&str>,
+        content: &str,
+    ) -> Result<(), AppError> {
+        self.set_setting(
+            &Self::prompt_live_snapshot_key(app_type, project_id),
+            content,
+        )
+    }
+
+    fn prompt_live_snapshot_key(app_type: &str, project_id: Option<&str>) -> String {
+        format!(
+            "{PROMPT_LIVE_SNAPSHOT_PREFIX}:{app_type}:{}",
+            project_id.unwrap_or("global")
+        )
+    }
 }
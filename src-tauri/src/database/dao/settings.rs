@@ -187,6 +187,71 @@ impl Database {
         self.set_setting("rectifier_config", &json)
     }
 
+    // --- 链路追踪配置 ---
+
+    /// 获取链路追踪配置（不存在时返回默认值：关闭）
+    pub fn get_tracing_config(&self) -> Result<crate::proxy::types::TracingConfig, AppError> {
+        match self.get_setting("tracing_config")? {
+            Some(json) => serde_json::from_str(&json)
+                .map_err(|e| AppError::Database(format!("解析链路追踪配置失败: {e}"))),
+            None => Ok(crate::proxy::types::TracingConfig::default()),
+        }
+    }
+
+    /// 更新链路追踪配置
+    pub fn set_tracing_config(
+        &self,
+        config: &crate::proxy::types::TracingConfig,
+    ) -> Result<(), AppError> {
+        let json = serde_json::to_string(config)
+            .map_err(|e| AppError::Database(format!("序列化链路追踪配置失败: {e}")))?;
+        self.set_setting("tracing_config", &json)
+    }
+
+    // --- 请求重写脚本（全局 / 应用级）---
+
+    fn request_hook_script_key(app_type: Option<&str>) -> String {
+        match app_type {
+            Some(app_type) => format!("request_hook_script_{app_type}"),
+            None => "request_hook_script".to_string(),
+        }
+    }
+
+    /// 获取全局（`app_type` 为 None）或应用级请求重写脚本
+    pub fn get_request_hook_script(
+        &self,
+        app_type: Option<&str>,
+    ) -> Result<Option<crate::provider::RequestHookScript>, AppError> {
+        match self.get_setting(&Self::request_hook_script_key(app_type))? {
+            Some(json) => serde_json::from_str(&json)
+                .map(Some)
+                .map_err(|e| AppError::Database(format!("解析请求重写脚本失败: {e}"))),
+            None => Ok(None),
+        }
+    }
+
+    /// 设置全局（`app_type` 为 None）或应用级请求重写脚本，传入 None 时删除
+    pub fn set_request_hook_script(
+        &self,
+        app_type: Option<&str>,
+        script: Option<&crate::provider::RequestHookScript>,
+    ) -> Result<(), AppError> {
+        let key = Self::request_hook_script_key(app_type);
+        match script {
+            Some(script) => {
+                let json = serde_json::to_string(script)
+                    .map_err(|e| AppError::Database(format!("序列化请求重写脚本失败: {e}")))?;
+                self.set_setting(&key, &json)
+            }
+            None => {
+                let conn = lock_conn!(self.conn);
+                conn.execute("DELETE FROM settings WHERE key = ?1", params![key])
+                    .map_err(|e| AppError::Database(e.to_string()))?;
+                Ok(())
+            }
+        }
+    }
+
     // --- 日志配置 ---
 
     /// 获取日志配置
@@ -0,0 +1,175 @@
+//! 自动切换规则数据访问对象
+//!
+//! 提供自动切换规则的 CRUD 操作及规则触发记录。
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::switch_rule::{SwitchRule, SwitchRuleFiring};
+use rusqlite::params;
+
+/// 每个应用保留的规则触发记录数量
+const SWITCH_RULE_LOG_RETAIN: i64 = 500;
+
+impl Database {
+    /// 获取自动切换规则（`app_type` 为 `None` 时返回所有应用）
+    pub fn get_switch_rules(&self, app_type: Option<&str>) -> Result<Vec<SwitchRule>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, name, app_type, enabled, condition, action, sort_index, created_at
+                 FROM switch_rules
+                 WHERE ?1 IS NULL OR app_type = ?1
+                 ORDER BY app_type ASC, COALESCE(sort_index, 999999), created_at ASC, id ASC",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rows = stmt
+            .query_map(params![app_type], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, bool>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, String>(5)?,
+                    row.get::<_, Option<i64>>(6)?,
+                    row.get::<_, Option<i64>>(7)?,
+                ))
+            })
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let mut rules = Vec::new();
+        for row in rows {
+            let (id, name, app_type, enabled, condition, action, sort_index, created_at) =
+                row.map_err(|e| AppError::Database(e.to_string()))?;
+            // 无法解析的规则（如旧版本未知的条件类型）跳过，不影响其他规则
+            let (Ok(condition), Ok(action)) = (
+                serde_json::from_str(&condition),
+                serde_json::from_str(&action),
+            ) else {
+                log::warn!("跳过无法解析的自动切换规则: {id}");
+                continue;
+            };
+            rules.push(SwitchRule {
+                id,
+                name,
+                app_type,
+                enabled,
+                condition,
+                action,
+                sort_index,
+                created_at,
+            });
+        }
+        Ok(rules)
+    }
+
+    /// 保存自动切换规则（新增或更新）
+    pub fn save_switch_rule(&self, rule: &SwitchRule) -> Result<(), AppError> {
+        let condition = serde_json::to_string(&rule.condition)
+            .map_err(|e| AppError::Database(format!("序列化规则条件失败: {e}")))?;
+        let action = serde_json::to_string(&rule.action)
+            .map_err(|e| AppError::Database(format!("序列化规则动作失败: {e}")))?;
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "INSERT OR REPLACE INTO switch_rules
+             (id, name, app_type, enabled, condition, action, sort_index, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                rule.id,
+                rule.name,
+                rule.app_type,
+                rule.enabled,
+                condition,
+                action,
+                rule.sort_index,
+                rule.created_at
+            ],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 删除自动切换规则
+    pub fn delete_switch_rule(&self, id: &str) -> Result<bool, AppError> {
+        let conn = lock_conn!(self.conn);
+        let affected = conn
+            .execute("DELETE FROM switch_rules WHERE id = ?1", params![id])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(affected > 0)
+    }
+
+    /// 记录一次规则触发
+    pub fn insert_switch_rule_firing(&self, firing: &SwitchRuleFiring) -> Result<i64, AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "INSERT INTO switch_rule_log
+             (rule_id, rule_name, app_type, action, from_provider_id, to_provider_id, success, message, fired_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                firing.rule_id,
+                firing.rule_name,
+                firing.app_type,
+                firing.action,
+                firing.from_provider_id,
+                firing.to_provider_id,
+                firing.success,
+                firing.message,
+                firing.fired_at,
+            ],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        let id = conn.last_insert_rowid();
+
+        conn.execute(
+            "DELETE FROM switch_rule_log
+             WHERE app_type = ?1 AND id NOT IN (
+                 SELECT id FROM switch_rule_log WHERE app_type = ?1
+                 ORDER BY id DESC LIMIT ?2
+             )",
+            params![firing.app_type, SWITCH_RULE_LOG_RETAIN],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(id)
+    }
+
+    /// 获取规则触发记录（最新在前，`app_type` 为 `None` 时返回所有应用）
+    pub fn get_switch_rule_log(
+        &self,
+        app_type: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<SwitchRuleFiring>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, rule_id, rule_name, app_type, action, from_provider_id,
+                        to_provider_id, success, message, fired_at
+                 FROM switch_rule_log
+                 WHERE ?1 IS NULL OR app_type = ?1
+                 ORDER BY id DESC LIMIT ?2",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let items = stmt
+            .query_map(params![app_type, limit], |row| {
+                Ok(SwitchRuleFiring {
+                    id: row.get(0)?,
+                    rule_id: row.get(1)?,
+                    rule_name: row.get(2)?,
+                    app_type: row.get(3)?,
+                    action: row.get(4)?,
+                    from_provider_id: row.get(5)?,
+                    to_provider_id: row.get(6)?,
+                    success: row.get(7)?,
+                    message: row.get(8)?,
+                    fired_at: row.get(9)?,
+                })
+            })
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(items)
+    }
+}
@@ -0,0 +1,154 @@
+//! 用量快照 DAO
+//!
+//! 保存用量脚本的查询结果，用于趋势展示、耗尽预测和低余额提醒。
+
+use std::collections::HashMap;
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::provider::{UsageData, UsageSnapshot};
+use rusqlite::params;
+
+/// 用量快照保留天数
+const USAGE_SNAPSHOT_RETAIN_SECS: i64 = 90 * 24 * 3600;
+
+impl Database {
+    /// 记录一次用量查询结果（每个套餐一行，同一次查询共享 `captured_at`）
+    pub fn insert_usage_snapshots(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        data: &[UsageData],
+        captured_at: i64,
+    ) -> Result<(), AppError> {
+        let mut conn = lock_conn!(self.conn);
+        let tx = conn
+            .transaction()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        for plan in data {
+            tx.execute(
+                "INSERT INTO usage_snapshots
+                 (app_type, provider_id, plan_name, total, used, remaining, unit, is_valid, captured_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    app_type,
+                    provider_id,
+                    plan.plan_name.as_deref().unwrap_or_default(),
+                    plan.total,
+                    plan.used,
+                    plan.remaining,
+                    plan.unit,
+                    plan.is_valid,
+                    captured_at,
+                ],
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        }
+
+        tx.execute(
+            "DELETE FROM usage_snapshots WHERE captured_at < ?1",
+            params![captured_at - USAGE_SNAPSHOT_RETAIN_SECS],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// 获取供应商自 `since` 起的用量快照（按时间升序）
+    pub fn get_usage_snapshots(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        since: i64,
+    ) -> Result<Vec<UsageSnapshot>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(
+                "SELECT plan_name, total, used, remaining, unit, is_valid, captured_at
+                 FROM usage_snapshots
+                 WHERE app_type = ?1 AND provider_id = ?2 AND captured_at >= ?3
+                 ORDER BY captured_at ASC, id ASC",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let snapshots = stmt
+            .query_map(params![app_type, provider_id, since], row_to_snapshot)
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(snapshots)
+    }
+
+    /// 获取供应商最近一次查询的用量快照
+    pub fn get_latest_usage_snapshots(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+    ) -> Result<Vec<UsageSnapshot>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(
+                "SELECT plan_name, total, used, remaining, unit, is_valid, captured_at
+                 FROM usage_snapshots
+                 WHERE app_type = ?1 AND provider_id = ?2
+                   AND captured_at = (
+                     SELECT MAX(captured_at) FROM usage_snapshots
+                     WHERE app_type = ?1 AND provider_id = ?2
+                   )
+                 ORDER BY id ASC",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let snapshots = stmt
+            .query_map(params![app_type, provider_id], row_to_snapshot)
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(snapshots)
+    }
+
+    /// 获取应用下各供应商最近一次查询的最小剩余额度 - key: provider_id
+    pub fn get_latest_usage_remaining(
+        &self,
+        app_type: &str,
+    ) -> Result<HashMap<String, f64>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(
+                "SELECT s.provider_id, MIN(s.remaining)
+                 FROM usage_snapshots s
+                 WHERE s.app_type = ?1 AND s.remaining IS NOT NULL
+                   AND s.captured_at = (
+                     SELECT MAX(captured_at) FROM usage_snapshots
+                     WHERE app_type = s.app_type AND provider_id = s.provider_id
+                   )
+                 GROUP BY s.provider_id",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rows = stmt
+            .query_map(params![app_type], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+            })
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<HashMap<_, _>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(rows)
+    }
+}
+
+fn row_to_snapshot(row: &rusqlite::Row<'_>) -> rusqlite::Result<UsageSnapshot> {
+    Ok(UsageSnapshot {
+        plan_name: row.get(0)?,
+        total: row.get(1)?,
+        used: row.get(2)?,
+        remaining: row.get(3)?,
+        unit: row.get(4)?,
+        is_valid: row.get(5)?,
+        captured_at: row.get(6)?,
+    })
+}
@@ -3,7 +3,7 @@
 //! 此模块提供应用的核心数据存储功能，包括：
 //! - 供应商配置管理
 //! - MCP 服务器配置
-//! - 提示词管理
+//! - 提示词管理（全局 / 项目级）
 //! - Skills 管理
 //! - 通用设置存储
 //!
@@ -19,6 +19,7 @@
 //!     ├── providers.rs
 //!     ├── mcp.rs
 //!     ├── prompts.rs
+//!     ├── projects.rs
 //!     ├── skills.rs
 //!     └── settings.rs
 //! ```
@@ -49,7 +50,7 @@ const DB_BACKUP_RETAIN: usize = 10;
 
 /// 当前 Schema 版本号
 /// 每次修改表结构时递增，并在 schema.rs 中添加相应的迁移逻辑
-pub(crate) const SCHEMA_VERSION: i32 = 5;
+pub(crate) const SCHEMA_VERSION: i32 = 11;
 
 /// 安全地序列化 JSON，避免 unwrap panic
 pub(crate) fn to_json_string<T: Serialize>(value: &T) -> Result<String, AppError> {
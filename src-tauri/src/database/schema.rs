@@ -68,6 +68,7 @@ impl Database {
         conn.execute("CREATE TABLE IF NOT EXISTS prompts (
             id TEXT NOT NULL, app_type TEXT NOT NULL, name TEXT NOT NULL, content TEXT NOT NULL,
             description TEXT, enabled BOOLEAN NOT NULL DEFAULT 1, created_at INTEGER, updated_at INTEGER,
+            project_id TEXT,
             PRIMARY KEY (id, app_type)
         )", []).map_err(|e| AppError::Database(e.to_string()))?;
 
@@ -86,7 +87,10 @@ impl Database {
             enabled_codex BOOLEAN NOT NULL DEFAULT 0,
             enabled_gemini BOOLEAN NOT NULL DEFAULT 0,
             enabled_opencode BOOLEAN NOT NULL DEFAULT 0,
-            installed_at INTEGER NOT NULL DEFAULT 0
+            installed_at INTEGER NOT NULL DEFAULT 0,
+            source_ref TEXT,
+            content_hash TEXT,
+            pinned_ref TEXT
         )",
             [],
         )
@@ -96,7 +100,8 @@ impl Database {
         conn.execute(
             "CREATE TABLE IF NOT EXISTS skill_repos (
             owner TEXT NOT NULL, name TEXT NOT NULL, branch TEXT NOT NULL DEFAULT 'main',
-            enabled BOOLEAN NOT NULL DEFAULT 1, PRIMARY KEY (owner, name)
+            enabled BOOLEAN NOT NULL DEFAULT 1, source TEXT NOT NULL DEFAULT 'github',
+            url TEXT, token TEXT, PRIMARY KEY (owner, name)
         )",
             [],
         )
@@ -180,7 +185,8 @@ impl Database {
             total_cost_usd TEXT NOT NULL DEFAULT '0', latency_ms INTEGER NOT NULL, first_token_ms INTEGER,
             duration_ms INTEGER, status_code INTEGER NOT NULL, error_message TEXT, session_id TEXT,
             provider_type TEXT, is_streaming INTEGER NOT NULL DEFAULT 0,
-            cost_multiplier TEXT NOT NULL DEFAULT '1.0', created_at INTEGER NOT NULL
+            cost_multiplier TEXT NOT NULL DEFAULT '1.0', created_at INTEGER NOT NULL,
+            api_key_id TEXT, client_token_id TEXT, routing_override TEXT
         )", []).map_err(|e| AppError::Database(e.to_string()))?;
 
         conn.execute("CREATE INDEX IF NOT EXISTS idx_request_logs_provider ON proxy_request_logs(provider_id, app_type)", [])
@@ -241,6 +247,127 @@ impl Database {
         )
         .map_err(|e| AppError::Database(e.to_string()))?;
 
+        // 17. Prompt Revisions 表（提示词历史修订）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS prompt_revisions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT, prompt_id TEXT NOT NULL, app_type TEXT NOT NULL,
+            content TEXT NOT NULL, source TEXT NOT NULL DEFAULT 'edit', created_at INTEGER NOT NULL
+        )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_prompt_revisions_prompt
+             ON prompt_revisions(app_type, prompt_id, id DESC)",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 18. Projects 表（项目目录登记）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS projects (
+            id TEXT PRIMARY KEY, name TEXT NOT NULL, path TEXT NOT NULL UNIQUE, created_at INTEGER
+        )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 19. Failover History 表（故障转移 / 自动切回历史）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS failover_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT, app_type TEXT NOT NULL, kind TEXT NOT NULL,
+            from_provider_id TEXT, from_provider_name TEXT,
+            to_provider_id TEXT NOT NULL, to_provider_name TEXT NOT NULL,
+            detail TEXT, created_at INTEGER NOT NULL
+        )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_failover_history_app
+             ON failover_history(app_type, id DESC)",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 20. Profiles 表（配置档案：按应用打包供应商 / MCP / 提示词 / Skills）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS profiles (
+            id TEXT PRIMARY KEY, name TEXT NOT NULL, description TEXT,
+            apps TEXT NOT NULL DEFAULT '{}', sort_index INTEGER, created_at INTEGER
+        )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 21. Project Providers 表（项目级供应商绑定）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS project_providers (
+            project_id TEXT NOT NULL, app_type TEXT NOT NULL, provider_id TEXT NOT NULL,
+            managed_keys TEXT NOT NULL DEFAULT '[]', updated_at INTEGER,
+            PRIMARY KEY (project_id, app_type)
+        )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 22. Switch Rules 表（基于时间 / 用量的自动切换规则）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS switch_rules (
+            id TEXT PRIMARY KEY, name TEXT NOT NULL, app_type TEXT NOT NULL,
+            enabled BOOLEAN NOT NULL DEFAULT 1, condition TEXT NOT NULL, action TEXT NOT NULL,
+            sort_index INTEGER, created_at INTEGER
+        )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 23. Switch Rule Log 表（规则触发记录）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS switch_rule_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT, rule_id TEXT NOT NULL, rule_name TEXT NOT NULL,
+            app_type TEXT NOT NULL, action TEXT NOT NULL, from_provider_id TEXT,
+            to_provider_id TEXT NOT NULL, success BOOLEAN NOT NULL, message TEXT,
+            fired_at INTEGER NOT NULL
+        )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 24. Usage Snapshots 表（用量脚本查询结果时间序列，每个套餐一行）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS usage_snapshots (
+            id INTEGER PRIMARY KEY AUTOINCREMENT, app_type TEXT NOT NULL,
+            provider_id TEXT NOT NULL, plan_name TEXT NOT NULL DEFAULT '',
+            total REAL, used REAL, remaining REAL, unit TEXT, is_valid BOOLEAN,
+            captured_at INTEGER NOT NULL
+        )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_usage_snapshots_provider
+             ON usage_snapshots(app_type, provider_id, captured_at)",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 25. Proxy Client Tokens 表（代理客户端访问令牌，仅保存哈希）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS proxy_client_tokens (
+            id TEXT PRIMARY KEY, name TEXT NOT NULL,
+            token_hash TEXT NOT NULL UNIQUE, token_prefix TEXT NOT NULL,
+            allowed_apps TEXT, allowed_providers TEXT,
+            enabled BOOLEAN NOT NULL DEFAULT 1,
+            created_at INTEGER NOT NULL, last_used_at INTEGER
+        )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
         // 尝试添加 live_takeover_active 列到 proxy_config 表
         let _ = conn.execute(
             "ALTER TABLE proxy_config ADD COLUMN live_takeover_active INTEGER NOT NULL DEFAULT 0",
@@ -360,6 +487,36 @@ impl Database {
                         Self::migrate_v4_to_v5(conn)?;
                         Self::set_user_version(conn, 5)?;
                     }
+                    5 => {
+                        log::info!("迁移数据库从 v5 到 v6（项目级提示词）");
+                        Self::migrate_v5_to_v6(conn)?;
+                        Self::set_user_version(conn, 6)?;
+                    }
+                    6 => {
+                        log::info!("迁移数据库从 v6 到 v7（Skill 仓库来源）");
+                        Self::migrate_v6_to_v7(conn)?;
+                        Self::set_user_version(conn, 7)?;
+                    }
+                    7 => {
+                        log::info!("迁移数据库从 v7 到 v8（Skill 版本记录）");
+                        Self::migrate_v7_to_v8(conn)?;
+                        Self::set_user_version(conn, 8)?;
+                    }
+                    8 => {
+                        log::info!("迁移数据库从 v8 到 v9（API Key 池用量归因）");
+                        Self::migrate_v8_to_v9(conn)?;
+                        Self::set_user_version(conn, 9)?;
+                    }
+                    9 => {
+                        log::info!("迁移数据库从 v9 到 v10（代理客户端令牌用量归因）");
+                        Self::migrate_v9_to_v10(conn)?;
+                        Self::set_user_version(conn, 10)?;
+                    }
+                    10 => {
+                        log::info!("迁移数据库从 v10 到 v11（请求头路由覆盖记录）");
+                        Self::migrate_v10_to_v11(conn)?;
+                        Self::set_user_version(conn, 11)?;
+                    }
                     _ => {
                         return Err(AppError::Database(format!(
                             "未知的数据库版本 {version}，无法迁移到 {SCHEMA_VERSION}"
@@ -914,6 +1071,77 @@ impl Database {
         Ok(())
     }
 
+    /// v5 -> v6 迁移：提示词支持项目作用域
+    ///
+    /// `project_id` 为 NULL 表示全局提示词。
+    fn migrate_v5_to_v6(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "prompts")? {
+            Self::add_column_if_missing(conn, "prompts", "project_id", "TEXT")?;
+        }
+
+        log::info!("v5 -> v6 迁移完成：已添加项目级提示词字段");
+        Ok(())
+    }
+
+    /// v6 -> v7 迁移：Skill 仓库支持 GitLab / Gitea / ZIP / 本地目录来源
+    fn migrate_v6_to_v7(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "skill_repos")? {
+            Self::add_column_if_missing(
+                conn,
+                "skill_repos",
+                "source",
+                "TEXT NOT NULL DEFAULT 'github'",
+            )?;
+            Self::add_column_if_missing(conn, "skill_repos", "url", "TEXT")?;
+            Self::add_column_if_missing(conn, "skill_repos", "token", "TEXT")?;
+        }
+
+        log::info!("v6 -> v7 迁移完成：已添加 Skill 仓库来源字段");
+        Ok(())
+    }
+
+    /// v7 -> v8 迁移：记录 Skill 安装的上游提交、内容哈希与固定版本
+    fn migrate_v7_to_v8(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "skills")? {
+            Self::add_column_if_missing(conn, "skills", "source_ref", "TEXT")?;
+            Self::add_column_if_missing(conn, "skills", "content_hash", "TEXT")?;
+            Self::add_column_if_missing(conn, "skills", "pinned_ref", "TEXT")?;
+        }
+
+        log::info!("v7 -> v8 迁移完成：已添加 Skill 版本字段");
+        Ok(())
+    }
+
+    /// v8 -> v9 迁移：请求日志记录使用的 API Key（Key 池）
+    fn migrate_v8_to_v9(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_request_logs")? {
+            Self::add_column_if_missing(conn, "proxy_request_logs", "api_key_id", "TEXT")?;
+        }
+
+        log::info!("v8 -> v9 迁移完成：已添加请求日志 Key 标识字段");
+        Ok(())
+    }
+
+    /// v9 -> v10 迁移：请求日志记录使用的客户端访问令牌
+    fn migrate_v9_to_v10(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_request_logs")? {
+            Self::add_column_if_missing(conn, "proxy_request_logs", "client_token_id", "TEXT")?;
+        }
+
+        log::info!("v9 -> v10 迁移完成：已添加请求日志客户端令牌字段");
+        Ok(())
+    }
+
+    /// v10 -> v11 迁移：请求日志记录请求头路由覆盖
+    fn migrate_v10_to_v11(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_request_logs")? {
+            Self::add_column_if_missing(conn, "proxy_request_logs", "routing_override", "TEXT")?;
+        }
+
+        log::info!("v10 -> v11 迁移完成：已添加请求日志路由覆盖字段");
+        Ok(())
+    }
+
     /// 插入默认模型定价数据
     /// 格式: (model_id, display_name, input, output, cache_read, cache_creation)
     /// 注意: model_id 使用短横线格式（如 claude-haiku-4-5），与 API 返回的模型名称标准化后一致
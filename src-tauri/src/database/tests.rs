@@ -200,7 +200,10 @@ fn schema_migration_adds_missing_columns_for_providers() {
         ("mcp_servers", "enabled_gemini"),
         ("prompts", "updated_at"),
         ("skills", "installed_at"),
+        ("skills", "pinned_ref"),
         ("skill_repos", "enabled"),
+        ("skill_repos", "source"),
+        ("skill_repos", "token"),
     ] {
         assert!(
             Database::has_column(&conn, table, column).expect("check column"),
@@ -624,3 +627,460 @@ fn schema_model_pricing_is_seeded_on_init() {
         gemini_count
     );
 }
+
+#[test]
+fn prompt_revisions_record_content_changes_only() {
+    use crate::prompt::{Prompt, PromptRevisionSource};
+
+    let db = Database::memory().expect("create memory db");
+    let mut prompt = Prompt {
+        id: "p1".to_string(),
+        name: "Rules".to_string(),
+        content: "v1".to_string(),
+        description: None,
+        enabled: false,
+        created_at: Some(1),
+        updated_at: Some(1),
+        project_id: None,
+    };
+    db.save_prompt("claude", &prompt).expect("save v1");
+
+    // 仅切换启用状态不产生新修订
+    prompt.enabled = true;
+    db.save_prompt("claude", &prompt).expect("toggle enabled");
+
+    prompt.content = "v2".to_string();
+    db.save_prompt_with_source("claude", &prompt, PromptRevisionSource::Backfill)
+        .expect("backfill v2");
+
+    let revisions = db.get_prompt_revisions("claude", "p1").expect("list");
+    let summary: Vec<_> = revisions
+        .iter()
+        .map(|r| (r.content.as_str(), r.source))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("v2", PromptRevisionSource::Backfill),
+            ("v1", PromptRevisionSource::Edit)
+        ]
+    );
+
+    db.delete_prompt("claude", "p1").expect("delete");
+    assert!(db
+        .get_prompt_revisions("claude", "p1")
+        .expect("list after delete")
+        .is_empty());
+}
+
+#[test]
+fn prompt_revisions_keep_pre_existing_content_as_baseline() {
+    use crate::prompt::{Prompt, PromptRevisionSource};
+
+    let db = Database::memory().expect("create memory db");
+    {
+        // 模拟升级前已存在、但没有任何修订的提示词
+        let conn = db.conn.lock().expect("lock conn");
+        conn.execute(
+            "INSERT INTO prompts (id, app_type, name, content, enabled) VALUES ('p1', 'codex', 'Old', 'tuned', 1)",
+            [],
+        )
+        .expect("seed prompt");
+    }
+
+    let prompt = Prompt {
+        id: "p1".to_string(),
+        name: "Old".to_string(),
+        content: "overwritten".to_string(),
+        description: None,
+        enabled: true,
+        created_at: None,
+        updated_at: None,
+        project_id: None,
+    };
+    db.save_prompt("codex", &prompt).expect("overwrite");
+
+    let revisions = db.get_prompt_revisions("codex", "p1").expect("list");
+    assert_eq!(revisions.len(), 2);
+    assert_eq!(revisions[1].content, "tuned");
+    assert_eq!(revisions[1].source, PromptRevisionSource::Baseline);
+    assert_eq!(revisions[0].content, "overwritten");
+}
+
+#[test]
+fn project_prompts_are_scoped_and_removed_with_project() {
+    use crate::project::Project;
+    use crate::prompt::Prompt;
+
+    let db = Database::memory().expect("create memory db");
+    db.save_project(&Project {
+        id: "proj-1".to_string(),
+        name: "repo".to_string(),
+        path: "/work/repo".to_string(),
+        created_at: Some(1),
+    })
+    .expect("save project");
+
+    let make_prompt = |id: &str, project_id: Option<&str>| Prompt {
+        id: id.to_string(),
+        name: id.to_string(),
+        content: format!("content of {id}"),
+        description: None,
+        enabled: true,
+        created_at: Some(1),
+        updated_at: Some(1),
+        project_id: project_id.map(str::to_string),
+    };
+    db.save_prompt("claude", &make_prompt("global", None))
+        .expect("save global");
+    db.save_prompt("claude", &make_prompt("scoped", Some("proj-1")))
+        .expect("save scoped");
+    // 其他应用中同 id 的全局提示词
+    db.save_prompt("codex", &make_prompt("scoped", None))
+        .expect("save codex prompt");
+
+    let global = db.get_prompts("claude").expect("global prompts");
+    assert_eq!(global.keys().collect::<Vec<_>>(), vec!["global"]);
+    let scoped = db
+        .get_prompts_in_scope("claude", Some("proj-1"))
+        .expect("project prompts");
+    assert_eq!(scoped.keys().collect::<Vec<_>>(), vec!["scoped"]);
+    assert_eq!(
+        db.get_prompt("claude", "scoped")
+            .expect("get prompt")
+            .and_then(|p| p.project_id)
+            .as_deref(),
+        Some("proj-1")
+    );
+
+    db.delete_project("proj-1").expect("delete project");
+    assert!(db.get_project("proj-1").expect("get project").is_none());
+    assert!(db.get_prompt("claude", "scoped").expect("get").is_none());
+    assert!(db
+        .get_prompt_revisions("claude", "scoped")
+        .expect("revisions")
+        .is_empty());
+    assert!(db.get_prompt("claude", "global").expect("get").is_some());
+    assert!(db.get_prompt("codex", "scoped").expect("get").is_some());
+    assert!(!db
+        .get_prompt_revisions("codex", "scoped")
+        .expect("revisions")
+        .is_empty());
+}
+
+#[test]
+fn failover_history_is_recorded_newest_first() {
+    use crate::proxy::types::FailoverTransitionKind;
+
+    let db = Database::memory().expect("create memory db");
+    db.insert_failover_transition(
+        "claude",
+        FailoverTransitionKind::Failover,
+        Some(("primary", "Primary")),
+        "backup",
+        "Backup",
+        None,
+    )
+    .expect("record failover");
+    db.insert_failover_transition(
+        "claude",
+        FailoverTransitionKind::Failback,
+        Some(("backup", "Backup")),
+        "primary",
+        "Primary",
+        Some("probes passed"),
+    )
+    .expect("record failback");
+
+    let history = db.get_failover_history("claude", 10).expect("history");
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].kind, FailoverTransitionKind::Failback);
+    assert_eq!(history[0].to_provider_id, "primary");
+    assert_eq!(history[0].detail.as_deref(), Some("probes passed"));
+    assert_eq!(history[1].from_provider_name.as_deref(), Some("Primary"));
+    assert!(db
+        .get_failover_history("codex", 10)
+        .expect("history")
+        .is_empty());
+
+    let config = db.get_failback_config("claude").expect("default config");
+    assert!(!config.enabled);
+    assert_eq!(config.probe_successes, 3);
+}
+
+#[test]
+fn profiles_round_trip_and_clear_active_on_delete() {
+    use crate::app_config::AppType;
+    use crate::profile::{Profile, ProfileAppConfig, ProfileApps};
+
+    let db = Database::memory().expect("create memory db");
+    let profile = Profile {
+        id: "work".to_string(),
+        name: "Work".to_string(),
+        description: None,
+        apps: ProfileApps {
+            claude: Some(ProfileAppConfig {
+                provider_id: Some("p1".to_string()),
+                mcp_servers: Some(vec!["fs".to_string()]),
+                prompt_id: Some(String::new()),
+                skills: None,
+            }),
+            ..Default::default()
+        },
+        sort_index: None,
+        created_at: Some(1),
+    };
+    db.save_profile(&profile).expect("save profile");
+
+    let loaded = db.get_profile("work").expect("get").expect("exists");
+    assert_eq!(loaded.apps, profile.apps);
+    assert_eq!(loaded.apps.configured_apps(), vec![AppType::Claude]);
+
+    db.set_active_profile_id(Some("work")).expect("set active");
+    assert_eq!(
+        db.get_active_profile_id().expect("active").as_deref(),
+        Some("work")
+    );
+
+    assert!(db.delete_profile("work").expect("delete"));
+    assert!(db.get_profiles().expect("list").is_empty());
+    assert!(db.get_active_profile_id().expect("active").is_none());
+}
+
+#[test]
+fn project_providers_are_removed_with_project() {
+    use crate::project::{Project, ProjectProvider};
+
+    let db = Database::memory().expect("create memory db");
+    db.save_project(&Project {
+        id: "proj".to_string(),
+        name: "Proj".to_string(),
+        path: "/tmp/proj".to_string(),
+        created_at: Some(1),
+    })
+    .expect("save project");
+    db.save_project_provider(&ProjectProvider {
+        project_id: "proj".to_string(),
+        app_type: "claude".to_string(),
+        provider_id: "p1".to_string(),
+        managed_keys: vec!["ANTHROPIC_BASE_URL".to_string()],
+        updated_at: Some(2),
+    })
+    .expect("save binding");
+
+    let binding = db
+        .get_project_provider("proj", "claude")
+        .expect("get")
+        .expect("exists");
+    assert_eq!(binding.managed_keys, vec!["ANTHROPIC_BASE_URL"]);
+    assert_eq!(db.get_project_providers(None).expect("list").len(), 1);
+    assert!(db
+        .get_project_providers(Some("other"))
+        .expect("list")
+        .is_empty());
+
+    db.delete_project("proj").expect("delete project");
+    assert!(db.get_project_providers(None).expect("list").is_empty());
+}
+
+#[test]
+fn switch_rules_and_firing_log_round_trip() {
+    use crate::switch_rule::{SwitchRule, SwitchRuleAction, SwitchRuleCondition, SwitchRuleFiring};
+
+    let db = Database::memory().expect("create memory db");
+    let rule = SwitchRule {
+        id: "night".to_string(),
+        name: "Night".to_string(),
+        app_type: "claude".to_string(),
+        enabled: true,
+        condition: SwitchRuleCondition::TimeWindow {
+            days: vec![1, 2, 3, 4, 5],
+            start: "22:00".to_string(),
+            end: "06:00".to_string(),
+        },
+        action: SwitchRuleAction::SwitchProvider {
+            provider_id: "cheap".to_string(),
+        },
+        sort_index: None,
+        created_at: Some(1),
+    };
+    db.save_switch_rule(&rule).expect("save rule");
+
+    let rules = db.get_switch_rules(Some("claude")).expect("list");
+    assert_eq!(rules.len(), 1);
+    assert_eq!(rules[0].condition, rule.condition);
+    assert_eq!(rules[0].action, rule.action);
+    assert!(db.get_switch_rules(Some("codex")).expect("list").is_empty());
+
+    let firing = SwitchRuleFiring {
+        id: 0,
+        rule_id: rule.id.clone(),
+        rule_name: rule.name.clone(),
+        app_type: rule.app_type.clone(),
+        action: rule.action.as_str().to_string(),
+        from_provider_id: Some("main".to_string()),
+        to_provider_id: "cheap".to_string(),
+        success: true,
+        message: None,
+        fired_at: 10,
+    };
+    db.insert_switch_rule_firing(&firing)
+        .expect("insert firing");
+    let log = db.get_switch_rule_log(None, 10).expect("log");
+    assert_eq!(log.len(), 1);
+    assert_eq!(log[0].from_provider_id.as_deref(), Some("main"));
+
+    assert!(db.delete_switch_rule("night").expect("delete"));
+    assert!(db.get_switch_rules(None).expect("list").is_empty());
+}
+
+#[test]
+fn usage_snapshots_keep_latest_query_per_provider() {
+    use crate::provider::UsageData;
+
+    let db = Database::memory().expect("create memory db");
+    let plan = |name: &str, remaining: f64| UsageData {
+        plan_name: Some(name.to_string()),
+        extra: None,
+        is_valid: Some(true),
+        invalid_message: None,
+        total: Some(100.0),
+        used: Some(100.0 - remaining),
+        remaining: Some(remaining),
+        unit: Some("USD".to_string()),
+    };
+    let now = chrono::Utc::now().timestamp();
+
+    db.insert_usage_snapshots("claude", "p1", &[plan("pro", 50.0)], now - 60)
+        .expect("insert first");
+    db.insert_usage_snapshots(
+        "claude",
+        "p1",
+        &[plan("pro", 40.0), plan("extra", 5.0)],
+        now,
+    )
+    .expect("insert second");
+
+    let latest = db
+        .get_latest_usage_snapshots("claude", "p1")
+        .expect("latest");
+    assert_eq!(latest.len(), 2);
+    assert!(latest.iter().all(|s| s.captured_at == now));
+
+    let history = db
+        .get_usage_snapshots("claude", "p1", now - 3600)
+        .expect("history");
+    assert_eq!(history.len(), 3);
+
+    let remaining = db.get_latest_usage_remaining("claude").expect("remaining");
+    assert_eq!(remaining.get("p1"), Some(&5.0));
+    assert!(db
+        .get_latest_usage_remaining("codex")
+        .expect("remaining")
+        .is_empty());
+}
+
+#[test]
+fn request_hook_scripts_chain_global_app_then_provider() {
+    use crate::provider::{ProviderMeta, RequestHookScript};
+    use crate::request_hook_script::{hook_chain, load_shared_hook_scripts, HookScope};
+
+    let db = Database::memory().expect("create memory db");
+    let script = |code: &str| RequestHookScript {
+        enabled: true,
+        language: "javascript".to_string(),
+        code: code.to_string(),
+        timeout_ms: None,
+        stream_event_budget_ms: None,
+    };
+
+    db.set_request_hook_script(None, Some(&script("global")))
+        .expect("set global");
+    db.set_request_hook_script(Some("claude"), Some(&script("claude")))
+        .expect("set app");
+    db.set_request_hook_script(
+        Some("codex"),
+        Some(&RequestHookScript {
+            enabled: false,
+            ..script("codex")
+        }),
+    )
+    .expect("set disabled app");
+
+    let mut provider = Provider::with_id("p1".to_string(), "P1".to_string(), json!({}), None);
+    provider.meta = Some(ProviderMeta {
+        request_hook_script: Some(script("provider")),
+        ..Default::default()
+    });
+
+    let shared = load_shared_hook_scripts(&db, "claude");
+    let chain = hook_chain(&shared, &provider, "Claude");
+    let order: Vec<_> = chain
+        .iter()
+        .map(|hook| (hook.scope, hook.script.code.as_str()))
+        .collect();
+    assert_eq!(
+        order,
+        vec![
+            (HookScope::Global, "global"),
+            (HookScope::App, "claude"),
+            (HookScope::Provider, "provider"),
+        ]
+    );
+
+    // 禁用的应用级脚本不进入脚本链
+    let shared = load_shared_hook_scripts(&db, "codex");
+    assert_eq!(hook_chain(&shared, &provider, "Codex").len(), 2);
+
+    db.set_request_hook_script(None, None)
+        .expect("delete global");
+    assert!(db.get_request_hook_script(None).expect("get").is_none());
+}
+
+#[test]
+fn client_tokens_are_stored_hashed_and_looked_up_by_hash() {
+    use crate::proxy::client_auth::{generate_token, hash_token, ClientToken};
+
+    let db = Database::memory().expect("create memory db");
+    let (token, token_prefix) = generate_token();
+    let info = ClientToken {
+        id: "t1".to_string(),
+        name: "laptop".to_string(),
+        token_prefix,
+        allowed_apps: Some(vec!["claude".to_string()]),
+        allowed_providers: None,
+        enabled: true,
+        created_at: 1,
+        last_used_at: None,
+    };
+    db.insert_client_token(&info, &hash_token(&token))
+        .expect("insert token");
+    assert_eq!(db.count_enabled_client_tokens().expect("count"), 1);
+
+    let found = db
+        .find_client_token_by_hash(&hash_token(&token))
+        .expect("find")
+        .expect("token exists");
+    assert_eq!(found, info);
+    assert!(found.allows_app("claude"));
+    assert!(!found.allows_app("codex"));
+    assert!(db
+        .find_client_token_by_hash(&token)
+        .expect("find by plaintext")
+        .is_none());
+
+    db.touch_client_token("t1").expect("touch");
+    assert!(db.get_client_tokens().expect("list")[0]
+        .last_used_at
+        .is_some());
+
+    assert!(db
+        .update_client_token(&ClientToken {
+            enabled: false,
+            ..info
+        })
+        .expect("update"));
+    assert_eq!(db.count_enabled_client_tokens().expect("count"), 0);
+    assert!(db.delete_client_token("t1").expect("delete"));
+    assert!(db.get_client_tokens().expect("list").is_empty());
+}
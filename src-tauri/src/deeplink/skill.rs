@@ -35,12 +35,8 @@ pub fn import_skill_from_deeplink(
     let name = parts[1].to_string();
 
     // Create SkillRepo
-    let repo = SkillRepo {
-        owner: owner.clone(),
-        name: name.clone(),
-        branch: request.branch.unwrap_or_else(|| "main".to_string()),
-        enabled: request.enabled.unwrap_or(true),
-    };
+    let mut repo = SkillRepo::github(&owner, &name, request.branch.as_deref().unwrap_or("main"));
+    repo.enabled = request.enabled.unwrap_or(true);
 
     // Save using Database
     state.db.save_skill_repo(&repo)?;
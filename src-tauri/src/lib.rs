@@ -7,6 +7,7 @@ mod claude_plugin;
 mod codex_config;
 mod commands;
 mod config;
+mod config_watcher;
 mod database;
 mod deeplink;
 mod error;
@@ -16,16 +17,22 @@ mod init_status;
 mod mcp;
 mod opencode_config;
 mod panic_hook;
+mod profile;
+mod project;
+mod project_config;
 mod prompt;
 mod prompt_files;
+mod prompt_template;
 mod provider;
 mod provider_defaults;
 mod proxy;
 mod request_hook_script;
+mod script_runtime;
 mod services;
 mod session_manager;
 mod settings;
 mod store;
+mod switch_rule;
 mod tray;
 mod usage_script;
 
@@ -748,6 +755,17 @@ pub fn run() {
                 restore_proxy_state_on_startup(&state).await;
             });
 
+            // Live 配置漂移检测（监听外部对配置文件的修改）
+            crate::config_watcher::start(app.handle().clone());
+
+            // 自动切换规则调度
+            crate::services::switch_rule::SwitchRuleService::start_scheduler(app.handle().clone());
+
+            // 用量脚本后台轮询与低余额提醒
+            crate::services::usage_history::UsageHistoryService::start_scheduler(
+                app.handle().clone(),
+            );
+
             // 静默启动：根据设置决定是否显示主窗口
             let settings = crate::settings::get_settings();
             if let Some(window) = app.get_webview_window("main") {
@@ -794,11 +812,17 @@ pub fn run() {
             commands::get_common_config_snippet,
             commands::set_common_config_snippet,
             commands::extract_common_config_snippet,
+            commands::get_config_drift,
+            commands::resolve_config_drift,
             commands::read_live_provider_settings,
             commands::get_settings,
             commands::save_settings,
             commands::get_rectifier_config,
             commands::set_rectifier_config,
+            commands::get_tracing_config,
+            commands::set_tracing_config,
+            commands::get_request_hook_script,
+            commands::set_request_hook_script,
             commands::get_log_config,
             commands::set_log_config,
             commands::restart_app,
@@ -819,6 +843,7 @@ pub fn run() {
             // usage query
             commands::queryProviderUsage,
             commands::testUsageScript,
+            commands::get_usage_script_trend,
             commands::testRequestHookScript,
             // New MCP via config.json (SSOT)
             commands::get_mcp_config,
@@ -838,6 +863,29 @@ pub fn run() {
             commands::enable_prompt,
             commands::import_prompt_from_file,
             commands::get_current_prompt_file_content,
+            commands::get_prompt_revisions,
+            commands::diff_prompt_revisions,
+            commands::restore_prompt_revision,
+            commands::preview_prompt,
+            // Project registry (project-level prompts)
+            commands::get_projects,
+            commands::add_project,
+            commands::remove_project,
+            commands::get_project_providers,
+            commands::set_project_provider,
+            // Profiles
+            commands::get_profiles,
+            commands::get_active_profile_id,
+            commands::save_profile,
+            commands::delete_profile,
+            commands::get_current_profile_snapshot,
+            commands::capture_current_profile,
+            commands::apply_profile,
+            // Automatic switch rules
+            commands::get_switch_rules,
+            commands::save_switch_rule,
+            commands::delete_switch_rule,
+            commands::get_switch_rule_log,
             // ours: endpoint speed test + custom endpoint management
             commands::test_api_endpoints,
             commands::get_custom_endpoints,
@@ -876,6 +924,9 @@ pub fn run() {
             commands::scan_unmanaged_skills,
             commands::import_skills_from_apps,
             commands::discover_available_skills,
+            commands::check_skill_updates,
+            commands::update_skill,
+            commands::pin_skill,
             // Skill management (legacy API compatibility)
             commands::get_skills,
             commands::get_skills_for_app,
@@ -903,6 +954,10 @@ pub fn run() {
             commands::update_global_proxy_config,
             commands::get_proxy_config_for_app,
             commands::update_proxy_config_for_app,
+            commands::get_proxy_client_tokens,
+            commands::create_proxy_client_token,
+            commands::update_proxy_client_token,
+            commands::delete_proxy_client_token,
             commands::get_default_cost_multiplier,
             commands::set_default_cost_multiplier,
             commands::get_pricing_model_source,
@@ -916,6 +971,7 @@ pub fn run() {
             commands::get_circuit_breaker_config,
             commands::update_circuit_breaker_config,
             commands::get_circuit_breaker_stats,
+            commands::get_key_circuit_breaker_stats,
             // Failover queue management
             commands::get_failover_queue,
             commands::get_available_providers_for_failover,
@@ -923,10 +979,20 @@ pub fn run() {
             commands::remove_from_failover_queue,
             commands::get_auto_failover_enabled,
             commands::set_auto_failover_enabled,
+            commands::get_failback_config,
+            commands::set_failback_config,
+            commands::get_failover_history,
+            commands::get_hedging_config,
+            commands::set_hedging_config,
+            commands::get_stream_resume_config,
+            commands::set_stream_resume_config,
+            commands::get_routing_rules,
+            commands::set_routing_rules,
             // Usage statistics
             commands::get_usage_summary,
             commands::get_usage_trends,
             commands::get_provider_stats,
+            commands::get_api_key_stats,
             commands::get_model_stats,
             commands::get_request_logs,
             commands::get_request_detail,
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+use crate::app_config::AppType;
+
+/// 配置档案中单个应用的设置
+///
+/// 字段为 `None` 表示应用档案时保持该项不变。
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProfileAppConfig {
+    /// 切换到的供应商
+    #[serde(rename = "providerId", skip_serializing_if = "Option::is_none")]
+    pub provider_id: Option<String>,
+    /// 启用的 MCP 服务器（未列出的服务器在该应用中禁用）
+    #[serde(rename = "mcpServers", skip_serializing_if = "Option::is_none")]
+    pub mcp_servers: Option<Vec<String>>,
+    /// 启用的全局提示词，空字符串表示禁用所有全局提示词
+    #[serde(rename = "promptId", skip_serializing_if = "Option::is_none")]
+    pub prompt_id: Option<String>,
+    /// 启用的 Skills（未列出的 Skill 在该应用中禁用）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skills: Option<Vec<String>>,
+}
+
+/// 配置档案按应用划分的设置
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProfileApps {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub claude: Option<ProfileAppConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub codex: Option<ProfileAppConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gemini: Option<ProfileAppConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opencode: Option<ProfileAppConfig>,
+}
+
+impl ProfileApps {
+    pub fn get(&self, app: &AppType) -> Option<&ProfileAppConfig> {
+        match app {
+            AppType::Claude => self.claude.as_ref(),
+            AppType::Codex => self.codex.as_ref(),
+            AppType::Gemini => self.gemini.as_ref(),
+            AppType::OpenCode => self.opencode.as_ref(),
+        }
+    }
+
+    pub fn set(&mut self, app: &AppType, config: Option<ProfileAppConfig>) {
+        match app {
+            AppType::Claude => self.claude = config,
+            AppType::Codex => self.codex = config,
+            AppType::Gemini => self.gemini = config,
+            AppType::OpenCode => self.opencode = config,
+        }
+    }
+
+    /// 返回档案中配置了的应用
+    pub fn configured_apps(&self) -> Vec<AppType> {
+        AppType::all()
+            .filter(|app| self.get(app).is_some())
+            .collect()
+    }
+}
+
+/// 配置档案（如“工作”“个人”“客户 X”）
+///
+/// 将各应用的供应商、MCP 服务器、提示词与 Skills 打包，一次切换。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub apps: ProfileApps,
+    #[serde(rename = "sortIndex", skip_serializing_if = "Option::is_none")]
+    pub sort_index: Option<i64>,
+    #[serde(rename = "createdAt", skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<i64>,
+}
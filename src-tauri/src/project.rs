@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+
+/// 已登记的项目目录
+///
+/// 项目级配置（如 `<project>/CLAUDE.md`）以项目根目录为基准解析。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Project {
+    pub id: String,
+    pub name: String,
+    /// 项目根目录（绝对路径）
+    pub path: String,
+    #[serde(rename = "createdAt", skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<i64>,
+}
+
+/// 项目级供应商绑定
+///
+/// 绑定后 cc-switch 将供应商配置写入项目目录（如 `<project>/.claude/settings.local.json`），
+/// 而不是切换全局 Live 配置。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectProvider {
+    #[serde(rename = "projectId")]
+    pub project_id: String,
+    #[serde(rename = "appType")]
+    pub app_type: String,
+    #[serde(rename = "providerId")]
+    pub provider_id: String,
+    /// 写入项目配置文件时由 cc-switch 管理的键，解绑时仅清理这些键
+    #[serde(rename = "managedKeys", default)]
+    pub managed_keys: Vec<String>,
+    #[serde(rename = "updatedAt", skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<i64>,
+}
+
+/// 项目覆盖供应商的列表项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectProviderOverride {
+    #[serde(rename = "projectId")]
+    pub project_id: String,
+    #[serde(rename = "projectName")]
+    pub project_name: String,
+    #[serde(rename = "projectPath")]
+    pub project_path: String,
+    #[serde(rename = "appType")]
+    pub app_type: String,
+    #[serde(rename = "providerId")]
+    pub provider_id: String,
+    /// 供应商已被删除时为空
+    #[serde(rename = "providerName", skip_serializing_if = "Option::is_none")]
+    pub provider_name: Option<String>,
+    /// 写入的项目配置文件
+    #[serde(rename = "configPath")]
+    pub config_path: String,
+}
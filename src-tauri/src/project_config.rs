@@ -0,0 +1,511 @@
+//! 项目级供应商配置文件
+//!
+//! 为登记的项目写入目录作用域的供应商配置，而不改动全局 Live 文件：
+//! - Claude: `<project>/.claude/settings.local.json` 的 `env` 块
+//! - Codex: `<project>/.codex/config.toml` 中名为 `cc-switch` 的 profile
+//! - Gemini: `<project>/.gemini/.env`
+//!
+//! 写入时返回由 cc-switch 管理的键（Codex 为 TOML 路径），移除或更换供应商时
+//! 只清理这些键，保留用户在同一文件中的其他配置。
+//!
+//! 这些文件会包含 API Key，写入时同时将其加入项目根目录的 `.gitignore`。
+
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+use toml_edit::{DocumentMut, Item};
+
+use crate::app_config::AppType;
+use crate::config::{delete_file, read_json_file, write_json_file, write_text_file};
+use crate::error::AppError;
+use crate::gemini_config::{json_to_env, parse_env_file, serialize_env_file};
+use crate::provider::Provider;
+
+/// 项目 `.codex/config.toml` 中由 cc-switch 管理的 profile 与 model provider 名称
+pub const CODEX_PROFILE_NAME: &str = "cc-switch";
+
+/// 管理键中记录用户原有顶层 `profile` 的前缀（`profile=<name>`），移除时据此还原
+const PREVIOUS_PROFILE_PREFIX: &str = "profile=";
+
+/// 项目级供应商配置文件路径
+pub fn project_provider_config_path(app: &AppType, root: &Path) -> Result<PathBuf, AppError> {
+    match app {
+        AppType::Claude => Ok(root.join(".claude").join("settings.local.json")),
+        AppType::Codex => Ok(root.join(".codex").join("config.toml")),
+        AppType::Gemini => Ok(root.join(".gemini").join(".env")),
+        AppType::OpenCode => Err(AppError::localized(
+            "project_provider_unsupported",
+            "OpenCode 暂不支持项目级供应商",
+            "Project-scoped providers are not supported for OpenCode",
+        )),
+    }
+}
+
+/// 写入项目级供应商配置
+///
+/// `previous_keys` 为上一次写入时管理的键，会先被清理。返回本次管理的键。
+pub fn write_project_provider(
+    app: &AppType,
+    root: &Path,
+    provider: &Provider,
+    previous_keys: &[String],
+) -> Result<Vec<String>, AppError> {
+    let path = project_provider_config_path(app, root)?;
+    let managed = match app {
+        AppType::Claude => write_claude_env(&path, provider, previous_keys),
+        AppType::Codex => write_codex_profile(&path, provider, previous_keys),
+        AppType::Gemini => write_gemini_env(&path, provider, previous_keys),
+        AppType::OpenCode => unreachable!("OpenCode 已在路径解析时拒绝"),
+    }?;
+
+    if ensure_gitignored(root, &path)? {
+        log::warn!(
+            "{} 含有 API Key，已加入 {} 以免提交到仓库；若该文件已被 Git 跟踪，请手动取消跟踪",
+            path.display(),
+            root.join(".gitignore").display()
+        );
+    }
+    Ok(managed)
+}
+
+/// 确保配置文件已列入项目根目录的 `.gitignore`，返回是否新增了条目
+///
+/// 移除供应商时不会撤销该条目。
+fn ensure_gitignored(root: &Path, path: &Path) -> Result<bool, AppError> {
+    let Ok(relative) = path.strip_prefix(root) else {
+        return Ok(false);
+    };
+    let relative = relative.to_string_lossy().replace('\\', "/");
+    let entry = format!("/{relative}");
+
+    let gitignore = root.join(".gitignore");
+    let mut content = if gitignore.exists() {
+        std::fs::read_to_string(&gitignore).map_err(|e| AppError::io(&gitignore, e))?
+    } else {
+        String::new()
+    };
+    if content
+        .lines()
+        .map(str::trim)
+        .any(|line| line == entry || line == relative)
+    {
+        return Ok(false);
+    }
+
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(&entry);
+    content.push('\n');
+    write_text_file(&gitignore, &content)?;
+    Ok(true)
+}
+
+/// 移除项目级供应商配置（仅清理 cc-switch 管理的键，文件为空时删除）
+pub fn remove_project_provider(
+    app: &AppType,
+    root: &Path,
+    managed_keys: &[String],
+) -> Result<(), AppError> {
+    let path = project_provider_config_path(app, root)?;
+    if !path.exists() {
+        return Ok(());
+    }
+
+    match app {
+        AppType::Claude => {
+            let mut settings: Value = read_json_file(&path)?;
+            remove_claude_env_keys(&mut settings, managed_keys);
+            if settings.as_object().is_some_and(|obj| obj.is_empty()) {
+                delete_file(&path)
+            } else {
+                write_json_file(&path, &settings)
+            }
+        }
+        AppType::Codex => {
+            let mut doc = read_toml_document(&path)?;
+            remove_codex_managed(&mut doc, managed_keys);
+            if doc.as_table().is_empty() {
+                delete_file(&path)
+            } else {
+                write_text_file(&path, &doc.to_string())
+            }
+        }
+        AppType::Gemini => {
+            let mut env = read_env(&path)?;
+            for key in managed_keys {
+                env.remove(key);
+            }
+            if env.is_empty() {
+                delete_file(&path)
+            } else {
+                write_text_file(&path, &serialize_env_file(&env))
+            }
+        }
+        AppType::OpenCode => Ok(()),
+    }
+}
+
+fn write_claude_env(
+    path: &Path,
+    provider: &Provider,
+    previous_keys: &[String],
+) -> Result<Vec<String>, AppError> {
+    let env = provider
+        .settings_config
+        .get("env")
+        .and_then(|v| v.as_object())
+        .cloned()
+        .unwrap_or_default();
+
+    let mut settings: Value = if path.exists() {
+        read_json_file(path)?
+    } else {
+        json!({})
+    };
+    remove_claude_env_keys(&mut settings, previous_keys);
+
+    let obj = settings
+        .as_object_mut()
+        .ok_or_else(|| AppError::Config(format!("{} 必须是 JSON 对象", path.display())))?;
+    let target = obj
+        .entry("env")
+        .or_insert_with(|| json!({}))
+        .as_object_mut()
+        .ok_or_else(|| AppError::Config(format!("{} 中的 env 必须是对象", path.display())))?;
+
+    let mut managed = Vec::with_capacity(env.len());
+    for (key, value) in env {
+        managed.push(key.clone());
+        target.insert(key, value);
+    }
+
+    write_json_file(path, &settings)?;
+    Ok(managed)
+}
+
+fn remove_claude_env_keys(settings: &mut Value, keys: &[String]) {
+    let Some(obj) = settings.as_object_mut() else {
+        return;
+    };
+    let env_empty = match obj.get_mut("env").and_then(|v| v.as_object_mut()) {
+        Some(env) => {
+            for key in keys {
+                env.remove(key);
+            }
+            env.is_empty()
+        }
+        None => false,
+    };
+    if env_empty {
+        obj.remove("env");
+    }
+}
+
+/// 将供应商的 Codex 配置写为项目 profile
+///
+/// 供应商 config.toml 的顶层标量（model、model_provider 等）写入
+/// `[profiles.cc-switch]`，对应的 `[model_providers.<key>]` 复制为
+/// `[model_providers.cc-switch]`，避免覆盖用户同名的 provider。
+/// 项目级配置无法引用全局 auth.json，若供应商未声明 `env_key`，
+/// 会将 API Key 写入 `experimental_bearer_token`。
+/// 用户原有的顶层 `profile` 会记录在管理键中，移除时还原。
+fn write_codex_profile(
+    path: &Path,
+    provider: &Provider,
+    previous_keys: &[String],
+) -> Result<Vec<String>, AppError> {
+    let config_text = provider
+        .settings_config
+        .get("config")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    let source = config_text
+        .parse::<DocumentMut>()
+        .map_err(|e| AppError::Config(format!("解析供应商 Codex 配置失败: {e}")))?;
+
+    let mut doc = if path.exists() {
+        read_toml_document(path)?
+    } else {
+        DocumentMut::new()
+    };
+    remove_codex_managed(&mut doc, previous_keys);
+
+    let mut profile = toml_edit::Table::new();
+    for (key, item) in source.as_table().iter() {
+        if key != "profile" && item.is_value() {
+            profile.insert(key, item.clone());
+        }
+    }
+
+    let mut managed = vec![
+        "profile".to_string(),
+        format!("profiles.{CODEX_PROFILE_NAME}"),
+    ];
+    if let Some(previous) = doc
+        .get("profile")
+        .and_then(|v| v.as_str())
+        .filter(|name| *name != CODEX_PROFILE_NAME)
+    {
+        managed.push(format!("{PREVIOUS_PROFILE_PREFIX}{previous}"));
+    }
+
+    if let Some(provider_key) = source.get("model_provider").and_then(|v| v.as_str()) {
+        if let Some(mut table) = source
+            .get("model_providers")
+            .and_then(|v| v.get(provider_key))
+            .and_then(|v| v.as_table())
+            .cloned()
+        {
+            let api_key = provider
+                .settings_config
+                .get("auth")
+                .and_then(|auth| auth.get("OPENAI_API_KEY"))
+                .and_then(|v| v.as_str())
+                .filter(|key| !key.is_empty());
+            if let (Some(api_key), false) = (api_key, table.contains_key("env_key")) {
+                table.insert("experimental_bearer_token", toml_edit::value(api_key));
+            }
+
+            ensure_table(&mut doc, "model_providers")
+                .insert(CODEX_PROFILE_NAME, Item::Table(table));
+            managed.push(format!("model_providers.{CODEX_PROFILE_NAME}"));
+            profile.insert("model_provider", toml_edit::value(CODEX_PROFILE_NAME));
+        }
+    }
+
+    ensure_table(&mut doc, "profiles").insert(CODEX_PROFILE_NAME, Item::Table(profile));
+    doc["profile"] = toml_edit::value(CODEX_PROFILE_NAME);
+
+    write_text_file(path, &doc.to_string())?;
+    Ok(managed)
+}
+
+fn write_gemini_env(
+    path: &Path,
+    provider: &Provider,
+    previous_keys: &[String],
+) -> Result<Vec<String>, AppError> {
+    let provider_env = json_to_env(&provider.settings_config)?;
+
+    let mut env = if path.exists() {
+        read_env(path)?
+    } else {
+        Default::default()
+    };
+    for key in previous_keys {
+        env.remove(key);
+    }
+
+    let mut managed: Vec<String> = provider_env.keys().cloned().collect();
+    managed.sort();
+    env.extend(provider_env);
+
+    write_text_file(path, &serialize_env_file(&env))?;
+    Ok(managed)
+}
+
+fn read_toml_document(path: &Path) -> Result<DocumentMut, AppError> {
+    let text = std::fs::read_to_string(path).map_err(|e| AppError::io(path, e))?;
+    text.parse::<DocumentMut>()
+        .map_err(|e| AppError::Config(format!("解析 {} 失败: {e}", path.display())))
+}
+
+fn read_env(path: &Path) -> Result<std::collections::HashMap<String, String>, AppError> {
+    let text = std::fs::read_to_string(path).map_err(|e| AppError::io(path, e))?;
+    Ok(parse_env_file(&text))
+}
+
+fn ensure_table<'a>(doc: &'a mut DocumentMut, key: &str) -> &'a mut toml_edit::Table {
+    if !doc.get(key).is_some_and(|item| item.is_table()) {
+        let mut table = toml_edit::Table::new();
+        table.set_implicit(true);
+        doc.insert(key, Item::Table(table));
+    }
+    doc[key].as_table_mut().expect("table inserted above")
+}
+
+/// 清理 cc-switch 管理的 Codex 配置，并还原用户原有的顶层 `profile`
+fn remove_codex_managed(doc: &mut DocumentMut, keys: &[String]) {
+    let (previous, paths): (Vec<String>, Vec<String>) = keys
+        .iter()
+        .cloned()
+        .partition(|key| key.starts_with(PREVIOUS_PROFILE_PREFIX));
+    remove_toml_paths(doc, &paths);
+    if let Some(name) = previous
+        .iter()
+        .find_map(|key| key.strip_prefix(PREVIOUS_PROFILE_PREFIX))
+    {
+        doc["profile"] = toml_edit::value(name);
+    }
+}
+
+/// 删除 `a` 或 `a.b` 形式的 TOML 路径，父表因此变空时一并删除
+fn remove_toml_paths(doc: &mut DocumentMut, paths: &[String]) {
+    for path in paths {
+        match path.split_once('.') {
+            None => {
+                doc.remove(path);
+            }
+            Some((parent, child)) => {
+                let parent_empty = match doc.get_mut(parent).and_then(|v| v.as_table_like_mut()) {
+                    Some(table) => {
+                        table.remove(child);
+                        table.is_empty()
+                    }
+                    None => false,
+                };
+                if parent_empty {
+                    doc.remove(parent);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn provider(settings_config: Value) -> Provider {
+        Provider::with_id(
+            "p1".to_string(),
+            "Client".to_string(),
+            settings_config,
+            None,
+        )
+    }
+
+    #[test]
+    fn claude_env_is_merged_and_removed_without_touching_user_settings() {
+        let dir = TempDir::new().expect("tempdir");
+        let path = dir.path().join(".claude").join("settings.local.json");
+        write_json_file(
+            &path,
+            &json!({ "permissions": { "allow": ["Bash"] }, "env": { "DEBUG": "1" } }),
+        )
+        .expect("seed settings");
+
+        let managed = write_project_provider(
+            &AppType::Claude,
+            dir.path(),
+            &provider(json!({ "env": { "ANTHROPIC_BASE_URL": "https://gw", "ANTHROPIC_AUTH_TOKEN": "sk" } })),
+            &[],
+        )
+        .expect("write provider");
+        assert_eq!(managed.len(), 2);
+
+        let written: Value = read_json_file(&path).expect("read");
+        assert_eq!(written["env"]["ANTHROPIC_BASE_URL"], "https://gw");
+        assert_eq!(written["env"]["DEBUG"], "1");
+
+        remove_project_provider(&AppType::Claude, dir.path(), &managed).expect("remove");
+        let restored: Value = read_json_file(&path).expect("read");
+        assert_eq!(
+            restored,
+            json!({ "permissions": { "allow": ["Bash"] }, "env": { "DEBUG": "1" } })
+        );
+    }
+
+    #[test]
+    fn codex_profile_is_written_and_cleaned_up() {
+        let dir = TempDir::new().expect("tempdir");
+        let config = "model = \"gpt-5\"\nmodel_provider = \"gw\"\n\n[model_providers.gw]\nname = \"gw\"\nbase_url = \"https://gw/v1\"\nwire_api = \"responses\"\n";
+        let managed = write_project_provider(
+            &AppType::Codex,
+            dir.path(),
+            &provider(json!({ "auth": { "OPENAI_API_KEY": "sk-1" }, "config": config })),
+            &[],
+        )
+        .expect("write provider");
+
+        let path = dir.path().join(".codex").join("config.toml");
+        let doc = read_toml_document(&path).expect("read");
+        assert_eq!(doc["profile"].as_str(), Some(CODEX_PROFILE_NAME));
+        assert_eq!(
+            doc["profiles"][CODEX_PROFILE_NAME]["model_provider"].as_str(),
+            Some(CODEX_PROFILE_NAME)
+        );
+        assert_eq!(
+            doc["model_providers"][CODEX_PROFILE_NAME]["experimental_bearer_token"].as_str(),
+            Some("sk-1")
+        );
+
+        remove_project_provider(&AppType::Codex, dir.path(), &managed).expect("remove");
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn codex_profile_keeps_user_model_providers() {
+        let dir = TempDir::new().expect("tempdir");
+        let path = dir.path().join(".codex").join("config.toml");
+        let user_config = "[model_providers.gw]\nname = \"mine\"\nbase_url = \"https://mine/v1\"\n";
+        write_text_file(&path, user_config).expect("seed config");
+
+        let config = "model_provider = \"gw\"\n\n[model_providers.gw]\nname = \"gw\"\nbase_url = \"https://gw/v1\"\n";
+        let managed = write_project_provider(
+            &AppType::Codex,
+            dir.path(),
+            &provider(json!({ "auth": { "OPENAI_API_KEY": "sk-1" }, "config": config })),
+            &[],
+        )
+        .expect("write provider");
+        assert!(!managed.contains(&"model_providers.gw".to_string()));
+
+        let doc = read_toml_document(&path).expect("read");
+        assert_eq!(
+            doc["model_providers"]["gw"]["base_url"].as_str(),
+            Some("https://mine/v1")
+        );
+
+        remove_project_provider(&AppType::Codex, dir.path(), &managed).expect("remove");
+        let restored = read_toml_document(&path).expect("read");
+        assert_eq!(
+            restored["model_providers"]["gw"]["name"].as_str(),
+            Some("mine")
+        );
+        assert!(restored.get("profiles").is_none());
+    }
+
+    #[test]
+    fn codex_profile_restores_user_profile_on_removal() {
+        let dir = TempDir::new().expect("tempdir");
+        let path = dir.path().join(".codex").join("config.toml");
+        let user_config = "profile = \"work\"\n\n[profiles.work]\nmodel = \"o3\"\n";
+        write_text_file(&path, user_config).expect("seed config");
+
+        let codex = provider(json!({ "config": "model = \"gpt-5\"\n" }));
+        let first = write_project_provider(&AppType::Codex, dir.path(), &codex, &[])
+            .expect("write provider");
+        // 更换供应商时依旧保留原有 profile 的记录
+        let managed = write_project_provider(&AppType::Codex, dir.path(), &codex, &first)
+            .expect("rewrite provider");
+        assert_eq!(first, managed);
+
+        let doc = read_toml_document(&path).expect("read");
+        assert_eq!(doc["profile"].as_str(), Some(CODEX_PROFILE_NAME));
+
+        remove_project_provider(&AppType::Codex, dir.path(), &managed).expect("remove");
+        let restored = read_toml_document(&path).expect("read");
+        assert_eq!(restored["profile"].as_str(), Some("work"));
+        assert_eq!(restored["profiles"]["work"]["model"].as_str(), Some("o3"));
+        assert!(restored["profiles"].get(CODEX_PROFILE_NAME).is_none());
+    }
+
+    #[test]
+    fn config_files_with_secrets_are_gitignored_once() {
+        let dir = TempDir::new().expect("tempdir");
+        let gitignore = dir.path().join(".gitignore");
+        write_text_file(&gitignore, "target").expect("seed gitignore");
+
+        let gemini = provider(json!({ "env": { "GEMINI_API_KEY": "sk-1" } }));
+        for _ in 0..2 {
+            write_project_provider(&AppType::Gemini, dir.path(), &gemini, &[])
+                .expect("write provider");
+        }
+
+        let content = std::fs::read_to_string(&gitignore).expect("read gitignore");
+        assert_eq!(content, "target\n/.gemini/.env\n");
+    }
+}
@@ -13,4 +13,79 @@ pub struct Prompt {
     pub created_at: Option<i64>,
     #[serde(rename = "updatedAt", skip_serializing_if = "Option::is_none")]
     pub updated_at: Option<i64>,
+    /// 所属项目 ID，`None` 表示全局提示词
+    #[serde(rename = "projectId", default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+}
+
+/// 提示词修订来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptRevisionSource {
+    /// 首次记录修订前已存在的内容
+    Baseline,
+    /// 用户编辑保存
+    Edit,
+    /// 从 live 文件回填
+    Backfill,
+    /// 从历史修订恢复
+    Restore,
+}
+
+impl PromptRevisionSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PromptRevisionSource::Baseline => "baseline",
+            PromptRevisionSource::Edit => "edit",
+            PromptRevisionSource::Backfill => "backfill",
+            PromptRevisionSource::Restore => "restore",
+        }
+    }
+
+    /// 从数据库字符串解析，未知值按 Edit 处理
+    pub fn from_db_str(s: &str) -> Self {
+        match s {
+            "baseline" => PromptRevisionSource::Baseline,
+            "backfill" => PromptRevisionSource::Backfill,
+            "restore" => PromptRevisionSource::Restore,
+            _ => PromptRevisionSource::Edit,
+        }
+    }
+}
+
+/// 提示词历史修订
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptRevision {
+    pub id: i64,
+    pub prompt_id: String,
+    pub content: String,
+    pub source: PromptRevisionSource,
+    pub created_at: i64,
+}
+
+/// 修订对比中的单行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptDiffLine {
+    /// equal / insert / delete
+    pub tag: String,
+    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_line: Option<usize>,
+}
+
+/// 两个修订之间的对比结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptRevisionDiff {
+    pub from_revision: i64,
+    pub to_revision: i64,
+    pub additions: usize,
+    pub deletions: usize,
+    pub lines: Vec<PromptDiffLine>,
+    /// unified diff 文本，便于直接展示或复制
+    pub unified: String,
 }
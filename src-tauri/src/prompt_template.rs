@@ -0,0 +1,346 @@
+//! 提示词模板渲染
+//!
+//! 支持的语法：
+//! - `{{variable}}`：变量替换（如 `{{project_name}}`、`{{date}}`、`{{os}}`、`{{provider_name}}`）
+//! - `{{> prompt}}`：引入同一应用下的另一个提示词（按 ID 或名称匹配），被引入内容同样会渲染
+//! - `\{{`：输出字面量 `{{`
+//!
+//! 花括号内不是合法标识符的内容（如 GitHub Actions 的 `${{ secrets.TOKEN }}`）原样保留。
+//! 未知变量在写入 live 文件时同样原样保留，仅在预览时报错。
+//!
+//! 变量在写入 live 文件时展开：保存、启用提示词以及切换供应商时会重新渲染，
+//! 因此 `{{date}}` 反映最近一次写入的日期，而不是逐日更新。
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::error::AppError;
+
+/// 引入嵌套的最大深度
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// 仅在项目级提示词中可用的变量
+const PROJECT_ONLY_VARIABLES: &[&str] = &["project_name", "project_path"];
+
+/// 渲染结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderedPrompt {
+    pub content: String,
+    /// 按首次出现顺序记录被引入的提示词 ID
+    pub includes: Vec<String>,
+}
+
+/// 被引入的提示词
+pub struct IncludedPrompt {
+    pub id: String,
+    pub content: String,
+}
+
+/// 未知变量的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownVariables {
+    /// 原样保留（写入 live 文件时使用，兼容含 Jinja / Handlebars 示例的提示词）
+    Keep,
+    /// 报错（预览时使用，便于发现变量名拼写错误）
+    Reject,
+}
+
+/// 渲染提示词模板
+///
+/// `root_id` 为当前提示词 ID（用于检测自引用），`resolve_include` 按引用名查找被引入的提示词。
+pub fn render_prompt_template<F>(
+    root_id: &str,
+    content: &str,
+    variables: &HashMap<String, String>,
+    unknown: UnknownVariables,
+    resolve_include: F,
+) -> Result<RenderedPrompt, AppError>
+where
+    F: Fn(&str) -> Option<IncludedPrompt>,
+{
+    let mut includes = Vec::new();
+    let mut stack = vec![root_id.to_string()];
+    let content = render_inner(
+        content,
+        variables,
+        unknown,
+        &resolve_include,
+        &mut stack,
+        &mut includes,
+    )?;
+    Ok(RenderedPrompt { content, includes })
+}
+
+fn render_inner<F>(
+    content: &str,
+    variables: &HashMap<String, String>,
+    unknown: UnknownVariables,
+    resolve_include: &F,
+    stack: &mut Vec<String>,
+    includes: &mut Vec<String>,
+) -> Result<String, AppError>
+where
+    F: Fn(&str) -> Option<IncludedPrompt>,
+{
+    let mut output = String::with_capacity(content.len());
+    let mut rest = content;
+
+    while let Some(start) = rest.find("{{") {
+        // 转义：\{{ 输出字面量 {{
+        if rest[..start].ends_with('\\') {
+            output.push_str(&rest[..start - 1]);
+            output.push_str("{{");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        let Some(end) = rest[start + 2..].find("}}").map(|i| start + 2 + i) else {
+            break;
+        };
+
+        output.push_str(&rest[..start]);
+        let tag = rest[start + 2..end].trim();
+
+        if let Some(reference) = tag.strip_prefix('>') {
+            let reference = reference.trim();
+            let rendered = render_include(
+                reference,
+                variables,
+                unknown,
+                resolve_include,
+                stack,
+                includes,
+            )?;
+            output.push_str(&rendered);
+        } else if is_identifier(tag) {
+            match (variables.get(tag), unknown) {
+                (Some(value), _) => output.push_str(value),
+                (None, UnknownVariables::Keep) => output.push_str(&rest[start..end + 2]),
+                (None, UnknownVariables::Reject) => {
+                    return Err(unknown_variable_error(tag, variables))
+                }
+            }
+        } else {
+            // 非模板语法，原样保留
+            output.push_str(&rest[start..end + 2]);
+        }
+
+        rest = &rest[end + 2..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+fn render_include<F>(
+    reference: &str,
+    variables: &HashMap<String, String>,
+    unknown: UnknownVariables,
+    resolve_include: &F,
+    stack: &mut Vec<String>,
+    includes: &mut Vec<String>,
+) -> Result<String, AppError>
+where
+    F: Fn(&str) -> Option<IncludedPrompt>,
+{
+    if reference.is_empty() {
+        return Err(AppError::InvalidInput(
+            "提示词模板错误: 引入语法缺少目标，应为 {{> 提示词名称或ID}}".to_string(),
+        ));
+    }
+
+    let included = resolve_include(reference).ok_or_else(|| {
+        AppError::InvalidInput(format!(
+            "提示词模板错误: 找不到被引入的提示词 '{reference}'"
+        ))
+    })?;
+
+    if stack.iter().any(|id| id == &included.id) {
+        let mut chain = stack.clone();
+        chain.push(included.id);
+        return Err(AppError::InvalidInput(format!(
+            "提示词模板错误: 检测到循环引入 {}",
+            chain.join(" -> ")
+        )));
+    }
+    if stack.len() > MAX_INCLUDE_DEPTH {
+        return Err(AppError::InvalidInput(format!(
+            "提示词模板错误: 引入层级超过 {MAX_INCLUDE_DEPTH} 层"
+        )));
+    }
+
+    if !includes.contains(&included.id) {
+        includes.push(included.id.clone());
+    }
+
+    stack.push(included.id);
+    let rendered = render_inner(
+        &included.content,
+        variables,
+        unknown,
+        resolve_include,
+        stack,
+        includes,
+    );
+    stack.pop();
+    rendered
+}
+
+fn unknown_variable_error(name: &str, variables: &HashMap<String, String>) -> AppError {
+    if PROJECT_ONLY_VARIABLES.contains(&name) {
+        return AppError::InvalidInput(format!(
+            "提示词模板错误: 变量 {{{{{name}}}}} 仅在项目级提示词中可用"
+        ));
+    }
+
+    let mut known: Vec<&str> = variables.keys().map(String::as_str).collect();
+    known.sort_unstable();
+    AppError::InvalidInput(format!(
+        "提示词模板错误: 未知变量 {{{{{name}}}}}（可用变量: {}；如需输出字面量请写作 \\{{{{）",
+        known.join(", ")
+    ))
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> HashMap<String, String> {
+        HashMap::from([
+            ("os".to_string(), "linux".to_string()),
+            ("project_name".to_string(), "demo".to_string()),
+        ])
+    }
+
+    fn library(reference: &str) -> Option<IncludedPrompt> {
+        let (id, content) = match reference {
+            "style" | "Style Guide" => ("style", "Use {{os}} paths."),
+            "a" => ("a", "A then {{> b}}"),
+            "b" => ("b", "B then {{> a}}"),
+            "self" => ("root", "loop"),
+            _ => return None,
+        };
+        Some(IncludedPrompt {
+            id: id.to_string(),
+            content: content.to_string(),
+        })
+    }
+
+    #[test]
+    fn renders_variables_and_includes() {
+        let rendered = render_prompt_template(
+            "root",
+            "# {{ project_name }}\n{{> Style Guide}}",
+            &vars(),
+            UnknownVariables::Reject,
+            library,
+        )
+        .expect("render");
+
+        assert_eq!(rendered.content, "# demo\nUse linux paths.");
+        assert_eq!(rendered.includes, vec!["style".to_string()]);
+    }
+
+    #[test]
+    fn leaves_non_identifier_braces_and_escapes_untouched() {
+        let rendered = render_prompt_template(
+            "root",
+            "token: ${{ secrets.TOKEN }} literal: \\{{os}}",
+            &vars(),
+            UnknownVariables::Reject,
+            library,
+        )
+        .expect("render");
+
+        assert_eq!(
+            rendered.content,
+            "token: ${{ secrets.TOKEN }} literal: {{os}}"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_variables() {
+        let err = render_prompt_template(
+            "root",
+            "{{nope}}",
+            &vars(),
+            UnknownVariables::Reject,
+            library,
+        )
+        .expect_err("unknown variable");
+        assert!(err.to_string().contains("未知变量"));
+    }
+
+    #[test]
+    fn keeps_unknown_variables_verbatim_when_not_rejecting() {
+        let rendered = render_prompt_template(
+            "root",
+            "Hello {{ user }} on {{os}}, {{project_name}}",
+            &HashMap::from([("os".to_string(), "linux".to_string())]),
+            UnknownVariables::Keep,
+            library,
+        )
+        .expect("render");
+        assert_eq!(
+            rendered.content,
+            "Hello {{ user }} on linux, {{project_name}}"
+        );
+    }
+
+    #[test]
+    fn rejects_project_variables_in_global_scope() {
+        let err = render_prompt_template(
+            "root",
+            "{{project_name}}",
+            &HashMap::new(),
+            UnknownVariables::Reject,
+            library,
+        )
+        .expect_err("project-only variable");
+        assert!(err.to_string().contains("仅在项目级提示词中可用"));
+    }
+
+    #[test]
+    fn detects_include_cycles() {
+        let err = render_prompt_template(
+            "root",
+            "{{> a}}",
+            &vars(),
+            UnknownVariables::Reject,
+            library,
+        )
+        .expect_err("cycle");
+        assert!(err.to_string().contains("root -> a -> b -> a"));
+
+        let err = render_prompt_template(
+            "root",
+            "{{> self}}",
+            &vars(),
+            UnknownVariables::Reject,
+            library,
+        )
+        .expect_err("self include");
+        assert!(err.to_string().contains("循环引入"));
+    }
+
+    #[test]
+    fn rejects_missing_includes() {
+        let err = render_prompt_template(
+            "root",
+            "{{> missing}}",
+            &vars(),
+            UnknownVariables::Reject,
+            library,
+        )
+        .expect_err("missing include");
+        assert!(err.to_string().contains("missing"));
+    }
+}
@@ -106,6 +106,10 @@ pub struct UsageScript {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "autoQueryInterval")]
     pub auto_query_interval: Option<u64>,
+    /// 低余额提醒阈值（任一套餐剩余额度低于该值时通知并在托盘标记）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "lowBalanceThreshold")]
+    pub low_balance_threshold: Option<f64>,
 }
 
 /// 用量数据
@@ -132,6 +136,26 @@ pub struct UsageData {
     pub unit: Option<String>,
 }
 
+/// 用量快照（单个套餐在某次查询时的用量）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageSnapshot {
+    #[serde(rename = "planName")]
+    pub plan_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub used: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "isValid")]
+    pub is_valid: Option<bool>,
+    #[serde(rename = "capturedAt")]
+    pub captured_at: i64,
+}
+
 /// 用量查询结果（支持多套餐）
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UsageResult {
@@ -140,6 +164,9 @@ pub struct UsageResult {
     pub data: Option<Vec<UsageData>>, // 支持返回多个套餐
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// 脚本 console 输出
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub logs: Vec<String>,
 }
 
 /// 供应商单独的模型测试配置
@@ -206,9 +233,11 @@ fn default_javascript_language() -> String {
     "javascript".to_string()
 }
 
-/// 请求重写脚本配置（onRequest）
+/// 请求重写脚本配置（onRequest / onResponse / onStreamEvent）
 ///
-/// 目前仅用于 Codex 供应商，用于重写请求/响应（headers/body/status）以适配上游/代理限制。
+/// 用于重写请求/响应（headers/body/status）以适配上游/代理限制。
+/// 可配置在供应商（`ProviderMeta.request_hook_script`）、应用级与全局（settings 表），
+/// 代理转发时按 全局 → 应用 → 供应商 的顺序链式执行。
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RequestHookScript {
     /// 是否启用
@@ -219,9 +248,77 @@ pub struct RequestHookScript {
     pub language: String,
     /// 脚本代码（需要 eval 成对象，可包含 onRequest/onResponse）
     pub code: String,
-    /// 预留：执行超时（毫秒）
+    /// 执行超时（毫秒，默认 1000，超时后脚本被中断并按失败处理）
     #[serde(rename = "timeoutMs", skip_serializing_if = "Option::is_none")]
     pub timeout_ms: Option<u64>,
+    /// onStreamEvent 单个 SSE 事件的执行预算（毫秒，默认 50，超出时原样透传该事件）
+    #[serde(
+        rename = "streamEventBudgetMs",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub stream_event_budget_ms: Option<u64>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// API Key 轮换策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyRotationStrategy {
+    /// 依次轮流使用
+    #[default]
+    RoundRobin,
+    /// 优先使用请求次数最少的 Key
+    LeastUsed,
+    /// 按顺序使用，遇到 401/429 时才切换到下一个 Key
+    Failover,
+}
+
+/// Key 池中的单个 API Key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyEntry {
+    /// Key 标识（用于熔断器与请求日志归因，不包含密钥本身）
+    pub id: String,
+    /// API Key
+    pub key: String,
+    /// 备注名称
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// 是否参与轮换
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+/// 供应商 API Key 池（同一端点的多个 Key）
+///
+/// 启用后代理按策略为每个请求选择一个 Key，覆盖 settingsConfig 中的 Key；
+/// 池中所有 Key 都不可用时回退到 settingsConfig 中的 Key。
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ApiKeyPool {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 轮换策略
+    #[serde(default)]
+    pub strategy: ApiKeyRotationStrategy,
+    /// Key 列表（顺序即 failover 策略下的优先级）
+    #[serde(default)]
+    pub keys: Vec<ApiKeyEntry>,
+}
+
+impl ApiKeyPool {
+    /// 返回参与轮换的 Key
+    pub fn active_keys(&self) -> Vec<&ApiKeyEntry> {
+        if !self.enabled {
+            return Vec::new();
+        }
+        self.keys
+            .iter()
+            .filter(|k| k.enabled && !k.key.trim().is_empty())
+            .collect()
+    }
 }
 
 /// 供应商元数据
@@ -277,6 +374,9 @@ pub struct ProviderMeta {
     /// 请求重写脚本（onRequest，脚本式修改 headers/body）
     #[serde(rename = "requestHookScript", skip_serializing_if = "Option::is_none")]
     pub request_hook_script: Option<RequestHookScript>,
+    /// API Key 池（多 Key 轮换）
+    #[serde(rename = "apiKeyPool", skip_serializing_if = "Option::is_none")]
+    pub api_key_pool: Option<ApiKeyPool>,
 }
 
 impl ProviderManager {
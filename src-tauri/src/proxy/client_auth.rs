@@ -0,0 +1,304 @@
+//! 客户端访问令牌
+//!
+//! 代理监听非回环地址（如 `0.0.0.0`）时，局域网内的任何人都能通过代理消耗上游 Key。
+//! 这里提供按客户端发放的访问令牌：
+//! - 令牌只在创建时返回一次，数据库中仅保存 SHA-256 哈希
+//! - 客户端通过 `Authorization: Bearer`、`x-api-key` 或 `x-goog-api-key` 携带令牌
+//!   （即把令牌配置为客户端的 API Key）
+//! - 每个令牌可限制允许访问的应用与供应商，请求日志按令牌归因
+//!
+//! 回环地址的请求未携带有效令牌时按匿名本机客户端放行（接管模式下本机客户端使用占位 Key），
+//! 非回环地址的请求必须携带有效令牌。
+
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::{server::ProxyState, ProxyError};
+use crate::app_config::AppType;
+
+/// 令牌前缀（便于识别与泄露扫描）
+const TOKEN_PREFIX: &str = "ccs-";
+
+/// 展示用的令牌前缀长度（含 `ccs-`）
+const DISPLAY_PREFIX_LEN: usize = 12;
+
+/// 客户端访问令牌（不含明文）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientToken {
+    pub id: String,
+    pub name: String,
+    /// 令牌开头若干字符（用于在界面中辨认）
+    pub token_prefix: String,
+    /// 允许访问的应用（为空表示不限制）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_apps: Option<Vec<String>>,
+    /// 允许使用的供应商 ID（为空表示不限制）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_providers: Option<Vec<String>>,
+    pub enabled: bool,
+    pub created_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<i64>,
+}
+
+impl ClientToken {
+    /// 是否允许访问指定应用
+    pub fn allows_app(&self, app_type: &str) -> bool {
+        self.allowed_apps
+            .as_ref()
+            .is_none_or(|apps| apps.iter().any(|app| app == app_type))
+    }
+}
+
+/// 新建令牌的结果（明文令牌只在此返回一次）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedClientToken {
+    pub token: String,
+    pub info: ClientToken,
+}
+
+/// 通过认证的客户端（由中间件写入请求扩展）
+#[derive(Debug, Clone)]
+pub struct ClientIdentity {
+    pub token_id: String,
+    pub name: String,
+    pub allowed_providers: Option<Vec<String>>,
+}
+
+impl ClientIdentity {
+    /// 是否允许使用指定供应商
+    pub fn allows_provider(&self, provider_id: &str) -> bool {
+        self.allowed_providers
+            .as_ref()
+            .is_none_or(|providers| providers.iter().any(|id| id == provider_id))
+    }
+}
+
+/// 生成新的明文令牌，返回 (令牌, 展示前缀)
+pub fn generate_token() -> (String, String) {
+    let token = format!(
+        "{TOKEN_PREFIX}{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    );
+    let prefix = token[..DISPLAY_PREFIX_LEN].to_string();
+    (token, prefix)
+}
+
+/// 计算令牌哈希（十六进制 SHA-256）
+pub fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// 监听地址是否为回环地址（无法解析的主机名按非回环处理）
+pub fn is_loopback_address(address: &str) -> bool {
+    if address.eq_ignore_ascii_case("localhost") {
+        return true;
+    }
+    address
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+        .is_ok_and(|ip| ip.is_loopback())
+}
+
+/// 跨域请求的来源（`Origin` 头，如 `http://localhost:5173`）是否为本机页面
+pub fn is_loopback_origin(origin: &str) -> bool {
+    let Some((_, rest)) = origin.split_once("://") else {
+        return false;
+    };
+    let authority = rest.split('/').next().unwrap_or_default();
+    let host = match authority.strip_prefix('[') {
+        Some(v6) => v6.split(']').next().unwrap_or_default(),
+        None => authority.split(':').next().unwrap_or_default(),
+    };
+    is_loopback_address(host)
+}
+
+/// 从请求头中取出客户端携带的令牌
+fn presented_token(headers: &HeaderMap) -> Option<&str> {
+    let bearer = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| {
+            v.strip_prefix("Bearer ")
+                .or_else(|| v.strip_prefix("bearer "))
+        });
+    bearer
+        .into_iter()
+        .chain(
+            ["x-api-key", "x-goog-api-key"]
+                .into_iter()
+                .filter_map(|name| headers.get(name).and_then(|v| v.to_str().ok())),
+        )
+        .map(str::trim)
+        .find(|token| token.starts_with(TOKEN_PREFIX))
+}
+
+/// 根据请求路径判断目标应用
+fn app_for_request(path: &str, headers: &HeaderMap) -> Option<AppType> {
+    if path.starts_with("/claude/") || path.starts_with("/v1/messages") {
+        Some(AppType::Claude)
+    } else if path.starts_with("/gemini/")
+        || path.starts_with("/v1beta/")
+        || path.starts_with("/v1internal")
+    {
+        Some(AppType::Gemini)
+    } else if path == "/v1/models" && headers.contains_key("anthropic-version") {
+        Some(AppType::Claude)
+    } else if path.starts_with("/codex/")
+        || path.starts_with("/v1/")
+        || path.starts_with("/responses")
+        || path.starts_with("/chat/completions")
+        || path == "/models"
+    {
+        Some(AppType::Codex)
+    } else {
+        None
+    }
+}
+
+/// 客户端认证中间件（挂载在所有 API 路由上）
+pub async fn require_client_token(
+    State(state): State<ProxyState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let token = match presented_token(request.headers()) {
+        Some(token) => match state.db.find_client_token_by_hash(&hash_token(token)) {
+            Ok(token) => token.filter(|t| t.enabled),
+            Err(e) => {
+                log::error!("[ClientAuth] 读取访问令牌失败: {e}");
+                return ProxyError::DatabaseError(e.to_string()).into_response();
+            }
+        },
+        None => None,
+    };
+
+    let Some(token) = token else {
+        if peer.ip().is_loopback() {
+            return next.run(request).await;
+        }
+        log::warn!(
+            "[ClientAuth] 拒绝来自 {} 的未认证请求: {}",
+            peer.ip(),
+            request.uri().path()
+        );
+        return ProxyError::AuthError("缺少有效的访问令牌".to_string()).into_response();
+    };
+
+    if let Some(app_type) = app_for_request(request.uri().path(), request.headers()) {
+        if !token.allows_app(app_type.as_str()) {
+            log::warn!(
+                "[ClientAuth] 令牌 {} 无权访问 {}",
+                token.name,
+                app_type.as_str()
+            );
+            return ProxyError::Forbidden(format!("访问令牌不允许访问 {}", app_type.as_str()))
+                .into_response();
+        }
+    }
+
+    if let Err(e) = state.db.touch_client_token(&token.id) {
+        log::debug!("[ClientAuth] 更新令牌使用时间失败: {e}");
+    }
+
+    request.extensions_mut().insert(ClientIdentity {
+        token_id: token.id,
+        name: token.name,
+        allowed_providers: token.allowed_providers,
+    });
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_tokens_are_unique_and_hashed() {
+        let (a, prefix) = generate_token();
+        let (b, _) = generate_token();
+        assert_ne!(a, b);
+        assert!(a.starts_with(TOKEN_PREFIX));
+        assert!(a.starts_with(&prefix));
+        assert_eq!(hash_token(&a).len(), 64);
+        assert_eq!(hash_token(&a), hash_token(&a));
+        assert_ne!(hash_token(&a), hash_token(&b));
+    }
+
+    #[test]
+    fn loopback_origins_are_detected() {
+        assert!(is_loopback_origin("http://localhost:5173"));
+        assert!(is_loopback_origin("http://127.0.0.1"));
+        assert!(is_loopback_origin("http://[::1]:8080"));
+        assert!(is_loopback_origin("tauri://localhost"));
+        assert!(!is_loopback_origin("https://example.com"));
+        assert!(!is_loopback_origin("http://localhost.example.com"));
+        assert!(!is_loopback_origin("http://192.168.1.10:3000"));
+        assert!(!is_loopback_origin("null"));
+    }
+
+    #[test]
+    fn token_is_read_from_auth_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", "PROXY_MANAGED".parse().unwrap());
+        assert_eq!(presented_token(&headers), None);
+
+        headers.insert("authorization", "Bearer ccs-abc".parse().unwrap());
+        assert_eq!(presented_token(&headers), Some("ccs-abc"));
+
+        let mut gemini = HeaderMap::new();
+        gemini.insert("x-goog-api-key", "ccs-gem".parse().unwrap());
+        assert_eq!(presented_token(&gemini), Some("ccs-gem"));
+    }
+
+    #[test]
+    fn request_paths_map_to_apps() {
+        let empty = HeaderMap::new();
+        assert_eq!(
+            app_for_request("/v1/messages", &empty),
+            Some(AppType::Claude)
+        );
+        assert_eq!(
+            app_for_request("/v1/chat/completions", &empty),
+            Some(AppType::Codex)
+        );
+        assert_eq!(
+            app_for_request("/v1internal:generateContent", &empty),
+            Some(AppType::Gemini)
+        );
+        assert_eq!(app_for_request("/v1/models", &empty), Some(AppType::Codex));
+
+        let mut anthropic = HeaderMap::new();
+        anthropic.insert("anthropic-version", "2023-06-01".parse().unwrap());
+        assert_eq!(
+            app_for_request("/v1/models", &anthropic),
+            Some(AppType::Claude)
+        );
+    }
+
+    #[test]
+    fn loopback_detection() {
+        assert!(is_loopback_address("127.0.0.1"));
+        assert!(is_loopback_address("localhost"));
+        assert!(is_loopback_address("::1"));
+        assert!(is_loopback_address("[::1]"));
+        assert!(!is_loopback_address("0.0.0.0"));
+        assert!(!is_loopback_address("192.168.1.10"));
+    }
+}
@@ -6,15 +6,37 @@
 //! - 托盘菜单更新
 //! - 前端事件发射
 //! - Live 备份更新
+//! - 切换历史记录
+//! - 主供应商恢复后的自动切回（按应用配置）
 
 use crate::database::Database;
 use crate::error::AppError;
-use std::collections::HashSet;
+use crate::proxy::provider_router::ProviderRouter;
+use crate::proxy::types::FailoverTransitionKind;
+use crate::services::stream_check::StreamCheckService;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tauri::{Emitter, Manager};
 use tokio::sync::RwLock;
 
+/// 自动切回探测的最小间隔（秒），避免配置过小导致频繁请求主供应商
+const MIN_FAILBACK_PROBE_INTERVAL_SECS: u64 = 10;
+
+/// 等待切回的主供应商
+#[derive(Debug, Clone)]
+struct FailbackWatch {
+    /// 故障转移前的主供应商
+    primary_id: String,
+    /// 当前使用的备用供应商
+    backup_id: String,
+    /// 最近一次故障转移的时间
+    since: Instant,
+}
+
 /// 故障转移切换管理器
 ///
 /// 负责处理故障转移成功后的供应商切换，确保 UI 能够直观反映当前使用的供应商。
@@ -22,6 +44,10 @@ use tokio::sync::RwLock;
 pub struct FailoverSwitchManager {
     /// 正在处理中的切换（key = "app_type:provider_id"）
     pending_switches: Arc<RwLock<HashSet<String>>>,
+    /// 等待自动切回的应用（key = app_type）
+    failback_watches: Arc<RwLock<HashMap<String, FailbackWatch>>>,
+    /// 共享的 ProviderRouter，切回前用于重置主供应商的熔断器
+    provider_router: Option<Arc<ProviderRouter>>,
     db: Arc<Database>,
 }
 
@@ -29,10 +55,18 @@ impl FailoverSwitchManager {
     pub fn new(db: Arc<Database>) -> Self {
         Self {
             pending_switches: Arc::new(RwLock::new(HashSet::new())),
+            failback_watches: Arc::new(RwLock::new(HashMap::new())),
+            provider_router: None,
             db,
         }
     }
 
+    /// 关联共享的 ProviderRouter
+    pub fn with_provider_router(mut self, provider_router: Arc<ProviderRouter>) -> Self {
+        self.provider_router = Some(provider_router);
+        self
+    }
+
     /// 尝试执行故障转移切换
     ///
     /// 如果相同的切换已在进行中，则跳过；否则执行切换逻辑。
@@ -47,6 +81,27 @@ impl FailoverSwitchManager {
         app_type: &str,
         provider_id: &str,
         provider_name: &str,
+    ) -> Result<bool, AppError> {
+        self.try_switch_with_kind(
+            app_handle,
+            app_type,
+            provider_id,
+            provider_name,
+            FailoverTransitionKind::Failover,
+            None,
+        )
+        .await
+    }
+
+    /// 尝试执行指定类型的切换，并记录切换历史
+    pub async fn try_switch_with_kind(
+        &self,
+        app_handle: Option<&tauri::AppHandle>,
+        app_type: &str,
+        provider_id: &str,
+        provider_name: &str,
+        kind: FailoverTransitionKind,
+        detail: Option<&str>,
     ) -> Result<bool, AppError> {
         let switch_key = format!("{app_type}:{provider_id}");
 
@@ -62,7 +117,14 @@ impl FailoverSwitchManager {
 
         // 执行切换（确保最后清理 pending 标记）
         let result = self
-            .do_switch(app_handle, app_type, provider_id, provider_name)
+            .do_switch(
+                app_handle,
+                app_type,
+                provider_id,
+                provider_name,
+                kind,
+                detail,
+            )
             .await;
 
         // 清理 pending 标记
@@ -80,6 +142,8 @@ impl FailoverSwitchManager {
         app_type: &str,
         provider_id: &str,
         provider_name: &str,
+        kind: FailoverTransitionKind,
+        detail: Option<&str>,
     ) -> Result<bool, AppError> {
         // 检查该应用是否已被代理接管（enabled=true）
         // 只有被接管的应用才允许执行故障转移切换
@@ -96,7 +160,25 @@ impl FailoverSwitchManager {
             return Ok(false);
         }
 
-        log::info!("[FO-001] 切换: {app_type} → {provider_name}");
+        log::info!("[FO-001] 切换({kind}): {app_type} → {provider_name}");
+
+        // 记录切换前的供应商（用于历史和自动切回）
+        let previous = self
+            .db
+            .get_current_provider(app_type)
+            .ok()
+            .flatten()
+            .filter(|id| id != provider_id)
+            .map(|id| {
+                let name = self
+                    .db
+                    .get_provider_by_id(&id, app_type)
+                    .ok()
+                    .flatten()
+                    .map(|p| p.name)
+                    .unwrap_or_else(|| id.clone());
+                (id, name)
+            });
 
         // 1. 更新数据库 is_current
         self.db.set_current_provider(app_type, provider_id)?;
@@ -106,7 +188,32 @@ impl FailoverSwitchManager {
             .map_err(|_| AppError::Message(format!("无效的应用类型: {app_type}")))?;
         crate::settings::set_current_provider(&app_type_enum, Some(provider_id))?;
 
-        // 3. 更新托盘菜单和发射事件
+        // 3. 记录切换历史，并维护自动切回状态
+        if let Some((from_id, from_name)) = &previous {
+            if let Err(e) = self.db.insert_failover_transition(
+                app_type,
+                kind,
+                Some((from_id, from_name)),
+                provider_id,
+                provider_name,
+                detail,
+            ) {
+                log::warn!("[Failover] 记录切换历史失败: {e}");
+            }
+        }
+        match kind {
+            FailoverTransitionKind::Failover => {
+                if let Some((from_id, _)) = &previous {
+                    self.watch_for_failback(app_handle, app_type, from_id, provider_id)
+                        .await;
+                }
+            }
+            FailoverTransitionKind::Failback | FailoverTransitionKind::Recovery => {
+                self.failback_watches.write().await.remove(app_type);
+            }
+        }
+
+        // 4. 更新托盘菜单和发射事件
         if let Some(app) = app_handle {
             // 更新托盘菜单
             if let Some(app_state) = app.try_state::<crate::store::AppState>() {
@@ -135,7 +242,7 @@ impl FailoverSwitchManager {
             let event_data = serde_json::json!({
                 "appType": app_type,
                 "providerId": provider_id,
-                "source": "failover"  // 标识来源是故障转移
+                "source": kind.as_str()  // 标识来源：failover / failback / recovery
             });
             if let Err(e) = app.emit("provider-switched", event_data) {
                 log::error!("[Failover] 发射事件失败: {e}");
@@ -144,4 +251,237 @@ impl FailoverSwitchManager {
 
         Ok(true)
     }
+
+    // ========== 自动切回 ==========
+
+    /// 故障转移后登记主供应商，按配置启动自动切回监控
+    ///
+    /// 连续故障转移（A → B → C）时仍以最初的 A 作为主供应商。
+    async fn watch_for_failback(
+        &self,
+        app_handle: Option<&tauri::AppHandle>,
+        app_type: &str,
+        from_id: &str,
+        to_id: &str,
+    ) {
+        let enabled = self
+            .db
+            .get_failback_config(app_type)
+            .map(|config| config.enabled)
+            .unwrap_or(false);
+        if !enabled {
+            return;
+        }
+
+        let spawn_monitor = {
+            let mut watches = self.failback_watches.write().await;
+            match watches.get_mut(app_type) {
+                Some(watch) if watch.primary_id == to_id => {
+                    // 已回到主供应商，无需继续监控
+                    watches.remove(app_type);
+                    false
+                }
+                Some(watch) => {
+                    watch.backup_id = to_id.to_string();
+                    watch.since = Instant::now();
+                    false
+                }
+                None => {
+                    watches.insert(
+                        app_type.to_string(),
+                        FailbackWatch {
+                            primary_id: from_id.to_string(),
+                            backup_id: to_id.to_string(),
+                            since: Instant::now(),
+                        },
+                    );
+                    true
+                }
+            }
+        };
+
+        if spawn_monitor {
+            log::info!("[Failback] {app_type} 开始监控主供应商 {from_id} 的恢复情况");
+            self.spawn_failback_monitor(app_handle.cloned(), app_type.to_string());
+        }
+    }
+
+    /// 在后台启动自动切回监控
+    ///
+    /// 监控循环最终会再次调用切换逻辑，这里显式装箱为 `Send` future 以打断异步调用环。
+    fn spawn_failback_monitor(&self, app_handle: Option<tauri::AppHandle>, app_type: String) {
+        let manager = self.clone();
+        let monitor: Pin<Box<dyn Future<Output = ()> + Send>> = Box::pin(async move {
+            manager.run_failback_monitor(app_handle, app_type).await;
+        });
+        tokio::spawn(monitor);
+    }
+
+    /// 自动切回监控循环
+    ///
+    /// 满足任一条件即切回主供应商：
+    /// - 主供应商连续通过 `probe_successes` 次健康探测
+    /// - 距最近一次故障转移超过 `cooldown_seconds`，且本轮健康探测通过
+    ///
+    /// 当前供应商被手动改为其他供应商、代理接管关闭或切回功能关闭时，停止监控。
+    async fn run_failback_monitor(&self, app_handle: Option<tauri::AppHandle>, app_type: String) {
+        let mut consecutive_successes = 0u32;
+
+        loop {
+            let interval = self
+                .db
+                .get_failback_config(&app_type)
+                .map(|config| config.probe_interval_seconds as u64)
+                .unwrap_or_default()
+                .max(MIN_FAILBACK_PROBE_INTERVAL_SECS);
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+
+            let Some(watch) = self.failback_watches.read().await.get(&app_type).cloned() else {
+                return;
+            };
+
+            let config = self.db.get_failback_config(&app_type).unwrap_or_default();
+            let proxy_enabled = self
+                .db
+                .get_proxy_config_for_app(&app_type)
+                .await
+                .map(|c| c.enabled)
+                .unwrap_or(false);
+            let still_on_backup = self
+                .db
+                .get_current_provider(&app_type)
+                .ok()
+                .flatten()
+                .as_deref()
+                == Some(watch.backup_id.as_str());
+
+            if !config.enabled || !proxy_enabled || !still_on_backup {
+                log::info!(
+                    "[Failback] {app_type} 停止监控主供应商 {}",
+                    watch.primary_id
+                );
+                self.failback_watches.write().await.remove(&app_type);
+                return;
+            }
+
+            let cooldown_expired = config.cooldown_seconds > 0
+                && watch.since.elapsed() >= Duration::from_secs(config.cooldown_seconds as u64);
+
+            if self.probe_provider(&app_type, &watch.primary_id).await {
+                consecutive_successes += 1;
+            } else {
+                consecutive_successes = 0;
+            }
+            let Some(detail) = failback_reason(
+                config.probe_successes,
+                config.cooldown_seconds,
+                cooldown_expired,
+                consecutive_successes,
+            ) else {
+                continue;
+            };
+
+            // 探测已证明主供应商可用，重置其熔断器，避免切回后请求仍被熔断器拦截
+            if let Some(router) = &self.provider_router {
+                router
+                    .reset_provider_breaker(&watch.primary_id, &app_type)
+                    .await;
+            }
+
+            let primary_name = self
+                .db
+                .get_provider_by_id(&watch.primary_id, &app_type)
+                .ok()
+                .flatten()
+                .map(|p| p.name)
+                .unwrap_or_else(|| watch.primary_id.clone());
+
+            log::info!("[Failback] {app_type} 切回主供应商 {primary_name}（{detail}）");
+            self.failback_watches.write().await.remove(&app_type);
+            if let Err(e) = self
+                .try_switch_with_kind(
+                    app_handle.as_ref(),
+                    &app_type,
+                    &watch.primary_id,
+                    &primary_name,
+                    FailoverTransitionKind::Failback,
+                    Some(&detail),
+                )
+                .await
+            {
+                log::error!("[Failback] 自动切回失败: {e}");
+            }
+            return;
+        }
+    }
+
+    /// 对主供应商执行一次健康探测（不重试），并记录到流式检查日志
+    async fn probe_provider(&self, app_type: &str, provider_id: &str) -> bool {
+        let Ok(app) = crate::app_config::AppType::from_str(app_type) else {
+            return false;
+        };
+        let Ok(Some(provider)) = self.db.get_provider_by_id(provider_id, app_type) else {
+            return false;
+        };
+
+        let mut config = self.db.get_stream_check_config().unwrap_or_default();
+        config.max_retries = 0;
+
+        match StreamCheckService::check_with_retry(&app, &provider, &config).await {
+            Ok(result) => {
+                let _ =
+                    self.db
+                        .save_stream_check_log(provider_id, &provider.name, app_type, &result);
+                log::debug!(
+                    "[Failback] 探测 {app_type}/{provider_id}: success={}",
+                    result.success
+                );
+                result.success
+            }
+            Err(e) => {
+                log::debug!("[Failback] 探测 {app_type}/{provider_id} 失败: {e}");
+                false
+            }
+        }
+    }
+}
+
+/// 判断本轮探测后是否切回主供应商，返回切回原因
+///
+/// 主供应商本轮探测失败时不切回（即使冷却时间已到），避免切回后立即再次故障转移。
+/// 已保存的 0 次探测配置按至少 1 次处理。
+fn failback_reason(
+    probe_successes: u32,
+    cooldown_seconds: u32,
+    cooldown_expired: bool,
+    consecutive_successes: u32,
+) -> Option<String> {
+    if consecutive_successes == 0 {
+        return None;
+    }
+    if consecutive_successes >= probe_successes.max(1) {
+        return Some(format!("连续 {consecutive_successes} 次健康探测通过"));
+    }
+    cooldown_expired.then(|| format!("冷却时间 {cooldown_seconds} 秒已到且健康探测通过"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cooldown_failback_requires_a_passing_probe() {
+        // 冷却时间已到但主供应商仍故障：不切回
+        assert_eq!(failback_reason(3, 60, true, 0), None);
+        // 冷却时间已到且本轮探测通过：切回
+        assert!(
+            failback_reason(3, 60, true, 1).is_some_and(|detail| detail.contains("冷却时间 60 秒"))
+        );
+        // 冷却时间未到：需连续通过配置的次数
+        assert_eq!(failback_reason(3, 60, false, 2), None);
+        assert!(failback_reason(3, 60, false, 3).is_some_and(|detail| detail.contains("连续 3 次")));
+        // 0 次按 1 次处理
+        assert!(failback_reason(0, 0, false, 1).is_some());
+        assert_eq!(failback_reason(0, 0, false, 0), None);
+    }
 }
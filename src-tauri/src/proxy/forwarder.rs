@@ -7,23 +7,107 @@ use super::{
     error::*,
     failover_switch::FailoverSwitchManager,
     header_filter::is_header_blacklisted,
+    key_pool::{is_key_rejection, SelectedApiKey},
+    otel::{RequestTrace, Span},
     provider_router::ProviderRouter,
-    providers::{get_adapter, ProviderAdapter, ProviderType},
+    providers::{ensure_fresh_access_token, get_adapter, ProviderAdapter, ProviderType},
+    routing_override::replace_model_in_path,
     thinking_rectifier::{rectify_anthropic_request, should_rectify_thinking_signature},
+    token_estimator::estimate_input_tokens,
     types::{ProxyStatus, RectifierConfig},
     ProxyError,
 };
 use crate::request_hook_script::{
     apply_query_string_map_to_url, build_header_string_map, build_query_string_map_from_url,
-    execute_on_request_script, HookRequest, RequestHookContext, RequestHookProviderInfo,
+    execute_on_request_script, hook_chain, HookRequest, RequestHookContext,
+    RequestHookProviderInfo, ScopedHookScript,
 };
 use crate::{app_config::AppType, provider::Provider};
+use futures::StreamExt;
 use reqwest::Response;
 use serde_json::Value;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
+/// 依次执行请求重写脚本链（onRequest）
+///
+/// 每个脚本的输入是上一个脚本的输出；单个脚本失败或输出无效时忽略该脚本，继续执行后续脚本。
+/// 返回 `None` 表示没有脚本修改请求，否则返回 (URL, 请求体, 发往上游的 headers)。
+fn apply_request_hooks(
+    hooks: &[ScopedHookScript],
+    tag: &str,
+    context: &RequestHookContext,
+    mut view: HookRequest,
+) -> Option<(String, Value, axum::http::HeaderMap)> {
+    let mut output = None;
+
+    for hook in hooks {
+        let prefix = format!("[{tag}] [Hook:{}]", hook.scope.as_str());
+        let run =
+            execute_on_request_script(&hook.script.code, hook.script.timeout_ms, context, &view);
+        run.log_console(&prefix);
+        let mut new_view = match run.result {
+            Ok(Some(v)) => v,
+            // undefined/null：放行（不修改）
+            Ok(None) => continue,
+            Err(e) => {
+                log::warn!("{prefix} 请求重写脚本执行失败，已忽略: {e}");
+                continue;
+            }
+        };
+
+        // 再次过滤私有参数，防止脚本重新注入 _ 前缀字段泄露
+        new_view.body = filter_private_params_with_whitelist(new_view.body, &[]);
+
+        // 应用 header 黑名单边界
+        new_view
+            .headers
+            .retain(|k, _| !is_header_blacklisted(k.as_str()));
+
+        let url = match apply_query_string_map_to_url(&context.url, &new_view.queries) {
+            Ok(v) => v,
+            Err(e) => {
+                log::warn!("{prefix} 请求重写脚本输出 query 无效，已忽略: {e}");
+                continue;
+            }
+        };
+
+        // 构建将要发往上游的 HeaderMap
+        let mut upstream_headers = axum::http::HeaderMap::new();
+        let mut build_failed = None;
+        for (k, v) in &new_view.headers {
+            let name = match axum::http::HeaderName::from_bytes(k.as_bytes()) {
+                Ok(n) => n,
+                Err(e) => {
+                    build_failed = Some(format!("非法 header name: {k} ({e})"));
+                    break;
+                }
+            };
+            let value = match axum::http::HeaderValue::from_str(v) {
+                Ok(v) => v,
+                Err(e) => {
+                    build_failed = Some(format!("非法 header value: {k}={v} ({e})"));
+                    break;
+                }
+            };
+            upstream_headers.insert(name, value);
+        }
+
+        if let Some(err) = build_failed {
+            log::warn!("{prefix} 请求重写脚本输出无效，已忽略: {err}");
+            continue;
+        }
+
+        output = Some((url, new_view.body.clone(), upstream_headers));
+        view = new_view;
+    }
+
+    output
+}
+
 /// 应用重写规则到 JSON 对象
 /// path 支持点分隔（如 "text.verbosity"），value 为 None 表示删除该字段，Some(v) 表示覆盖
 fn apply_rewrite_rule(obj: &mut serde_json::Map<String, Value>, path: &str, value: Option<Value>) {
@@ -53,6 +137,7 @@ fn apply_rewrite_rule(obj: &mut serde_json::Map<String, Value>, path: &str, valu
     }
 }
 
+#[derive(Debug)]
 pub struct ForwardResult {
     pub response: Response,
     pub provider: Provider,
@@ -60,11 +145,192 @@ pub struct ForwardResult {
     pub _original_model: Option<String>,
     /// 实际发送到上游的模型名（映射后）
     pub mapped_model: Option<String>,
+    /// 使用的 Key 池 Key 标识（未使用 Key 池时为 None）
+    pub api_key_id: Option<String>,
+    /// 对冲请求中被取消的一方（未发生对冲时为 None）
+    pub hedge_loser: Option<HedgeLoser>,
+}
+
+/// 对冲请求中被取消的一方（用于记录请求日志）
+#[derive(Debug)]
+pub struct HedgeLoser {
+    pub provider: Provider,
+    /// 使用的 Key 池 Key 标识
+    pub api_key_id: Option<String>,
+    /// 实际发送到上游的模型名（上游未返回响应头时为 None）
+    pub mapped_model: Option<String>,
+    /// 估算的输入 Token 数：上游已返回响应头（已开始处理请求）时按请求体估算，否则为 0
+    pub estimated_input_tokens: u32,
+    /// 从发出请求到被取消的耗时（毫秒）
+    pub latency_ms: u64,
+}
+
+/// 故障转移主循环的行为选项
+#[derive(Clone, Copy)]
+struct FailoverOptions {
+    /// 跳过熔断器放行检查（单 Provider 场景）
+    bypass_circuit_breaker: bool,
+    /// 成功的供应商不是请求开始时的当前供应商时，将其同步为当前供应商（更新 UI/托盘）
+    sync_current_provider: bool,
+    /// 所有供应商都失败时计入失败统计
+    count_final_failure: bool,
+}
+
+/// 对冲中的单路请求：只有一个供应商，由对冲逻辑决定胜负与失败统计，不切换当前供应商
+const HEDGE_LEG_OPTIONS: FailoverOptions = FailoverOptions {
+    bypass_circuit_breaker: false,
+    sync_current_provider: false,
+    count_final_failure: false,
+};
+
+/// 对冲中的单路请求状态
+struct HedgeLeg<'a> {
+    provider: &'a Provider,
+    started_at: Instant,
+    /// 上游返回响应头后记录 (Key 标识, 映射后模型)
+    accepted: OnceLock<(Option<String>, Option<String>)>,
+}
+
+impl<'a> HedgeLeg<'a> {
+    fn new(provider: &'a Provider) -> Self {
+        Self {
+            provider,
+            started_at: Instant::now(),
+            accepted: OnceLock::new(),
+        }
+    }
+
+    /// 生成被取消一方的日志信息
+    fn to_loser(&self, body: &Value) -> HedgeLoser {
+        let (api_key_id, mapped_model, estimated_input_tokens) = match self.accepted.get() {
+            Some((api_key_id, mapped_model)) => (
+                api_key_id.clone(),
+                mapped_model.clone(),
+                estimate_input_tokens(body).min(u32::MAX as u64) as u32,
+            ),
+            None => (None, None, 0),
+        };
+        HedgeLoser {
+            provider: self.provider.clone(),
+            api_key_id,
+            mapped_model,
+            estimated_input_tokens,
+            latency_ms: self.started_at.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+/// 单个供应商尝试的守卫：尝试在记录结果前被取消（如对冲中落后的一方被丢弃）时，
+/// 在后台中性归还 HalfOpen 探测名额并撤销已计入的请求数，
+/// 避免探测名额一直被占用（供应商在重启前都被拒绝）以及成功率被拉低。
+struct AttemptGuard {
+    router: Arc<ProviderRouter>,
+    status: Arc<RwLock<ProxyStatus>>,
+    provider_id: String,
+    app_type: String,
+    used_half_open_permit: bool,
+    /// 已计入 `total_requests`
+    counted: bool,
+    /// 结果尚未交给熔断器（记录或中性归还）
+    pending: bool,
+}
+
+impl AttemptGuard {
+    /// 结果即将交给熔断器记录，之后不再需要守卫
+    fn complete(&mut self) {
+        self.pending = false;
+    }
+}
+
+impl Drop for AttemptGuard {
+    fn drop(&mut self) {
+        if !self.pending || !(self.used_half_open_permit || self.counted) {
+            return;
+        }
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let router = self.router.clone();
+        let status = self.status.clone();
+        let provider_id = std::mem::take(&mut self.provider_id);
+        let app_type = std::mem::take(&mut self.app_type);
+        let used_half_open_permit = self.used_half_open_permit;
+        let counted = self.counted;
+        handle.spawn(async move {
+            router
+                .release_permit_neutral(&provider_id, &app_type, used_half_open_permit)
+                .await;
+            if counted {
+                let mut status = status.write().await;
+                status.total_requests = status.total_requests.saturating_sub(1);
+                status.success_rate = if status.total_requests > 0 {
+                    (status.success_requests as f32 / status.total_requests as f32) * 100.0
+                } else {
+                    0.0
+                };
+            }
+        });
+    }
+}
+
+/// Key 池 Key 的 HalfOpen 探测名额守卫：请求在记录 Key 结果前被取消
+/// （对冲中落后的一方被丢弃、客户端断开）时，在后台中性归还该 Key 的探测名额。
+struct KeyPermitGuard {
+    router: Arc<ProviderRouter>,
+    app_type: String,
+    provider_id: String,
+    key: Option<SelectedApiKey>,
 }
 
+impl KeyPermitGuard {
+    fn new(
+        router: &Arc<ProviderRouter>,
+        app_type: &str,
+        provider_id: &str,
+        key: Option<SelectedApiKey>,
+    ) -> Self {
+        Self {
+            router: router.clone(),
+            app_type: app_type.to_string(),
+            provider_id: provider_id.to_string(),
+            key,
+        }
+    }
+
+    fn key(&self) -> Option<&SelectedApiKey> {
+        self.key.as_ref()
+    }
+
+    /// 取出 Key，由调用方记录结果或中性归还名额
+    fn take(&mut self) -> Option<SelectedApiKey> {
+        self.key.take()
+    }
+}
+
+impl Drop for KeyPermitGuard {
+    fn drop(&mut self) {
+        let Some(key) = self.key.take().filter(|key| key.used_half_open_permit) else {
+            return;
+        };
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let router = self.router.clone();
+        let app_type = std::mem::take(&mut self.app_type);
+        let provider_id = std::mem::take(&mut self.provider_id);
+        handle.spawn(async move {
+            router
+                .release_key_permit_neutral(&app_type, &provider_id, &key)
+                .await;
+        });
+    }
+}
+
+#[derive(Debug)]
 pub struct ForwardError {
     pub error: ProxyError,
     pub provider: Option<Provider>,
+    pub api_key_id: Option<String>,
 }
 
 pub struct RequestForwarder {
@@ -82,6 +348,22 @@ pub struct RequestForwarder {
     rectifier_config: RectifierConfig,
     /// 非流式请求超时（秒）
     non_streaming_timeout: std::time::Duration,
+    /// 全局与应用级请求重写脚本
+    hook_scripts: Vec<ScopedHookScript>,
+    /// 链路追踪
+    trace: RequestTrace,
+    /// 本次请求实际发往上游的次数（含 Key 池换 Key 与整流重试）
+    upstream_attempts: AtomicUsize,
+    /// 本次请求实际尝试过的供应商 ID（按尝试顺序，含对冲中被取消的一方）
+    attempted_provider_ids: Mutex<Vec<String>>,
+    /// 请求头指定的上游模型（优先于供应商的模型映射）
+    model_override: Option<String>,
+    /// 流式首字超时（None 表示不限制，仅用于对冲时等待首字节）
+    streaming_first_byte_timeout: Option<Duration>,
+    /// 请求对冲等待时间（None 表示不对冲）
+    hedge_delay: Option<Duration>,
+    /// 成功的供应商不是当前供应商时是否同步为当前供应商（路由规则命中的请求不同步）
+    sync_current_provider: bool,
 }
 
 impl RequestForwarder {
@@ -94,9 +376,10 @@ impl RequestForwarder {
         failover_manager: Arc<FailoverSwitchManager>,
         app_handle: Option<tauri::AppHandle>,
         current_provider_id_at_start: String,
-        _streaming_first_byte_timeout: u64,
+        streaming_first_byte_timeout: u64,
         _streaming_idle_timeout: u64,
         rectifier_config: RectifierConfig,
+        hook_scripts: Vec<ScopedHookScript>,
     ) -> Self {
         Self {
             router,
@@ -107,9 +390,50 @@ impl RequestForwarder {
             current_provider_id_at_start,
             rectifier_config,
             non_streaming_timeout: std::time::Duration::from_secs(non_streaming_timeout),
+            hook_scripts,
+            trace: RequestTrace::disabled(),
+            upstream_attempts: AtomicUsize::new(0),
+            attempted_provider_ids: Mutex::new(Vec::new()),
+            model_override: None,
+            streaming_first_byte_timeout: (streaming_first_byte_timeout > 0)
+                .then(|| Duration::from_secs(streaming_first_byte_timeout)),
+            hedge_delay: None,
+            sync_current_provider: true,
         }
     }
 
+    /// 关联链路追踪
+    pub fn with_trace(mut self, trace: RequestTrace) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    /// 设置请求头指定的上游模型
+    pub fn with_model_override(mut self, model: Option<String>) -> Self {
+        self.model_override = model;
+        self
+    }
+
+    /// 启用请求对冲（仅对流式请求且故障转移链中至少有两个供应商时生效）
+    pub fn with_hedging(mut self, delay: Option<Duration>) -> Self {
+        self.hedge_delay = delay;
+        self
+    }
+
+    /// 设置是否把实际成功的供应商同步为当前供应商（默认同步）
+    pub fn with_current_provider_sync(mut self, enabled: bool) -> Self {
+        self.sync_current_provider = enabled;
+        self
+    }
+
+    /// 本次请求实际尝试过的供应商 ID（被熔断器拒绝的不计入）
+    pub fn attempted_provider_ids(&self) -> Vec<String> {
+        self.attempted_provider_ids
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
     /// 转发请求（带故障转移）
     ///
     /// # Arguments
@@ -119,12 +443,78 @@ impl RequestForwarder {
     /// * `headers` - 请求头
     /// * `providers` - 已选择的 Provider 列表（由 RequestContext 提供，避免重复调用 select_providers）
     pub async fn forward_with_retry(
+        &self,
+        app_type: &AppType,
+        endpoint: &str,
+        body: Value,
+        headers: axum::http::HeaderMap,
+        providers: Vec<Provider>,
+    ) -> Result<ForwardResult, ForwardError> {
+        let mut span = self.trace.span("forward_with_retry");
+        span.set_attribute("cc_switch.provider_count", providers.len());
+        let primary_provider_id = providers.first().map(|p| p.id.clone());
+
+        // 单 Provider 场景下跳过熔断器检查（故障转移关闭时）
+        let bypass_circuit_breaker = providers.len() == 1;
+        let hedge_delay = self
+            .hedge_delay
+            .filter(|_| providers.len() > 1 && is_streaming_request(&body, endpoint));
+
+        let result = match hedge_delay {
+            Some(delay) => {
+                self.forward_hedged(app_type, endpoint, body, headers, providers, delay)
+                    .await
+            }
+            None => {
+                let options = FailoverOptions {
+                    bypass_circuit_breaker,
+                    sync_current_provider: self.sync_current_provider,
+                    count_final_failure: true,
+                };
+                self.forward_with_failover(app_type, endpoint, body, headers, providers, options)
+                    .await
+            }
+        };
+
+        let attempts = self.upstream_attempts.load(Ordering::Relaxed);
+        span.set_attribute("cc_switch.upstream_attempts", attempts);
+        self.trace
+            .set_attribute("cc_switch.upstream_attempts", attempts);
+        match &result {
+            Ok(result) => {
+                let failover = primary_provider_id.as_deref() != Some(result.provider.id.as_str());
+                for (key, value) in [
+                    ("cc_switch.provider.id", result.provider.id.as_str()),
+                    ("cc_switch.provider.name", result.provider.name.as_str()),
+                ] {
+                    span.set_attribute(key, value);
+                    self.trace.set_attribute(key, value);
+                }
+                span.set_attribute("cc_switch.failover", failover);
+                self.trace.set_attribute("cc_switch.failover", failover);
+                if let Some(loser) = &result.hedge_loser {
+                    span.set_attribute(
+                        "cc_switch.hedge.cancelled_provider",
+                        loser.provider.id.as_str(),
+                    );
+                    self.trace.set_attribute("cc_switch.hedged", true);
+                }
+            }
+            Err(err) => span.set_error(err.error.to_string()),
+        }
+        span.end();
+        result
+    }
+
+    /// 依次尝试 Provider 列表直到成功（故障转移主循环）
+    async fn forward_with_failover(
         &self,
         app_type: &AppType,
         endpoint: &str,
         mut body: Value,
         headers: axum::http::HeaderMap,
         providers: Vec<Provider>,
+        options: FailoverOptions,
     ) -> Result<ForwardResult, ForwardError> {
         // 获取适配器
         let adapter = get_adapter(app_type);
@@ -134,24 +524,23 @@ impl RequestForwarder {
             return Err(ForwardError {
                 error: ProxyError::NoAvailableProvider,
                 provider: None,
+                api_key_id: None,
             });
         }
 
         let mut last_error = None;
         let mut last_provider = None;
+        let mut last_api_key_id = None;
         let mut attempted_providers = 0usize;
 
         // 整流器重试标记：确保整流最多触发一次
         let mut rectifier_retried = false;
 
-        // 单 Provider 场景下跳过熔断器检查（故障转移关闭时）
-        let bypass_circuit_breaker = providers.len() == 1;
-
         // 依次尝试每个供应商
         for provider in providers.iter() {
             // 发起请求前先获取熔断器放行许可（HalfOpen 会占用探测名额）
             // 单 Provider 场景下跳过此检查，避免熔断器阻塞所有请求
-            let (allowed, used_half_open_permit) = if bypass_circuit_breaker {
+            let (allowed, used_half_open_permit) = if options.bypass_circuit_breaker {
                 (true, false)
             } else {
                 let permit = self
@@ -166,6 +555,25 @@ impl RequestForwarder {
             }
 
             attempted_providers += 1;
+            {
+                let mut ids = self
+                    .attempted_provider_ids
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner());
+                if !ids.contains(&provider.id) {
+                    ids.push(provider.id.clone());
+                }
+            }
+            // 请求在记录结果前被取消时，由守卫归还探测名额并撤销请求计数
+            let mut attempt = AttemptGuard {
+                router: self.router.clone(),
+                status: self.status.clone(),
+                provider_id: provider.id.clone(),
+                app_type: app_type_str.to_string(),
+                used_half_open_permit,
+                counted: false,
+                pending: true,
+            };
 
             // 更新状态中的当前Provider信息
             {
@@ -173,16 +581,25 @@ impl RequestForwarder {
                 status.current_provider = Some(provider.name.clone());
                 status.current_provider_id = Some(provider.id.clone());
                 status.total_requests += 1;
+                attempt.counted = true;
                 status.last_request_at = Some(chrono::Utc::now().to_rfc3339());
             }
 
-            // 转发请求（每个 Provider 只尝试一次，重试由客户端控制）
-            match self
-                .forward(provider, endpoint, &body, &headers, adapter.as_ref())
-                .await
-            {
+            // 转发请求（每个 Provider 只尝试一次，重试由客户端控制；Key 池内可换 Key 重试）
+            let (result, api_key_id) = self
+                .forward_with_key_pool(
+                    app_type_str,
+                    provider,
+                    endpoint,
+                    &body,
+                    &headers,
+                    adapter.as_ref(),
+                )
+                .await;
+            match result {
                 Ok((response, orig_model, final_model)) => {
                     // 成功：记录成功并更新熔断器
+                    attempt.complete();
                     let _ = self
                         .router
                         .record_result(
@@ -208,8 +625,8 @@ impl RequestForwarder {
                         let mut status = self.status.write().await;
                         status.success_requests += 1;
                         status.last_error = None;
-                        let should_switch =
-                            self.current_provider_id_at_start.as_str() != provider.id.as_str();
+                        let should_switch = options.sync_current_provider
+                            && self.current_provider_id_at_start.as_str() != provider.id.as_str();
                         if should_switch {
                             status.failover_count += 1;
 
@@ -237,6 +654,8 @@ impl RequestForwarder {
                         provider: provider.clone(),
                         _original_model: orig_model,
                         mapped_model: final_model,
+                        api_key_id,
+                        hedge_loser: None,
                     });
                 }
                 Err(e) => {
@@ -257,6 +676,7 @@ impl RequestForwarder {
                             if rectifier_retried {
                                 log::warn!("[{app_type_str}] [RECT-005] 整流器已触发过，不再重试");
                                 // 释放 HalfOpen permit（不记录熔断器，这是客户端兼容性问题）
+                                attempt.complete();
                                 self.router
                                     .release_permit_neutral(
                                         &provider.id,
@@ -275,6 +695,7 @@ impl RequestForwarder {
                                 return Err(ForwardError {
                                     error: e,
                                     provider: Some(provider.clone()),
+                                    api_key_id,
                                 });
                             }
 
@@ -287,6 +708,7 @@ impl RequestForwarder {
                                     "[{app_type_str}] [RECT-006] 整流器触发但无可整流内容，不做无意义重试"
                                 );
                                 // 释放 HalfOpen permit（不记录熔断器，这是客户端兼容性问题）
+                                attempt.complete();
                                 self.router
                                     .release_permit_neutral(
                                         &provider.id,
@@ -305,6 +727,7 @@ impl RequestForwarder {
                                 return Err(ForwardError {
                                     error: e,
                                     provider: Some(provider.clone()),
+                                    api_key_id,
                                 });
                             }
 
@@ -320,13 +743,21 @@ impl RequestForwarder {
                             let _ = std::mem::replace(&mut rectifier_retried, true);
 
                             // 使用同一供应商重试（不计入熔断器）
-                            match self
-                                .forward(provider, endpoint, &body, &headers, adapter.as_ref())
-                                .await
-                            {
+                            let (retry_result, retry_key_id) = self
+                                .forward_with_key_pool(
+                                    app_type_str,
+                                    provider,
+                                    endpoint,
+                                    &body,
+                                    &headers,
+                                    adapter.as_ref(),
+                                )
+                                .await;
+                            match retry_result {
                                 Ok((response, orig_model, final_model)) => {
                                     log::info!("[{app_type_str}] [RECT-002] 整流重试成功");
                                     // 记录成功
+                                    attempt.complete();
                                     let _ = self
                                         .router
                                         .record_result(
@@ -353,8 +784,8 @@ impl RequestForwarder {
                                         let mut status = self.status.write().await;
                                         status.success_requests += 1;
                                         status.last_error = None;
-                                        let should_switch =
-                                            self.current_provider_id_at_start.as_str()
+                                        let should_switch = options.sync_current_provider
+                                            && self.current_provider_id_at_start.as_str()
                                                 != provider.id.as_str();
                                         if should_switch {
                                             status.failover_count += 1;
@@ -384,6 +815,8 @@ impl RequestForwarder {
                                         provider: provider.clone(),
                                         _original_model: orig_model,
                                         mapped_model: final_model,
+                                        api_key_id: retry_key_id,
+                                        hedge_loser: None,
                                     });
                                 }
                                 Err(retry_err) => {
@@ -403,6 +836,7 @@ impl RequestForwarder {
 
                                     if is_provider_error {
                                         // Provider 问题：记录失败到熔断器
+                                        attempt.complete();
                                         let _ = self
                                             .router
                                             .record_result(
@@ -415,6 +849,7 @@ impl RequestForwarder {
                                             .await;
                                     } else {
                                         // 客户端问题：仅释放 permit，不记录熔断器
+                                        attempt.complete();
                                         self.router
                                             .release_permit_neutral(
                                                 &provider.id,
@@ -435,6 +870,7 @@ impl RequestForwarder {
                                     return Err(ForwardError {
                                         error: retry_err,
                                         provider: Some(provider.clone()),
+                                        api_key_id: retry_key_id,
                                     });
                                 }
                             }
@@ -442,6 +878,7 @@ impl RequestForwarder {
                     }
 
                     // 失败：记录失败并更新熔断器
+                    attempt.complete();
                     let _ = self
                         .router
                         .record_result(
@@ -475,6 +912,7 @@ impl RequestForwarder {
 
                             last_error = Some(e);
                             last_provider = Some(provider.clone());
+                            last_api_key_id = api_key_id;
                             // 继续尝试下一个供应商
                             continue;
                         }
@@ -493,6 +931,7 @@ impl RequestForwarder {
                             return Err(ForwardError {
                                 error: e,
                                 provider: Some(provider.clone()),
+                                api_key_id,
                             });
                         }
                     }
@@ -502,40 +941,298 @@ impl RequestForwarder {
 
         if attempted_providers == 0 {
             // providers 列表非空，但全部被熔断器拒绝（典型：HalfOpen 探测名额被占用）
-            {
-                let mut status = self.status.write().await;
-                status.failed_requests += 1;
-                status.last_error = Some("所有供应商暂时不可用（熔断器限制）".to_string());
-                if status.total_requests > 0 {
-                    status.success_rate =
-                        (status.success_requests as f32 / status.total_requests as f32) * 100.0;
-                }
+            if options.count_final_failure {
+                self.record_request_failure("所有供应商暂时不可用（熔断器限制）".to_string())
+                    .await;
             }
             return Err(ForwardError {
                 error: ProxyError::NoAvailableProvider,
                 provider: None,
+                api_key_id: None,
             });
         }
 
         // 所有供应商都失败了
-        {
-            let mut status = self.status.write().await;
-            status.failed_requests += 1;
-            status.last_error = Some("所有供应商都失败".to_string());
-            if status.total_requests > 0 {
-                status.success_rate =
-                    (status.success_requests as f32 / status.total_requests as f32) * 100.0;
-            }
+        if options.count_final_failure {
+            self.record_request_failure("所有供应商都失败".to_string())
+                .await;
+            log::warn!("[{app_type_str}] [FWD-002] 所有 Provider 均失败");
         }
 
-        log::warn!("[{app_type_str}] [FWD-002] 所有 Provider 均失败");
-
         Err(ForwardError {
             error: last_error.unwrap_or(ProxyError::MaxRetriesExceeded),
             provider: last_provider,
+            api_key_id: last_api_key_id,
         })
     }
 
+    /// 记录整个请求最终失败（更新失败统计与成功率）
+    async fn record_request_failure(&self, message: String) {
+        let mut status = self.status.write().await;
+        status.failed_requests += 1;
+        status.last_error = Some(message);
+        if status.total_requests > 0 {
+            status.success_rate =
+                (status.success_requests as f32 / status.total_requests as f32) * 100.0;
+        }
+    }
+
+    /// 对冲转发：首选供应商超过 `delay` 仍未返回首字节时，向下一个供应商发送相同请求
+    ///
+    /// 两路请求中先返回首字节的一方胜出，另一方被取消并通过 `ForwardResult::hedge_loser` 返回；
+    /// 一方失败时继续等待另一方，两路都失败时按常规故障转移尝试剩余的供应商。
+    async fn forward_hedged(
+        &self,
+        app_type: &AppType,
+        endpoint: &str,
+        body: Value,
+        headers: axum::http::HeaderMap,
+        providers: Vec<Provider>,
+        delay: Duration,
+    ) -> Result<ForwardResult, ForwardError> {
+        let app_type_str = app_type.as_str();
+
+        // 对冲阶段在独立作用域中进行，结束时丢弃未完成的请求，释放对请求体的借用
+        let (tried, last_error) = {
+            let primary = HedgeLeg::new(&providers[0]);
+            let mut primary_fut =
+                Box::pin(self.hedge_leg(app_type, endpoint, &body, &headers, &primary));
+
+            let early = tokio::select! {
+                result = &mut primary_fut => Some(result),
+                _ = tokio::time::sleep(delay) => None,
+            };
+
+            // 已尝试的供应商数量（之后的供应商按常规故障转移继续）
+            let mut tried = 1;
+            let last_error = match early {
+                // 首选供应商在等待时间内完成，无需对冲
+                Some(Ok(result)) => return Ok(result),
+                Some(Err(err)) => err,
+                None => {
+                    tried = 2;
+                    let backup = HedgeLeg::new(&providers[1]);
+                    log::info!(
+                        "[{}] [FWD-003] Provider {} 在 {}ms 内未返回首字节，向 {} 发起对冲请求",
+                        app_type_str,
+                        primary.provider.name,
+                        delay.as_millis(),
+                        backup.provider.name
+                    );
+                    let mut backup_fut =
+                        Box::pin(self.hedge_leg(app_type, endpoint, &body, &headers, &backup));
+
+                    let (first, primary_finished) = tokio::select! {
+                        result = &mut primary_fut => (result, true),
+                        result = &mut backup_fut => (result, false),
+                    };
+
+                    match first {
+                        Ok(mut result) => {
+                            // 取消落后的一方（丢弃 future 即中断上游连接）
+                            let loser = if primary_finished {
+                                drop(backup_fut);
+                                &backup
+                            } else {
+                                drop(primary_fut);
+                                &primary
+                            };
+                            log::info!(
+                                "[{}] [FWD-004] Provider {} 先返回首字节，已取消 {} 的请求",
+                                app_type_str,
+                                result.provider.name,
+                                loser.provider.name
+                            );
+                            result.hedge_loser = Some(loser.to_loser(&body));
+                            return Ok(result);
+                        }
+                        // 不可重试错误已在单路请求中计入失败统计
+                        Err(err) if !self.should_try_next(&err.error) => return Err(err),
+                        // 一方失败：继续等待另一方
+                        Err(_) => {
+                            let other = if primary_finished {
+                                backup_fut.await
+                            } else {
+                                primary_fut.await
+                            };
+                            match other {
+                                Ok(result) => return Ok(result),
+                                Err(err) => err,
+                            }
+                        }
+                    }
+                }
+            };
+            (tried, last_error)
+        };
+
+        // 不可重试错误已在单路请求中计入失败统计
+        if !self.should_try_next(&last_error.error) {
+            return Err(last_error);
+        }
+        let remaining = providers[tried..].to_vec();
+        if remaining.is_empty() {
+            self.record_request_failure("所有供应商都失败".to_string())
+                .await;
+            log::warn!("[{app_type_str}] [FWD-002] 所有 Provider 均失败");
+            return Err(last_error);
+        }
+
+        let options = FailoverOptions {
+            bypass_circuit_breaker: false,
+            sync_current_provider: self.sync_current_provider,
+            count_final_failure: true,
+        };
+        match self
+            .forward_with_failover(app_type, endpoint, body, headers, remaining, options)
+            .await
+        {
+            // 剩余供应商均被熔断器拒绝时，返回更有意义的上游错误
+            Err(err) if matches!(err.error, ProxyError::NoAvailableProvider) => Err(last_error),
+            result => result,
+        }
+    }
+
+    /// 对冲中的单路请求失败后，是否继续尝试其他供应商（熔断器拒绝也视为可继续）
+    fn should_try_next(&self, error: &ProxyError) -> bool {
+        matches!(error, ProxyError::NoAvailableProvider)
+            || matches!(self.categorize_proxy_error(error), ErrorCategory::Retryable)
+    }
+
+    /// 对冲中的单路请求：转发到单个供应商并等待首字节
+    async fn hedge_leg(
+        &self,
+        app_type: &AppType,
+        endpoint: &str,
+        body: &Value,
+        headers: &axum::http::HeaderMap,
+        leg: &HedgeLeg<'_>,
+    ) -> Result<ForwardResult, ForwardError> {
+        let result = self
+            .forward_with_failover(
+                app_type,
+                endpoint,
+                body.clone(),
+                headers.clone(),
+                vec![leg.provider.clone()],
+                HEDGE_LEG_OPTIONS,
+            )
+            .await?;
+        let _ = leg
+            .accepted
+            .set((result.api_key_id.clone(), result.mapped_model.clone()));
+        await_first_chunk(result, self.streaming_first_byte_timeout).await
+    }
+
+    /// 向单个 Provider 转发辅助请求（如 count_tokens）
+    ///
+    /// 与 `forward_with_retry` 使用相同的模型映射、认证（含 Key 池选 Key）与请求重写脚本，
+    /// 但不更新代理统计、供应商与 Key 的熔断器以及故障转移状态：辅助端点失败不应影响主请求的供应商选择。
+    pub async fn forward_direct(
+        &self,
+        app_type: &AppType,
+        endpoint: &str,
+        body: &Value,
+        headers: &axum::http::HeaderMap,
+        provider: &Provider,
+    ) -> Result<Response, ProxyError> {
+        let adapter = get_adapter(app_type);
+        let app_type_str = app_type.as_str();
+        // 按轮换策略选择 Key，但不记录 Key 的成功 / 失败，只归还可能占用的 HalfOpen 名额
+        let selected = self
+            .router
+            .select_api_key(app_type_str, provider, &[])
+            .await;
+        let mut key = KeyPermitGuard::new(&self.router, app_type_str, &provider.id, selected);
+        let result = self
+            .forward(
+                provider,
+                endpoint,
+                body,
+                headers,
+                adapter.as_ref(),
+                key.key(),
+            )
+            .await;
+        if let Some(key) = key.take() {
+            self.router
+                .release_key_permit_neutral(app_type_str, &provider.id, &key)
+                .await;
+        }
+        result.map(|(response, _, _)| response)
+    }
+
+    /// 使用 Provider 的 Key 池转发请求
+    ///
+    /// 按轮换策略选择 Key；上游以 401/403/429 拒绝当前 Key 时，
+    /// 记录该 Key 失败并换下一个可用 Key 重试，Key 池耗尽后返回最后的错误。
+    /// 未启用 Key 池时直接使用 settingsConfig 中的 Key 转发一次。
+    ///
+    /// 返回 (转发结果, 最终使用的 Key 标识)
+    async fn forward_with_key_pool(
+        &self,
+        app_type: &str,
+        provider: &Provider,
+        endpoint: &str,
+        body: &Value,
+        headers: &axum::http::HeaderMap,
+        adapter: &dyn ProviderAdapter,
+    ) -> (
+        Result<(Response, Option<String>, Option<String>), ProxyError>,
+        Option<String>,
+    ) {
+        let mut excluded = Vec::new();
+        let selected = self
+            .router
+            .select_api_key(app_type, provider, &excluded)
+            .await;
+        let mut key = KeyPermitGuard::new(&self.router, app_type, &provider.id, selected);
+
+        loop {
+            let result = self
+                .forward(provider, endpoint, body, headers, adapter, key.key())
+                .await;
+            let Some(current) = key.take() else {
+                return (result, None);
+            };
+
+            match &result {
+                Ok(_) => {
+                    self.router
+                        .record_key_result(app_type, &provider.id, &current, true)
+                        .await;
+                }
+                Err(e) if is_key_rejection(e) => {
+                    self.router
+                        .record_key_result(app_type, &provider.id, &current, false)
+                        .await;
+                    excluded.push(current.id.clone());
+                    if let Some(next) = self
+                        .router
+                        .select_api_key(app_type, provider, &excluded)
+                        .await
+                    {
+                        log::warn!(
+                            "[{app_type}] [KEY-001] Provider {} 的 Key {} 被拒绝（{e}），切换到 Key {}",
+                            provider.name,
+                            current.id,
+                            next.id
+                        );
+                        key = KeyPermitGuard::new(&self.router, app_type, &provider.id, Some(next));
+                        continue;
+                    }
+                }
+                Err(_) => {
+                    self.router
+                        .release_key_permit_neutral(app_type, &provider.id, &current)
+                        .await;
+                }
+            }
+
+            return (result, Some(current.id));
+        }
+    }
+
     /// 转发单个请求（使用适配器）
     ///
     /// 返回 (Response, 原始模型, 映射后模型)
@@ -546,6 +1243,51 @@ impl RequestForwarder {
         body: &Value,
         headers: &axum::http::HeaderMap,
         adapter: &dyn ProviderAdapter,
+        api_key: Option<&SelectedApiKey>,
+    ) -> Result<(Response, Option<String>, Option<String>), ProxyError> {
+        self.upstream_attempts.fetch_add(1, Ordering::Relaxed);
+
+        // 上游请求 span：从构建请求到收到响应头（即上游首字节耗时）
+        let mut span = self.trace.client_span("upstream_request");
+        span.set_attribute("cc_switch.provider.id", provider.id.as_str());
+        span.set_attribute("cc_switch.provider.name", provider.name.as_str());
+        if let Some(key) = api_key {
+            span.set_attribute("cc_switch.api_key.id", key.id.as_str());
+        }
+
+        let result = self
+            .send_upstream(
+                provider, endpoint, body, headers, adapter, api_key, &mut span,
+            )
+            .await;
+        match &result {
+            Ok((response, _, final_model)) => {
+                span.set_attribute("http.response.status_code", response.status().as_u16());
+                if let Some(model) = final_model {
+                    span.set_attribute("gen_ai.request.model", model.as_str());
+                }
+            }
+            Err(ProxyError::UpstreamError { status, .. }) => {
+                span.set_attribute("http.response.status_code", *status);
+                span.set_error(format!("HTTP {status}"));
+            }
+            Err(e) => span.set_error(e.to_string()),
+        }
+        span.end();
+        result
+    }
+
+    /// 构建并发送单个上游请求
+    #[allow(clippy::too_many_arguments)]
+    async fn send_upstream(
+        &self,
+        provider: &Provider,
+        endpoint: &str,
+        body: &Value,
+        headers: &axum::http::HeaderMap,
+        adapter: &dyn ProviderAdapter,
+        api_key: Option<&SelectedApiKey>,
+        span: &mut Span,
     ) -> Result<(Response, Option<String>, Option<String>), ProxyError> {
         // 使用适配器提取 base_url
         let base_url = adapter.extract_base_url(provider)?;
@@ -560,11 +1302,18 @@ impl RequestForwarder {
                 endpoint
             };
 
+        // 请求头指定了模型时替换端点中的模型（Gemini 的模型位于路径中）
+        let overridden_endpoint = self
+            .model_override
+            .as_deref()
+            .map(|model| replace_model_in_path(effective_endpoint, model));
+        let effective_endpoint = overridden_endpoint.as_deref().unwrap_or(effective_endpoint);
+
         // 使用适配器构建 URL
         let mut url = adapter.build_url(&base_url, effective_endpoint);
 
         // 应用模型映射（根据适配器类型选择不同的映射器）
-        let (mapped_body, orig_model, final_model) = if adapter.name() == "Codex" {
+        let (mut mapped_body, orig_model, mut final_model) = if adapter.name() == "Codex" {
             // Codex 使用专用映射器，支持 effort 组合映射
             super::codex_model_mapper::apply_codex_model_mapping(body.clone(), provider)
         } else {
@@ -572,6 +1321,16 @@ impl RequestForwarder {
             super::model_mapper::apply_model_mapping(body.clone(), provider)
         };
 
+        // 请求头指定的模型优先于供应商的模型映射
+        if let Some(model) = &self.model_override {
+            if let Some(obj) = mapped_body.as_object_mut() {
+                if obj.contains_key("model") || adapter.name() != "Gemini" {
+                    obj.insert("model".to_string(), Value::String(model.clone()));
+                }
+            }
+            final_model = Some(model.clone());
+        }
+
         // 转换请求体（如果需要）
         let request_body = if needs_transform {
             adapter.transform_request(mapped_body, provider)?
@@ -598,127 +1357,49 @@ impl RequestForwarder {
             }
         }
 
-        // 请求重写脚本（onRequest，仅 Codex）
+        // 请求重写脚本（onRequest）：全局 → 应用 → 供应商 链式执行
         //
         // - 允许用户像抓包工具一样自由修改 headers/body
-        // - 失败策略：线上降级继续（忽略脚本输出）
+        // - 失败策略：线上降级继续（忽略出错脚本的输出）
         let mut scripted_upstream_headers: Option<axum::http::HeaderMap> = None;
-        if adapter.name() == "Codex" {
-            if let Some(script) = provider
-                .meta
-                .as_ref()
-                .and_then(|m| m.request_hook_script.as_ref())
-            {
-                if script.enabled && !script.code.trim().is_empty() {
-                    if !script.language.eq_ignore_ascii_case("javascript") {
-                        log::warn!(
-                            "[Codex] [Hook] 不支持的脚本语言: {}（当前仅支持 javascript）",
-                            script.language
-                        );
-                        // 忽略脚本
-                    } else {
-                        let incoming_headers = build_header_string_map(headers);
-                        let request_headers: HashMap<String, String> = incoming_headers
-                            .iter()
-                            .filter_map(|(k, v)| {
-                                if is_header_blacklisted(k) {
-                                    None
-                                } else {
-                                    Some((k.clone(), v.clone()))
-                                }
-                            })
-                            .collect();
-
-                        let context = RequestHookContext {
-                            app: "codex".to_string(),
-                            method: "POST".to_string(),
-                            path: effective_endpoint.to_string(),
-                            endpoint: effective_endpoint.to_string(),
-                            url: url.clone(),
-                            provider: RequestHookProviderInfo {
-                                id: provider.id.clone(),
-                                name: provider.name.clone(),
-                            },
-                            incoming_headers,
-                        };
-
-                        let original_view = HookRequest {
-                            headers: request_headers,
-                            queries: build_query_string_map_from_url(&url),
-                            body: filtered_body.clone(),
-                        };
-
-                        match execute_on_request_script(&script.code, &context, &original_view) {
-                            Ok(Some(mut new_view)) => {
-                                // 再次过滤私有参数，防止脚本重新注入 _ 前缀字段泄露
-                                let new_body =
-                                    filter_private_params_with_whitelist(new_view.body, &[]);
-
-                                // 应用 header 黑名单边界
-                                new_view
-                                    .headers
-                                    .retain(|k, _| !is_header_blacklisted(k.as_str()));
-
-                                let rewritten_url = match apply_query_string_map_to_url(
-                                    &url,
-                                    &new_view.queries,
-                                ) {
-                                    Ok(v) => Some(v),
-                                    Err(e) => {
-                                        log::warn!(
-                                                "[Codex] [Hook] 请求重写脚本输出 query 无效，已忽略: {e}"
-                                            );
-                                        None
-                                    }
-                                };
-
-                                if let Some(rewritten_url) = rewritten_url {
-                                    // 构建将要发往上游的 HeaderMap
-                                    let mut upstream_headers = axum::http::HeaderMap::new();
-                                    let mut build_failed = None;
-                                    for (k, v) in new_view.headers {
-                                        let name = match axum::http::HeaderName::from_bytes(
-                                            k.as_bytes(),
-                                        ) {
-                                            Ok(n) => n,
-                                            Err(e) => {
-                                                build_failed =
-                                                    Some(format!("非法 header name: {k} ({e})"));
-                                                break;
-                                            }
-                                        };
-                                        let value = match axum::http::HeaderValue::from_str(&v) {
-                                            Ok(v) => v,
-                                            Err(e) => {
-                                                build_failed = Some(format!(
-                                                    "非法 header value: {k}={v} ({e})"
-                                                ));
-                                                break;
-                                            }
-                                        };
-                                        upstream_headers.insert(name, value);
-                                    }
+        let hooks = hook_chain(&self.hook_scripts, provider, adapter.name());
+        if !hooks.is_empty() {
+            let incoming_headers = build_header_string_map(headers);
+            let request_headers: HashMap<String, String> = incoming_headers
+                .iter()
+                .filter(|(k, _)| !is_header_blacklisted(k))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect();
+
+            let context = RequestHookContext {
+                app: adapter.name().to_ascii_lowercase(),
+                method: "POST".to_string(),
+                path: effective_endpoint.to_string(),
+                endpoint: effective_endpoint.to_string(),
+                url: url.clone(),
+                provider: RequestHookProviderInfo {
+                    id: provider.id.clone(),
+                    name: provider.name.clone(),
+                },
+                incoming_headers,
+            };
 
-                                    if let Some(err) = build_failed {
-                                        log::warn!(
-                                            "[Codex] [Hook] 请求重写脚本输出无效，已忽略: {err}"
-                                        );
-                                    } else {
-                                        filtered_body = new_body;
-                                        url = rewritten_url;
-                                        scripted_upstream_headers = Some(upstream_headers);
-                                    }
-                                }
-                            }
-                            Ok(None) => {
-                                // undefined/null：放行（不修改）
-                            }
-                            Err(e) => {
-                                log::warn!("[Codex] [Hook] 请求重写脚本执行失败，已忽略: {e}");
-                            }
-                        }
-                    }
-                }
+            let original_view = HookRequest {
+                headers: request_headers,
+                queries: build_query_string_map_from_url(&url),
+                body: filtered_body.clone(),
+            };
+
+            let mut hook_span = span.child("request_hook_script");
+            hook_span.set_attribute("cc_switch.hook_count", hooks.len());
+            let hooked = apply_request_hooks(&hooks, adapter.name(), &context, original_view);
+            hook_span.set_attribute("cc_switch.hook_applied", hooked.is_some());
+            hook_span.end();
+
+            if let Some((new_url, new_body, upstream_headers)) = hooked {
+                url = new_url;
+                filtered_body = new_body;
+                scripted_upstream_headers = Some(upstream_headers);
             }
         }
 
@@ -786,12 +1467,24 @@ impl RequestForwarder {
             }
         }
 
+        // 链路追踪：以当前上游请求 span 为父传递 trace（客户端的 traceparent 已被黑名单过滤）
+        if let Some(traceparent) = span.traceparent() {
+            request = request.header("traceparent", traceparent);
+        }
+        if let Ok(parsed) = reqwest::Url::parse(&url) {
+            if let Some(host) = parsed.host_str() {
+                span.set_attribute("server.address", host);
+            }
+        }
+
         // 禁用压缩，避免 gzip 流式响应解析错误
         // 参考 CCH: undici 在连接提前关闭时会对不完整的 gzip 流抛出错误
         request = request.header("accept-encoding", "identity");
 
-        // 使用适配器添加认证头
-        if let Some(auth) = adapter.extract_auth(provider) {
+        // 使用适配器添加认证头（启用 Key 池时使用选中的 Key）
+        if let Some(auth) = adapter.extract_auth_with_key(provider, api_key) {
+            // Gemini OAuth 凭证：access_token 过期前自动刷新
+            let auth = ensure_fresh_access_token(auth, &client).await?;
             request = adapter.add_auth_headers(request, &auth);
         }
 
@@ -892,3 +1585,544 @@ fn extract_error_message(error: &ProxyError) -> Option<String> {
         _ => Some(error.to_string()),
     }
 }
+
+/// 等待流式响应的首个数据块，并将其放回响应体以便后续透传
+///
+/// `first_byte_timeout` 为 None 时不限制等待时间
+async fn await_first_chunk(
+    result: ForwardResult,
+    first_byte_timeout: Option<Duration>,
+) -> Result<ForwardResult, ForwardError> {
+    let ForwardResult {
+        response,
+        provider,
+        _original_model,
+        mapped_model,
+        api_key_id,
+        hedge_loser,
+    } = result;
+
+    let status = response.status();
+    let version = response.version();
+    let headers = response.headers().clone();
+    let mut stream = response.bytes_stream();
+
+    let first = match first_byte_timeout {
+        Some(timeout) => tokio::time::timeout(timeout, stream.next())
+            .await
+            .map_err(|_| {
+                ProxyError::Timeout(format!("流式响应首字节超时（{}秒）", timeout.as_secs()))
+            }),
+        None => Ok(stream.next().await),
+    };
+    let first = match first {
+        Ok(Some(Ok(chunk))) => Some(chunk),
+        Ok(None) => None,
+        Ok(Some(Err(e))) => {
+            return Err(ForwardError {
+                error: ProxyError::ForwardFailed(format!("读取流式响应失败: {e}")),
+                provider: Some(provider),
+                api_key_id,
+            })
+        }
+        Err(error) => {
+            return Err(ForwardError {
+                error,
+                provider: Some(provider),
+                api_key_id,
+            })
+        }
+    };
+
+    let body = futures::stream::iter(first.map(Ok::<_, reqwest::Error>)).chain(stream);
+    let mut rebuilt = axum::http::Response::new(reqwest::Body::wrap_stream(body));
+    *rebuilt.status_mut() = status;
+    *rebuilt.version_mut() = version;
+    *rebuilt.headers_mut() = headers;
+
+    Ok(ForwardResult {
+        response: Response::from(rebuilt),
+        provider,
+        _original_model,
+        mapped_model,
+        api_key_id,
+        hedge_loser,
+    })
+}
+
+/// 判断是否为流式请求（请求体 `stream: true` 或 Gemini 的 streamGenerateContent 端点）
+fn is_streaming_request(body: &Value, endpoint: &str) -> bool {
+    body.get("stream")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+        || endpoint.contains("streamGenerateContent")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use crate::proxy::{
+        circuit_breaker::CircuitBreakerConfig, handler_context::RequestContext,
+        metrics::ProxyMetrics, model_catalog::ModelCatalog, routing_rules::RoutingRule,
+        server::ProxyState, types::ProxyConfig,
+    };
+    use bytes::Bytes;
+    use serde_json::json;
+    use serial_test::serial;
+    use std::env;
+    use tempfile::TempDir;
+
+    struct TempHome {
+        #[allow(dead_code)]
+        dir: TempDir,
+        original_home: Option<String>,
+        original_userprofile: Option<String>,
+    }
+
+    impl TempHome {
+        fn new() -> Self {
+            let dir = TempDir::new().expect("failed to create temp home");
+            let original_home = env::var("HOME").ok();
+            let original_userprofile = env::var("USERPROFILE").ok();
+
+            env::set_var("HOME", dir.path());
+            env::set_var("USERPROFILE", dir.path());
+            crate::settings::reload_settings().expect("reload settings");
+
+            Self {
+                dir,
+                original_home,
+                original_userprofile,
+            }
+        }
+    }
+
+    impl Drop for TempHome {
+        fn drop(&mut self) {
+            match &self.original_home {
+                Some(value) => env::set_var("HOME", value),
+                None => env::remove_var("HOME"),
+            }
+
+            match &self.original_userprofile {
+                Some(value) => env::set_var("USERPROFILE", value),
+                None => env::remove_var("USERPROFILE"),
+            }
+        }
+    }
+
+    fn build_state(db: Arc<Database>) -> ProxyState {
+        ProxyState {
+            db: db.clone(),
+            config: Arc::new(RwLock::new(ProxyConfig::default())),
+            status: Arc::new(RwLock::new(ProxyStatus::default())),
+            start_time: Arc::new(RwLock::new(None)),
+            current_providers: Arc::new(RwLock::new(HashMap::new())),
+            provider_router: Arc::new(ProviderRouter::new(db.clone())),
+            app_handle: None,
+            failover_manager: Arc::new(FailoverSwitchManager::new(db)),
+            model_catalog: Arc::new(ModelCatalog::new()),
+            metrics: Arc::new(ProxyMetrics::new()),
+        }
+    }
+
+    /// 启动模拟上游：等待 `delay` 后以 `status` 返回一段 SSE 响应，返回 base URL
+    async fn spawn_upstream(delay: Duration, status: u16) -> String {
+        let app = axum::Router::new().fallback(move || async move {
+            tokio::time::sleep(delay).await;
+            (
+                axum::http::StatusCode::from_u16(status).expect("valid status code"),
+                [("content-type", "text/event-stream")],
+                "data: {\"type\":\"message_stop\"}\n\n",
+            )
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock upstream");
+        let addr = listener.local_addr().expect("mock upstream address");
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        format!("http://{addr}")
+    }
+
+    fn claude_provider(id: &str, base_url: &str) -> Provider {
+        Provider::with_id(
+            id.to_string(),
+            id.to_string(),
+            json!({
+                "env": {
+                    "ANTHROPIC_BASE_URL": base_url,
+                    "ANTHROPIC_AUTH_TOKEN": format!("sk-{id}"),
+                }
+            }),
+            None,
+        )
+    }
+
+    fn forward_result(chunks: Vec<&'static str>, delay: Duration) -> ForwardResult {
+        let stream = futures::stream::iter(chunks).then(move |chunk| async move {
+            tokio::time::sleep(delay).await;
+            Ok::<_, std::io::Error>(Bytes::from_static(chunk.as_bytes()))
+        });
+        let mut response = axum::http::Response::new(reqwest::Body::wrap_stream(stream));
+        response.headers_mut().insert(
+            "content-type",
+            axum::http::HeaderValue::from_static("text/event-stream"),
+        );
+        ForwardResult {
+            response: Response::from(response),
+            provider: Provider::with_id("p".to_string(), "P".to_string(), json!({}), None),
+            _original_model: None,
+            mapped_model: None,
+            api_key_id: None,
+            hedge_loser: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn first_chunk_is_kept_in_response_body() {
+        let result = forward_result(vec!["data: a\n\n", "data: b\n\n"], Duration::ZERO);
+        let result = await_first_chunk(result, Some(Duration::from_secs(1)))
+            .await
+            .expect("first chunk arrives in time");
+        assert_eq!(
+            result.response.headers()["content-type"],
+            "text/event-stream"
+        );
+        assert_eq!(
+            result.response.text().await.unwrap(),
+            "data: a\n\ndata: b\n\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn first_chunk_times_out() {
+        let result = forward_result(vec!["data: a\n\n"], Duration::from_millis(200));
+        let err = await_first_chunk(result, Some(Duration::from_millis(20)))
+            .await
+            .expect_err("first chunk times out");
+        assert!(matches!(err.error, ProxyError::Timeout(_)));
+        assert_eq!(err.provider.map(|p| p.id), Some("p".to_string()));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn rule_routed_success_keeps_current_provider() {
+        let _home = TempHome::new();
+        let db = Arc::new(Database::memory().expect("create database"));
+        let upstream = spawn_upstream(Duration::ZERO, 200).await;
+
+        db.save_provider("claude", &claude_provider("official", "http://127.0.0.1:9"))
+            .expect("save official provider");
+        db.save_provider("claude", &claude_provider("cheap", &upstream))
+            .expect("save cheap provider");
+        db.set_current_provider("claude", "official")
+            .expect("set current provider");
+        crate::settings::set_current_provider(&AppType::Claude, Some("official"))
+            .expect("set local current provider");
+        db.add_to_failover_queue("claude", "official")
+            .expect("add to failover queue");
+
+        let mut config = db
+            .get_proxy_config_for_app("claude")
+            .await
+            .expect("read proxy config");
+        config.enabled = true;
+        config.auto_failover_enabled = true;
+        db.update_proxy_config_for_app(config)
+            .await
+            .expect("update proxy config");
+        db.set_routing_rules(
+            "claude",
+            &[RoutingRule {
+                id: "haiku".to_string(),
+                name: "Haiku".to_string(),
+                enabled: true,
+                model_pattern: Some("*haiku*".to_string()),
+                thinking: None,
+                min_body_bytes: None,
+                max_body_bytes: None,
+                endpoint_pattern: None,
+                provider_ids: vec!["cheap".to_string()],
+            }],
+        )
+        .expect("set routing rules");
+
+        let state = build_state(db.clone());
+        let body = json!({"model": "claude-haiku-4-5", "messages": []});
+        let headers = axum::http::HeaderMap::new();
+        let ctx = RequestContext::new(
+            &state,
+            &body,
+            &headers,
+            "/v1/messages",
+            None,
+            AppType::Claude,
+            "Claude",
+            "claude",
+        )
+        .await
+        .expect("create request context");
+        assert_eq!(ctx.routing_rule.as_deref(), Some("Haiku"));
+
+        let result = ctx
+            .create_forwarder(&state)
+            .forward_with_retry(
+                &AppType::Claude,
+                "/v1/messages",
+                body,
+                headers,
+                ctx.get_providers(),
+            )
+            .await
+            .expect("routed request succeeds");
+        assert_eq!(result.provider.id, "cheap");
+
+        // 切换在后台任务中执行，稍等后确认没有发生
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(state.status.read().await.failover_count, 0);
+        assert_eq!(
+            db.get_current_provider("claude")
+                .expect("read current provider")
+                .as_deref(),
+            Some("official")
+        );
+        assert_eq!(
+            crate::settings::get_current_provider(&AppType::Claude).as_deref(),
+            Some("official")
+        );
+        assert!(db
+            .get_failover_history("claude", 10)
+            .expect("read failover history")
+            .is_empty());
+    }
+
+    /// 按 (ID, 响应延迟, 状态码) 启动模拟上游并保存对应的供应商
+    async fn spawn_providers(db: &Database, specs: &[(&str, u64, u16)]) -> Vec<Provider> {
+        let mut providers = Vec::new();
+        for (id, delay_ms, status) in specs {
+            let upstream = spawn_upstream(Duration::from_millis(*delay_ms), *status).await;
+            let provider = claude_provider(id, &upstream);
+            db.save_provider("claude", &provider)
+                .expect("save provider");
+            providers.push(provider);
+        }
+        providers
+    }
+
+    fn hedging_forwarder(state: &ProxyState, delay_ms: u64) -> RequestForwarder {
+        RequestForwarder::new(
+            state.provider_router.clone(),
+            30,
+            state.status.clone(),
+            state.current_providers.clone(),
+            state.failover_manager.clone(),
+            None,
+            String::new(),
+            5,
+            0,
+            RectifierConfig::default(),
+            Vec::new(),
+        )
+        .with_hedging(Some(Duration::from_millis(delay_ms)))
+    }
+
+    async fn forward_streaming(
+        forwarder: &RequestForwarder,
+        providers: Vec<Provider>,
+    ) -> Result<ForwardResult, ForwardError> {
+        forwarder
+            .forward_with_retry(
+                &AppType::Claude,
+                "/v1/messages",
+                json!({"model": "claude-sonnet-4-5", "stream": true, "messages": []}),
+                axum::http::HeaderMap::new(),
+                providers,
+            )
+            .await
+    }
+
+    #[tokio::test]
+    async fn hedge_backup_wins_and_releases_cancelled_probe() {
+        let db = Arc::new(Database::memory().expect("create database"));
+        db.update_circuit_breaker_config(&CircuitBreakerConfig {
+            failure_threshold: 1,
+            timeout_seconds: 0,
+            ..Default::default()
+        })
+        .await
+        .expect("update circuit breaker config");
+        let providers = spawn_providers(&db, &[("slow", 2000, 200), ("fast", 0, 200)]).await;
+        let state = build_state(db);
+
+        // 首选供应商处于 HalfOpen：对冲的一路会占用唯一的探测名额
+        state
+            .provider_router
+            .record_result("slow", "claude", false, false, Some("fail".to_string()))
+            .await
+            .expect("open circuit breaker");
+
+        let forwarder = hedging_forwarder(&state, 50);
+        let result = forward_streaming(&forwarder, providers)
+            .await
+            .expect("backup provider wins");
+        assert_eq!(result.provider.id, "fast");
+        let loser = result.hedge_loser.expect("primary is cancelled");
+        assert_eq!(loser.provider.id, "slow");
+        assert_eq!(forwarder.attempted_provider_ids(), vec!["slow", "fast"]);
+
+        // 被取消的一方在后台归还探测名额并撤销请求计数
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let permit = state
+            .provider_router
+            .allow_provider_request("slow", "claude")
+            .await;
+        assert!(permit.allowed);
+        assert!(permit.used_half_open_permit);
+        let status = state.status.read().await;
+        assert_eq!(status.total_requests, 1);
+        assert_eq!(status.success_requests, 1);
+        assert_eq!(status.success_rate, 100.0);
+    }
+
+    #[tokio::test]
+    async fn hedge_releases_key_pool_probe_of_cancelled_leg() {
+        use crate::provider::{ApiKeyEntry, ApiKeyPool, ApiKeyRotationStrategy, ProviderMeta};
+
+        let db = Arc::new(Database::memory().expect("create database"));
+        db.update_circuit_breaker_config(&CircuitBreakerConfig {
+            failure_threshold: 1,
+            timeout_seconds: 0,
+            ..Default::default()
+        })
+        .await
+        .expect("update circuit breaker config");
+        let mut providers = spawn_providers(&db, &[("slow", 2000, 200), ("fast", 0, 200)]).await;
+        providers[0].meta = Some(ProviderMeta {
+            api_key_pool: Some(ApiKeyPool {
+                enabled: true,
+                strategy: ApiKeyRotationStrategy::Failover,
+                keys: vec![ApiKeyEntry {
+                    id: "k1".to_string(),
+                    key: "sk-pool".to_string(),
+                    label: None,
+                    enabled: true,
+                }],
+            }),
+            ..Default::default()
+        });
+        db.save_provider("claude", &providers[0])
+            .expect("save provider");
+        let state = build_state(db);
+        let router = state.provider_router.clone();
+
+        // 首选供应商唯一的 Key 处于 HalfOpen：对冲的一路会占用它唯一的探测名额
+        let key = router
+            .select_api_key("claude", &providers[0], &[])
+            .await
+            .expect("select key");
+        router
+            .record_key_result("claude", "slow", &key, false)
+            .await;
+
+        let forwarder = hedging_forwarder(&state, 50);
+        let result = forward_streaming(&forwarder, providers.clone())
+            .await
+            .expect("backup provider wins");
+        assert_eq!(result.provider.id, "fast");
+        let loser = result.hedge_loser.expect("primary is cancelled");
+        assert_eq!(loser.provider.id, "slow");
+
+        // 被取消的一方在后台归还 Key 的探测名额
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let key = router
+            .select_api_key("claude", &providers[0], &[])
+            .await
+            .expect("key probe permit is released");
+        assert_eq!(key.id, "k1");
+        assert!(key.used_half_open_permit);
+    }
+
+    #[tokio::test]
+    async fn hedge_primary_wins_after_hedge_started() {
+        let db = Arc::new(Database::memory().expect("create database"));
+        let providers = spawn_providers(&db, &[("primary", 200, 200), ("backup", 2000, 200)]).await;
+        let state = build_state(db);
+
+        let result = forward_streaming(&hedging_forwarder(&state, 30), providers)
+            .await
+            .expect("primary provider wins");
+        assert_eq!(result.provider.id, "primary");
+        let loser = result.hedge_loser.expect("backup is cancelled");
+        assert_eq!(loser.provider.id, "backup");
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(state.status.read().await.total_requests, 1);
+    }
+
+    #[tokio::test]
+    async fn hedge_awaits_other_leg_when_one_fails() {
+        let db = Arc::new(Database::memory().expect("create database"));
+        let providers = spawn_providers(&db, &[("primary", 300, 200), ("backup", 0, 500)]).await;
+        let state = build_state(db);
+
+        let result = forward_streaming(&hedging_forwarder(&state, 30), providers)
+            .await
+            .expect("primary provider succeeds after backup fails");
+        assert_eq!(result.provider.id, "primary");
+        assert!(result.hedge_loser.is_none());
+
+        let status = state.status.read().await;
+        assert_eq!(status.total_requests, 2);
+        assert_eq!(status.success_requests, 1);
+    }
+
+    #[tokio::test]
+    async fn hedge_falls_back_to_remaining_providers_when_both_fail() {
+        let db = Arc::new(Database::memory().expect("create database"));
+        let providers = spawn_providers(
+            &db,
+            &[("primary", 100, 500), ("backup", 0, 502), ("last", 0, 200)],
+        )
+        .await;
+        let all_failed = spawn_providers(&db, &[("a", 100, 500), ("b", 0, 503)]).await;
+        let state = build_state(db);
+
+        let forwarder = hedging_forwarder(&state, 30);
+        let result = forward_streaming(&forwarder, providers)
+            .await
+            .expect("remaining provider succeeds");
+        assert_eq!(result.provider.id, "last");
+        assert!(result.hedge_loser.is_none());
+        assert_eq!(
+            forwarder.attempted_provider_ids(),
+            vec!["primary", "backup", "last"]
+        );
+
+        let err = forward_streaming(&hedging_forwarder(&state, 30), all_failed)
+            .await
+            .expect_err("all providers fail");
+        assert!(matches!(
+            err.error,
+            ProxyError::UpstreamError { status: 500, .. }
+        ));
+    }
+
+    #[test]
+    fn detects_streaming_requests() {
+        assert!(is_streaming_request(
+            &json!({"stream": true}),
+            "/v1/messages"
+        ));
+        assert!(!is_streaming_request(
+            &json!({"stream": false}),
+            "/v1/messages"
+        ));
+        assert!(is_streaming_request(
+            &json!({}),
+            "/v1beta/models/gemini-2.5-pro:streamGenerateContent?alt=sse"
+        ));
+    }
+}
@@ -5,16 +5,22 @@
 use crate::app_config::AppType;
 use crate::provider::Provider;
 use crate::proxy::{
+    client_auth::ClientIdentity,
     extract_session_id,
     forwarder::RequestForwarder,
+    otel::RequestTrace,
+    routing_override::{find_pinned_provider, RoutingOverrides},
+    routing_rules::RouteRequest,
     server::ProxyState,
     types::{AppProxyConfig, RectifierConfig},
     ProxyError,
 };
-use crate::request_hook_script::build_header_string_map;
+use crate::request_hook_script::{
+    build_header_string_map, load_shared_hook_scripts, ScopedHookScript,
+};
 use axum::http::HeaderMap;
 use std::collections::HashMap;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 /// 流式超时配置
 #[derive(Debug, Clone, Copy)]
@@ -62,10 +68,22 @@ pub struct RequestContext {
     pub session_id: String,
     /// 整流器配置
     pub rectifier_config: RectifierConfig,
+    /// 全局与应用级请求重写脚本（供应商级脚本在转发时按实际供应商追加）
+    pub hook_scripts: Vec<ScopedHookScript>,
     /// 入站请求头（小写 key，逗号连接多值）
     pub incoming_headers: HashMap<String, String>,
     /// 本次请求端点（用于 Hook 上下文）
     pub request_endpoint: String,
+    /// 实际使用的 Key 池 Key 标识（用于用量归因）
+    pub api_key_id: Option<String>,
+    /// 通过访问令牌认证的客户端（匿名本机请求为空）
+    pub client: Option<ClientIdentity>,
+    /// 链路追踪（未启用时为空操作）
+    pub trace: RequestTrace,
+    /// 请求头中的路由覆盖（指定供应商 / 模型、禁用故障转移）
+    pub routing: RoutingOverrides,
+    /// 命中的路由规则名称（命中时供应商链由规则决定，不把成功的供应商同步为当前供应商）
+    pub routing_rule: Option<String>,
 }
 
 impl RequestContext {
@@ -75,16 +93,21 @@ impl RequestContext {
     /// * `state` - 代理服务器状态
     /// * `body` - 请求体 JSON
     /// * `headers` - 请求头（用于提取 Session ID）
+    /// * `endpoint` - 本次请求端点（用于路由规则匹配与 Hook 上下文）
+    /// * `client` - 通过访问令牌认证的客户端（用于供应商限制与用量归因）
     /// * `app_type` - 应用类型
     /// * `tag` - 日志标签
     /// * `app_type_str` - 应用类型字符串
     ///
     /// # Errors
     /// 返回 `ProxyError` 如果 Provider 选择失败
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         state: &ProxyState,
         body: &serde_json::Value,
         headers: &HeaderMap,
+        endpoint: &str,
+        client: Option<ClientIdentity>,
         app_type: AppType,
         tag: &'static str,
         app_type_str: &'static str,
@@ -101,6 +124,9 @@ impl RequestContext {
         // 从数据库读取整流器配置
         let rectifier_config = state.db.get_rectifier_config().unwrap_or_default();
 
+        // 读取全局与应用级请求重写脚本
+        let hook_scripts = load_shared_hook_scripts(&state.db, app_type_str);
+
         let current_provider_id =
             crate::settings::get_current_provider(&app_type).unwrap_or_default();
 
@@ -116,6 +142,16 @@ impl RequestContext {
         let session_id = session_result.session_id.clone();
         let incoming_headers = build_header_string_map(headers);
 
+        // 链路追踪：每个请求一条 trace（配置读取失败时视为关闭）
+        let trace = RequestTrace::start(
+            &state.db.get_tracing_config().unwrap_or_default(),
+            headers,
+            &format!("{app_type_str} proxy request"),
+        );
+        trace.set_attribute("cc_switch.app", app_type_str);
+        trace.set_attribute("session.id", session_id.as_str());
+        trace.set_attribute("gen_ai.request.model", request_model.as_str());
+
         log::debug!(
             "[{}] Session ID: {} (from {:?}, client_provided: {})",
             tag,
@@ -124,19 +160,84 @@ impl RequestContext {
             session_result.client_provided
         );
 
+        let routing = RoutingOverrides::from_headers(headers);
+        if let Some(value) = routing.to_log_value() {
+            log::info!("[{tag}] 请求头路由覆盖: {value}");
+            trace.set_attribute("cc_switch.routing_override", value);
+        }
+
         // 使用共享的 ProviderRouter 选择 Provider（熔断器状态跨请求保持）
         // 注意：只在这里调用一次，结果传递给 forwarder，避免重复消耗 HalfOpen 名额
-        let providers = state
-            .provider_router
-            .select_providers(app_type_str)
-            .await
-            .map_err(|e| match e {
-                crate::error::AppError::AllProvidersCircuitOpen => {
-                    ProxyError::AllProvidersCircuitOpen
+        // 请求头指定了供应商时直接使用该供应商（不经过熔断器筛选）
+        let mut select_span = trace.span("select_providers");
+        let mut routing_rule = None;
+        let providers = match routing.provider.as_deref() {
+            Some(key) => state
+                .db
+                .get_all_providers(app_type_str)
+                .map_err(|e| ProxyError::DatabaseError(e.to_string()))
+                .and_then(|all| {
+                    find_pinned_provider(all.values(), key)
+                        .cloned()
+                        .map(|provider| vec![provider])
+                        .ok_or_else(|| ProxyError::InvalidRequest(format!("未找到供应商: {key}")))
+                }),
+            None => state
+                .provider_router
+                .select_providers_for_request(app_type_str, &RouteRequest::new(body, endpoint))
+                .await
+                .map(|routed| {
+                    if let Some(rule) = &routed.rule {
+                        log::debug!("[{tag}] 命中路由规则: {rule}");
+                        select_span.set_attribute("cc_switch.routing_rule", rule.as_str());
+                    }
+                    routing_rule = routed.rule;
+                    routed.providers
+                })
+                .map_err(|e| match e {
+                    crate::error::AppError::AllProvidersCircuitOpen => {
+                        ProxyError::AllProvidersCircuitOpen
+                    }
+                    crate::error::AppError::NoProvidersConfigured => {
+                        ProxyError::NoProvidersConfigured
+                    }
+                    _ => ProxyError::DatabaseError(e.to_string()),
+                }),
+        };
+        let providers = match providers {
+            Ok(providers) => providers,
+            Err(e) => {
+                select_span.set_error(e.to_string());
+                select_span.end();
+                trace.set_error(e.to_string());
+                return Err(e);
+            }
+        };
+
+        // 访问令牌限制了可用供应商时，从故障转移链中剔除其余供应商
+        let mut providers = match &client {
+            Some(client) if client.allowed_providers.is_some() => {
+                let allowed: Vec<Provider> = providers
+                    .into_iter()
+                    .filter(|p| client.allows_provider(&p.id))
+                    .collect();
+                if allowed.is_empty() {
+                    return Err(ProxyError::Forbidden(format!(
+                        "访问令牌 {} 不允许使用当前可用的供应商",
+                        client.name
+                    )));
                 }
-                crate::error::AppError::NoProvidersConfigured => ProxyError::NoProvidersConfigured,
-                _ => ProxyError::DatabaseError(e.to_string()),
-            })?;
+                allowed
+            }
+            _ => providers,
+        };
+
+        // 禁用故障转移：只保留首选供应商
+        if routing.no_failover {
+            providers.truncate(1);
+        }
+        select_span.set_attribute("cc_switch.provider_count", providers.len());
+        select_span.end();
 
         let provider = providers
             .first()
@@ -165,8 +266,14 @@ impl RequestContext {
             app_type,
             session_id,
             rectifier_config,
+            hook_scripts,
             incoming_headers,
-            request_endpoint: String::new(),
+            request_endpoint: endpoint.to_string(),
+            api_key_id: None,
+            client,
+            trace,
+            routing,
+            routing_rule,
         })
     }
 
@@ -191,17 +298,16 @@ impl RequestContext {
         self
     }
 
-    /// 设置本次请求端点（用于 hook 上下文）
-    pub fn with_request_endpoint(mut self, endpoint: &str) -> Self {
-        self.request_endpoint = endpoint.to_string();
-        self
-    }
-
     /// 设置映射后的模型（如果发生了模型映射）
     pub fn set_mapped_model(&mut self, mapped_model: Option<String>) {
         self.mapped_model = mapped_model;
     }
 
+    /// 客户端访问令牌标识（用于用量归因）
+    pub fn client_token_id(&self) -> Option<String> {
+        self.client.as_ref().map(|c| c.token_id.clone())
+    }
+
     /// 获取实际使用的模型（优先返回映射后的模型，否则返回原始模型）
     pub fn get_actual_model(&self) -> &str {
         self.mapped_model.as_deref().unwrap_or(&self.request_model)
@@ -232,6 +338,21 @@ impl RequestContext {
                 (0, 0, 0)
             };
 
+        // 指定供应商的请求只是一次性路由，不应把它同步为“当前供应商”
+        let current_provider_id = if self.routing.provider.is_some() {
+            self.provider.id.clone()
+        } else {
+            self.current_provider_id.clone()
+        };
+
+        // 请求对冲（配置读取失败时视为关闭）
+        let hedge_delay = state
+            .db
+            .get_hedging_config(self.app_type_str)
+            .ok()
+            .filter(|config| config.enabled)
+            .map(|config| Duration::from_millis(config.delay_ms as u64));
+
         RequestForwarder::new(
             state.provider_router.clone(),
             non_streaming_timeout,
@@ -239,11 +360,21 @@ impl RequestContext {
             state.current_providers.clone(),
             state.failover_manager.clone(),
             state.app_handle.clone(),
-            self.current_provider_id.clone(),
+            current_provider_id,
             first_byte_timeout,
             idle_timeout,
             self.rectifier_config.clone(),
+            self.hook_scripts.clone(),
         )
+        .with_trace(self.trace.clone())
+        .with_model_override(self.routing.model.clone())
+        .with_hedging(hedge_delay)
+        .with_current_provider_sync(self.routing_rule.is_none())
+    }
+
+    /// 路由覆盖的日志值（用于请求日志）
+    pub fn routing_override(&self) -> Option<String> {
+        self.routing.to_log_value()
     }
 
     /// 获取 Provider 列表（用于故障转移）
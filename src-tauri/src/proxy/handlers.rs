@@ -8,20 +8,33 @@
 //! - Claude 的格式转换逻辑保留在此文件（用于 OpenRouter 旧接口回退）
 
 use super::{
+    client_auth::ClientIdentity,
     error_mapper::{get_error_message, map_proxy_error_to_status},
+    forwarder::HedgeLoser,
     handler_config::{
         CLAUDE_PARSER_CONFIG, CODEX_PARSER_CONFIG, GEMINI_PARSER_CONFIG, OPENAI_PARSER_CONFIG,
     },
     handler_context::RequestContext,
+    metrics, model_catalog,
     providers::{get_adapter, streaming::create_anthropic_sse_stream, transform},
-    response_processor::{create_logged_passthrough_stream, process_response, SseUsageCollector},
+    response_processor::{
+        create_logged_passthrough_stream, process_response, process_response_with_resume,
+        stream_event_hook, SseUsageCollector,
+    },
     server::ProxyState,
+    stream_resume::{ResumeProtocol, StreamResume},
+    token_estimator::estimate_input_tokens,
     types::*,
     usage::parser::TokenUsage,
     ProxyError,
 };
 use crate::app_config::AppType;
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
 use serde_json::{json, Value};
 
 // ============================================================================
@@ -45,6 +58,20 @@ pub async fn get_status(State(state): State<ProxyState>) -> Result<Json<ProxySta
     Ok(Json(status))
 }
 
+/// Prometheus 指标
+pub async fn handle_metrics(State(state): State<ProxyState>) -> axum::response::Response {
+    let status = state.status.read().await.clone();
+    let providers = metrics::collect_provider_snapshots(&state).await;
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        state.metrics.render(&status, &providers),
+    )
+        .into_response()
+}
+
 // ============================================================================
 // Claude API 处理器（包含格式转换逻辑）
 // ============================================================================
@@ -56,18 +83,37 @@ pub async fn get_status(State(state): State<ProxyState>) -> Result<Json<ProxySta
 /// - 现在 OpenRouter 已推出 Claude Code 兼容接口，默认不再启用该转换（逻辑保留以备回退）
 pub async fn handle_messages(
     State(state): State<ProxyState>,
+    client: Option<Extension<ClientIdentity>>,
     headers: axum::http::HeaderMap,
     Json(body): Json<Value>,
 ) -> Result<axum::response::Response, ProxyError> {
-    let mut ctx = RequestContext::new(&state, &body, &headers, AppType::Claude, "Claude", "claude")
-        .await?
-        .with_request_endpoint("/v1/messages");
+    let mut ctx = RequestContext::new(
+        &state,
+        &body,
+        &headers,
+        "/v1/messages",
+        client.map(|c| c.0),
+        AppType::Claude,
+        "Claude",
+        "claude",
+    )
+    .await?;
 
     let is_stream = body
         .get("stream")
         .and_then(|s| s.as_bool())
         .unwrap_or(false);
 
+    // 断流续传需要保留原始请求（转发会消耗请求头）
+    let resume = StreamResume::prepare(
+        &state,
+        &ctx,
+        ResumeProtocol::Anthropic,
+        "/v1/messages",
+        &body,
+        &headers,
+    );
+
     // 转发请求
     let forwarder = ctx.create_forwarder(&state);
     let result = match forwarder
@@ -85,12 +131,17 @@ pub async fn handle_messages(
             if let Some(provider) = err.provider.take() {
                 ctx.provider = provider;
             }
+            ctx.api_key_id = err.api_key_id.take();
             log_forward_error(&state, &ctx, is_stream, &err.error).await;
             return Err(err.error);
         }
     };
 
+    if let Some(loser) = result.hedge_loser {
+        log_hedge_loser(&state, &ctx, &result.provider, loser);
+    }
     ctx.provider = result.provider;
+    ctx.api_key_id = result.api_key_id;
     let response = result.response;
     // 设置映射后的模型（如果有映射）
     ctx.set_mapped_model(result.mapped_model);
@@ -104,8 +155,100 @@ pub async fn handle_messages(
         return handle_claude_transform(response, &ctx, &state, &body, is_stream).await;
     }
 
+    // 续传时跳过流开始前已经尝试过的供应商
+    let resume =
+        resume.map(|resume| resume.with_tried_providers(forwarder.attempted_provider_ids()));
     // 通用响应处理（透传模式）
-    process_response(response, &ctx, &state, &CLAUDE_PARSER_CONFIG).await
+    process_response_with_resume(response, &ctx, &state, &CLAUDE_PARSER_CONFIG, resume).await
+}
+
+/// 处理 /v1/messages/count_tokens 请求（Claude API）
+///
+/// 与 `handle_messages` 使用相同的供应商选择、模型映射与认证，但：
+/// - 上游为 `openai_chat` 格式（没有等价接口）或请求失败时，使用本地估算结果
+/// - 不记录用量与费用，不参与熔断与故障转移
+pub async fn handle_count_tokens(
+    State(state): State<ProxyState>,
+    client: Option<Extension<ClientIdentity>>,
+    headers: axum::http::HeaderMap,
+    Json(body): Json<Value>,
+) -> Result<axum::response::Response, ProxyError> {
+    let ctx = RequestContext::new(
+        &state,
+        &body,
+        &headers,
+        "/v1/messages/count_tokens",
+        client.map(|c| c.0),
+        AppType::Claude,
+        "Claude",
+        "claude",
+    )
+    .await?;
+
+    let adapter = get_adapter(&AppType::Claude);
+    if adapter.needs_transform(&ctx.provider) {
+        return Ok(local_count_tokens(&body));
+    }
+
+    let forwarder = ctx.create_forwarder(&state);
+    let response = match forwarder
+        .forward_direct(
+            &AppType::Claude,
+            "/v1/messages/count_tokens",
+            &body,
+            &headers,
+            &ctx.provider,
+        )
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            log::debug!(
+                "[Claude] Provider {} count_tokens 请求失败，使用本地估算: {e}",
+                ctx.provider.name
+            );
+            return Ok(local_count_tokens(&body));
+        }
+    };
+
+    let status = response.status();
+    let response_headers = response.headers().clone();
+    let body_bytes = match response.bytes().await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            log::debug!("[Claude] 读取 count_tokens 响应失败，使用本地估算: {e}");
+            return Ok(local_count_tokens(&body));
+        }
+    };
+
+    build_passthrough_response(status, &response_headers, body_bytes)
+}
+
+/// 原样构建上游响应（跳过由 axum 重新计算的长度相关头）
+fn build_passthrough_response(
+    status: StatusCode,
+    headers: &axum::http::HeaderMap,
+    body: bytes::Bytes,
+) -> Result<axum::response::Response, ProxyError> {
+    let mut builder = axum::response::Response::builder().status(status);
+    for (key, value) in headers.iter() {
+        if key.as_str().eq_ignore_ascii_case("content-length")
+            || key.as_str().eq_ignore_ascii_case("transfer-encoding")
+        {
+            continue;
+        }
+        builder = builder.header(key, value);
+    }
+    builder
+        .body(axum::body::Body::from(body))
+        .map_err(|e| ProxyError::Internal(format!("Failed to build response: {e}")))
+}
+
+/// 本地估算的 count_tokens 响应
+fn local_count_tokens(body: &Value) -> axum::response::Response {
+    let input_tokens = estimate_input_tokens(body);
+    log::debug!("[Claude] count_tokens 本地估算: {input_tokens}");
+    Json(json!({ "input_tokens": input_tokens })).into_response()
 }
 
 /// Claude 格式转换处理（独有逻辑）
@@ -132,6 +275,9 @@ async fn handle_claude_transform(
             let model = ctx.request_model.clone();
             let status_code = status.as_u16();
             let start_time = ctx.start_time;
+            let api_key_id = ctx.api_key_id.clone();
+            let client_token_id = ctx.client_token_id();
+            let routing_override = ctx.routing_override();
 
             SseUsageCollector::new(start_time, move |events, first_token_ms| {
                 if let Some(usage) = TokenUsage::from_claude_stream_events(&events) {
@@ -139,6 +285,9 @@ async fn handle_claude_transform(
                     let state = state.clone();
                     let provider_id = provider_id.clone();
                     let model = model.clone();
+                    let api_key_id = api_key_id.clone();
+                    let client_token_id = client_token_id.clone();
+                    let routing_override = routing_override.clone();
 
                     tokio::spawn(async move {
                         log_usage(
@@ -152,6 +301,9 @@ async fn handle_claude_transform(
                             first_token_ms,
                             true,
                             status_code,
+                            api_key_id,
+                            client_token_id,
+                            routing_override,
                         )
                         .await;
                     });
@@ -169,6 +321,8 @@ async fn handle_claude_transform(
             "Claude/OpenRouter",
             Some(usage_collector),
             timeout_config,
+            stream_event_hook(ctx),
+            Some(state.metrics.track_stream(ctx.app_type_str)),
         );
 
         let mut headers = axum::http::HeaderMap::new();
@@ -222,6 +376,9 @@ async fn handle_claude_transform(
             let state = state.clone();
             let provider_id = ctx.provider.id.clone();
             let model = model.to_string();
+            let api_key_id = ctx.api_key_id.clone();
+            let client_token_id = ctx.client_token_id();
+            let routing_override = ctx.routing_override();
             async move {
                 log_usage(
                     &state,
@@ -234,6 +391,9 @@ async fn handle_claude_transform(
                     None,
                     false,
                     status.as_u16(),
+                    api_key_id,
+                    client_token_id,
+                    routing_override,
                 )
                 .await;
             }
@@ -272,12 +432,21 @@ async fn handle_claude_transform(
 /// 处理 /v1/chat/completions 请求（OpenAI Chat Completions API - Codex CLI）
 pub async fn handle_chat_completions(
     State(state): State<ProxyState>,
+    client: Option<Extension<ClientIdentity>>,
     headers: axum::http::HeaderMap,
     Json(body): Json<Value>,
 ) -> Result<axum::response::Response, ProxyError> {
-    let mut ctx = RequestContext::new(&state, &body, &headers, AppType::Codex, "Codex", "codex")
-        .await?
-        .with_request_endpoint("/chat/completions");
+    let mut ctx = RequestContext::new(
+        &state,
+        &body,
+        &headers,
+        "/chat/completions",
+        client.map(|c| c.0),
+        AppType::Codex,
+        "Codex",
+        "codex",
+    )
+    .await?;
 
     let is_stream = body
         .get("stream")
@@ -300,12 +469,17 @@ pub async fn handle_chat_completions(
             if let Some(provider) = err.provider.take() {
                 ctx.provider = provider;
             }
+            ctx.api_key_id = err.api_key_id.take();
             log_forward_error(&state, &ctx, is_stream, &err.error).await;
             return Err(err.error);
         }
     };
 
+    if let Some(loser) = result.hedge_loser {
+        log_hedge_loser(&state, &ctx, &result.provider, loser);
+    }
     ctx.provider = result.provider;
+    ctx.api_key_id = result.api_key_id;
     let response = result.response;
     // 设置映射后的模型（如果有映射）
     ctx.set_mapped_model(result.mapped_model);
@@ -316,18 +490,37 @@ pub async fn handle_chat_completions(
 /// 处理 /v1/responses 请求（OpenAI Responses API - Codex CLI 透传）
 pub async fn handle_responses(
     State(state): State<ProxyState>,
+    client: Option<Extension<ClientIdentity>>,
     headers: axum::http::HeaderMap,
     Json(body): Json<Value>,
 ) -> Result<axum::response::Response, ProxyError> {
-    let mut ctx = RequestContext::new(&state, &body, &headers, AppType::Codex, "Codex", "codex")
-        .await?
-        .with_request_endpoint("/responses");
+    let mut ctx = RequestContext::new(
+        &state,
+        &body,
+        &headers,
+        "/responses",
+        client.map(|c| c.0),
+        AppType::Codex,
+        "Codex",
+        "codex",
+    )
+    .await?;
 
     let is_stream = body
         .get("stream")
         .and_then(|v| v.as_bool())
         .unwrap_or(false);
 
+    // 断流续传需要保留原始请求（转发会消耗请求体与请求头）
+    let resume = StreamResume::prepare(
+        &state,
+        &ctx,
+        ResumeProtocol::Responses,
+        "/responses",
+        &body,
+        &headers,
+    );
+
     let forwarder = ctx.create_forwarder(&state);
     let result = match forwarder
         .forward_with_retry(
@@ -344,17 +537,93 @@ pub async fn handle_responses(
             if let Some(provider) = err.provider.take() {
                 ctx.provider = provider;
             }
+            ctx.api_key_id = err.api_key_id.take();
             log_forward_error(&state, &ctx, is_stream, &err.error).await;
             return Err(err.error);
         }
     };
 
+    if let Some(loser) = result.hedge_loser {
+        log_hedge_loser(&state, &ctx, &result.provider, loser);
+    }
     ctx.provider = result.provider;
+    ctx.api_key_id = result.api_key_id;
     let response = result.response;
     // 设置映射后的模型（如果有映射）
     ctx.set_mapped_model(result.mapped_model);
 
-    process_response(response, &ctx, &state, &CODEX_PARSER_CONFIG).await
+    // 续传时跳过流开始前已经尝试过的供应商
+    let resume =
+        resume.map(|resume| resume.with_tried_providers(forwarder.attempted_provider_ids()));
+    process_response_with_resume(response, &ctx, &state, &CODEX_PARSER_CONFIG, resume).await
+}
+
+// ============================================================================
+// 模型列表
+// ============================================================================
+
+/// 处理 GET /v1/models 请求
+///
+/// 带 `anthropic-version` 头（Anthropic SDK / Claude Code）时返回 Claude 的模型列表（Anthropic 格式），
+/// 否则返回 Codex 的模型列表（OpenAI 格式）。
+pub async fn handle_models(
+    State(state): State<ProxyState>,
+    client: Option<Extension<ClientIdentity>>,
+    headers: axum::http::HeaderMap,
+) -> Json<Value> {
+    if headers.contains_key("anthropic-version") {
+        handle_claude_models(State(state), client).await
+    } else {
+        handle_codex_models(State(state), client).await
+    }
+}
+
+/// 处理 GET /claude/v1/models 请求（Anthropic 格式）
+pub async fn handle_claude_models(
+    State(state): State<ProxyState>,
+    client: Option<Extension<ClientIdentity>>,
+) -> Json<Value> {
+    let models = state
+        .model_catalog
+        .list(
+            &state.provider_router,
+            &AppType::Claude,
+            client.as_ref().map(|c| &c.0),
+        )
+        .await;
+    Json(model_catalog::to_anthropic_list(&models))
+}
+
+/// 处理 GET /models、/codex/v1/models 请求（OpenAI 格式）
+pub async fn handle_codex_models(
+    State(state): State<ProxyState>,
+    client: Option<Extension<ClientIdentity>>,
+) -> Json<Value> {
+    let models = state
+        .model_catalog
+        .list(
+            &state.provider_router,
+            &AppType::Codex,
+            client.as_ref().map(|c| &c.0),
+        )
+        .await;
+    Json(model_catalog::to_openai_list(&models))
+}
+
+/// 处理 GET /v1beta/models 请求（Gemini 格式）
+pub async fn handle_gemini_models(
+    State(state): State<ProxyState>,
+    client: Option<Extension<ClientIdentity>>,
+) -> Json<Value> {
+    let models = state
+        .model_catalog
+        .list(
+            &state.provider_router,
+            &AppType::Gemini,
+            client.as_ref().map(|c| &c.0),
+        )
+        .await;
+    Json(model_catalog::to_gemini_list(&models))
 }
 
 // ============================================================================
@@ -364,21 +633,30 @@ pub async fn handle_responses(
 /// 处理 Gemini API 请求（透传，包括查询参数）
 pub async fn handle_gemini(
     State(state): State<ProxyState>,
+    client: Option<Extension<ClientIdentity>>,
     uri: axum::http::Uri,
     headers: axum::http::HeaderMap,
     Json(body): Json<Value>,
 ) -> Result<axum::response::Response, ProxyError> {
-    // Gemini 的模型名称在 URI 中
-    let mut ctx = RequestContext::new(&state, &body, &headers, AppType::Gemini, "Gemini", "gemini")
-        .await?
-        .with_model_from_uri(&uri);
-
     // 提取完整的路径和查询参数
     let endpoint = uri
         .path_and_query()
         .map(|pq| pq.as_str())
         .unwrap_or(uri.path());
-    ctx = ctx.with_request_endpoint(endpoint);
+
+    // Gemini 的模型名称在 URI 中
+    let mut ctx = RequestContext::new(
+        &state,
+        &body,
+        &headers,
+        endpoint,
+        client.map(|c| c.0),
+        AppType::Gemini,
+        "Gemini",
+        "gemini",
+    )
+    .await?
+    .with_model_from_uri(&uri);
 
     let is_stream = body
         .get("stream")
@@ -401,12 +679,17 @@ pub async fn handle_gemini(
             if let Some(provider) = err.provider.take() {
                 ctx.provider = provider;
             }
+            ctx.api_key_id = err.api_key_id.take();
             log_forward_error(&state, &ctx, is_stream, &err.error).await;
             return Err(err.error);
         }
     };
 
+    if let Some(loser) = result.hedge_loser {
+        log_hedge_loser(&state, &ctx, &result.provider, loser);
+    }
     ctx.provider = result.provider;
+    ctx.api_key_id = result.api_key_id;
     let response = result.response;
     // 设置映射后的模型（如果有映射）
     ctx.set_mapped_model(result.mapped_model);
@@ -414,6 +697,90 @@ pub async fn handle_gemini(
     process_response(response, &ctx, &state, &GEMINI_PARSER_CONFIG).await
 }
 
+/// 处理 Gemini CLI Code Assist 请求（OAuth 模式，`/v1internal:{method}`）
+///
+/// - `generateContent` / `streamGenerateContent`：走完整的转发流程（故障转移、用量记录）
+/// - 其余方法（`countTokens`、`loadCodeAssist` 等）：直接透传到当前供应商，不记录用量
+pub async fn handle_gemini_cli(
+    State(state): State<ProxyState>,
+    client: Option<Extension<ClientIdentity>>,
+    Path(method): Path<String>,
+    uri: axum::http::Uri,
+    headers: axum::http::HeaderMap,
+    Json(body): Json<Value>,
+) -> Result<axum::response::Response, ProxyError> {
+    // 路由参数从 `v1internal` 之后开始匹配，必须是 `:{method}` 形式；
+    // `/v1internalFoo` 之类的路径同样会命中该路由，按未知路径处理
+    let Some(method) = method.strip_prefix(':').filter(|m| !m.is_empty()) else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    let endpoint = match uri.query() {
+        Some(query) => format!("/v1internal:{method}?{query}"),
+        None => format!("/v1internal:{method}"),
+    };
+    // Code Assist 请求的模型在请求体的 model 字段中
+    let mut ctx = RequestContext::new(
+        &state,
+        &body,
+        &headers,
+        &endpoint,
+        client.map(|c| c.0),
+        AppType::Gemini,
+        "Gemini",
+        "gemini",
+    )
+    .await?;
+
+    let is_stream = match method {
+        "generateContent" => false,
+        "streamGenerateContent" => true,
+        _ => {
+            let forwarder = ctx.create_forwarder(&state);
+            let response = forwarder
+                .forward_direct(&AppType::Gemini, &endpoint, &body, &headers, &ctx.provider)
+                .await?;
+            let status = response.status();
+            let response_headers = response.headers().clone();
+            let body_bytes = response
+                .bytes()
+                .await
+                .map_err(|e| ProxyError::ForwardFailed(format!("读取 {method} 响应失败: {e}")))?;
+            return build_passthrough_response(status, &response_headers, body_bytes);
+        }
+    };
+
+    let forwarder = ctx.create_forwarder(&state);
+    let result = match forwarder
+        .forward_with_retry(
+            &AppType::Gemini,
+            &endpoint,
+            body,
+            headers,
+            ctx.get_providers(),
+        )
+        .await
+    {
+        Ok(result) => result,
+        Err(mut err) => {
+            if let Some(provider) = err.provider.take() {
+                ctx.provider = provider;
+            }
+            ctx.api_key_id = err.api_key_id.take();
+            log_forward_error(&state, &ctx, is_stream, &err.error).await;
+            return Err(err.error);
+        }
+    };
+
+    if let Some(loser) = result.hedge_loser {
+        log_hedge_loser(&state, &ctx, &result.provider, loser);
+    }
+    ctx.provider = result.provider;
+    ctx.api_key_id = result.api_key_id;
+    ctx.set_mapped_model(result.mapped_model);
+
+    process_response(result.response, &ctx, &state, &GEMINI_PARSER_CONFIG).await
+}
+
 // ============================================================================
 // 使用量记录（保留用于 Claude 转换逻辑）
 // ============================================================================
@@ -426,11 +793,15 @@ async fn log_forward_error(
 ) {
     use super::usage::logger::UsageLogger;
 
-    let logger = UsageLogger::new(state.db.clone());
+    let logger = UsageLogger::new(state.db.clone()).with_metrics(state.metrics.clone());
     let status_code = map_proxy_error_to_status(error);
     let error_message = get_error_message(error);
     let request_id = uuid::Uuid::new_v4().to_string();
 
+    ctx.trace
+        .set_attribute("http.response.status_code", status_code);
+    ctx.trace.set_error(error_message.clone());
+
     if let Err(e) = logger
         .log_error_with_context(
             request_id,
@@ -443,6 +814,9 @@ async fn log_forward_error(
             is_streaming,
             Some(ctx.session_id.clone()),
             None,
+            ctx.api_key_id.clone(),
+            ctx.client_token_id(),
+            ctx.routing_override(),
         )
         .await
     {
@@ -450,6 +824,80 @@ async fn log_forward_error(
     }
 }
 
+/// 记录对冲请求中被取消的一方
+///
+/// 上游已返回响应头（已开始处理请求）时，按估算的输入 Token 计算费用；状态码记为 499。
+fn log_hedge_loser(
+    state: &ProxyState,
+    ctx: &RequestContext,
+    winner: &crate::provider::Provider,
+    loser: HedgeLoser,
+) {
+    use super::usage::{
+        calculator::CostCalculator,
+        logger::{RequestLog, UsageLogger},
+    };
+
+    let logger = UsageLogger::new(state.db.clone()).with_metrics(state.metrics.clone());
+    let app_type = ctx.app_type_str;
+    let request_model = ctx.request_model.clone();
+    let session_id = ctx.session_id.clone();
+    let client_token_id = ctx.client_token_id();
+    let routing_override = ctx.routing_override();
+    let error_message = format!("对冲请求已取消：{} 先返回首字节", winner.name);
+
+    tokio::spawn(async move {
+        let model = loser
+            .mapped_model
+            .clone()
+            .unwrap_or_else(|| request_model.clone());
+        let usage = TokenUsage {
+            input_tokens: loser.estimated_input_tokens,
+            ..Default::default()
+        };
+
+        let (multiplier, pricing_model_source) = logger
+            .resolve_pricing_config(&loser.provider.id, app_type)
+            .await;
+        let pricing_model = if pricing_model_source == "request" {
+            &request_model
+        } else {
+            &model
+        };
+        let cost = match logger.get_model_pricing(pricing_model).await {
+            Ok(pricing) => CostCalculator::try_calculate(&usage, pricing.as_ref(), multiplier),
+            Err(e) => {
+                log::warn!("[USG-002] 查询模型定价失败: {e}");
+                None
+            }
+        };
+
+        let log = RequestLog {
+            request_id: uuid::Uuid::new_v4().to_string(),
+            provider_id: loser.provider.id,
+            app_type: app_type.to_string(),
+            model,
+            request_model,
+            usage,
+            cost,
+            latency_ms: loser.latency_ms,
+            first_token_ms: None,
+            status_code: 499,
+            error_message: Some(error_message),
+            session_id: Some(session_id),
+            provider_type: None,
+            is_streaming: true,
+            cost_multiplier: multiplier.to_string(),
+            api_key_id: loser.api_key_id,
+            client_token_id,
+            routing_override,
+        };
+        if let Err(e) = logger.log_request(log).await {
+            log::warn!("[USG-001] 记录对冲请求日志失败: {e}");
+        }
+    });
+}
+
 /// 记录请求使用量
 #[allow(clippy::too_many_arguments)]
 async fn log_usage(
@@ -463,10 +911,13 @@ async fn log_usage(
     first_token_ms: Option<u64>,
     is_streaming: bool,
     status_code: u16,
+    api_key_id: Option<String>,
+    client_token_id: Option<String>,
+    routing_override: Option<String>,
 ) {
     use super::usage::logger::UsageLogger;
 
-    let logger = UsageLogger::new(state.db.clone());
+    let logger = UsageLogger::new(state.db.clone()).with_metrics(state.metrics.clone());
 
     let (multiplier, pricing_model_source) =
         logger.resolve_pricing_config(provider_id, app_type).await;
@@ -494,6 +945,9 @@ async fn log_usage(
             None,
             None, // provider_type
             is_streaming,
+            api_key_id,
+            client_token_id,
+            routing_override,
         )
         .await
     {
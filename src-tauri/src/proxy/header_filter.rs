@@ -49,6 +49,10 @@ pub(crate) const HEADER_BLACKLIST: &[&str] = &[
     // 客户端 IP 单独处理（默认透传）
     "x-forwarded-for",
     "x-real-ip",
+    // 单次请求路由覆盖（仅代理自身使用）
+    "x-ccswitch-provider",
+    "x-ccswitch-model",
+    "x-ccswitch-no-failover",
 ];
 
 pub(crate) fn is_header_blacklisted(name: &str) -> bool {
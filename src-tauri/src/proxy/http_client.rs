@@ -213,8 +213,26 @@ pub fn is_proxy_enabled() -> bool {
     get_current_proxy_url().is_some()
 }
 
+/// 构建使用当前全局代理配置、但自定义重定向策略的 HTTP 客户端
+///
+/// 用于需要逐跳校验重定向目标的场景（如脚本 `fetch`）。
+pub fn build_with_redirect_policy(policy: reqwest::redirect::Policy) -> Result<Client, String> {
+    let proxy_url = get_current_proxy_url();
+    client_builder(proxy_url.as_deref())?
+        .redirect(policy)
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {e}"))
+}
+
 /// 构建 HTTP 客户端
 fn build_client(proxy_url: Option<&str>) -> Result<Client, String> {
+    client_builder(proxy_url)?
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {e}"))
+}
+
+/// 按代理配置准备客户端构建器
+fn client_builder(proxy_url: Option<&str>) -> Result<reqwest::ClientBuilder, String> {
     let mut builder = Client::builder()
         .timeout(Duration::from_secs(600))
         .connect_timeout(Duration::from_secs(30))
@@ -253,9 +271,7 @@ fn build_client(proxy_url: Option<&str>) -> Result<Client, String> {
         }
     }
 
-    builder
-        .build()
-        .map_err(|e| format!("Failed to build HTTP client: {e}"))
+    Ok(builder)
 }
 
 fn system_proxy_points_to_loopback() -> bool {
@@ -0,0 +1,194 @@
+//! API Key 池
+//!
+//! 为启用了 Key 池的供应商按轮换策略决定每个请求的 Key 尝试顺序。
+//! 每个 Key 的熔断状态由 `ProviderRouter` 统一管理（熔断器 key 格式: "app_type:provider_id#key_id"）。
+
+use crate::provider::{ApiKeyEntry, ApiKeyRotationStrategy};
+use crate::proxy::ProxyError;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// 为本次请求选中的 Key
+#[derive(Debug, Clone)]
+pub struct SelectedApiKey {
+    /// Key 标识
+    pub id: String,
+    /// API Key
+    pub key: String,
+    /// 是否占用了该 Key 熔断器的 HalfOpen 探测名额
+    pub used_half_open_permit: bool,
+}
+
+/// Key 熔断器的 key
+pub fn key_circuit_key(app_type: &str, provider_id: &str, key_id: &str) -> String {
+    format!("{app_type}:{provider_id}#{key_id}")
+}
+
+/// 判断错误是否说明 Key 本身被上游拒绝（鉴权失败或限流）
+///
+/// 这类错误换一个 Key 即可能成功，不应直接切换供应商。
+pub fn is_key_rejection(error: &ProxyError) -> bool {
+    matches!(
+        error,
+        ProxyError::UpstreamError {
+            status: 401 | 403 | 429,
+            ..
+        }
+    )
+}
+
+/// Key 轮换状态（进程内，代理重启后重置）
+#[derive(Default)]
+pub struct ApiKeySelector {
+    /// 轮询游标 - key: "app_type:provider_id"
+    cursors: Mutex<HashMap<String, usize>>,
+    /// 请求计数 - key: "app_type:provider_id#key_id"
+    usage: Mutex<HashMap<String, u64>>,
+}
+
+impl ApiKeySelector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 按策略返回候选 Key 的尝试顺序
+    ///
+    /// - RoundRobin：从游标位置开始轮转，每次调用游标前进一位
+    /// - LeastUsed：按请求次数升序（次数相同时保持配置顺序）
+    /// - Failover：保持配置顺序
+    pub fn order_candidates<'a>(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        strategy: ApiKeyRotationStrategy,
+        keys: &[&'a ApiKeyEntry],
+    ) -> Vec<&'a ApiKeyEntry> {
+        let mut ordered = keys.to_vec();
+        if ordered.len() < 2 {
+            return ordered;
+        }
+
+        match strategy {
+            ApiKeyRotationStrategy::RoundRobin => {
+                let scope = format!("{app_type}:{provider_id}");
+                let mut cursors = self.cursors.lock().unwrap_or_else(|e| e.into_inner());
+                let cursor = cursors.entry(scope).or_insert(0);
+                let start = *cursor % ordered.len();
+                *cursor = (start + 1) % ordered.len();
+                ordered.rotate_left(start);
+            }
+            ApiKeyRotationStrategy::LeastUsed => {
+                let usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+                ordered.sort_by_key(|k| {
+                    usage
+                        .get(&key_circuit_key(app_type, provider_id, &k.id))
+                        .copied()
+                        .unwrap_or(0)
+                });
+            }
+            ApiKeyRotationStrategy::Failover => {}
+        }
+
+        ordered
+    }
+
+    /// 记录一次 Key 使用
+    pub fn record_use(&self, app_type: &str, provider_id: &str, key_id: &str) {
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        *usage
+            .entry(key_circuit_key(app_type, provider_id, key_id))
+            .or_insert(0) += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str) -> ApiKeyEntry {
+        ApiKeyEntry {
+            id: id.to_string(),
+            key: format!("sk-{id}"),
+            label: None,
+            enabled: true,
+        }
+    }
+
+    fn ids(keys: &[&ApiKeyEntry]) -> Vec<String> {
+        keys.iter().map(|k| k.id.clone()).collect()
+    }
+
+    #[test]
+    fn round_robin_rotates_start_key() {
+        let selector = ApiKeySelector::new();
+        let (a, b, c) = (entry("a"), entry("b"), entry("c"));
+        let keys = vec![&a, &b, &c];
+        let strategy = ApiKeyRotationStrategy::RoundRobin;
+
+        assert_eq!(
+            ids(&selector.order_candidates("claude", "p1", strategy, &keys)),
+            vec!["a", "b", "c"]
+        );
+        assert_eq!(
+            ids(&selector.order_candidates("claude", "p1", strategy, &keys)),
+            vec!["b", "c", "a"]
+        );
+        assert_eq!(
+            ids(&selector.order_candidates("claude", "p1", strategy, &keys)),
+            vec!["c", "a", "b"]
+        );
+        // 不同供应商使用独立游标
+        assert_eq!(
+            ids(&selector.order_candidates("claude", "p2", strategy, &keys)),
+            vec!["a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn least_used_prefers_fewest_requests() {
+        let selector = ApiKeySelector::new();
+        let (a, b, c) = (entry("a"), entry("b"), entry("c"));
+        let keys = vec![&a, &b, &c];
+        selector.record_use("claude", "p1", "a");
+        selector.record_use("claude", "p1", "a");
+        selector.record_use("claude", "p1", "c");
+
+        assert_eq!(
+            ids(&selector.order_candidates(
+                "claude",
+                "p1",
+                ApiKeyRotationStrategy::LeastUsed,
+                &keys
+            )),
+            vec!["b", "c", "a"]
+        );
+    }
+
+    #[test]
+    fn failover_keeps_configured_order() {
+        let selector = ApiKeySelector::new();
+        let (a, b) = (entry("a"), entry("b"));
+        let keys = vec![&a, &b];
+        for _ in 0..3 {
+            assert_eq!(
+                ids(&selector.order_candidates(
+                    "codex",
+                    "p1",
+                    ApiKeyRotationStrategy::Failover,
+                    &keys
+                )),
+                vec!["a", "b"]
+            );
+        }
+    }
+
+    #[test]
+    fn key_rejection_covers_auth_and_rate_limit() {
+        let upstream = |status| ProxyError::UpstreamError { status, body: None };
+        assert!(is_key_rejection(&upstream(401)));
+        assert!(is_key_rejection(&upstream(403)));
+        assert!(is_key_rejection(&upstream(429)));
+        assert!(!is_key_rejection(&upstream(500)));
+        assert!(!is_key_rejection(&ProxyError::Timeout("t".to_string())));
+    }
+}
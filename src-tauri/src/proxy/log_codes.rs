@@ -32,6 +32,8 @@ pub mod srv {
 pub mod fwd {
     pub const PROVIDER_FAILED_RETRY: &str = "FWD-001";
     pub const ALL_PROVIDERS_FAILED: &str = "FWD-002";
+    pub const HEDGE_STARTED: &str = "FWD-003";
+    pub const HEDGE_CANCELLED: &str = "FWD-004";
 }
 
 /// 故障转移日志码
@@ -50,6 +52,8 @@ pub mod rsp {
     pub const BUILD_RESPONSE_ERROR: &str = "RSP-003";
     pub const STREAM_TIMEOUT: &str = "RSP-004";
     pub const STREAM_ERROR: &str = "RSP-005";
+    pub const STREAM_RESUMED: &str = "RSP-006";
+    pub const STREAM_RESUME_FAILED: &str = "RSP-007";
 }
 
 /// 使用量日志码
@@ -0,0 +1,608 @@
+//! Prometheus 指标
+//!
+//! 在 `/metrics` 以 Prometheus 文本格式（0.0.4）暴露代理运行指标：
+//! - 请求计数与耗时直方图（按应用 / 供应商 / 模型 / 状态码）
+//! - 首字耗时直方图、Token 与费用计数（按应用 / 供应商 / 模型）
+//! - 各供应商熔断器状态、故障转移次数、进行中的流式响应数
+//!
+//! 请求指标在写入请求日志时同步记录（见 `UsageLogger::with_metrics`），
+//! 熔断器状态与故障转移次数在抓取时实时读取。
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+
+use rust_decimal::prelude::ToPrimitive;
+
+use super::circuit_breaker::{CircuitBreakerStats, CircuitState};
+use super::server::ProxyState;
+use super::types::ProxyStatus;
+use super::usage::logger::RequestLog;
+use crate::app_config::AppType;
+
+/// 指标名前缀
+const PREFIX: &str = "cc_switch_proxy";
+
+/// 请求总耗时直方图桶（秒）
+const LATENCY_BUCKETS: &[f64] = &[
+    0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0,
+];
+
+/// 每个应用 / 供应商下单独统计的模型数上限
+///
+/// 模型名来自客户端请求，超出上限的模型归入 `model="other"`，避免时间序列无限增长。
+const MAX_MODELS_PER_PROVIDER: usize = 50;
+
+/// 超出模型数上限时使用的模型标签
+const OTHER_MODEL: &str = "other";
+
+/// 首字耗时直方图桶（秒）
+const FIRST_TOKEN_BUCKETS: &[f64] = &[0.1, 0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 30.0];
+
+/// 固定桶直方图（桶内计数为非累积值，输出时再累加）
+#[derive(Debug, Clone)]
+struct Histogram {
+    bounds: &'static [f64],
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            counts: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        if let Some(idx) = self.bounds.iter().position(|bound| value <= *bound) {
+            self.counts[idx] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, count) in self.bounds.iter().zip(&self.counts) {
+            cumulative += count;
+            let _ = writeln!(out, "{name}_bucket{{{labels},le=\"{bound}\"}} {cumulative}");
+        }
+        let _ = writeln!(out, "{name}_bucket{{{labels},le=\"+Inf\"}} {}", self.count);
+        let _ = writeln!(out, "{name}_sum{{{labels}}} {}", self.sum);
+        let _ = writeln!(out, "{name}_count{{{labels}}} {}", self.count);
+    }
+}
+
+/// 指标维度（应用 / 供应商 / 模型）
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct SeriesKey {
+    app: String,
+    provider: String,
+    model: String,
+}
+
+impl SeriesKey {
+    fn labels(&self) -> String {
+        format!(
+            "app=\"{}\",provider=\"{}\",model=\"{}\"",
+            escape_label(&self.app),
+            escape_label(&self.provider),
+            escape_label(&self.model)
+        )
+    }
+}
+
+/// 单个状态码下的请求统计
+#[derive(Debug, Clone)]
+struct StatusSeries {
+    requests: u64,
+    latency: Histogram,
+}
+
+/// 单个应用 / 供应商 / 模型组合的统计
+#[derive(Debug, Clone)]
+struct ModelSeries {
+    by_status: BTreeMap<u16, StatusSeries>,
+    first_token: Histogram,
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_read_tokens: u64,
+    cache_creation_tokens: u64,
+    cost_usd: f64,
+}
+
+impl Default for ModelSeries {
+    fn default() -> Self {
+        Self {
+            by_status: BTreeMap::new(),
+            first_token: Histogram::new(FIRST_TOKEN_BUCKETS),
+            input_tokens: 0,
+            output_tokens: 0,
+            cache_read_tokens: 0,
+            cache_creation_tokens: 0,
+            cost_usd: 0.0,
+        }
+    }
+}
+
+/// 抓取时的供应商快照（用于输出供应商信息与熔断器状态）
+#[derive(Debug, Clone)]
+pub struct ProviderSnapshot {
+    pub app: String,
+    pub id: String,
+    pub name: String,
+    /// 尚未创建熔断器（未被请求过）时为 None
+    pub breaker: Option<CircuitBreakerStats>,
+}
+
+/// 代理指标注册表（跨请求共享）
+#[derive(Debug, Default)]
+pub struct ProxyMetrics {
+    series: Mutex<BTreeMap<SeriesKey, ModelSeries>>,
+    active_streams: Mutex<BTreeMap<String, i64>>,
+}
+
+impl ProxyMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一次已完成（或失败）的请求
+    pub fn record_request(&self, log: &RequestLog) {
+        let mut key = SeriesKey {
+            app: log.app_type.clone(),
+            provider: log.provider_id.clone(),
+            model: log.model.clone(),
+        };
+        let mut series = self.series.lock().unwrap_or_else(|e| e.into_inner());
+        if !series.contains_key(&key) {
+            let tracked = series
+                .keys()
+                .filter(|k| k.app == key.app && k.provider == key.provider)
+                .filter(|k| k.model != OTHER_MODEL)
+                .count();
+            if tracked >= MAX_MODELS_PER_PROVIDER {
+                key.model = OTHER_MODEL.to_string();
+            }
+        }
+        let entry = series.entry(key).or_default();
+
+        let status = entry
+            .by_status
+            .entry(log.status_code)
+            .or_insert_with(|| StatusSeries {
+                requests: 0,
+                latency: Histogram::new(LATENCY_BUCKETS),
+            });
+        status.requests += 1;
+        status.latency.observe(log.latency_ms as f64 / 1000.0);
+
+        if let Some(first_token_ms) = log.first_token_ms {
+            entry.first_token.observe(first_token_ms as f64 / 1000.0);
+        }
+        entry.input_tokens += u64::from(log.usage.input_tokens);
+        entry.output_tokens += u64::from(log.usage.output_tokens);
+        entry.cache_read_tokens += u64::from(log.usage.cache_read_tokens);
+        entry.cache_creation_tokens += u64::from(log.usage.cache_creation_tokens);
+        if let Some(cost) = &log.cost {
+            entry.cost_usd += cost.total_cost.to_f64().unwrap_or(0.0);
+        }
+    }
+
+    /// 标记一个流式响应开始，返回的守卫在流结束（或客户端断开）时自动减计数
+    pub fn track_stream(self: &Arc<Self>, app_type: &str) -> ActiveStreamGuard {
+        self.adjust_active_streams(app_type, 1);
+        ActiveStreamGuard {
+            metrics: self.clone(),
+            app_type: app_type.to_string(),
+        }
+    }
+
+    fn adjust_active_streams(&self, app_type: &str, delta: i64) {
+        let mut streams = self
+            .active_streams
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        *streams.entry(app_type.to_string()).or_insert(0) += delta;
+    }
+
+    /// 以 Prometheus 文本格式输出全部指标
+    pub fn render(&self, status: &ProxyStatus, providers: &[ProviderSnapshot]) -> String {
+        let mut out = String::new();
+        let series = self
+            .series
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+
+        header(
+            &mut out,
+            "requests_total",
+            "counter",
+            "Proxied requests by app, provider, model and status code.",
+        );
+        for (key, entry) in &series {
+            let labels = key.labels();
+            for (code, s) in &entry.by_status {
+                let _ = writeln!(
+                    out,
+                    "{PREFIX}_requests_total{{{labels},status=\"{code}\"}} {}",
+                    s.requests
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "request_duration_seconds",
+            "histogram",
+            "End-to-end request latency in seconds.",
+        );
+        for (key, entry) in &series {
+            let labels = key.labels();
+            for (code, s) in &entry.by_status {
+                s.latency.render(
+                    &mut out,
+                    &format!("{PREFIX}_request_duration_seconds"),
+                    &format!("{labels},status=\"{code}\""),
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "first_token_seconds",
+            "histogram",
+            "Time to first streamed event in seconds.",
+        );
+        for (key, entry) in &series {
+            if entry.first_token.count > 0 {
+                entry.first_token.render(
+                    &mut out,
+                    &format!("{PREFIX}_first_token_seconds"),
+                    &key.labels(),
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "tokens_total",
+            "counter",
+            "Tokens consumed by app, provider, model and token type.",
+        );
+        for (key, entry) in &series {
+            let labels = key.labels();
+            for (kind, value) in [
+                ("input", entry.input_tokens),
+                ("output", entry.output_tokens),
+                ("cache_read", entry.cache_read_tokens),
+                ("cache_creation", entry.cache_creation_tokens),
+            ] {
+                let _ = writeln!(
+                    out,
+                    "{PREFIX}_tokens_total{{{labels},type=\"{kind}\"}} {value}"
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "cost_usd_total",
+            "counter",
+            "Estimated cost in USD (after provider cost multiplier).",
+        );
+        for (key, entry) in &series {
+            let _ = writeln!(
+                out,
+                "{PREFIX}_cost_usd_total{{{}}} {}",
+                key.labels(),
+                entry.cost_usd
+            );
+        }
+
+        header(
+            &mut out,
+            "active_streams",
+            "gauge",
+            "Streaming responses currently being relayed.",
+        );
+        let streams = self
+            .active_streams
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        for (app, count) in &streams {
+            let _ = writeln!(
+                out,
+                "{PREFIX}_active_streams{{app=\"{}\"}} {count}",
+                escape_label(app)
+            );
+        }
+
+        header(
+            &mut out,
+            "failovers_total",
+            "counter",
+            "Requests served by a provider other than the current one.",
+        );
+        let _ = writeln!(out, "{PREFIX}_failovers_total {}", status.failover_count);
+
+        header(
+            &mut out,
+            "provider_info",
+            "gauge",
+            "Provider display names, for joining on the provider label.",
+        );
+        for p in providers {
+            let _ = writeln!(
+                out,
+                "{PREFIX}_provider_info{{app=\"{}\",provider=\"{}\",provider_name=\"{}\"}} 1",
+                escape_label(&p.app),
+                escape_label(&p.id),
+                escape_label(&p.name)
+            );
+        }
+
+        header(
+            &mut out,
+            "circuit_breaker_state",
+            "gauge",
+            "Circuit breaker state per provider (0 = closed, 1 = half-open, 2 = open).",
+        );
+        for (p, stats) in providers
+            .iter()
+            .filter_map(|p| p.breaker.as_ref().map(|s| (p, s)))
+        {
+            let value = match stats.state {
+                CircuitState::Closed => 0,
+                CircuitState::HalfOpen => 1,
+                CircuitState::Open => 2,
+            };
+            let _ = writeln!(
+                out,
+                "{PREFIX}_circuit_breaker_state{{app=\"{}\",provider=\"{}\"}} {value}",
+                escape_label(&p.app),
+                escape_label(&p.id)
+            );
+        }
+
+        header(
+            &mut out,
+            "circuit_breaker_consecutive_failures",
+            "gauge",
+            "Consecutive failures recorded by each provider's circuit breaker.",
+        );
+        for (p, stats) in providers
+            .iter()
+            .filter_map(|p| p.breaker.as_ref().map(|s| (p, s)))
+        {
+            let _ = writeln!(
+                out,
+                "{PREFIX}_circuit_breaker_consecutive_failures{{app=\"{}\",provider=\"{}\"}} {}",
+                escape_label(&p.app),
+                escape_label(&p.id),
+                stats.consecutive_failures
+            );
+        }
+
+        out
+    }
+}
+
+/// 进行中的流式响应守卫
+#[derive(Debug)]
+pub struct ActiveStreamGuard {
+    metrics: Arc<ProxyMetrics>,
+    app_type: String,
+}
+
+impl Drop for ActiveStreamGuard {
+    fn drop(&mut self) {
+        self.metrics.adjust_active_streams(&self.app_type, -1);
+    }
+}
+
+/// 收集所有应用的供应商及其熔断器状态
+pub async fn collect_provider_snapshots(state: &ProxyState) -> Vec<ProviderSnapshot> {
+    let mut snapshots = Vec::new();
+    for app_type in AppType::all() {
+        let app = app_type.as_str().to_string();
+        let providers = match state.db.get_all_providers(&app) {
+            Ok(providers) => providers,
+            Err(e) => {
+                log::warn!("[Metrics] 读取 {app} 供应商失败: {e}");
+                continue;
+            }
+        };
+        for (id, provider) in providers {
+            let breaker = state
+                .provider_router
+                .get_circuit_breaker_stats(&id, &app)
+                .await;
+            snapshots.push(ProviderSnapshot {
+                app: app.clone(),
+                id,
+                name: provider.name,
+                breaker,
+            });
+        }
+    }
+    snapshots
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {PREFIX}_{name} {help}");
+    let _ = writeln!(out, "# TYPE {PREFIX}_{name} {kind}");
+}
+
+/// 按 Prometheus 文本格式转义标签值
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::usage::calculator::CostBreakdown;
+    use crate::proxy::usage::parser::TokenUsage;
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+
+    fn request_log(status_code: u16, latency_ms: u64, first_token_ms: Option<u64>) -> RequestLog {
+        RequestLog {
+            request_id: "req".to_string(),
+            provider_id: "p1".to_string(),
+            app_type: "claude".to_string(),
+            model: "claude-sonnet".to_string(),
+            request_model: "claude-sonnet".to_string(),
+            usage: TokenUsage {
+                input_tokens: 100,
+                output_tokens: 20,
+                ..Default::default()
+            },
+            cost: Some(CostBreakdown {
+                input_cost: Decimal::ZERO,
+                output_cost: Decimal::ZERO,
+                cache_read_cost: Decimal::ZERO,
+                cache_creation_cost: Decimal::ZERO,
+                total_cost: Decimal::from_str("0.5").unwrap(),
+            }),
+            latency_ms,
+            first_token_ms,
+            status_code,
+            error_message: None,
+            session_id: None,
+            provider_type: None,
+            is_streaming: first_token_ms.is_some(),
+            cost_multiplier: "1".to_string(),
+            api_key_id: None,
+            client_token_id: None,
+            routing_override: None,
+        }
+    }
+
+    #[test]
+    fn renders_request_counters_and_histograms() {
+        let metrics = ProxyMetrics::new();
+        metrics.record_request(&request_log(200, 300, Some(150)));
+        metrics.record_request(&request_log(200, 4000, None));
+        metrics.record_request(&request_log(502, 50, None));
+
+        let text = metrics.render(&ProxyStatus::default(), &[]);
+        let labels = r#"app="claude",provider="p1",model="claude-sonnet""#;
+
+        assert!(text.contains(&format!(
+            "cc_switch_proxy_requests_total{{{labels},status=\"200\"}} 2"
+        )));
+        assert!(text.contains(&format!(
+            "cc_switch_proxy_requests_total{{{labels},status=\"502\"}} 1"
+        )));
+        assert!(text.contains(&format!(
+            "cc_switch_proxy_request_duration_seconds_bucket{{{labels},status=\"200\",le=\"0.5\"}} 1"
+        )));
+        assert!(text.contains(&format!(
+            "cc_switch_proxy_request_duration_seconds_bucket{{{labels},status=\"200\",le=\"5\"}} 2"
+        )));
+        assert!(text.contains(&format!(
+            "cc_switch_proxy_first_token_seconds_count{{{labels}}} 1"
+        )));
+        assert!(text.contains(&format!(
+            "cc_switch_proxy_tokens_total{{{labels},type=\"input\"}} 300"
+        )));
+        assert!(text.contains(&format!("cc_switch_proxy_cost_usd_total{{{labels}}} 1.5")));
+        assert!(text.contains("cc_switch_proxy_failovers_total 0"));
+    }
+
+    #[test]
+    fn models_beyond_the_cap_are_folded_into_other() {
+        let metrics = ProxyMetrics::new();
+        for i in 0..MAX_MODELS_PER_PROVIDER + 10 {
+            let mut log = request_log(200, 100, None);
+            log.model = format!("model-{i}");
+            metrics.record_request(&log);
+        }
+        // 已统计的模型继续单独计数
+        let mut log = request_log(200, 100, None);
+        log.model = "model-0".to_string();
+        metrics.record_request(&log);
+
+        let series = metrics.series.lock().unwrap();
+        assert_eq!(series.len(), MAX_MODELS_PER_PROVIDER + 1);
+        let requests = |model: &str| {
+            series
+                .iter()
+                .find(|(key, _)| key.model == model)
+                .map(|(_, series)| series.by_status[&200].requests)
+        };
+        assert_eq!(requests("model-0"), Some(2));
+        assert_eq!(requests(OTHER_MODEL), Some(10));
+    }
+
+    #[test]
+    fn active_streams_follow_guard_lifetime() {
+        let metrics = Arc::new(ProxyMetrics::new());
+        let first = metrics.track_stream("codex");
+        let second = metrics.track_stream("codex");
+        assert!(metrics
+            .render(&ProxyStatus::default(), &[])
+            .contains("cc_switch_proxy_active_streams{app=\"codex\"} 2"));
+
+        drop(first);
+        drop(second);
+        assert!(metrics
+            .render(&ProxyStatus::default(), &[])
+            .contains("cc_switch_proxy_active_streams{app=\"codex\"} 0"));
+    }
+
+    #[test]
+    fn renders_circuit_breaker_state_and_escapes_labels() {
+        let providers = vec![
+            ProviderSnapshot {
+                app: "claude".to_string(),
+                id: "p1".to_string(),
+                name: "My \"Relay\"".to_string(),
+                breaker: Some(CircuitBreakerStats {
+                    state: CircuitState::Open,
+                    consecutive_failures: 5,
+                    consecutive_successes: 0,
+                    total_requests: 10,
+                    failed_requests: 5,
+                }),
+            },
+            ProviderSnapshot {
+                app: "claude".to_string(),
+                id: "p2".to_string(),
+                name: "Unused".to_string(),
+                breaker: None,
+            },
+        ];
+        let status = ProxyStatus {
+            failover_count: 3,
+            ..Default::default()
+        };
+
+        let text = ProxyMetrics::new().render(&status, &providers);
+        assert!(text.contains(
+            r#"cc_switch_proxy_provider_info{app="claude",provider="p1",provider_name="My \"Relay\""} 1"#
+        ));
+        assert!(
+            text.contains(r#"cc_switch_proxy_circuit_breaker_state{app="claude",provider="p1"} 2"#)
+        );
+        assert!(text.contains(
+            r#"cc_switch_proxy_circuit_breaker_consecutive_failures{app="claude",provider="p1"} 5"#
+        ));
+        assert!(
+            !text.contains(r#"cc_switch_proxy_circuit_breaker_state{app="claude",provider="p2"}"#)
+        );
+        assert!(text.contains("cc_switch_proxy_failovers_total 3"));
+    }
+}
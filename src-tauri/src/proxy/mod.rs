@@ -4,6 +4,7 @@
 
 pub mod body_filter;
 pub mod circuit_breaker;
+pub mod client_auth;
 pub mod codex_model_mapper;
 pub mod error;
 pub mod error_mapper;
@@ -15,15 +16,24 @@ mod handlers;
 pub(crate) mod header_filter;
 mod health;
 pub mod http_client;
+pub mod key_pool;
 pub mod log_codes;
+pub mod metrics;
+pub mod model_catalog;
 pub mod model_mapper;
+pub mod otel;
 pub mod provider_router;
 pub mod providers;
 pub mod response_handler;
 pub mod response_processor;
+pub mod routing_override;
+pub mod routing_rules;
 pub(crate) mod server;
 pub mod session;
+pub mod stream_hook;
+pub mod stream_resume;
 pub mod thinking_rectifier;
+pub mod token_estimator;
 pub(crate) mod types;
 pub mod usage;
 
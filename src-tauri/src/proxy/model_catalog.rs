@@ -0,0 +1,379 @@
+//! 模型列表聚合
+//!
+//! 为代理的 `/v1/models`（OpenAI / Anthropic 格式）与 Gemini `models` 列表提供数据：
+//! 汇总应用当前可路由供应商的上游模型列表（带缓存），并加入模型映射中定义的别名，
+//! 让客户端能发现可以通过代理请求的模型。
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+use tokio::sync::RwLock;
+
+use super::{
+    client_auth::ClientIdentity,
+    codex_model_mapper::extract_mapping_config,
+    http_client,
+    model_mapper::ModelMapping,
+    provider_router::ProviderRouter,
+    providers::{ensure_fresh_access_token, get_adapter},
+};
+use crate::app_config::AppType;
+use crate::provider::Provider;
+
+/// 上游模型列表缓存时间
+const CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// 拉取失败时的缓存时间（避免每次请求都访问不支持该接口的上游）
+const FAILURE_TTL: Duration = Duration::from_secs(60);
+
+/// 单个供应商拉取模型列表的超时
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 聚合后的模型条目
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogModel {
+    pub id: String,
+    /// 提供该模型的供应商名称
+    pub owned_by: String,
+    /// 别名实际映射到的上游模型（非别名时为空）
+    pub alias_of: Option<String>,
+}
+
+/// 模型列表缓存 - key: "app_type:provider_id"
+#[derive(Default)]
+pub struct ModelCatalog {
+    cache: RwLock<HashMap<String, (Instant, Vec<String>)>>,
+}
+
+impl ModelCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 使指定应用的缓存失效（供应商配置变化时调用）
+    pub async fn invalidate(&self, app_type: &str) {
+        let prefix = format!("{app_type}:");
+        self.cache
+            .write()
+            .await
+            .retain(|key, _| !key.starts_with(&prefix));
+    }
+
+    /// 列出应用可路由的模型：按供应商顺序，先别名后上游模型，重复的模型 ID 只保留第一次出现
+    ///
+    /// `client` 限制了可用供应商时，只列出允许的供应商的模型
+    pub async fn list(
+        &self,
+        router: &ProviderRouter,
+        app_type: &AppType,
+        client: Option<&ClientIdentity>,
+    ) -> Vec<CatalogModel> {
+        let providers = match router.select_providers(app_type.as_str()).await {
+            Ok(providers) => providers
+                .into_iter()
+                .filter(|p| client.is_none_or(|c| c.allows_provider(&p.id)))
+                .collect::<Vec<_>>(),
+            Err(e) => {
+                log::debug!("[{}] 模型列表：没有可用的供应商: {e}", app_type.as_str());
+                return Vec::new();
+            }
+        };
+
+        let upstream = futures::future::join_all(
+            providers
+                .iter()
+                .map(|provider| self.provider_models(app_type, provider)),
+        )
+        .await;
+
+        let mut seen = HashSet::new();
+        let mut models = Vec::new();
+        for (provider, upstream) in providers.iter().zip(upstream) {
+            let upstream = upstream.into_iter().map(|id| CatalogModel {
+                id,
+                owned_by: provider.name.clone(),
+                alias_of: None,
+            });
+            for model in model_aliases(app_type, provider)
+                .into_iter()
+                .chain(upstream)
+            {
+                if seen.insert(model.id.clone()) {
+                    models.push(model);
+                }
+            }
+        }
+        models
+    }
+
+    /// 获取单个供应商的上游模型列表（优先使用缓存）
+    async fn provider_models(&self, app_type: &AppType, provider: &Provider) -> Vec<String> {
+        let key = format!("{}:{}", app_type.as_str(), provider.id);
+        if let Some((fetched_at, models)) = self.cache.read().await.get(&key) {
+            let ttl = if models.is_empty() {
+                FAILURE_TTL
+            } else {
+                CACHE_TTL
+            };
+            if fetched_at.elapsed() < ttl {
+                return models.clone();
+            }
+        }
+
+        let models = match fetch_upstream_models(app_type, provider).await {
+            Ok(models) => models,
+            Err(e) => {
+                log::debug!(
+                    "[{}] 拉取 {} 的模型列表失败: {e}",
+                    app_type.as_str(),
+                    provider.name
+                );
+                Vec::new()
+            }
+        };
+        self.cache
+            .write()
+            .await
+            .insert(key, (Instant::now(), models.clone()));
+        models
+    }
+}
+
+async fn fetch_upstream_models(
+    app_type: &AppType,
+    provider: &Provider,
+) -> Result<Vec<String>, String> {
+    let endpoint = match app_type {
+        AppType::Claude => "/v1/models",
+        AppType::Codex => "/models",
+        AppType::Gemini => "/v1beta/models",
+        AppType::OpenCode => return Ok(Vec::new()),
+    };
+
+    let adapter = get_adapter(app_type);
+    let base_url = adapter
+        .extract_base_url(provider)
+        .map_err(|e| e.to_string())?;
+    let url = adapter.build_url(&base_url, endpoint);
+
+    let proxy_config = provider.meta.as_ref().and_then(|m| m.proxy_config.as_ref());
+    let client = http_client::get_for_provider(proxy_config);
+    let mut request = client.get(&url).timeout(FETCH_TIMEOUT);
+    if let Some(auth) = adapter.extract_auth(provider) {
+        let auth = ensure_fresh_access_token(auth, &client)
+            .await
+            .map_err(|e| e.to_string())?;
+        request = adapter.add_auth_headers(request, &auth);
+    }
+    if *app_type == AppType::Claude {
+        request = request.header("anthropic-version", "2023-06-01");
+    }
+
+    let response = request.send().await.map_err(|e| format!("请求失败: {e}"))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("HTTP {}", status.as_u16()));
+    }
+    let body: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("解析响应失败: {e}"))?;
+    Ok(parse_model_ids(&body))
+}
+
+/// 解析上游模型列表（兼容 OpenAI / Anthropic 的 `data[].id` 与 Gemini 的 `models[].name`）
+fn parse_model_ids(body: &Value) -> Vec<String> {
+    let openai = body
+        .get("data")
+        .and_then(|d| d.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|m| m.get("id").and_then(|id| id.as_str()));
+    let gemini = body
+        .get("models")
+        .and_then(|d| d.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|m| m.get("name").and_then(|name| name.as_str()))
+        .map(|name| name.strip_prefix("models/").unwrap_or(name));
+
+    openai
+        .chain(gemini)
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// 模型映射中定义的别名（客户端可直接请求、由代理映射到上游的模型名）
+fn model_aliases(app_type: &AppType, provider: &Provider) -> Vec<CatalogModel> {
+    let alias = |id: &str, target: Option<&str>| CatalogModel {
+        id: id.to_string(),
+        owned_by: provider.name.clone(),
+        alias_of: target.map(str::to_string),
+    };
+
+    match app_type {
+        AppType::Claude => {
+            let mapping = ModelMapping::from_provider(provider);
+            // 模型名包含 haiku / sonnet / opus 即会被映射，短别名同样可路由
+            let families = [
+                ("haiku", &mapping.haiku_model),
+                ("sonnet", &mapping.sonnet_model),
+                ("opus", &mapping.opus_model),
+            ];
+            let family_aliases = families
+                .into_iter()
+                .filter_map(|(family, target)| target.as_deref().map(|t| alias(family, Some(t))));
+            let targets = [&mapping.default_model, &mapping.reasoning_model]
+                .into_iter()
+                .flatten()
+                .map(|target| alias(target, None));
+            family_aliases.chain(targets).collect()
+        }
+        AppType::Codex => {
+            let config = extract_mapping_config(provider);
+            if !config.enabled {
+                return Vec::new();
+            }
+            let mut model_map: Vec<_> = config.model_map.iter().collect();
+            model_map.sort();
+            let mut aliases: Vec<CatalogModel> = model_map
+                .into_iter()
+                .map(|(from, to)| alias(from, Some(to)))
+                .collect();
+
+            // effort 组合映射的 key 为 "模型@effort"，请求时使用的是其中的模型名
+            let mut effort_models: Vec<&str> = config
+                .effort_map
+                .keys()
+                .filter_map(|key| key.split_once('@').map(|(model, _)| model))
+                .filter(|model| !config.model_map.contains_key(*model))
+                .collect();
+            effort_models.sort();
+            effort_models.dedup();
+            aliases.extend(effort_models.into_iter().map(|model| alias(model, None)));
+            aliases
+        }
+        AppType::Gemini | AppType::OpenCode => Vec::new(),
+    }
+}
+
+/// OpenAI 格式：`{"object": "list", "data": [...]}`
+pub fn to_openai_list(models: &[CatalogModel]) -> Value {
+    let data: Vec<Value> = models
+        .iter()
+        .map(|m| {
+            let mut entry = json!({
+                "id": m.id,
+                "object": "model",
+                "created": 0,
+                "owned_by": m.owned_by,
+            });
+            if let Some(target) = &m.alias_of {
+                entry["alias_of"] = json!(target);
+            }
+            entry
+        })
+        .collect();
+    json!({ "object": "list", "data": data })
+}
+
+/// Anthropic 格式：`{"data": [...], "has_more": false, "first_id", "last_id"}`
+pub fn to_anthropic_list(models: &[CatalogModel]) -> Value {
+    let data: Vec<Value> = models
+        .iter()
+        .map(|m| {
+            json!({
+                "type": "model",
+                "id": m.id,
+                "display_name": match &m.alias_of {
+                    Some(target) => format!("{} → {target}", m.id),
+                    None => m.id.clone(),
+                },
+                "created_at": "1970-01-01T00:00:00Z",
+            })
+        })
+        .collect();
+    json!({
+        "data": data,
+        "has_more": false,
+        "first_id": models.first().map(|m| m.id.as_str()),
+        "last_id": models.last().map(|m| m.id.as_str()),
+    })
+}
+
+/// Gemini 格式：`{"models": [{"name": "models/..."}]}`
+pub fn to_gemini_list(models: &[CatalogModel]) -> Value {
+    let data: Vec<Value> = models
+        .iter()
+        .map(|m| {
+            json!({
+                "name": format!("models/{}", m.id),
+                "displayName": m.id,
+                "supportedGenerationMethods": ["generateContent", "streamGenerateContent"],
+            })
+        })
+        .collect();
+    json!({ "models": data })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::ProviderMeta;
+    use crate::proxy::codex_model_mapper::CodexModelMappingConfig;
+
+    fn provider(settings_config: Value) -> Provider {
+        Provider::with_id("p1".to_string(), "Relay".to_string(), settings_config, None)
+    }
+
+    #[test]
+    fn parses_openai_anthropic_and_gemini_lists() {
+        let openai = json!({ "object": "list", "data": [{ "id": "gpt-5" }, { "id": "o3" }] });
+        assert_eq!(parse_model_ids(&openai), vec!["gpt-5", "o3"]);
+
+        let gemini = json!({ "models": [{ "name": "models/gemini-2.5-pro" }] });
+        assert_eq!(parse_model_ids(&gemini), vec!["gemini-2.5-pro"]);
+
+        assert!(parse_model_ids(&json!({ "error": "nope" })).is_empty());
+    }
+
+    #[test]
+    fn claude_mapping_exposes_family_aliases_and_targets() {
+        let p = provider(json!({
+            "env": {
+                "ANTHROPIC_MODEL": "glm-4.6",
+                "ANTHROPIC_DEFAULT_HAIKU_MODEL": "glm-4.5-air",
+            }
+        }));
+        let aliases = model_aliases(&AppType::Claude, &p);
+        let ids: Vec<_> = aliases
+            .iter()
+            .map(|m| (m.id.as_str(), m.alias_of.as_deref()))
+            .collect();
+        assert_eq!(ids, vec![("haiku", Some("glm-4.5-air")), ("glm-4.6", None)]);
+    }
+
+    #[test]
+    fn codex_mapping_exposes_model_map_and_effort_models() {
+        let mut p = provider(json!({}));
+        p.meta = Some(ProviderMeta {
+            codex_model_mapping: Some(CodexModelMappingConfig {
+                enabled: true,
+                model_map: HashMap::from([("gpt-5".to_string(), "relay-gpt-5".to_string())]),
+                effort_map: HashMap::from([
+                    ("gpt-5@high".to_string(), "relay-gpt-5-high".to_string()),
+                    ("gpt-5-codex@low".to_string(), "relay-codex-low".to_string()),
+                ]),
+            }),
+            ..Default::default()
+        });
+
+        let openai = to_openai_list(&model_aliases(&AppType::Codex, &p));
+        assert_eq!(openai["data"][0]["id"], "gpt-5");
+        assert_eq!(openai["data"][0]["alias_of"], "relay-gpt-5");
+        assert_eq!(openai["data"][1]["id"], "gpt-5-codex");
+        assert_eq!(openai["data"].as_array().map(Vec::len), Some(2));
+    }
+}
@@ -0,0 +1,477 @@
+//! OpenTelemetry 链路追踪（OTLP/HTTP JSON 导出）
+//!
+//! 每个代理请求对应一条 trace：
+//! - 根 span 覆盖整个请求生命周期（从请求进入到流式响应结束、用量记录完成）
+//! - 子 span 记录供应商选择、请求重写脚本、上游首字节（TTFB）、流式传输与用量记录
+//! - 客户端携带 `traceparent` 时沿用其 trace id，转发上游时写入新的 `traceparent`
+//!
+//! 根 span 在最后一个 `RequestTrace` 句柄释放时结束并异步导出，
+//! 因此无论请求从哪条路径结束都会上报。未启用时所有操作均为空操作。
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use axum::http::HeaderMap;
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+
+use super::types::TracingConfig;
+
+/// 导出专用客户端：Collector 通常在本机，不走系统代理
+static EXPORT_CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .no_proxy()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .unwrap_or_default()
+});
+
+/// OTLP SpanKind
+const SPAN_KIND_INTERNAL: u8 = 1;
+const SPAN_KIND_SERVER: u8 = 2;
+const SPAN_KIND_CLIENT: u8 = 3;
+
+/// OTLP StatusCode（未记录错误时保持 UNSET，由后端自行判定，不主动标记 OK）
+const STATUS_UNSET: u8 = 0;
+const STATUS_ERROR: u8 = 2;
+
+/// span 属性值
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttrValue {
+    Str(String),
+    Int(i64),
+    Bool(bool),
+}
+
+impl From<&str> for AttrValue {
+    fn from(value: &str) -> Self {
+        Self::Str(value.to_string())
+    }
+}
+
+impl From<String> for AttrValue {
+    fn from(value: String) -> Self {
+        Self::Str(value)
+    }
+}
+
+impl From<i64> for AttrValue {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<u64> for AttrValue {
+    fn from(value: u64) -> Self {
+        Self::Int(value as i64)
+    }
+}
+
+impl From<usize> for AttrValue {
+    fn from(value: usize) -> Self {
+        Self::Int(value as i64)
+    }
+}
+
+impl From<u16> for AttrValue {
+    fn from(value: u16) -> Self {
+        Self::Int(value.into())
+    }
+}
+
+impl From<bool> for AttrValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl AttrValue {
+    fn to_otlp(&self) -> Value {
+        match self {
+            Self::Str(v) => json!({ "stringValue": v }),
+            // OTLP JSON 中 int64 以字符串表示
+            Self::Int(v) => json!({ "intValue": v.to_string() }),
+            Self::Bool(v) => json!({ "boolValue": v }),
+        }
+    }
+}
+
+/// 已结束的 span
+#[derive(Debug, Clone)]
+struct SpanData {
+    span_id: String,
+    parent_span_id: Option<String>,
+    name: String,
+    kind: u8,
+    start_nanos: u128,
+    end_nanos: u128,
+    attributes: Vec<(String, AttrValue)>,
+    error: Option<String>,
+}
+
+impl SpanData {
+    fn to_otlp(&self, trace_id: &str) -> Value {
+        let mut span = json!({
+            "traceId": trace_id,
+            "spanId": self.span_id,
+            "name": self.name,
+            "kind": self.kind,
+            "startTimeUnixNano": self.start_nanos.to_string(),
+            "endTimeUnixNano": self.end_nanos.to_string(),
+            "attributes": self
+                .attributes
+                .iter()
+                .map(|(k, v)| json!({ "key": k, "value": v.to_otlp() }))
+                .collect::<Vec<_>>(),
+            "status": match &self.error {
+                Some(message) => json!({ "code": STATUS_ERROR, "message": message }),
+                None => json!({ "code": STATUS_UNSET }),
+            },
+        });
+        if let Some(parent) = &self.parent_span_id {
+            span["parentSpanId"] = json!(parent);
+        }
+        span
+    }
+}
+
+struct TraceInner {
+    endpoint: String,
+    service_name: String,
+    trace_id: String,
+    /// 根 span（生命周期结束时写入结束时间）
+    root: Mutex<SpanData>,
+    spans: Mutex<Vec<SpanData>>,
+}
+
+impl Drop for TraceInner {
+    fn drop(&mut self) {
+        let mut root = self.root.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        root.end_nanos = now_nanos();
+        let mut spans = std::mem::take(&mut *self.spans.lock().unwrap_or_else(|e| e.into_inner()));
+        spans.insert(0, root);
+
+        let payload = build_export_payload(&self.service_name, &self.trace_id, &spans);
+        let endpoint = self.endpoint.clone();
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        handle.spawn(async move {
+            match EXPORT_CLIENT.post(&endpoint).json(&payload).send().await {
+                Ok(resp) if !resp.status().is_success() => {
+                    log::debug!("[OTEL] 导出 trace 失败: HTTP {}", resp.status());
+                }
+                Ok(_) => {}
+                Err(e) => log::debug!("[OTEL] 导出 trace 失败: {e}"),
+            }
+        });
+    }
+}
+
+/// 单个请求的 trace 句柄（可廉价克隆，未启用时为空操作）
+#[derive(Clone, Default)]
+pub struct RequestTrace {
+    inner: Option<Arc<TraceInner>>,
+}
+
+impl RequestTrace {
+    /// 未启用追踪的空句柄
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// 按配置开始一条 trace，客户端携带合法 `traceparent` 时沿用其 trace id
+    pub fn start(config: &TracingConfig, incoming: &HeaderMap, name: &str) -> Self {
+        if !config.enabled || config.endpoint.trim().is_empty() {
+            return Self::disabled();
+        }
+
+        let parent = incoming
+            .get("traceparent")
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_traceparent);
+        let (trace_id, parent_span_id) = match parent {
+            Some((trace_id, span_id)) => (trace_id, Some(span_id)),
+            None => (new_trace_id(), None),
+        };
+
+        let root = SpanData {
+            span_id: new_span_id(),
+            parent_span_id,
+            name: name.to_string(),
+            kind: SPAN_KIND_SERVER,
+            start_nanos: now_nanos(),
+            end_nanos: 0,
+            attributes: Vec::new(),
+            error: None,
+        };
+
+        Self {
+            inner: Some(Arc::new(TraceInner {
+                endpoint: config.endpoint.trim().to_string(),
+                service_name: config.service_name.clone(),
+                trace_id,
+                root: Mutex::new(root),
+                spans: Mutex::new(Vec::new()),
+            })),
+        }
+    }
+
+    /// 设置根 span 属性（重复设置时覆盖）
+    pub fn set_attribute(&self, key: &str, value: impl Into<AttrValue>) {
+        if let Some(inner) = &self.inner {
+            let mut root = inner.root.lock().unwrap_or_else(|e| e.into_inner());
+            upsert(&mut root.attributes, key, value.into());
+        }
+    }
+
+    /// 标记整个请求失败
+    pub fn set_error(&self, message: impl Into<String>) {
+        if let Some(inner) = &self.inner {
+            inner.root.lock().unwrap_or_else(|e| e.into_inner()).error = Some(message.into());
+        }
+    }
+
+    /// 开始一个根 span 下的内部子 span
+    pub fn span(&self, name: &str) -> Span {
+        let parent = self.inner.as_ref().map(|inner| {
+            inner
+                .root
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .span_id
+                .clone()
+        });
+        self.new_span(name, SPAN_KIND_INTERNAL, parent)
+    }
+
+    /// 开始一个发往上游的客户端 span
+    pub fn client_span(&self, name: &str) -> Span {
+        let mut span = self.span(name);
+        if let Some(data) = span.data.as_mut() {
+            data.kind = SPAN_KIND_CLIENT;
+        }
+        span
+    }
+
+    fn new_span(&self, name: &str, kind: u8, parent_span_id: Option<String>) -> Span {
+        Span {
+            trace: self.clone(),
+            data: self.inner.as_ref().map(|_| SpanData {
+                span_id: new_span_id(),
+                parent_span_id,
+                name: name.to_string(),
+                kind,
+                start_nanos: now_nanos(),
+                end_nanos: 0,
+                attributes: Vec::new(),
+                error: None,
+            }),
+        }
+    }
+
+    fn record(&self, span: SpanData) {
+        if let Some(inner) = &self.inner {
+            inner
+                .spans
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(span);
+        }
+    }
+}
+
+/// 进行中的 span，调用 `end` 后记录到所属 trace
+#[derive(Clone)]
+pub struct Span {
+    trace: RequestTrace,
+    data: Option<SpanData>,
+}
+
+impl Span {
+    pub fn set_attribute(&mut self, key: &str, value: impl Into<AttrValue>) {
+        if let Some(data) = self.data.as_mut() {
+            upsert(&mut data.attributes, key, value.into());
+        }
+    }
+
+    pub fn set_error(&mut self, message: impl Into<String>) {
+        if let Some(data) = self.data.as_mut() {
+            data.error = Some(message.into());
+        }
+    }
+
+    /// 开始当前 span 下的子 span
+    pub fn child(&self, name: &str) -> Span {
+        let parent = self.data.as_ref().map(|d| d.span_id.clone());
+        self.trace.new_span(name, SPAN_KIND_INTERNAL, parent)
+    }
+
+    /// 传递给上游的 W3C `traceparent`（以当前 span 为父）
+    pub fn traceparent(&self) -> Option<String> {
+        let inner = self.trace.inner.as_ref()?;
+        let data = self.data.as_ref()?;
+        Some(format!("00-{}-{}-01", inner.trace_id, data.span_id))
+    }
+
+    /// 结束 span（以当前时间为结束时间）
+    pub fn end(&self) {
+        if let Some(data) = &self.data {
+            let mut data = data.clone();
+            data.end_nanos = now_nanos();
+            self.trace.record(data);
+        }
+    }
+}
+
+fn upsert(attributes: &mut Vec<(String, AttrValue)>, key: &str, value: AttrValue) {
+    match attributes.iter_mut().find(|(k, _)| k == key) {
+        Some((_, existing)) => *existing = value,
+        None => attributes.push((key.to_string(), value)),
+    }
+}
+
+fn build_export_payload(service_name: &str, trace_id: &str, spans: &[SpanData]) -> Value {
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [
+                    { "key": "service.name", "value": { "stringValue": service_name } },
+                    { "key": "service.version", "value": { "stringValue": env!("CARGO_PKG_VERSION") } },
+                ]
+            },
+            "scopeSpans": [{
+                "scope": { "name": "cc-switch.proxy" },
+                "spans": spans.iter().map(|s| s.to_otlp(trace_id)).collect::<Vec<_>>(),
+            }]
+        }]
+    })
+}
+
+/// 解析 W3C `traceparent`，返回 (trace id, 父 span id)
+fn parse_traceparent(value: &str) -> Option<(String, String)> {
+    let mut parts = value.trim().split('-');
+    let version = parts.next()?;
+    let trace_id = parts.next()?;
+    let span_id = parts.next()?;
+    let _flags = parts.next()?;
+
+    let is_hex = |s: &str, len: usize| {
+        s.len() == len && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    };
+    if version == "ff"
+        || !is_hex(version, 2)
+        || !is_hex(trace_id, 32)
+        || !is_hex(span_id, 16)
+        || trace_id.bytes().all(|b| b == b'0')
+        || span_id.bytes().all(|b| b == b'0')
+    {
+        return None;
+    }
+    Some((trace_id.to_string(), span_id.to_string()))
+}
+
+fn new_trace_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+fn new_span_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()[..16].to_string()
+}
+
+fn now_nanos() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled_config() -> TracingConfig {
+        TracingConfig {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn disabled_trace_is_noop() {
+        let trace = RequestTrace::start(&TracingConfig::default(), &HeaderMap::new(), "req");
+        assert!(trace.inner.is_none());
+        let span = trace.client_span("upstream_request");
+        assert_eq!(span.traceparent(), None);
+        span.end();
+    }
+
+    #[test]
+    fn traceparent_is_parsed_and_validated() {
+        let trace_id = "4bf92f3577b34da6a3ce929d0e0e4736";
+        let span_id = "00f067aa0ba902b7";
+        assert_eq!(
+            parse_traceparent(&format!("00-{trace_id}-{span_id}-01")),
+            Some((trace_id.to_string(), span_id.to_string()))
+        );
+        assert_eq!(parse_traceparent("00-abc-def-01"), None);
+        assert_eq!(
+            parse_traceparent(&format!("00-{}-{span_id}-01", "0".repeat(32))),
+            None
+        );
+        assert_eq!(
+            parse_traceparent(&format!("00-{}-{span_id}-01", trace_id.to_uppercase())),
+            None
+        );
+    }
+
+    #[test]
+    fn incoming_trace_id_is_continued_upstream() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+                .parse()
+                .unwrap(),
+        );
+        let trace = RequestTrace::start(&enabled_config(), &headers, "req");
+        let span = trace.client_span("upstream_request");
+        let traceparent = span.traceparent().unwrap();
+        assert!(traceparent.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+        assert!(!traceparent.contains("00f067aa0ba902b7"));
+        assert!(parse_traceparent(&traceparent).is_some());
+    }
+
+    #[test]
+    fn export_payload_follows_otlp_json() {
+        let trace = RequestTrace::start(&enabled_config(), &HeaderMap::new(), "req");
+        trace.set_attribute("session.id", "s1");
+        let mut span = trace.span("select_providers");
+        span.set_attribute("cc_switch.provider_count", 2usize);
+        span.end();
+        let mut failed = trace.client_span("upstream_request");
+        failed.set_error("HTTP 502");
+        failed.end();
+
+        let inner = trace.inner.as_ref().unwrap();
+        let spans = inner.spans.lock().unwrap().clone();
+        let payload = build_export_payload("svc", &inner.trace_id, &spans);
+        let exported = &payload["resourceSpans"][0]["scopeSpans"][0]["spans"];
+
+        assert_eq!(exported.as_array().unwrap().len(), 2);
+        assert_eq!(exported[0]["name"], "select_providers");
+        assert_eq!(exported[0]["kind"], SPAN_KIND_INTERNAL);
+        assert_eq!(exported[0]["attributes"][0]["value"]["intValue"], "2");
+        assert_eq!(
+            exported[0]["parentSpanId"],
+            inner.root.lock().unwrap().span_id.as_str()
+        );
+        assert_eq!(exported[1]["kind"], SPAN_KIND_CLIENT);
+        assert_eq!(exported[0]["status"]["code"], STATUS_UNSET);
+        assert_eq!(exported[1]["status"]["code"], STATUS_ERROR);
+        assert_eq!(
+            payload["resourceSpans"][0]["resource"]["attributes"][0]["value"]["stringValue"],
+            "svc"
+        );
+    }
+}
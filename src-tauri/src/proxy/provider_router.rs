@@ -7,12 +7,22 @@ use crate::database::Database;
 use crate::error::AppError;
 use crate::provider::Provider;
 use crate::proxy::circuit_breaker::{AllowResult, CircuitBreaker, CircuitBreakerConfig};
+use crate::proxy::key_pool::{key_circuit_key, ApiKeySelector, SelectedApiKey};
+use crate::proxy::routing_rules::{match_rule, RouteRequest, RoutingRule};
 use crate::proxy::types::AppProxyConfig;
 use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+/// 按请求特征选择的供应商链
+pub struct RoutedProviders {
+    /// 按优先级排序的可用供应商
+    pub providers: Vec<Provider>,
+    /// 命中的路由规则名称（未命中时为空）
+    pub rule: Option<String>,
+}
+
 /// 供应商路由器
 pub struct ProviderRouter {
     /// 数据库连接
@@ -26,6 +36,10 @@ pub struct ProviderRouter {
     /// 存储 select_providers() 的结果（不包含熔断器状态过滤），
     /// 即：若故障转移开启则为队列，若关闭则为当前供应商。
     candidate_cache: Arc<RwLock<HashMap<String, Vec<Provider>>>>,
+    /// 路由规则缓存 - key: app_type
+    rules_cache: Arc<RwLock<HashMap<String, Vec<RoutingRule>>>>,
+    /// API Key 池轮换状态
+    key_selector: ApiKeySelector,
 }
 
 impl ProviderRouter {
@@ -36,6 +50,8 @@ impl ProviderRouter {
             circuit_breakers: Arc::new(RwLock::new(HashMap::new())),
             config_cache: Arc::new(RwLock::new(HashMap::new())),
             candidate_cache: Arc::new(RwLock::new(HashMap::new())),
+            rules_cache: Arc::new(RwLock::new(HashMap::new())),
+            key_selector: ApiKeySelector::new(),
         }
     }
 
@@ -150,6 +166,10 @@ impl ProviderRouter {
             let mut candidate_cache = self.candidate_cache.write().await;
             candidate_cache.remove(app_type);
         }
+        {
+            let mut rules_cache = self.rules_cache.write().await;
+            rules_cache.remove(app_type);
+        }
         log::debug!("[{app_type}] ProviderRouter 缓存已失效");
     }
 
@@ -203,6 +223,82 @@ impl ProviderRouter {
         Ok(result)
     }
 
+    /// 获取路由规则（带缓存，读取失败时视为无规则）
+    async fn get_routing_rules(&self, app_type: &str) -> Vec<RoutingRule> {
+        {
+            let cache = self.rules_cache.read().await;
+            if let Some(rules) = cache.get(app_type) {
+                return rules.clone();
+            }
+        }
+
+        let rules = self.db.get_routing_rules(app_type).unwrap_or_else(|e| {
+            log::error!("[{app_type}] 读取路由规则失败: {e}，忽略路由规则");
+            Vec::new()
+        });
+
+        {
+            let mut cache = self.rules_cache.write().await;
+            cache.insert(app_type.to_string(), rules.clone());
+        }
+
+        rules
+    }
+
+    /// 按请求特征选择可用的供应商
+    ///
+    /// 先按顺序评估路由规则，命中时使用规则中的供应商列表（同样遵循故障转移开关与熔断器状态）；
+    /// 未命中任何规则，或规则中的供应商均不可用（已删除或已熔断）时，回退到 `select_providers()`。
+    pub async fn select_providers_for_request(
+        &self,
+        app_type: &str,
+        request: &RouteRequest<'_>,
+    ) -> Result<RoutedProviders, AppError> {
+        let rules = self.get_routing_rules(app_type).await;
+        if let Some(rule) = match_rule(&rules, request) {
+            let auto_failover_enabled = self.get_config(app_type).await.auto_failover_enabled;
+            let all_providers = self.db.get_all_providers(app_type)?;
+
+            let mut providers = Vec::new();
+            for provider_id in &rule.provider_ids {
+                let Some(provider) = all_providers.get(provider_id) else {
+                    continue;
+                };
+                // 故障转移关闭时，与 select_providers() 一致：仅使用首个供应商且跳过熔断器检查
+                if !auto_failover_enabled {
+                    providers.push(provider.clone());
+                    break;
+                }
+                let circuit_key = format!("{app_type}:{provider_id}");
+                let breaker = self.get_or_create_circuit_breaker(&circuit_key).await;
+                if breaker.is_available().await {
+                    providers.push(provider.clone());
+                }
+            }
+
+            if !providers.is_empty() {
+                log::debug!(
+                    "[{app_type}] 命中路由规则 {}，供应商链: {} 个",
+                    rule.name,
+                    providers.len()
+                );
+                return Ok(RoutedProviders {
+                    providers,
+                    rule: Some(rule.name.clone()),
+                });
+            }
+            log::warn!(
+                "[{app_type}] 路由规则 {} 中的供应商均不可用，回退到默认供应商链",
+                rule.name
+            );
+        }
+
+        Ok(RoutedProviders {
+            providers: self.select_providers(app_type).await?,
+            rule: None,
+        })
+    }
+
     /// 请求执行前获取熔断器“放行许可”
     ///
     /// - Closed：直接放行
@@ -256,6 +352,101 @@ impl ProviderRouter {
         Ok(())
     }
 
+    /// 为请求从供应商的 Key 池中选择一个 Key
+    ///
+    /// 按轮换策略排序后，跳过 `excluded` 中的 Key 与熔断中的 Key。
+    /// 未启用 Key 池或没有可用 Key 时返回 `None`（使用 settingsConfig 中的 Key）。
+    ///
+    /// 注意：调用方必须通过 `record_key_result()` 或 `release_key_permit_neutral()`
+    /// 释放选中 Key 的 HalfOpen 名额。
+    pub async fn select_api_key(
+        &self,
+        app_type: &str,
+        provider: &Provider,
+        excluded: &[String],
+    ) -> Option<SelectedApiKey> {
+        let pool = provider.meta.as_ref()?.api_key_pool.as_ref()?;
+        let keys: Vec<_> = pool
+            .active_keys()
+            .into_iter()
+            .filter(|k| !excluded.contains(&k.id))
+            .collect();
+        if keys.is_empty() {
+            return None;
+        }
+
+        let ordered =
+            self.key_selector
+                .order_candidates(app_type, &provider.id, pool.strategy, &keys);
+        for key in ordered {
+            let circuit_key = key_circuit_key(app_type, &provider.id, &key.id);
+            let breaker = self.get_or_create_circuit_breaker(&circuit_key).await;
+            let permit = breaker.allow_request().await;
+            if permit.allowed {
+                self.key_selector
+                    .record_use(app_type, &provider.id, &key.id);
+                return Some(SelectedApiKey {
+                    id: key.id.clone(),
+                    key: key.key.clone(),
+                    used_half_open_permit: permit.used_half_open_permit,
+                });
+            }
+        }
+
+        log::warn!(
+            "[{app_type}] [KEY-002] Provider {} 的 Key 池均已熔断，回退到默认 Key",
+            provider.name
+        );
+        None
+    }
+
+    /// 记录 Key 请求结果（仅影响该 Key 的熔断器，不更新供应商健康状态）
+    pub async fn record_key_result(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        key: &SelectedApiKey,
+        success: bool,
+    ) {
+        let circuit_key = key_circuit_key(app_type, provider_id, &key.id);
+        let breaker = self.get_or_create_circuit_breaker(&circuit_key).await;
+        if success {
+            breaker.record_success(key.used_half_open_permit).await;
+        } else {
+            breaker.record_failure(key.used_half_open_permit).await;
+        }
+    }
+
+    /// 仅释放 Key 的 HalfOpen permit（请求失败原因与 Key 无关时使用）
+    pub async fn release_key_permit_neutral(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        key: &SelectedApiKey,
+    ) {
+        if !key.used_half_open_permit {
+            return;
+        }
+        let circuit_key = key_circuit_key(app_type, provider_id, &key.id);
+        let breaker = self.get_or_create_circuit_breaker(&circuit_key).await;
+        breaker.release_half_open_permit();
+    }
+
+    /// 获取 Key 的熔断器状态
+    pub async fn get_key_circuit_breaker_stats(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        key_id: &str,
+    ) -> Option<crate::proxy::circuit_breaker::CircuitBreakerStats> {
+        let circuit_key = key_circuit_key(app_type, provider_id, key_id);
+        let breakers = self.circuit_breakers.read().await;
+        match breakers.get(&circuit_key) {
+            Some(breaker) => Some(breaker.get_stats().await),
+            None => None,
+        }
+    }
+
     /// 重置熔断器（手动恢复）
     pub async fn reset_circuit_breaker(&self, circuit_key: &str) {
         let breakers = self.circuit_breakers.read().await;
@@ -297,7 +488,6 @@ impl ProviderRouter {
     }
 
     /// 获取熔断器状态
-    #[allow(dead_code)]
     pub async fn get_circuit_breaker_stats(
         &self,
         provider_id: &str,
@@ -412,6 +602,56 @@ mod tests {
         assert!(breaker.allow_request().await.allowed);
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn test_select_api_key_skips_open_key_breakers() {
+        use crate::provider::{ApiKeyEntry, ApiKeyPool, ApiKeyRotationStrategy, ProviderMeta};
+
+        let _home = TempHome::new();
+        let db = Arc::new(Database::memory().unwrap());
+        let router = ProviderRouter::new(db);
+
+        let entry = |id: &str| ApiKeyEntry {
+            id: id.to_string(),
+            key: format!("sk-{id}"),
+            label: None,
+            enabled: true,
+        };
+        let mut provider =
+            Provider::with_id("p".to_string(), "Pooled".to_string(), json!({}), None);
+        provider.meta = Some(ProviderMeta {
+            api_key_pool: Some(ApiKeyPool {
+                enabled: true,
+                strategy: ApiKeyRotationStrategy::Failover,
+                keys: vec![entry("k1"), entry("k2")],
+            }),
+            ..Default::default()
+        });
+
+        let first = router
+            .select_api_key("claude", &provider, &[])
+            .await
+            .unwrap();
+        assert_eq!(first.id, "k1");
+        assert_eq!(first.key, "sk-k1");
+
+        // 连续失败直到 k1 熔断
+        for _ in 0..10 {
+            router.record_key_result("claude", "p", &first, false).await;
+        }
+        let next = router
+            .select_api_key("claude", &provider, &[])
+            .await
+            .unwrap();
+        assert_eq!(next.id, "k2");
+
+        // 排除全部可用 Key 后回退到默认 Key
+        assert!(router
+            .select_api_key("claude", &provider, &["k2".to_string()])
+            .await
+            .is_none());
+    }
+
     #[tokio::test]
     #[serial]
     async fn test_failover_disabled_uses_current_provider() {
@@ -500,6 +740,86 @@ mod tests {
         assert_eq!(providers[0].id, "b");
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn test_routing_rule_overrides_queue_and_falls_back() {
+        let _home = TempHome::new();
+        let db = Arc::new(Database::memory().unwrap());
+
+        db.update_circuit_breaker_config(&CircuitBreakerConfig {
+            failure_threshold: 1,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        for id in ["official", "relay", "cheap"] {
+            let provider = Provider::with_id(id.to_string(), id.to_string(), json!({}), None);
+            db.save_provider("claude", &provider).unwrap();
+        }
+        db.add_to_failover_queue("claude", "official").unwrap();
+        db.add_to_failover_queue("claude", "relay").unwrap();
+
+        let mut config = db.get_proxy_config_for_app("claude").await.unwrap();
+        config.auto_failover_enabled = true;
+        db.update_proxy_config_for_app(config).await.unwrap();
+
+        db.set_routing_rules(
+            "claude",
+            &[RoutingRule {
+                id: "haiku".to_string(),
+                name: "Haiku".to_string(),
+                enabled: true,
+                model_pattern: Some("*haiku*".to_string()),
+                thinking: None,
+                min_body_bytes: None,
+                max_body_bytes: None,
+                endpoint_pattern: None,
+                provider_ids: vec![
+                    "missing".to_string(),
+                    "cheap".to_string(),
+                    "relay".to_string(),
+                ],
+            }],
+        )
+        .unwrap();
+
+        let router = ProviderRouter::new(db.clone());
+
+        let body = json!({"model": "claude-haiku-4-5"});
+        let routed = router
+            .select_providers_for_request("claude", &RouteRequest::new(&body, "/v1/messages"))
+            .await
+            .unwrap();
+        assert_eq!(routed.rule.as_deref(), Some("Haiku"));
+        let ids: Vec<&str> = routed.providers.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["cheap", "relay"]);
+
+        // 未命中规则时使用故障转移队列
+        let body = json!({"model": "claude-opus-4-1"});
+        let routed = router
+            .select_providers_for_request("claude", &RouteRequest::new(&body, "/v1/messages"))
+            .await
+            .unwrap();
+        assert!(routed.rule.is_none());
+        assert_eq!(routed.providers[0].id, "official");
+
+        // 规则中的供应商全部熔断时回退到队列
+        for id in ["cheap", "relay"] {
+            router
+                .record_result(id, "claude", false, false, Some("fail".to_string()))
+                .await
+                .unwrap();
+        }
+        let body = json!({"model": "claude-haiku-4-5"});
+        let routed = router
+            .select_providers_for_request("claude", &RouteRequest::new(&body, "/v1/messages"))
+            .await
+            .unwrap();
+        assert!(routed.rule.is_none());
+        assert_eq!(routed.providers[0].id, "official");
+    }
+
     #[tokio::test]
     #[serial]
     async fn test_select_providers_does_not_consume_half_open_permit() {
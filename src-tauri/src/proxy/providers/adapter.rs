@@ -2,9 +2,10 @@
 //!
 //! 定义供应商适配器的统一接口，抽象不同上游供应商的处理逻辑。
 
-use super::auth::AuthInfo;
+use super::auth::{AuthInfo, AuthStrategy};
 use crate::provider::Provider;
 use crate::proxy::error::ProxyError;
+use crate::proxy::key_pool::SelectedApiKey;
 use reqwest::RequestBuilder;
 use serde_json::Value;
 
@@ -64,6 +65,33 @@ pub trait ProviderAdapter: Send + Sync {
     /// * `None` - 未找到认证信息
     fn extract_auth(&self, provider: &Provider) -> Option<AuthInfo>;
 
+    /// Key 池中的 Key 使用的认证策略（Provider 配置中没有 Key、只配置了 Key 池时使用）
+    ///
+    /// 默认返回 `Bearer`。
+    fn pool_key_strategy(&self, _provider: &Provider) -> AuthStrategy {
+        AuthStrategy::Bearer
+    }
+
+    /// 提取认证信息，并使用 Key 池中选中的 Key 覆盖 API Key
+    ///
+    /// 认证方式仍由 Provider 配置决定；配置中没有 Key（只配置了 Key 池）时按 `pool_key_strategy` 构建。
+    /// `key` 为 `None` 时等同于 `extract_auth`
+    fn extract_auth_with_key(
+        &self,
+        provider: &Provider,
+        key: Option<&SelectedApiKey>,
+    ) -> Option<AuthInfo> {
+        match key {
+            Some(key) => {
+                let auth = self.extract_auth(provider).unwrap_or_else(|| {
+                    AuthInfo::new(String::new(), self.pool_key_strategy(provider))
+                });
+                Some(auth.with_pool_key(&key.id, &key.key))
+            }
+            None => self.extract_auth(provider),
+        }
+    }
+
     /// 构建请求 URL
     ///
     /// # Arguments
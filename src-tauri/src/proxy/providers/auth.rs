@@ -13,6 +13,8 @@ pub struct AuthInfo {
     pub strategy: AuthStrategy,
     /// OAuth access_token（用于 GoogleOAuth 策略）
     pub access_token: Option<String>,
+    /// 来自 Key 池时对应的 Key 标识（用于熔断与用量归因）
+    pub key_id: Option<String>,
 }
 
 impl AuthInfo {
@@ -22,6 +24,7 @@ impl AuthInfo {
             api_key,
             strategy,
             access_token: None,
+            key_id: None,
         }
     }
 
@@ -31,9 +34,22 @@ impl AuthInfo {
             api_key,
             strategy: AuthStrategy::GoogleOAuth,
             access_token: Some(access_token),
+            key_id: None,
         }
     }
 
+    /// 使用 Key 池中选出的 Key 替换 API Key
+    ///
+    /// OAuth 凭证不参与 Key 池轮换，保持原样
+    pub fn with_pool_key(mut self, key_id: &str, api_key: &str) -> Self {
+        if self.strategy == AuthStrategy::GoogleOAuth {
+            return self;
+        }
+        self.api_key = api_key.to_string();
+        self.key_id = Some(key_id.to_string());
+        self
+    }
+
     /// 返回遮蔽后的 API Key（用于日志输出）
     ///
     /// 显示前4位和后4位，中间用 `...` 代替
@@ -203,6 +219,23 @@ mod tests {
         assert!(auth.masked_access_token().is_none());
     }
 
+    #[test]
+    fn test_with_pool_key_overrides_api_key() {
+        let auth = AuthInfo::new("sk-default".to_string(), AuthStrategy::Anthropic)
+            .with_pool_key("key-2", "sk-pooled");
+        assert_eq!(auth.api_key, "sk-pooled");
+        assert_eq!(auth.key_id.as_deref(), Some("key-2"));
+        assert_eq!(auth.strategy, AuthStrategy::Anthropic);
+    }
+
+    #[test]
+    fn test_with_pool_key_keeps_oauth_credentials() {
+        let auth = AuthInfo::with_access_token("refresh".to_string(), "ya29.token".to_string())
+            .with_pool_key("key-2", "sk-pooled");
+        assert_eq!(auth.api_key, "refresh");
+        assert!(auth.key_id.is_none());
+    }
+
     #[test]
     fn test_claude_auth_strategy() {
         let auth = AuthInfo::new("sk-test".to_string(), AuthStrategy::ClaudeAuth);
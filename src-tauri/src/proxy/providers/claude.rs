@@ -233,15 +233,17 @@ impl ProviderAdapter for ClaudeAdapter {
     }
 
     fn extract_auth(&self, provider: &Provider) -> Option<AuthInfo> {
-        let provider_type = self.provider_type(provider);
-        let strategy = match provider_type {
+        let strategy = self.pool_key_strategy(provider);
+        self.extract_key(provider)
+            .map(|key| AuthInfo::new(key, strategy))
+    }
+
+    fn pool_key_strategy(&self, provider: &Provider) -> AuthStrategy {
+        match self.provider_type(provider) {
             ProviderType::OpenRouter => AuthStrategy::Bearer,
             ProviderType::ClaudeAuth => AuthStrategy::ClaudeAuth,
             _ => AuthStrategy::Anthropic,
-        };
-
-        self.extract_key(provider)
-            .map(|key| AuthInfo::new(key, strategy))
+        }
     }
 
     fn build_url(&self, base_url: &str, endpoint: &str) -> String {
@@ -319,6 +321,7 @@ impl ProviderAdapter for ClaudeAdapter {
 mod tests {
     use super::*;
     use crate::provider::ProviderMeta;
+    use crate::proxy::key_pool::SelectedApiKey;
     use serde_json::json;
 
     fn create_provider(config: serde_json::Value) -> Provider {
@@ -398,6 +401,31 @@ mod tests {
         assert_eq!(auth.strategy, AuthStrategy::Anthropic);
     }
 
+    #[test]
+    fn test_extract_auth_with_pool_key_only() {
+        let adapter = ClaudeAdapter::new();
+        // Key 只配置在 Key 池中，settingsConfig 没有 Key
+        let provider = create_provider(json!({
+            "env": {
+                "ANTHROPIC_BASE_URL": "https://api.anthropic.com"
+            }
+        }));
+        assert!(adapter.extract_auth(&provider).is_none());
+        assert!(adapter.extract_auth_with_key(&provider, None).is_none());
+
+        let key = SelectedApiKey {
+            id: "key-1".to_string(),
+            key: "sk-ant-pooled".to_string(),
+            used_half_open_permit: false,
+        };
+        let auth = adapter
+            .extract_auth_with_key(&provider, Some(&key))
+            .expect("pool key should provide auth");
+        assert_eq!(auth.api_key, "sk-ant-pooled");
+        assert_eq!(auth.key_id.as_deref(), Some("key-1"));
+        assert_eq!(auth.strategy, AuthStrategy::Anthropic);
+    }
+
     #[test]
     fn test_extract_auth_openrouter() {
         let adapter = ClaudeAdapter::new();
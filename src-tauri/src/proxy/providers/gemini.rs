@@ -5,30 +5,50 @@
 //! ## 认证模式
 //! - **Gemini**: API Key 认证 (x-goog-api-key)
 //! - **GeminiCli**: OAuth Bearer 认证 (用于 Gemini CLI)
+//!
+//! OAuth 凭证包含 refresh_token 时，access_token 过期前会自动刷新，
+//! 刷新结果按 refresh_token 缓存在内存中（不回写供应商配置）。
 
 use super::{AuthInfo, AuthStrategy, ProviderAdapter, ProviderType};
 use crate::provider::Provider;
 use crate::proxy::error::ProxyError;
-use reqwest::RequestBuilder;
+use once_cell::sync::Lazy;
+use reqwest::{Client, RequestBuilder};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Google OAuth token 端点
+const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+
+/// access_token 距过期不足该时间（毫秒）时提前刷新
+const REFRESH_SKEW_MS: i64 = 60_000;
+
+/// 刷新后的 access_token 缓存 - key: refresh_token, value: (access_token, 过期时间毫秒)
+static REFRESHED_TOKENS: Lazy<Mutex<HashMap<String, (String, i64)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
 
 /// Gemini 适配器
 pub struct GeminiAdapter;
 
 /// OAuth 凭证结构
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 pub struct OAuthCredentials {
     pub access_token: String,
     pub refresh_token: Option<String>,
     pub client_id: Option<String>,
     pub client_secret: Option<String>,
+    /// access_token 过期时间（Unix 毫秒，与 Gemini CLI 的 oauth_creds.json 一致）
+    pub expiry_date: Option<i64>,
 }
 
-#[allow(dead_code)]
 impl OAuthCredentials {
-    /// 检查是否需要刷新 token（有 refresh_token 但没有有效的 access_token）
+    /// 检查是否需要刷新 token（有 refresh_token，且 access_token 为空或即将过期）
     pub fn needs_refresh(&self) -> bool {
-        self.refresh_token.is_some() && self.access_token.is_empty()
+        self.refresh_token.is_some()
+            && (self.access_token.is_empty()
+                || self.expiry_date.is_some_and(|expiry| {
+                    expiry - chrono::Utc::now().timestamp_millis() < REFRESH_SKEW_MS
+                }))
     }
 
     /// 检查是否可以刷新 token
@@ -78,6 +98,7 @@ impl GeminiAdapter {
                 refresh_token: None,
                 client_id: None,
                 client_secret: None,
+                expiry_date: None,
             });
         }
 
@@ -101,6 +122,7 @@ impl GeminiAdapter {
                     .get("client_secret")
                     .and_then(|v| v.as_str())
                     .map(|s| s.to_string());
+                let expiry_date = json.get("expiry_date").and_then(|v| v.as_i64());
 
                 // 如果有 access_token 或 refresh_token，返回凭证
                 if !access_token.is_empty() || refresh_token.is_some() {
@@ -109,6 +131,7 @@ impl GeminiAdapter {
                         refresh_token,
                         client_id,
                         client_secret,
+                        expiry_date,
                     });
                 }
             }
@@ -140,6 +163,114 @@ impl GeminiAdapter {
     }
 }
 
+/// 确保 OAuth 认证使用有效的 access_token
+///
+/// 凭证需要刷新时优先使用缓存的刷新结果，否则向 Google 请求新的 access_token。
+/// 刷新失败时沿用原 access_token（为空则返回认证错误）。
+pub async fn ensure_fresh_access_token(
+    mut auth: AuthInfo,
+    client: &Client,
+) -> Result<AuthInfo, ProxyError> {
+    if auth.strategy != AuthStrategy::GoogleOAuth {
+        return Ok(auth);
+    }
+    let Some(creds) = GeminiAdapter::new().parse_oauth_credentials(&auth.api_key) else {
+        return Ok(auth);
+    };
+    if !creds.needs_refresh() {
+        return Ok(auth);
+    }
+    let Some(refresh_token) = creds.refresh_token.as_deref() else {
+        return Ok(auth);
+    };
+
+    let now = chrono::Utc::now().timestamp_millis();
+    let cached = REFRESHED_TOKENS
+        .lock()
+        .ok()
+        .and_then(|cache| cache.get(refresh_token).cloned())
+        .filter(|(_, expiry)| expiry - now >= REFRESH_SKEW_MS);
+    if let Some((access_token, _)) = cached {
+        auth.access_token = Some(access_token);
+        return Ok(auth);
+    }
+
+    match refresh_access_token(&creds, refresh_token, client).await {
+        Ok((access_token, expiry)) => {
+            log::info!("[Gemini] OAuth access_token 已刷新");
+            if let Ok(mut cache) = REFRESHED_TOKENS.lock() {
+                cache.insert(refresh_token.to_string(), (access_token.clone(), expiry));
+            }
+            auth.access_token = Some(access_token);
+            Ok(auth)
+        }
+        Err(e) if creds.access_token.is_empty() => Err(e),
+        Err(e) => {
+            log::warn!("[Gemini] OAuth access_token 刷新失败，继续使用原 token: {e}");
+            Ok(auth)
+        }
+    }
+}
+
+/// 使用 refresh_token 换取新的 access_token，返回 (access_token, 过期时间毫秒)
+async fn refresh_access_token(
+    creds: &OAuthCredentials,
+    refresh_token: &str,
+    client: &Client,
+) -> Result<(String, i64), ProxyError> {
+    if !creds.can_refresh() {
+        return Err(ProxyError::AuthError(
+            "OAuth 凭证缺少 client_id / client_secret，无法刷新 access_token".to_string(),
+        ));
+    }
+    let form = [
+        ("grant_type", "refresh_token"),
+        ("refresh_token", refresh_token),
+        ("client_id", creds.client_id.as_deref().unwrap_or_default()),
+        (
+            "client_secret",
+            creds.client_secret.as_deref().unwrap_or_default(),
+        ),
+    ];
+
+    let response = client
+        .post(GOOGLE_TOKEN_URL)
+        .form(&form)
+        .send()
+        .await
+        .map_err(|e| ProxyError::AuthError(format!("刷新 OAuth token 失败: {e}")))?;
+    let status = response.status();
+    let body: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| ProxyError::AuthError(format!("解析 OAuth token 响应失败: {e}")))?;
+    if !status.is_success() {
+        let reason = body
+            .get("error_description")
+            .or_else(|| body.get("error"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown");
+        return Err(ProxyError::AuthError(format!(
+            "刷新 OAuth token 失败 (HTTP {}): {reason}",
+            status.as_u16()
+        )));
+    }
+
+    let access_token = body
+        .get("access_token")
+        .and_then(|v| v.as_str())
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| ProxyError::AuthError("OAuth token 响应缺少 access_token".to_string()))?;
+    let expires_in = body
+        .get("expires_in")
+        .and_then(|v| v.as_i64())
+        .unwrap_or(3600);
+    Ok((
+        access_token.to_string(),
+        chrono::Utc::now().timestamp_millis() + expires_in * 1000,
+    ))
+}
+
 impl Default for GeminiAdapter {
     fn default() -> Self {
         Self::new()
@@ -199,6 +330,11 @@ impl ProviderAdapter for GeminiAdapter {
         }
     }
 
+    fn pool_key_strategy(&self, _provider: &Provider) -> AuthStrategy {
+        // Key 池只存放 API Key，OAuth 凭证不参与轮换
+        AuthStrategy::Google
+    }
+
     fn build_url(&self, base_url: &str, endpoint: &str) -> String {
         let base_trimmed = base_url.trim_end_matches('/');
         let endpoint_trimmed = endpoint.trim_start_matches('/');
@@ -413,6 +549,37 @@ mod tests {
         assert_eq!(creds.refresh_token, Some("1//refresh".to_string()));
     }
 
+    #[test]
+    fn test_oauth_needs_refresh_when_expired_or_missing() {
+        let adapter = GeminiAdapter::new();
+        let now = chrono::Utc::now().timestamp_millis();
+
+        let expired = adapter
+            .parse_oauth_credentials(&format!(
+                "{{\"access_token\":\"ya29.old\",\"refresh_token\":\"1//r\",\"expiry_date\":{}}}",
+                now - 1000
+            ))
+            .unwrap();
+        assert!(expired.needs_refresh());
+
+        let valid = adapter
+            .parse_oauth_credentials(&format!(
+                "{{\"access_token\":\"ya29.ok\",\"refresh_token\":\"1//r\",\"expiry_date\":{}}}",
+                now + 3_600_000
+            ))
+            .unwrap();
+        assert!(!valid.needs_refresh());
+
+        let missing = adapter
+            .parse_oauth_credentials("{\"refresh_token\":\"1//r\"}")
+            .unwrap();
+        assert!(missing.needs_refresh());
+
+        // 没有 refresh_token 时无法刷新
+        let direct = adapter.parse_oauth_credentials("ya29.direct").unwrap();
+        assert!(!direct.needs_refresh());
+    }
+
     #[test]
     fn test_parse_oauth_credentials_invalid() {
         let adapter = GeminiAdapter::new();
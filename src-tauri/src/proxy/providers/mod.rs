@@ -29,7 +29,7 @@ pub use adapter::ProviderAdapter;
 pub use auth::{AuthInfo, AuthStrategy};
 pub use claude::ClaudeAdapter;
 pub use codex::CodexAdapter;
-pub use gemini::GeminiAdapter;
+pub use gemini::{ensure_fresh_access_token, GeminiAdapter};
 
 /// 供应商类型枚举
 ///
@@ -5,15 +5,19 @@
 use super::{
     handler_config::UsageParserConfig,
     handler_context::{RequestContext, StreamingTimeoutConfig},
+    metrics::ActiveStreamGuard,
+    otel::RequestTrace,
     providers::get_adapter,
     server::ProxyState,
+    stream_hook::StreamEventHook,
+    stream_resume::{take_event, ResumeCollectorFactory, ResumeRuntime, StreamResume},
     usage::parser::TokenUsage,
     ProxyError,
 };
 use crate::app_config::AppType;
 use crate::request_hook_script::{
-    build_header_string_map, execute_on_response_script, HookResponse, RequestHookContext,
-    RequestHookProviderInfo,
+    build_header_string_map, execute_on_response_script, hook_chain, HookResponse,
+    RequestHookContext, RequestHookProviderInfo, ScopedHookScript,
 };
 use axum::response::{IntoResponse, Response};
 use bytes::Bytes;
@@ -45,11 +49,14 @@ pub fn is_sse_response(response: &reqwest::Response) -> bool {
 }
 
 /// 处理流式响应
+///
+/// 传入 `resume` 时，上游流在结束事件前中断会换供应商续写（见 `stream_resume`）。
 pub async fn handle_streaming(
     response: reqwest::Response,
     ctx: &RequestContext,
     state: &ProxyState,
     parser_config: &UsageParserConfig,
+    resume: Option<StreamResume>,
 ) -> Response {
     let status = response.status();
     log::debug!(
@@ -75,12 +82,39 @@ pub async fn handle_streaming(
 
     // 获取流式超时配置
     let timeout_config = ctx.streaming_timeout_config();
+    let active_stream = Some(state.metrics.track_stream(ctx.app_type_str));
+    let body = match resume.filter(|_| status.is_success()) {
+        Some(resume) => {
+            // 续传流自行处理超时并按供应商分别记录用量，透传层只负责日志与钩子
+            let runtime = resume_runtime(ctx, state, parser_config, usage_collector);
+            let resumable_stream = resume.into_stream(stream, runtime);
+            let logged_stream = create_logged_passthrough_stream(
+                resumable_stream,
+                ctx.tag,
+                None,
+                StreamingTimeoutConfig {
+                    first_byte_timeout: 0,
+                    idle_timeout: 0,
+                },
+                stream_event_hook(ctx),
+                active_stream,
+            );
+            axum::body::Body::from_stream(logged_stream)
+        }
+        None => {
+            // 创建带日志和超时的透传流
+            let logged_stream = create_logged_passthrough_stream(
+                stream,
+                ctx.tag,
+                Some(usage_collector),
+                timeout_config,
+                stream_event_hook(ctx),
+                active_stream,
+            );
+            axum::body::Body::from_stream(logged_stream)
+        }
+    };
 
-    // 创建带日志和超时的透传流
-    let logged_stream =
-        create_logged_passthrough_stream(stream, ctx.tag, Some(usage_collector), timeout_config);
-
-    let body = axum::body::Body::from_stream(logged_stream);
     match builder.body(body) {
         Ok(resp) => resp,
         Err(e) => {
@@ -90,6 +124,39 @@ pub async fn handle_streaming(
     }
 }
 
+/// 创建续传运行时：续写的供应商按自己的 Key 与映射后模型记录用量，耗时从续传开始计算
+fn resume_runtime(
+    ctx: &RequestContext,
+    state: &ProxyState,
+    parser_config: &UsageParserConfig,
+    usage_collector: SseUsageCollector,
+) -> ResumeRuntime {
+    let attribution = UsageAttribution::from_ctx(ctx);
+    let collector_state = state.clone();
+    let parser_config = *parser_config;
+    let new_collector: ResumeCollectorFactory =
+        Box::new(move |provider, api_key_id, mapped_model| {
+            let attribution = UsageAttribution {
+                provider_id: provider.id.clone(),
+                effective_model: mapped_model.unwrap_or_else(|| attribution.request_model.clone()),
+                start_time: std::time::Instant::now(),
+                api_key_id,
+                ..attribution.clone()
+            };
+            create_attributed_usage_collector(attribution, &collector_state, 200, &parser_config)
+        });
+
+    ResumeRuntime {
+        forwarder: ctx.create_forwarder(state).with_hedging(None),
+        app_type: ctx.app_type.clone(),
+        tag: ctx.tag,
+        timeout_config: ctx.streaming_timeout_config(),
+        provider: ctx.provider.clone(),
+        collector: usage_collector,
+        new_collector,
+    }
+}
+
 /// 处理非流式响应
 pub async fn handle_non_streaming(
     response: reqwest::Response,
@@ -101,10 +168,14 @@ pub async fn handle_non_streaming(
     let status = response.status();
 
     // 读取响应体
+    let mut read_span = ctx.trace.span("read_response_body");
     let body_bytes = response.bytes().await.map_err(|e| {
         log::error!("[{}] 读取响应失败: {e}", ctx.tag);
+        read_span.set_error(e.to_string());
         ProxyError::ForwardFailed(format!("Failed to read response body: {e}"))
-    })?;
+    });
+    read_span.end();
+    let body_bytes = body_bytes?;
     let mut final_body_bytes = body_bytes.to_vec();
     let mut final_status = status;
 
@@ -181,8 +252,8 @@ pub async fn handle_non_streaming(
         );
     }
 
-    // onResponse 脚本拦截（仅 Codex 非流式）
-    maybe_apply_codex_on_response_hook(
+    // onResponse 脚本拦截（非流式）
+    maybe_apply_on_response_hooks(
         ctx,
         &mut final_status,
         &mut response_headers,
@@ -215,62 +286,49 @@ pub async fn process_response(
     ctx: &RequestContext,
     state: &ProxyState,
     parser_config: &UsageParserConfig,
+) -> Result<Response, ProxyError> {
+    process_response_with_resume(response, ctx, state, parser_config, None).await
+}
+
+/// 通用响应处理入口（流式响应支持断流续传）
+pub async fn process_response_with_resume(
+    response: reqwest::Response,
+    ctx: &RequestContext,
+    state: &ProxyState,
+    parser_config: &UsageParserConfig,
+    resume: Option<StreamResume>,
 ) -> Result<Response, ProxyError> {
     if is_sse_response(&response) {
-        Ok(handle_streaming(response, ctx, state, parser_config).await)
+        Ok(handle_streaming(response, ctx, state, parser_config, resume).await)
     } else {
         handle_non_streaming(response, ctx, state, parser_config).await
     }
 }
 
-fn maybe_apply_codex_on_response_hook(
-    ctx: &RequestContext,
-    status: &mut reqwest::StatusCode,
-    headers: &mut HeaderMap,
-    body: &mut Vec<u8>,
-) {
-    if ctx.app_type != AppType::Codex {
-        return;
-    }
-
-    let Some(script) = ctx
-        .provider
-        .meta
-        .as_ref()
-        .and_then(|m| m.request_hook_script.as_ref())
-    else {
-        return;
-    };
-
-    if !script.enabled || script.code.trim().is_empty() {
-        return;
-    }
-
-    if !script.language.eq_ignore_ascii_case("javascript") {
-        log::warn!(
-            "[Codex] [Hook] 不支持的脚本语言: {}（当前仅支持 javascript）",
-            script.language
-        );
-        return;
-    }
+/// 响应侧的脚本链（与 onRequest 顺序相反：供应商 → 应用 → 全局）
+fn response_hook_chain(ctx: &RequestContext) -> Vec<ScopedHookScript> {
+    let mut hooks = hook_chain(&ctx.hook_scripts, &ctx.provider, ctx.tag);
+    hooks.reverse();
+    hooks
+}
 
+/// 构建响应侧钩子的只读上下文
+fn response_hook_context(ctx: &RequestContext) -> Result<RequestHookContext, String> {
     let adapter = get_adapter(&ctx.app_type);
-    let base_url = match adapter.extract_base_url(&ctx.provider) {
-        Ok(v) => v,
-        Err(e) => {
-            log::warn!("[Codex] [Hook] 读取 provider base_url 失败，已忽略 onResponse: {e}");
-            return;
-        }
-    };
-    let endpoint = if ctx.request_endpoint.is_empty() {
+    let base_url = adapter
+        .extract_base_url(&ctx.provider)
+        .map_err(|e| format!("读取 provider base_url 失败: {e}"))?;
+    let endpoint = if !ctx.request_endpoint.is_empty() {
+        ctx.request_endpoint.as_str()
+    } else if ctx.app_type == AppType::Codex {
         "/responses"
     } else {
-        &ctx.request_endpoint
+        "/v1/messages"
     };
     let path = endpoint.split('?').next().unwrap_or(endpoint).to_string();
     let url = adapter.build_url(&base_url, endpoint);
 
-    let context = RequestHookContext {
+    Ok(RequestHookContext {
         app: ctx.app_type_str.to_string(),
         method: "POST".to_string(),
         path,
@@ -281,80 +339,110 @@ fn maybe_apply_codex_on_response_hook(
             name: ctx.provider.name.clone(),
         },
         incoming_headers: ctx.incoming_headers.clone(),
-    };
-
-    let body_value = serde_json::from_slice::<Value>(body)
-        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(body).to_string()));
-    let response_view = HookResponse {
-        code: status.as_u16(),
-        headers: build_header_string_map(headers),
-        body: body_value,
-    };
+    })
+}
 
-    let output = match execute_on_response_script(&script.code, &context, &response_view) {
+/// 为流式响应创建 onStreamEvent 钩子（没有脚本定义该钩子时返回 `None`）
+pub fn stream_event_hook(ctx: &RequestContext) -> Option<StreamEventHook> {
+    let hooks = response_hook_chain(ctx);
+    if hooks.is_empty() {
+        return None;
+    }
+    let context = match response_hook_context(ctx) {
         Ok(v) => v,
         Err(e) => {
-            log::warn!("[Codex] [Hook] onResponse 执行失败，已忽略: {e}");
-            return;
+            log::warn!("[{}] [Hook] {e}，已忽略 onStreamEvent", ctx.tag);
+            return None;
         }
     };
+    StreamEventHook::spawn(ctx.tag, &hooks, &context)
+}
 
-    let Some(new_view) = output else {
+fn maybe_apply_on_response_hooks(
+    ctx: &RequestContext,
+    status: &mut reqwest::StatusCode,
+    headers: &mut HeaderMap,
+    body: &mut Vec<u8>,
+) {
+    let hooks = response_hook_chain(ctx);
+    if hooks.is_empty() {
         return;
-    };
+    }
 
-    let new_status = match reqwest::StatusCode::from_u16(new_view.code) {
+    let context = match response_hook_context(ctx) {
         Ok(v) => v,
         Err(e) => {
-            log::warn!(
-                "[Codex] [Hook] onResponse 输出 status 无效，已忽略: {} ({e})",
-                new_view.code
-            );
+            log::warn!("[{}] [Hook] {e}，已忽略 onResponse", ctx.tag);
             return;
         }
     };
 
-    let mut new_headers = HeaderMap::new();
-    for (key, value) in new_view.headers {
-        let name = match reqwest::header::HeaderName::from_bytes(key.as_bytes()) {
-            Ok(v) => v,
-            Err(e) => {
-                log::warn!("[Codex] [Hook] onResponse 输出 header name 无效，已忽略: {key} ({e})");
-                return;
-            }
+    for hook in &hooks {
+        let prefix = format!("[{}] [Hook:{}]", ctx.tag, hook.scope.as_str());
+        let body_value = serde_json::from_slice::<Value>(body)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(body).to_string()));
+        let response_view = HookResponse {
+            code: status.as_u16(),
+            headers: build_header_string_map(headers),
+            body: body_value,
         };
-        let value = match reqwest::header::HeaderValue::from_str(&value) {
-            Ok(v) => v,
-            Err(e) => {
-                log::warn!("[Codex] [Hook] onResponse 输出 header value 无效，已忽略: {key} ({e})");
-                return;
+
+        let run = execute_on_response_script(
+            &hook.script.code,
+            hook.script.timeout_ms,
+            &context,
+            &response_view,
+        );
+        run.log_console(&prefix);
+        match run.result {
+            Ok(Some(new_view)) => {
+                if let Err(e) = apply_hook_response(new_view, status, headers, body) {
+                    log::warn!("{prefix} onResponse 输出无效，已忽略: {e}");
+                }
             }
-        };
+            Ok(None) => {}
+            Err(e) => log::warn!("{prefix} onResponse 执行失败，已忽略: {e}"),
+        }
+    }
+}
+
+/// 将 onResponse 输出写回响应（任一字段无效时不做任何修改）
+fn apply_hook_response(
+    new_view: HookResponse,
+    status: &mut reqwest::StatusCode,
+    headers: &mut HeaderMap,
+    body: &mut Vec<u8>,
+) -> Result<(), String> {
+    let new_status = reqwest::StatusCode::from_u16(new_view.code)
+        .map_err(|e| format!("status 无效: {} ({e})", new_view.code))?;
+
+    let mut new_headers = HeaderMap::new();
+    for (key, value) in new_view.headers {
+        let name = reqwest::header::HeaderName::from_bytes(key.as_bytes())
+            .map_err(|e| format!("header name 无效: {key} ({e})"))?;
+        let value = reqwest::header::HeaderValue::from_str(&value)
+            .map_err(|e| format!("header value 无效: {key} ({e})"))?;
         new_headers.insert(name, value);
     }
 
     let body_bytes = match new_view.body {
         Value::String(s) => s.into_bytes(),
-        value => match serde_json::to_vec(&value) {
-            Ok(v) => {
-                if !new_headers.contains_key("content-type") {
-                    new_headers.insert(
-                        "content-type",
-                        reqwest::header::HeaderValue::from_static("application/json"),
-                    );
-                }
-                v
+        value => {
+            let bytes = serde_json::to_vec(&value).map_err(|e| format!("body 无法序列化: {e}"))?;
+            if !new_headers.contains_key("content-type") {
+                new_headers.insert(
+                    "content-type",
+                    reqwest::header::HeaderValue::from_static("application/json"),
+                );
             }
-            Err(e) => {
-                log::warn!("[Codex] [Hook] onResponse 输出 body 无法序列化，已忽略: {e}");
-                return;
-            }
-        },
+            bytes
+        }
     };
 
     *status = new_status;
     *headers = new_headers;
     *body = body_bytes;
+    Ok(())
 }
 
 // ============================================================================
@@ -432,33 +520,102 @@ impl SseUsageCollector {
 // 内部辅助函数
 // ============================================================================
 
+/// 用量归因信息（从请求上下文中提取，续传时替换为续写的供应商）
+#[derive(Clone)]
+struct UsageAttribution {
+    provider_id: String,
+    request_model: String,
+    effective_model: String,
+    tag: &'static str,
+    start_time: std::time::Instant,
+    session_id: String,
+    api_key_id: Option<String>,
+    client_token_id: Option<String>,
+    routing_override: Option<String>,
+    trace: RequestTrace,
+}
+
+impl UsageAttribution {
+    fn from_ctx(ctx: &RequestContext) -> Self {
+        Self {
+            provider_id: ctx.provider.id.clone(),
+            request_model: ctx.request_model.clone(),
+            effective_model: ctx.get_actual_model().to_string(),
+            tag: ctx.tag,
+            start_time: ctx.start_time,
+            session_id: ctx.session_id.clone(),
+            api_key_id: ctx.api_key_id.clone(),
+            client_token_id: ctx.client_token_id(),
+            routing_override: ctx.routing_override(),
+            trace: ctx.trace.clone(),
+        }
+    }
+}
+
 /// 创建使用量收集器
 fn create_usage_collector(
     ctx: &RequestContext,
     state: &ProxyState,
     status_code: u16,
     parser_config: &UsageParserConfig,
+) -> SseUsageCollector {
+    create_attributed_usage_collector(
+        UsageAttribution::from_ctx(ctx),
+        state,
+        status_code,
+        parser_config,
+    )
+}
+
+/// 按归因信息创建使用量收集器
+fn create_attributed_usage_collector(
+    attribution: UsageAttribution,
+    state: &ProxyState,
+    status_code: u16,
+    parser_config: &UsageParserConfig,
 ) -> SseUsageCollector {
     let state = state.clone();
-    let provider_id = ctx.provider.id.clone();
-    let request_model = ctx.request_model.clone();
-    let effective_model = ctx.get_actual_model().to_string();
+    let UsageAttribution {
+        provider_id,
+        request_model,
+        effective_model,
+        tag,
+        start_time,
+        session_id,
+        api_key_id,
+        client_token_id,
+        routing_override,
+        trace,
+    } = attribution;
     let app_type_str = parser_config.app_type_str;
-    let tag = ctx.tag;
-    let start_time = ctx.start_time;
     let stream_parser = parser_config.stream_parser;
     let model_extractor = parser_config.model_extractor;
-    let session_id = ctx.session_id.clone();
+    // 流式传输 span：从开始转发到流结束
+    let stream_span = trace.span("stream_response");
 
     SseUsageCollector::new(start_time, move |events, first_token_ms| {
+        let mut span = stream_span.clone();
+        span.set_attribute("cc_switch.sse_events", events.len());
+        if let Some(first_token_ms) = first_token_ms {
+            span.set_attribute("cc_switch.first_token_ms", first_token_ms);
+            trace.set_attribute("cc_switch.first_token_ms", first_token_ms);
+        }
+        span.end();
+        trace.set_attribute("http.response.status_code", status_code);
+
         if let Some(usage) = stream_parser(&events) {
             let model = model_extractor(&events, &effective_model);
             let latency_ms = start_time.elapsed().as_millis() as u64;
+            record_usage_attributes(&trace, &model, &usage);
 
             let state = state.clone();
             let provider_id = provider_id.clone();
             let session_id = session_id.clone();
             let request_model = request_model.clone();
+            let api_key_id = api_key_id.clone();
+            let client_token_id = client_token_id.clone();
+            let routing_override = routing_override.clone();
+            let usage_span = trace.span("usage_logging");
 
             tokio::spawn(async move {
                 log_usage_internal(
@@ -473,8 +630,12 @@ fn create_usage_collector(
                     true, // is_streaming
                     status_code,
                     Some(session_id),
+                    api_key_id,
+                    client_token_id,
+                    routing_override,
                 )
                 .await;
+                usage_span.end();
             });
         } else {
             let model = model_extractor(&events, &effective_model);
@@ -483,6 +644,10 @@ fn create_usage_collector(
             let provider_id = provider_id.clone();
             let session_id = session_id.clone();
             let request_model = request_model.clone();
+            let api_key_id = api_key_id.clone();
+            let client_token_id = client_token_id.clone();
+            let routing_override = routing_override.clone();
+            let usage_span = trace.span("usage_logging");
 
             tokio::spawn(async move {
                 log_usage_internal(
@@ -497,8 +662,12 @@ fn create_usage_collector(
                     true, // is_streaming
                     status_code,
                     Some(session_id),
+                    api_key_id,
+                    client_token_id,
+                    routing_override,
                 )
                 .await;
+                usage_span.end();
             });
             log::debug!("[{tag}] 流式响应缺少 usage 统计，跳过消费记录");
         }
@@ -522,6 +691,13 @@ fn spawn_log_usage(
     let request_model = request_model.to_string();
     let latency_ms = ctx.latency_ms();
     let session_id = ctx.session_id.clone();
+    let api_key_id = ctx.api_key_id.clone();
+    let client_token_id = ctx.client_token_id();
+    let routing_override = ctx.routing_override();
+    ctx.trace
+        .set_attribute("http.response.status_code", status_code);
+    record_usage_attributes(&ctx.trace, &model, &usage);
+    let usage_span = ctx.trace.span("usage_logging");
 
     tokio::spawn(async move {
         log_usage_internal(
@@ -536,11 +712,22 @@ fn spawn_log_usage(
             is_streaming,
             status_code,
             Some(session_id),
+            api_key_id,
+            client_token_id,
+            routing_override,
         )
         .await;
+        usage_span.end();
     });
 }
 
+/// 在 trace 根 span 上记录模型与 Token 用量
+fn record_usage_attributes(trace: &RequestTrace, model: &str, usage: &TokenUsage) {
+    trace.set_attribute("gen_ai.response.model", model);
+    trace.set_attribute("gen_ai.usage.input_tokens", u64::from(usage.input_tokens));
+    trace.set_attribute("gen_ai.usage.output_tokens", u64::from(usage.output_tokens));
+}
+
 /// 内部使用量记录函数
 #[allow(clippy::too_many_arguments)]
 async fn log_usage_internal(
@@ -555,10 +742,13 @@ async fn log_usage_internal(
     is_streaming: bool,
     status_code: u16,
     session_id: Option<String>,
+    api_key_id: Option<String>,
+    client_token_id: Option<String>,
+    routing_override: Option<String>,
 ) {
     use super::usage::logger::UsageLogger;
 
-    let logger = UsageLogger::new(state.db.clone());
+    let logger = UsageLogger::new(state.db.clone()).with_metrics(state.metrics.clone());
     let (multiplier, pricing_model_source) =
         logger.resolve_pricing_config(provider_id, app_type).await;
     let pricing_model = if pricing_model_source == "request" {
@@ -594,6 +784,9 @@ async fn log_usage_internal(
             session_id,
             None, // provider_type
             is_streaming,
+            api_key_id,
+            client_token_id,
+            routing_override,
         )
         .await
     {
@@ -607,10 +800,15 @@ pub fn create_logged_passthrough_stream(
     tag: &'static str,
     usage_collector: Option<SseUsageCollector>,
     timeout_config: StreamingTimeoutConfig,
+    stream_hook: Option<StreamEventHook>,
+    active_stream: Option<ActiveStreamGuard>,
 ) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Send {
     async_stream::stream! {
-        let mut buffer = String::new();
+        // 流结束或客户端断开（流被丢弃）时释放，减少进行中的流式响应计数
+        let _active_stream = active_stream;
+        let mut buffer: Vec<u8> = Vec::new();
         let mut collector = usage_collector;
+        let mut hook = stream_hook;
         let mut is_first_chunk = true;
 
         // 超时配置
@@ -652,7 +850,7 @@ pub fn create_logged_passthrough_stream(
                 None => stream.next().await, // 无超时限制
             };
 
-            match chunk_result {
+            let (bytes, eof) = match chunk_result {
                 Some(Ok(bytes)) => {
                     if is_first_chunk {
                         log::debug!(
@@ -661,46 +859,58 @@ pub fn create_logged_passthrough_stream(
                         );
                     }
                     is_first_chunk = false;
-                    let text = String::from_utf8_lossy(&bytes);
-                    buffer.push_str(&text);
-
-                    // 尝试解析并记录完整的 SSE 事件
-                    while let Some(pos) = buffer.find("\n\n") {
-                        let event_text = buffer[..pos].to_string();
-                        buffer = buffer[pos + 2..].to_string();
-
-                        if !event_text.trim().is_empty() {
-                            // 提取 data 部分并尝试解析为 JSON
-                            for line in event_text.lines() {
-                                if let Some(data) = line.strip_prefix("data: ") {
-                                    if data.trim() != "[DONE]" {
-                                        if let Ok(json_value) = serde_json::from_str::<Value>(data) {
-                                            if let Some(c) = &collector {
-                                                c.push(json_value.clone()).await;
-                                            }
-                                            log::debug!("[{tag}] <<< SSE 事件: {data}");
-                                        } else {
-                                            log::debug!("[{tag}] <<< SSE 数据: {data}");
-                                        }
-                                    } else {
-                                        log::debug!("[{tag}] <<< SSE: [DONE]");
+                    buffer.extend_from_slice(&bytes);
+                    (Some(bytes), false)
+                }
+                Some(Err(e)) => {
+                    log::error!("[{tag}] 流错误: {e}");
+                    yield Err(std::io::Error::other(e.to_string()));
+                    break;
+                }
+                None => (None, true),
+            };
+
+            // 尝试解析并记录完整的 SSE 事件（兼容 \n、\r\n、\r 分隔）；
+            // 流结束时剩余内容作为最后一个事件
+            let mut hooked = String::new();
+            while let Some(event_bytes) = take_event(&mut buffer, eof) {
+                let event_text = sse_event_text(&event_bytes);
+
+                if !event_text.trim().is_empty() {
+                    // 提取 data 部分并尝试解析为 JSON
+                    for line in event_text.lines() {
+                        if let Some(data) = line.strip_prefix("data: ") {
+                            if data.trim() != "[DONE]" {
+                                if let Ok(json_value) = serde_json::from_str::<Value>(data) {
+                                    if let Some(c) = &collector {
+                                        c.push(json_value.clone()).await;
                                     }
+                                    log::debug!("[{tag}] <<< SSE 事件: {data}");
+                                } else {
+                                    log::debug!("[{tag}] <<< SSE 数据: {data}");
                                 }
+                            } else {
+                                log::debug!("[{tag}] <<< SSE: [DONE]");
                             }
                         }
                     }
-
-                    yield Ok(bytes);
                 }
-                Some(Err(e)) => {
-                    log::error!("[{tag}] 流错误: {e}");
-                    yield Err(std::io::Error::other(e.to_string()));
-                    break;
+
+                // 用量统计基于上游原始事件，钩子只影响返回给客户端的内容
+                if let Some(h) = hook.as_mut() {
+                    hooked.push_str(&h.process(&event_text).await);
                 }
-                None => {
-                    // 流正常结束
-                    break;
+            }
+
+            if hook.is_none() {
+                if let Some(bytes) = bytes {
+                    yield Ok(bytes);
                 }
+            } else if !hooked.is_empty() {
+                yield Ok(Bytes::from(hooked));
+            }
+            if eof {
+                break;
             }
         }
 
@@ -710,6 +920,15 @@ pub fn create_logged_passthrough_stream(
     }
 }
 
+/// SSE 事件文本：统一换行为 `\n` 并去掉结尾空行（钩子脚本按 `\n` 分行解析）
+fn sse_event_text(event: &[u8]) -> String {
+    String::from_utf8_lossy(event)
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .trim_end_matches('\n')
+        .to_string()
+}
+
 fn format_headers(headers: &HeaderMap) -> String {
     headers
         .iter()
@@ -728,6 +947,8 @@ mod tests {
     use crate::error::AppError;
     use crate::provider::ProviderMeta;
     use crate::proxy::failover_switch::FailoverSwitchManager;
+    use crate::proxy::metrics::ProxyMetrics;
+    use crate::proxy::model_catalog::ModelCatalog;
     use crate::proxy::provider_router::ProviderRouter;
     use crate::proxy::types::{ProxyConfig, ProxyStatus};
     use rust_decimal::Decimal;
@@ -746,6 +967,8 @@ mod tests {
             provider_router: Arc::new(ProviderRouter::new(db.clone())),
             app_handle: None,
             failover_manager: Arc::new(FailoverSwitchManager::new(db)),
+            model_catalog: Arc::new(ModelCatalog::new()),
+            metrics: Arc::new(ProxyMetrics::new()),
         }
     }
 
@@ -819,6 +1042,9 @@ mod tests {
             false,
             200,
             None,
+            None,
+            None,
+            None,
         )
         .await;
 
@@ -878,6 +1104,9 @@ mod tests {
             false,
             200,
             None,
+            None,
+            None,
+            None,
         )
         .await;
 
@@ -901,4 +1130,63 @@ mod tests {
         );
         Ok(())
     }
+
+    #[tokio::test]
+    async fn hooked_stream_emits_crlf_framed_events_without_waiting_for_eof() {
+        use crate::request_hook_script::HookScope;
+
+        let script = ScopedHookScript {
+            scope: HookScope::Provider,
+            script: serde_json::from_value(serde_json::json!({
+                "enabled": true,
+                "language": "javascript",
+                "code": "({ onStreamEvent: function (event) { event.data.n += 1; return event; } })",
+                "streamEventBudgetMs": 1000,
+            }))
+            .expect("script"),
+        };
+        let context = RequestHookContext {
+            app: "claude".to_string(),
+            method: "POST".to_string(),
+            path: "/v1/messages".to_string(),
+            endpoint: "/v1/messages".to_string(),
+            url: "https://api.example.com/v1/messages".to_string(),
+            provider: RequestHookProviderInfo {
+                id: "p1".to_string(),
+                name: "Example".to_string(),
+            },
+            incoming_headers: HashMap::new(),
+        };
+        let hook = StreamEventHook::spawn("Test", &[script], &context).expect("hook");
+
+        let (tx, rx) = futures::channel::mpsc::unbounded::<Result<Bytes, std::io::Error>>();
+        let stream = create_logged_passthrough_stream(
+            rx,
+            "Test",
+            None,
+            StreamingTimeoutConfig {
+                first_byte_timeout: 0,
+                idle_timeout: 0,
+            },
+            Some(hook),
+            None,
+        );
+        tokio::pin!(stream);
+
+        // 上游未关闭时，CRLF 与 CR 分隔的事件也应立即输出
+        tx.unbounded_send(Ok(Bytes::from_static(
+            b"data: {\"n\":1}\r\n\r\ndata: {\"n\":5}\r\r",
+        )))
+        .expect("send chunk");
+        let chunk = tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("event must not wait for EOF")
+            .expect("chunk")
+            .expect("ok chunk");
+        assert_eq!(chunk, Bytes::from("data: {\"n\":2}\n\n"));
+
+        drop(tx);
+        let rest: Vec<Bytes> = stream.map(|chunk| chunk.expect("ok chunk")).collect().await;
+        assert_eq!(rest, vec![Bytes::from("data: {\"n\":6}\n\n")]);
+    }
 }
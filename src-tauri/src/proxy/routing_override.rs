@@ -0,0 +1,164 @@
+//! 单次请求的路由覆盖
+//!
+//! 客户端可以通过以下请求头临时改变本次请求的路由（不影响当前供应商与熔断器状态）：
+//! - `x-ccswitch-provider`：指定供应商（ID 或名称），跳过供应商选择与故障转移
+//! - `x-ccswitch-model`：覆盖发往上游的模型（优先于供应商的模型映射）
+//! - `x-ccswitch-no-failover`：本次请求只尝试首选供应商
+//!
+//! 这些请求头由 `header_filter` 过滤，不会透传到上游；生效的覆盖项记录在请求日志中。
+
+use axum::http::HeaderMap;
+use serde::{Deserialize, Serialize};
+
+use crate::provider::Provider;
+
+pub const PROVIDER_HEADER: &str = "x-ccswitch-provider";
+pub const MODEL_HEADER: &str = "x-ccswitch-model";
+pub const NO_FAILOVER_HEADER: &str = "x-ccswitch-no-failover";
+
+/// 请求头中的路由覆盖项
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutingOverrides {
+    /// 指定的供应商（请求头原值，ID 或名称）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// 覆盖的上游模型
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// 禁用故障转移
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub no_failover: bool,
+}
+
+impl RoutingOverrides {
+    /// 从请求头解析（空值视为未设置）
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let value = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        };
+
+        Self {
+            provider: value(PROVIDER_HEADER),
+            model: value(MODEL_HEADER),
+            no_failover: value(NO_FAILOVER_HEADER).is_some_and(|v| {
+                matches!(v.to_ascii_lowercase().as_str(), "1" | "true" | "yes" | "on")
+            }),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.provider.is_none() && self.model.is_none() && !self.no_failover
+    }
+
+    /// 写入请求日志的 JSON（无覆盖时为 None）
+    pub fn to_log_value(&self) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+        serde_json::to_string(self).ok()
+    }
+}
+
+/// 按 ID 或名称（不区分大小写）查找指定的供应商，ID 精确匹配优先
+pub fn find_pinned_provider<'a>(
+    providers: impl IntoIterator<Item = &'a Provider>,
+    key: &str,
+) -> Option<&'a Provider> {
+    let mut by_name = None;
+    for provider in providers {
+        if provider.id == key {
+            return Some(provider);
+        }
+        if by_name.is_none() && provider.name.eq_ignore_ascii_case(key) {
+            by_name = Some(provider);
+        }
+    }
+    by_name
+}
+
+/// 替换 Gemini 端点路径中的模型（`.../models/{model}:method`），无模型段时原样返回
+pub fn replace_model_in_path(endpoint: &str, model: &str) -> String {
+    let Some(start) = endpoint.find("models/").map(|i| i + "models/".len()) else {
+        return endpoint.to_string();
+    };
+    let rest = &endpoint[start..];
+    let end = rest.find([':', '?', '/']).unwrap_or(rest.len());
+    format!("{}{model}{}", &endpoint[..start], &rest[end..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn provider(id: &str, name: &str) -> Provider {
+        Provider::with_id(id.to_string(), name.to_string(), json!({}), None)
+    }
+
+    #[test]
+    fn parses_override_headers() {
+        let mut headers = HeaderMap::new();
+        assert!(RoutingOverrides::from_headers(&headers).is_empty());
+
+        headers.insert(PROVIDER_HEADER, " relay-a ".parse().unwrap());
+        headers.insert(MODEL_HEADER, "claude-haiku-4-5".parse().unwrap());
+        headers.insert(NO_FAILOVER_HEADER, "TRUE".parse().unwrap());
+        let overrides = RoutingOverrides::from_headers(&headers);
+        assert_eq!(overrides.provider.as_deref(), Some("relay-a"));
+        assert_eq!(overrides.model.as_deref(), Some("claude-haiku-4-5"));
+        assert!(overrides.no_failover);
+
+        headers.insert(NO_FAILOVER_HEADER, "0".parse().unwrap());
+        assert!(!RoutingOverrides::from_headers(&headers).no_failover);
+
+        // 覆盖头只供代理使用，不透传到上游
+        for name in [PROVIDER_HEADER, MODEL_HEADER, NO_FAILOVER_HEADER] {
+            assert!(crate::proxy::header_filter::is_header_blacklisted(name));
+        }
+    }
+
+    #[test]
+    fn log_value_omits_unset_fields() {
+        assert_eq!(RoutingOverrides::default().to_log_value(), None);
+        let overrides = RoutingOverrides {
+            model: Some("m".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            overrides.to_log_value().as_deref(),
+            Some(r#"{"model":"m"}"#)
+        );
+    }
+
+    #[test]
+    fn pinned_provider_matches_id_before_name() {
+        let providers = vec![provider("a", "Relay"), provider("relay", "Other")];
+        assert_eq!(
+            find_pinned_provider(&providers, "relay").map(|p| p.id.as_str()),
+            Some("relay")
+        );
+        assert_eq!(
+            find_pinned_provider(&providers, "RELAY").map(|p| p.id.as_str()),
+            Some("a")
+        );
+        assert!(find_pinned_provider(&providers, "missing").is_none());
+    }
+
+    #[test]
+    fn replaces_gemini_model_segment() {
+        assert_eq!(
+            replace_model_in_path(
+                "/v1beta/models/gemini-2.5-pro:streamGenerateContent?alt=sse",
+                "gemini-2.5-flash"
+            ),
+            "/v1beta/models/gemini-2.5-flash:streamGenerateContent?alt=sse"
+        );
+        assert_eq!(replace_model_in_path("/v1/messages", "x"), "/v1/messages");
+    }
+}
@@ -0,0 +1,305 @@
+//! 按请求特征路由
+//!
+//! 每个应用可以配置一组有序的路由规则（模型 glob / thinking / 请求体大小 / 端点 → 供应商列表），
+//! 由 `ProviderRouter` 在故障转移队列之前按顺序评估，首条命中的规则决定本次请求的供应商链。
+//!
+//! 与供应商级的模型映射（`ModelMapping`）互补：规则决定请求发给哪些供应商，
+//! 映射决定发给该供应商时使用的模型名。
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::OnceLock;
+
+/// 路由规则
+///
+/// 存储在 settings 表的 routing_rules:{app_type} 字段中（JSON 数组，按数组顺序评估）。
+/// 所有已设置的条件同时满足才算命中，未设置的条件不参与匹配。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutingRule {
+    pub id: String,
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// 模型 glob（`*` 匹配任意字符，`?` 匹配单个字符，不区分大小写），如 `*haiku*`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_pattern: Option<String>,
+    /// 要求启用（true）或未启用（false）thinking
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<bool>,
+    /// 请求体最小字节数（含）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_body_bytes: Option<u64>,
+    /// 请求体最大字节数（含）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_body_bytes: Option<u64>,
+    /// 端点 glob（只匹配路径，不含查询参数），如 `/v1/messages`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint_pattern: Option<String>,
+    /// 命中后按顺序尝试的供应商 ID
+    pub provider_ids: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl RoutingRule {
+    /// 校验规则配置
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("路由规则名称不能为空".to_string());
+        }
+        if self.provider_ids.is_empty() {
+            return Err(format!("路由规则 {} 未指定供应商", self.name));
+        }
+        if let (Some(min), Some(max)) = (self.min_body_bytes, self.max_body_bytes) {
+            if min > max {
+                return Err(format!(
+                    "路由规则 {} 的最小请求体大小不能大于最大值",
+                    self.name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// 判断请求是否命中该规则
+    pub fn matches(&self, request: &RouteRequest<'_>) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if let Some(pattern) = &self.model_pattern {
+            match request.model() {
+                Some(model) if glob_match(pattern, model) => {}
+                _ => return false,
+            }
+        }
+        if let Some(thinking) = self.thinking {
+            if request.thinking() != thinking {
+                return false;
+            }
+        }
+        if self.min_body_bytes.is_some() || self.max_body_bytes.is_some() {
+            let size = request.body_bytes();
+            if self.min_body_bytes.is_some_and(|min| size < min)
+                || self.max_body_bytes.is_some_and(|max| size > max)
+            {
+                return false;
+            }
+        }
+        if let Some(pattern) = &self.endpoint_pattern {
+            let path = request.endpoint.split('?').next().unwrap_or_default();
+            if !glob_match(pattern, path) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// 返回首条命中的规则
+pub fn match_rule<'r>(
+    rules: &'r [RoutingRule],
+    request: &RouteRequest<'_>,
+) -> Option<&'r RoutingRule> {
+    rules.iter().find(|rule| rule.matches(request))
+}
+
+/// 参与规则匹配的请求特征
+pub struct RouteRequest<'a> {
+    body: &'a Value,
+    endpoint: &'a str,
+    body_bytes: OnceLock<u64>,
+}
+
+impl<'a> RouteRequest<'a> {
+    pub fn new(body: &'a Value, endpoint: &'a str) -> Self {
+        Self {
+            body,
+            endpoint,
+            body_bytes: OnceLock::new(),
+        }
+    }
+
+    /// 请求模型：优先取请求体的 model 字段，其次取 Gemini 端点路径中的模型
+    pub fn model(&self) -> Option<&'a str> {
+        self.body
+            .get("model")
+            .and_then(|m| m.as_str())
+            .or_else(|| model_in_path(self.endpoint))
+    }
+
+    /// 是否启用了 thinking / reasoning（兼容 Claude、OpenAI 与 Gemini 请求格式）
+    pub fn thinking(&self) -> bool {
+        let body = self.body;
+        if matches!(
+            body.pointer("/thinking/type").and_then(|t| t.as_str()),
+            Some("enabled" | "adaptive")
+        ) {
+            return true;
+        }
+
+        let effort = body
+            .pointer("/reasoning/effort")
+            .or_else(|| body.get("reasoning_effort"))
+            .and_then(|e| e.as_str());
+        if effort.is_some_and(|e| e != "none") {
+            return true;
+        }
+
+        // Gemini（Code Assist 请求包裹在 request 字段中）
+        body.pointer("/generationConfig/thinkingConfig")
+            .or_else(|| body.pointer("/request/generationConfig/thinkingConfig"))
+            .is_some_and(|config| {
+                config.get("thinkingBudget").and_then(|b| b.as_i64()) != Some(0)
+                    || config.get("includeThoughts").and_then(|v| v.as_bool()) == Some(true)
+            })
+    }
+
+    /// 请求体大小（按 JSON 序列化后的字节数计算，首次使用时计算）
+    pub fn body_bytes(&self) -> u64 {
+        *self.body_bytes.get_or_init(|| {
+            serde_json::to_vec(self.body)
+                .map(|bytes| bytes.len() as u64)
+                .unwrap_or(0)
+        })
+    }
+}
+
+/// 提取 Gemini 端点路径中的模型（`.../models/{model}:method`）
+fn model_in_path(endpoint: &str) -> Option<&str> {
+    let start = endpoint.find("models/")? + "models/".len();
+    let rest = &endpoint[start..];
+    let end = rest.find([':', '?', '/']).unwrap_or(rest.len());
+    Some(&rest[..end]).filter(|m| !m.is_empty())
+}
+
+/// 简单 glob 匹配（`*` / `?`，不区分大小写）
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            // 让上一个 `*` 多吞一个字符后重试
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rule(name: &str) -> RoutingRule {
+        RoutingRule {
+            id: name.to_string(),
+            name: name.to_string(),
+            enabled: true,
+            model_pattern: None,
+            thinking: None,
+            min_body_bytes: None,
+            max_body_bytes: None,
+            endpoint_pattern: None,
+            provider_ids: vec!["p".to_string()],
+        }
+    }
+
+    #[test]
+    fn glob_matches_wildcards_case_insensitively() {
+        assert!(glob_match("*haiku*", "claude-3-5-HAIKU-20241022"));
+        assert!(glob_match("claude-opus-4-?", "claude-opus-4-1"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(!glob_match("a*b*c", "axxbyy"));
+        assert!(!glob_match("claude-opus-*", "claude-sonnet-4"));
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let mut haiku = rule("haiku");
+        haiku.model_pattern = Some("*haiku*".to_string());
+        let mut thinking = rule("thinking");
+        thinking.thinking = Some(true);
+        let mut disabled = rule("disabled");
+        disabled.enabled = false;
+        let rules = vec![disabled, haiku, thinking];
+
+        let body = json!({"model": "claude-haiku-4-5", "thinking": {"type": "enabled"}});
+        let request = RouteRequest::new(&body, "/v1/messages");
+        assert_eq!(
+            match_rule(&rules, &request).map(|r| r.id.as_str()),
+            Some("haiku")
+        );
+
+        let body = json!({"model": "claude-opus-4-1", "thinking": {"type": "enabled"}});
+        let request = RouteRequest::new(&body, "/v1/messages");
+        assert_eq!(
+            match_rule(&rules, &request).map(|r| r.id.as_str()),
+            Some("thinking")
+        );
+
+        let body = json!({"model": "claude-opus-4-1"});
+        assert!(match_rule(&rules, &RouteRequest::new(&body, "/v1/messages")).is_none());
+    }
+
+    #[test]
+    fn matches_endpoint_and_body_size() {
+        let mut large = rule("large");
+        large.min_body_bytes = Some(64);
+        large.endpoint_pattern = Some("/v1/messages".to_string());
+
+        let small = json!({"model": "m"});
+        assert!(!large.matches(&RouteRequest::new(&small, "/v1/messages")));
+
+        let big = json!({"model": "m", "messages": [{"role": "user", "content": "x".repeat(100)}]});
+        assert!(large.matches(&RouteRequest::new(&big, "/v1/messages?beta=true")));
+        assert!(!large.matches(&RouteRequest::new(&big, "/v1/messages/count_tokens")));
+    }
+
+    #[test]
+    fn reads_model_and_thinking_across_formats() {
+        let gemini = json!({"generationConfig": {"thinkingConfig": {"thinkingBudget": 1024}}});
+        let request = RouteRequest::new(
+            &gemini,
+            "/v1beta/models/gemini-2.5-pro:streamGenerateContent?alt=sse",
+        );
+        assert_eq!(request.model(), Some("gemini-2.5-pro"));
+        assert!(request.thinking());
+
+        let codex = json!({"model": "gpt-5", "reasoning": {"effort": "high"}});
+        assert!(RouteRequest::new(&codex, "/responses").thinking());
+
+        let no_thinking = json!({"model": "gpt-5", "reasoning_effort": "none"});
+        assert!(!RouteRequest::new(&no_thinking, "/chat/completions").thinking());
+    }
+
+    #[test]
+    fn validate_rejects_incomplete_rules() {
+        assert!(rule("ok").validate().is_ok());
+
+        let mut empty = rule("empty");
+        empty.provider_ids.clear();
+        assert!(empty.validate().is_err());
+
+        let mut inverted = rule("inverted");
+        inverted.min_body_bytes = Some(10);
+        inverted.max_body_bytes = Some(1);
+        assert!(inverted.validate().is_err());
+    }
+}
@@ -3,12 +3,14 @@
 //! 基于Axum的HTTP服务器，处理代理请求
 
 use super::{
-    failover_switch::FailoverSwitchManager, handlers, log_codes::srv as log_srv,
-    provider_router::ProviderRouter, types::*, ProxyError,
+    client_auth, failover_switch::FailoverSwitchManager, handlers, log_codes::srv as log_srv,
+    metrics::ProxyMetrics, model_catalog::ModelCatalog, provider_router::ProviderRouter, types::*,
+    ProxyError,
 };
 use crate::database::Database;
 use axum::{
     extract::DefaultBodyLimit,
+    middleware,
     routing::{get, post},
     Router,
 };
@@ -16,7 +18,7 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::{oneshot, RwLock};
 use tokio::task::JoinHandle;
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 
 /// 代理服务器状态（共享）
 #[derive(Clone)]
@@ -33,6 +35,10 @@ pub struct ProxyState {
     pub app_handle: Option<tauri::AppHandle>,
     /// 故障转移切换管理器
     pub failover_manager: Arc<FailoverSwitchManager>,
+    /// 聚合模型列表（缓存各供应商的上游模型列表）
+    pub model_catalog: Arc<ModelCatalog>,
+    /// Prometheus 指标
+    pub metrics: Arc<ProxyMetrics>,
 }
 
 /// 代理HTTP服务器
@@ -53,7 +59,9 @@ impl ProxyServer {
         // 创建共享的 ProviderRouter（熔断器状态将跨所有请求保持）
         let provider_router = Arc::new(ProviderRouter::new(db.clone()));
         // 创建故障转移切换管理器
-        let failover_manager = Arc::new(FailoverSwitchManager::new(db.clone()));
+        let failover_manager = Arc::new(
+            FailoverSwitchManager::new(db.clone()).with_provider_router(provider_router.clone()),
+        );
 
         let state = ProxyState {
             db,
@@ -64,6 +72,8 @@ impl ProxyServer {
             provider_router,
             app_handle,
             failover_manager,
+            model_catalog: Arc::new(ModelCatalog::new()),
+            metrics: Arc::new(ProxyMetrics::new()),
         };
 
         Self {
@@ -85,6 +95,21 @@ impl ProxyServer {
                 .parse()
                 .map_err(|e| ProxyError::BindFailed(format!("无效的地址: {e}")))?;
 
+        // 非回环地址对局域网开放，必须至少配置一个访问令牌
+        if !client_auth::is_loopback_address(&self.config.listen_address) {
+            let tokens = self
+                .state
+                .db
+                .count_enabled_client_tokens()
+                .map_err(|e| ProxyError::DatabaseError(e.to_string()))?;
+            if tokens == 0 {
+                return Err(ProxyError::BindFailed(format!(
+                    "监听非回环地址 {} 前请至少创建一个客户端访问令牌",
+                    self.config.listen_address
+                )));
+            }
+        }
+
         // 创建关闭通道
         let (shutdown_tx, shutdown_rx) = oneshot::channel();
 
@@ -117,12 +142,15 @@ impl ProxyServer {
         // 启动服务器
         let state = self.state.clone();
         let handle = tokio::spawn(async move {
-            axum::serve(listener, app)
-                .with_graceful_shutdown(async {
-                    shutdown_rx.await.ok();
-                })
-                .await
-                .ok();
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .with_graceful_shutdown(async {
+                shutdown_rx.await.ok();
+            })
+            .await
+            .ok();
 
             // 服务器停止后更新状态
             state.status.write().await.running = false;
@@ -206,18 +234,39 @@ impl ProxyServer {
     }
 
     fn build_router(&self) -> Router {
+        // 对局域网开放时只允许本机页面跨域访问，避免任意网页借助局域网内的浏览器调用代理
+        let allow_origin = if client_auth::is_loopback_address(&self.config.listen_address) {
+            AllowOrigin::any()
+        } else {
+            AllowOrigin::predicate(|origin, _| {
+                origin.to_str().is_ok_and(client_auth::is_loopback_origin)
+            })
+        };
         let cors = CorsLayer::new()
-            .allow_origin(Any)
+            .allow_origin(allow_origin)
             .allow_methods(Any)
             .allow_headers(Any);
 
         Router::new()
-            // 健康检查
-            .route("/health", get(handlers::health_check))
-            .route("/status", get(handlers::get_status))
             // Claude API (支持带前缀和不带前缀两种格式)
             .route("/v1/messages", post(handlers::handle_messages))
             .route("/claude/v1/messages", post(handlers::handle_messages))
+            .route(
+                "/v1/messages/count_tokens",
+                post(handlers::handle_count_tokens),
+            )
+            .route(
+                "/claude/v1/messages/count_tokens",
+                post(handlers::handle_count_tokens),
+            )
+            // 模型列表（聚合各供应商上游模型与映射别名）
+            .route("/v1/models", get(handlers::handle_models))
+            .route("/claude/v1/models", get(handlers::handle_claude_models))
+            .route("/models", get(handlers::handle_codex_models))
+            .route("/v1/v1/models", get(handlers::handle_codex_models))
+            .route("/codex/v1/models", get(handlers::handle_codex_models))
+            .route("/v1beta/models", get(handlers::handle_gemini_models))
+            .route("/gemini/v1beta/models", get(handlers::handle_gemini_models))
             // OpenAI Chat Completions API (Codex CLI，支持带前缀和不带前缀)
             .route("/chat/completions", post(handlers::handle_chat_completions))
             .route(
@@ -240,6 +289,22 @@ impl ProxyServer {
             // Gemini API (支持带前缀和不带前缀)
             .route("/v1beta/*path", post(handlers::handle_gemini))
             .route("/gemini/v1beta/*path", post(handlers::handle_gemini))
+            // Gemini CLI Code Assist API（OAuth 模式，/v1internal:{method}）
+            .route("/v1internal:method", post(handlers::handle_gemini_cli))
+            .route(
+                "/gemini/v1internal:method",
+                post(handlers::handle_gemini_cli),
+            )
+            // Prometheus 指标与运行状态（本机可直接访问，局域网访问需携带访问令牌）
+            .route("/metrics", get(handlers::handle_metrics))
+            .route("/status", get(handlers::get_status))
+            // 客户端访问令牌校验（仅作用于以上 API 路由）
+            .route_layer(middleware::from_fn_with_state(
+                self.state.clone(),
+                client_auth::require_client_token,
+            ))
+            // 健康检查
+            .route("/health", get(handlers::health_check))
             // 提高默认请求体大小限制（避免 413 Payload Too Large）
             .layer(DefaultBodyLimit::max(200 * 1024 * 1024))
             .layer(cors)
@@ -269,8 +334,110 @@ impl ProxyServer {
             .await;
     }
 
+    /// 获取 Key 池中指定 Key 的熔断器状态
+    pub async fn get_key_circuit_breaker_stats(
+        &self,
+        provider_id: &str,
+        app_type: &str,
+        key_id: &str,
+    ) -> Option<super::circuit_breaker::CircuitBreakerStats> {
+        self.state
+            .provider_router
+            .get_key_circuit_breaker_stats(app_type, provider_id, key_id)
+            .await
+    }
+
     /// 使 ProviderRouter 缓存失效
     pub async fn invalidate_provider_cache(&self, app_type: &str) {
         self.state.provider_router.invalidate_cache(app_type).await;
+        self.state.model_catalog.invalidate(app_type).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::extract::connect_info::MockConnectInfo;
+    use axum::http::{Request, StatusCode};
+    use tower::Service;
+
+    fn router(listen_address: &str, peer: &str) -> Router {
+        let config = ProxyConfig {
+            listen_address: listen_address.to_string(),
+            ..Default::default()
+        };
+        let db = Arc::new(Database::memory().expect("create database"));
+        let peer: SocketAddr = peer.parse().expect("peer address");
+        ProxyServer::new(config, db, None)
+            .build_router()
+            .layer(MockConnectInfo(peer))
+    }
+
+    async fn send(mut router: Router, request: Request<Body>) -> axum::response::Response {
+        std::future::poll_fn(|cx| Service::<Request<Body>>::poll_ready(&mut router, cx))
+            .await
+            .expect("router ready");
+        router.call(request).await.expect("response")
+    }
+
+    fn get(path: &str) -> Request<Body> {
+        Request::get(path)
+            .body(Body::empty())
+            .expect("build request")
+    }
+
+    fn preflight(origin: &str) -> Request<Body> {
+        Request::builder()
+            .method("OPTIONS")
+            .uri("/v1/messages")
+            .header("origin", origin)
+            .header("access-control-request-method", "POST")
+            .body(Body::empty())
+            .expect("build request")
+    }
+
+    #[tokio::test]
+    async fn status_requires_token_from_lan() {
+        let lan = router("0.0.0.0", "192.168.1.20:50000");
+        let response = send(lan.clone(), get("/status")).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = send(lan, get("/health")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let local = router("0.0.0.0", "127.0.0.1:50000");
+        let response = send(local, get("/status")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn cors_only_allows_local_origins_when_bound_to_lan() {
+        let allowed_origin = |router: Router, origin: &'static str| async move {
+            send(router, preflight(origin))
+                .await
+                .headers()
+                .get("access-control-allow-origin")
+                .map(|v| v.to_str().expect("header value").to_string())
+        };
+
+        let lan = router("0.0.0.0", "192.168.1.20:50000");
+        assert_eq!(
+            allowed_origin(lan.clone(), "https://example.com").await,
+            None
+        );
+        assert_eq!(
+            allowed_origin(lan, "http://localhost:5173")
+                .await
+                .as_deref(),
+            Some("http://localhost:5173")
+        );
+
+        let local = router("127.0.0.1", "127.0.0.1:50000");
+        assert_eq!(
+            allowed_origin(local, "https://example.com")
+                .await
+                .as_deref(),
+            Some("*")
+        );
     }
 }
@@ -0,0 +1,518 @@
+//! 流式响应的 onStreamEvent 钩子
+//!
+//! 对每个完整的 SSE 事件调用请求重写脚本中的 `onStreamEvent(event, context)`
+//! （多个作用范围的脚本按 供应商 → 应用 → 全局 的顺序依次处理）：
+//! - 返回 `undefined` / `null`：原样透传
+//! - 返回对象：替换当前事件（未提供的字段沿用原值）
+//! - 返回数组：依次输出数组中的事件，`[]` 表示丢弃
+//!
+//! QuickJS 运行时不能跨线程，每个流在独立的工作线程中持有运行时，脚本只 eval 一次，
+//! 脚本对象作为 `this` 传入，可在多个事件之间保存状态。
+//! 单个事件超出执行预算或脚本出错时原样透传该事件，保证不阻塞流。
+
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use rquickjs::function::This;
+use rquickjs::{Function, Object, Value as JsValue};
+use serde_json::{json, Map, Value};
+use tokio::sync::oneshot;
+
+use crate::request_hook_script::{
+    hook_script_limits, HookScope, RequestHookContext, ScopedHookScript,
+};
+use crate::script_runtime::ScriptRuntime;
+
+/// 单个事件的默认执行预算（毫秒）
+const DEFAULT_STREAM_EVENT_BUDGET_MS: u64 = 50;
+
+/// 等待工作线程回复的额外余量（中断处理器触发后仍需少量时间返回）
+const REPLY_MARGIN: Duration = Duration::from_millis(20);
+
+/// 解析后的 SSE 事件
+#[derive(Debug, Clone, PartialEq)]
+pub struct SseEvent {
+    pub event: Option<String>,
+    pub id: Option<String>,
+    pub data: String,
+}
+
+impl SseEvent {
+    /// 解析单个事件文本（不含结尾空行），仅包含注释等无 data/event 的块返回 `None`
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut event = None;
+        let mut id = None;
+        let mut data: Option<String> = None;
+
+        for line in text.lines() {
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (line, ""),
+            };
+            match field {
+                "event" => event = Some(value.to_string()),
+                "id" => id = Some(value.to_string()),
+                "data" => match data.as_mut() {
+                    Some(data) => {
+                        data.push('\n');
+                        data.push_str(value);
+                    }
+                    None => data = Some(value.to_string()),
+                },
+                _ => {}
+            }
+        }
+
+        if event.is_none() && data.is_none() {
+            return None;
+        }
+        Some(Self {
+            event,
+            id,
+            data: data.unwrap_or_default(),
+        })
+    }
+
+    /// 序列化为 SSE 文本（不含结尾空行）
+    pub fn serialize(&self) -> String {
+        let mut lines = Vec::new();
+        if let Some(event) = &self.event {
+            lines.push(format!("event: {event}"));
+        }
+        if let Some(id) = &self.id {
+            lines.push(format!("id: {id}"));
+        }
+        for line in self.data.split('\n') {
+            lines.push(format!("data: {line}"));
+        }
+        lines.join("\n")
+    }
+
+    /// 传给脚本的事件视图（data 为 JSON 时解析为对象）
+    fn to_view(&self) -> Value {
+        let data = serde_json::from_str::<Value>(&self.data)
+            .unwrap_or_else(|_| Value::String(self.data.clone()));
+        json!({
+            "event": self.event,
+            "id": self.id,
+            "data": data,
+        })
+    }
+
+    /// 以脚本返回的对象覆盖当前事件的字段
+    fn merge(&self, value: &Map<String, Value>) -> Result<Self, String> {
+        let text_field = |key: &str, original: &Option<String>| match value.get(key) {
+            None => Ok(original.clone()),
+            Some(Value::Null) => Ok(None),
+            Some(Value::String(s)) => Ok(Some(s.clone())),
+            Some(other) => Err(format!("onStreamEvent 返回的 {key} 必须是字符串: {other}")),
+        };
+        let data = match value.get("data") {
+            None => self.data.clone(),
+            Some(Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+        };
+        Ok(Self {
+            event: text_field("event", &self.event)?,
+            id: text_field("id", &self.id)?,
+            data,
+        })
+    }
+}
+
+/// 将脚本返回值转换为输出事件，`None` 表示原样透传
+fn apply_hook_result(
+    result: Option<Value>,
+    original: &SseEvent,
+) -> Result<Option<Vec<SseEvent>>, String> {
+    match result {
+        None => Ok(None),
+        Some(Value::Object(map)) => original.merge(&map).map(|event| Some(vec![event])),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| match item {
+                Value::Object(map) => original.merge(map),
+                other => Err(format!("onStreamEvent 返回的数组元素必须是对象: {other}")),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Some),
+        Some(other) => Err(format!("onStreamEvent 返回值必须是对象或数组: {other}")),
+    }
+}
+
+type HookReply = Result<Option<Value>, String>;
+
+struct Job {
+    event: Value,
+    reply: oneshot::Sender<HookReply>,
+}
+
+/// 单个脚本的工作线程
+struct Worker {
+    scope: HookScope,
+    jobs: Option<mpsc::Sender<Job>>,
+    budget: Duration,
+}
+
+impl Worker {
+    fn spawn(tag: &'static str, hook: &ScopedHookScript, context_json: &str) -> Option<Self> {
+        // 快速预检，避免为没有 onStreamEvent 的脚本创建线程
+        if !hook.script.code.contains("onStreamEvent") {
+            return None;
+        }
+
+        let budget = Duration::from_millis(
+            hook.script
+                .stream_event_budget_ms
+                .unwrap_or(DEFAULT_STREAM_EVENT_BUDGET_MS)
+                .clamp(1, 5_000),
+        );
+
+        let (tx, rx) = mpsc::channel::<Job>();
+        let code = hook.script.code.clone();
+        let timeout_ms = hook.script.timeout_ms;
+        let context_json = context_json.to_string();
+        let spawned = thread::Builder::new()
+            .name("ccs-stream-hook".to_string())
+            .spawn(move || run_worker(tag, &code, timeout_ms, &context_json, budget, rx));
+        if let Err(e) = spawned {
+            log::warn!("[{tag}] [Hook] 启动 onStreamEvent 工作线程失败: {e}");
+            return None;
+        }
+
+        Some(Self {
+            scope: hook.scope,
+            jobs: Some(tx),
+            budget,
+        })
+    }
+
+    /// 处理单个事件文本（不含结尾空行），返回输出的事件文本
+    async fn process(&mut self, tag: &str, text: String) -> Vec<String> {
+        let Some(jobs) = &self.jobs else {
+            return vec![text];
+        };
+        let Some(event) = SseEvent::parse(&text) else {
+            return vec![text];
+        };
+
+        let (reply, rx) = oneshot::channel();
+        if jobs
+            .send(Job {
+                event: event.to_view(),
+                reply,
+            })
+            .is_err()
+        {
+            // 工作线程已退出（脚本初始化失败或没有 onStreamEvent）
+            self.jobs = None;
+            return vec![text];
+        }
+
+        let result = match tokio::time::timeout(self.budget + REPLY_MARGIN, rx).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => {
+                self.jobs = None;
+                return vec![text];
+            }
+            Err(_) => Err(format!("超出执行预算（{} ms）", self.budget.as_millis())),
+        };
+
+        match result.and_then(|value| apply_hook_result(value, &event)) {
+            Ok(None) => vec![text],
+            Ok(Some(events)) => events.iter().map(SseEvent::serialize).collect(),
+            Err(e) => {
+                log::warn!(
+                    "[{tag}] [Hook:{}] onStreamEvent 执行失败，已原样透传: {e}",
+                    self.scope.as_str()
+                );
+                vec![text]
+            }
+        }
+    }
+}
+
+/// 单个流使用的 onStreamEvent 钩子链
+pub struct StreamEventHook {
+    tag: &'static str,
+    workers: Vec<Worker>,
+}
+
+impl StreamEventHook {
+    /// 为定义了 onStreamEvent 的脚本启动工作线程，`hooks` 按执行顺序排列
+    pub fn spawn(
+        tag: &'static str,
+        hooks: &[ScopedHookScript],
+        context: &RequestHookContext,
+    ) -> Option<Self> {
+        if !hooks
+            .iter()
+            .any(|hook| hook.script.code.contains("onStreamEvent"))
+        {
+            return None;
+        }
+
+        let context_json = match serde_json::to_string(context) {
+            Ok(v) => v,
+            Err(e) => {
+                log::warn!("[{tag}] [Hook] 序列化 context 失败，已忽略 onStreamEvent: {e}");
+                return None;
+            }
+        };
+
+        let workers: Vec<Worker> = hooks
+            .iter()
+            .filter_map(|hook| Worker::spawn(tag, hook, &context_json))
+            .collect();
+        (!workers.is_empty()).then_some(Self { tag, workers })
+    }
+
+    /// 处理单个事件文本（不含结尾空行），返回要输出给客户端的文本
+    ///
+    /// 前一个脚本输出的每个事件依次交给下一个脚本处理。
+    pub async fn process(&mut self, text: &str) -> String {
+        let mut events = vec![text.to_string()];
+        for worker in &mut self.workers {
+            let mut next = Vec::with_capacity(events.len());
+            for event in events {
+                next.extend(worker.process(self.tag, event).await);
+            }
+            events = next;
+        }
+        events.iter().map(|event| format!("{event}\n\n")).collect()
+    }
+}
+
+fn run_worker(
+    tag: &'static str,
+    code: &str,
+    timeout_ms: Option<u64>,
+    context_json: &str,
+    budget: Duration,
+    jobs: mpsc::Receiver<Job>,
+) {
+    let runtime = match ScriptRuntime::new(hook_script_limits(timeout_ms)) {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            log::warn!("[{tag}] [Hook] {e}，已忽略 onStreamEvent");
+            return;
+        }
+    };
+
+    let init = runtime.with(|ctx| -> Result<bool, String> {
+        let config: Object = ctx.eval(code).map_err(|e| {
+            format!(
+                "解析脚本失败（脚本必须 eval 成一个对象）: {}",
+                runtime.describe_error(&ctx, e)
+            )
+        })?;
+        let Ok(hook_fn) = config.get::<_, Function>("onStreamEvent") else {
+            return Ok(false);
+        };
+        let context: JsValue = ctx
+            .json_parse(context_json)
+            .map_err(|e| format!("解析 context JSON 失败: {e}"))?;
+        let globals = ctx.globals();
+        globals
+            .set("__ccs_hook_config", config)
+            .and_then(|_| globals.set("__ccs_on_stream_event", hook_fn))
+            .and_then(|_| globals.set("__ccs_stream_context", context))
+            .map_err(|e| format!("初始化 onStreamEvent 失败: {e}"))?;
+        Ok(true)
+    });
+    log_console(tag, &runtime);
+    match init {
+        Ok(true) => {}
+        Ok(false) => return,
+        Err(e) => {
+            log::warn!("[{tag}] [Hook] {e}，已忽略 onStreamEvent");
+            return;
+        }
+    }
+
+    while let Ok(job) = jobs.recv() {
+        runtime.reset_deadline(budget);
+        let result = runtime.with(|ctx| -> HookReply {
+            let globals = ctx.globals();
+            let (Ok(config), Ok(hook_fn), Ok(context)) = (
+                globals.get::<_, Object>("__ccs_hook_config"),
+                globals.get::<_, Function>("__ccs_on_stream_event"),
+                globals.get::<_, JsValue>("__ccs_stream_context"),
+            ) else {
+                return Err("onStreamEvent 未初始化".to_string());
+            };
+
+            let event_json =
+                serde_json::to_string(&job.event).map_err(|e| format!("序列化事件失败: {e}"))?;
+            let event: JsValue = ctx
+                .json_parse(event_json)
+                .map_err(|e| format!("解析事件 JSON 失败: {e}"))?;
+
+            let result: JsValue = hook_fn
+                .call((This(config), event, context))
+                .map_err(|e| runtime.describe_error(&ctx, e))?;
+            if result.is_undefined() || result.is_null() {
+                return Ok(None);
+            }
+
+            let result_json = ctx
+                .json_stringify(result)
+                .map_err(|e| format!("序列化返回值失败: {e}"))?;
+            let Some(result_json) = result_json else {
+                return Ok(None);
+            };
+            let result_str: String = result_json
+                .get()
+                .map_err(|e| format!("获取返回值字符串失败: {e}"))?;
+            serde_json::from_str(&result_str)
+                .map(Some)
+                .map_err(|e| format!("解析返回值 JSON 失败: {e}"))
+        });
+        log_console(tag, &runtime);
+        // 接收方已超时放弃时忽略发送失败
+        let _ = job.reply.send(result);
+    }
+}
+
+fn log_console(tag: &str, runtime: &ScriptRuntime) {
+    for line in runtime.take_logs() {
+        log::debug!("[{tag}] [Hook] console: {line}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request_hook_script::RequestHookProviderInfo;
+    use std::collections::HashMap;
+
+    fn script(scope: HookScope, code: &str) -> ScopedHookScript {
+        ScopedHookScript {
+            scope,
+            script: serde_json::from_value(json!({
+                "enabled": true,
+                "language": "javascript",
+                "code": code,
+                "streamEventBudgetMs": 100,
+            }))
+            .expect("script"),
+        }
+    }
+
+    fn context() -> RequestHookContext {
+        RequestHookContext {
+            app: "claude".to_string(),
+            method: "POST".to_string(),
+            path: "/v1/messages".to_string(),
+            endpoint: "/v1/messages".to_string(),
+            url: "https://api.example.com/v1/messages".to_string(),
+            provider: RequestHookProviderInfo {
+                id: "p1".to_string(),
+                name: "Example".to_string(),
+            },
+            incoming_headers: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn sse_event_parse_and_serialize() {
+        let event =
+            SseEvent::parse("event: message_start\ndata: {\"a\":1}\ndata: 2").expect("event");
+        assert_eq!(event.event.as_deref(), Some("message_start"));
+        assert_eq!(event.data, "{\"a\":1}\n2");
+        assert_eq!(
+            event.serialize(),
+            "event: message_start\ndata: {\"a\":1}\ndata: 2"
+        );
+        assert_eq!(SseEvent::parse(": keep-alive"), None);
+    }
+
+    #[tokio::test]
+    async fn stream_hook_can_modify_drop_and_inject() {
+        let code = r#"({
+          onStreamEvent: function (event, context) {
+            this.count = (this.count || 0) + 1;
+            if (event.event === "ping") return [];
+            if (event.event === "done") {
+              return [event, { event: "note", id: context.app, data: { count: this.count } }];
+            }
+            if (event.data && event.data.text) {
+              event.data.text = event.data.text.toUpperCase();
+              return event;
+            }
+          }
+        })"#;
+        let mut hook =
+            StreamEventHook::spawn("Test", &[script(HookScope::Provider, code)], &context())
+                .expect("hook");
+
+        assert_eq!(
+            hook.process("data: {\"text\":\"hi\"}").await,
+            "data: {\"text\":\"HI\"}\n\n"
+        );
+        assert_eq!(hook.process("event: ping\ndata: {}").await, "");
+        assert_eq!(hook.process("data: [DONE]").await, "data: [DONE]\n\n");
+        assert_eq!(
+            hook.process("event: done\ndata: x").await,
+            "event: done\ndata: x\n\nevent: note\nid: claude\ndata: {\"count\":4}\n\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn slow_or_failing_hook_passes_events_through() {
+        let code = r#"({
+          onStreamEvent: function (event) {
+            if (event.event === "slow") { while (true) {} }
+            throw new Error("boom");
+          }
+        })"#;
+        let mut hook =
+            StreamEventHook::spawn("Test", &[script(HookScope::Provider, code)], &context())
+                .expect("hook");
+
+        assert_eq!(
+            hook.process("event: slow\ndata: 1").await,
+            "event: slow\ndata: 1\n\n"
+        );
+        assert_eq!(hook.process("data: 2").await, "data: 2\n\n");
+    }
+
+    #[test]
+    fn scripts_without_stream_hook_are_skipped() {
+        let code = "({ onResponse: function (ctx, resp) { return resp; } })";
+        assert!(
+            StreamEventHook::spawn("Test", &[script(HookScope::Provider, code)], &context())
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_hooks_are_chained_in_order() {
+        let provider = r#"({
+          onStreamEvent: function (event) {
+            return [event, { event: "extra", data: "b" }];
+          }
+        })"#;
+        let global = r#"({
+          onStreamEvent: function (event) {
+            return { data: event.data + "!" };
+          }
+        })"#;
+        let mut hook = StreamEventHook::spawn(
+            "Test",
+            &[
+                script(HookScope::Provider, provider),
+                script(HookScope::App, "({ onResponse: function () {} })"),
+                script(HookScope::Global, global),
+            ],
+            &context(),
+        )
+        .expect("hook");
+
+        assert_eq!(
+            hook.process("data: a").await,
+            "data: a!\n\nevent: extra\ndata: b!\n\n"
+        );
+    }
+}
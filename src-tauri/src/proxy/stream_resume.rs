@@ -0,0 +1,1395 @@
+//! 流式断流续传
+//!
+//! Anthropic Messages / OpenAI Responses 流在结束事件（`message_stop` / `response.completed`）之前中断时
+//! （流错误、静默期超时、上游错误事件或连接提前关闭），把已经发给客户端的助手内容作为预填充，
+//! 向故障转移链中尚未使用过的供应商重新发起请求，并把续写流改写后拼接进同一个客户端流：
+//! - 丢弃续写流重复的开始事件（`message_start` / `response.created`）
+//! - 续写的首个文本块合并进中断时未结束的文本块，其余块的 index 顺延
+//! - Responses 的 item id、`sequence_number` 与最终 `response.output` 保持与原流一致
+//!
+//! 只有已输出内容为文本（以及已完整结束的 thinking / reasoning 块）时才能续写；
+//! 工具调用或未结束的 thinking 块无法通过预填充续写，此时按原样把中断传给客户端。
+//! Responses API 没有原生的预填充，已输出文本以助手消息的形式追加到 `input` 末尾。
+
+use super::{
+    forwarder::RequestForwarder,
+    handler_context::{RequestContext, StreamingTimeoutConfig},
+    providers::get_adapter,
+    response_processor::{is_sse_response, SseUsageCollector},
+    server::ProxyState,
+};
+use crate::{app_config::AppType, provider::Provider};
+use axum::http::HeaderMap;
+use bytes::Bytes;
+use futures::stream::{BoxStream, Stream, StreamExt};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// 支持续传的流式协议
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResumeProtocol {
+    /// Anthropic Messages（`/v1/messages`）
+    Anthropic,
+    /// OpenAI Responses（`/responses`）
+    Responses,
+}
+
+impl ResumeProtocol {
+    fn splice(self) -> Box<dyn Splice> {
+        match self {
+            Self::Anthropic => Box::<AnthropicSplice>::default(),
+            Self::Responses => Box::<ResponsesSplice>::default(),
+        }
+    }
+}
+
+/// 断流续传所需的原始请求
+///
+/// 由 handler 在转发前准备（转发会消耗请求体与请求头），未启用续传时为 `None`。
+pub struct StreamResume {
+    protocol: ResumeProtocol,
+    max_resumes: u32,
+    endpoint: &'static str,
+    body: Value,
+    headers: HeaderMap,
+    providers: Vec<Provider>,
+    /// 流开始前已经尝试过的供应商（续传时不再使用）
+    tried: Vec<String>,
+}
+
+impl StreamResume {
+    /// 按应用配置准备续传
+    ///
+    /// 未启用、非流式请求或故障转移链中没有其他供应商（故障转移关闭、请求头指定供应商等）时返回 `None`。
+    pub fn prepare(
+        state: &ProxyState,
+        ctx: &RequestContext,
+        protocol: ResumeProtocol,
+        endpoint: &'static str,
+        body: &Value,
+        headers: &HeaderMap,
+    ) -> Option<Self> {
+        let config = state
+            .db
+            .get_stream_resume_config(ctx.app_type_str)
+            .ok()
+            .filter(|config| config.enabled && config.max_resumes > 0)?;
+        let is_stream = body
+            .get("stream")
+            .and_then(|s| s.as_bool())
+            .unwrap_or(false);
+        // 需要格式转换的供应商返回的不是原协议的流，无法拼接
+        let adapter = get_adapter(&ctx.app_type);
+        let providers: Vec<Provider> = ctx
+            .get_providers()
+            .into_iter()
+            .filter(|provider| !adapter.needs_transform(provider))
+            .collect();
+        if !is_stream || providers.len() < 2 {
+            return None;
+        }
+
+        Some(Self {
+            protocol,
+            max_resumes: config.max_resumes,
+            endpoint,
+            body: body.clone(),
+            headers: headers.clone(),
+            providers,
+            tried: Vec::new(),
+        })
+    }
+
+    /// 记录流开始前已经尝试过的供应商（故障转移中失败的、对冲中被取消的），续传时跳过
+    pub fn with_tried_providers(mut self, provider_ids: Vec<String>) -> Self {
+        self.tried = provider_ids;
+        self
+    }
+
+    /// 包装上游字节流：中断时换供应商续写，并把续写内容拼接进同一个流
+    ///
+    /// 返回的流自行处理首字节 / 静默期超时（超时即视为中断），并分别为每个供应商记录用量。
+    pub fn into_stream(
+        self,
+        upstream: impl Stream<Item = Result<Bytes, std::io::Error>> + Send + 'static,
+        runtime: ResumeRuntime,
+    ) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Send {
+        let StreamResume {
+            protocol,
+            max_resumes,
+            endpoint,
+            body,
+            headers,
+            providers,
+            tried,
+        } = self;
+        let ResumeRuntime {
+            forwarder,
+            app_type,
+            tag,
+            timeout_config,
+            provider,
+            collector,
+            new_collector,
+        } = runtime;
+
+        async_stream::stream! {
+            let first_byte_timeout = (timeout_config.first_byte_timeout > 0)
+                .then(|| Duration::from_secs(timeout_config.first_byte_timeout));
+            let idle_timeout = (timeout_config.idle_timeout > 0)
+                .then(|| Duration::from_secs(timeout_config.idle_timeout));
+
+            let mut splice = protocol.splice();
+            let mut upstream: BoxStream<'static, Result<Bytes, std::io::Error>> = upstream.boxed();
+            let mut collector = Some(collector);
+            let mut current_provider = provider;
+            let mut tried = tried;
+            if !tried.contains(&current_provider.id) {
+                tried.push(current_provider.id.clone());
+            }
+            let mut resumes = 0u32;
+            let mut continuing = false;
+            let mut buffer: Vec<u8> = Vec::new();
+            let mut is_first_chunk = true;
+            // 上游已关闭（不再轮询），缓冲区中剩余的内容视为最后一个事件
+            let mut upstream_ended = false;
+
+            loop {
+                let timeout_duration = if is_first_chunk {
+                    first_byte_timeout
+                } else {
+                    idle_timeout
+                };
+                let next = if upstream_ended {
+                    Ok(None)
+                } else {
+                    match timeout_duration {
+                        Some(duration) => tokio::time::timeout(duration, upstream.next()).await,
+                        None => Ok(upstream.next().await),
+                    }
+                };
+                let next = match next {
+                    // 最后一个事件缺少结尾空行：上游关闭后按完整事件处理
+                    Ok(None) if !upstream_ended && event_data(&buffer).is_some() => {
+                        upstream_ended = true;
+                        Ok(Some(Ok(Bytes::new())))
+                    }
+                    next => next,
+                };
+
+                // 上游错误事件：先不发给客户端，续传失败时再原样输出
+                let mut error_event: Option<Vec<u8>> = None;
+                let reason = match next {
+                    Ok(Some(Ok(bytes))) => {
+                        is_first_chunk = false;
+                        buffer.extend_from_slice(&bytes);
+
+                        let mut output: Vec<u8> = Vec::new();
+                        while let Some(event) = take_event(&mut buffer, upstream_ended) {
+                            let data = event_data(&event);
+                            if let (Some(c), Some(data)) = (&collector, &data) {
+                                c.push(data.clone()).await;
+                            }
+                            match data {
+                                Some(data) if splice.is_error(&data) => {
+                                    error_event = Some(event);
+                                    break;
+                                }
+                                Some(data) if continuing => {
+                                    for rewritten in splice.rewrite(data) {
+                                        splice.observe(&rewritten);
+                                        output.extend_from_slice(format_event(&rewritten).as_bytes());
+                                    }
+                                }
+                                Some(data) => {
+                                    splice.observe(&data);
+                                    output.extend_from_slice(&event);
+                                }
+                                None => output.extend_from_slice(&event),
+                            }
+                        }
+                        if !output.is_empty() {
+                            yield Ok(Bytes::from(output));
+                        }
+
+                        match &error_event {
+                            Some(event) => format!(
+                                "上游返回错误事件: {}",
+                                String::from_utf8_lossy(event).trim()
+                            ),
+                            None => continue,
+                        }
+                    }
+                    Ok(Some(Err(e))) => format!("流错误: {e}"),
+                    Ok(None) if splice.is_complete() => {
+                        if !buffer.is_empty() {
+                            yield Ok(Bytes::from(std::mem::take(&mut buffer)));
+                        }
+                        break;
+                    }
+                    Ok(None) => "上游在结束事件之前关闭了连接".to_string(),
+                    Err(_) => {
+                        let timeout_type = if is_first_chunk { "首字节" } else { "静默期" };
+                        format!("流式响应{timeout_type}超时")
+                    }
+                };
+
+                // 当前上游已中断：结算它的用量，再尝试换供应商续写
+                if let Some(c) = collector.take() {
+                    c.finish().await;
+                }
+                log::warn!(
+                    "[{tag}] Provider {} 的流式响应在结束前中断: {reason}",
+                    current_provider.name
+                );
+
+                let continuation = if resumes < max_resumes {
+                    splice.continuation_body(&body)
+                } else {
+                    None
+                };
+                let remaining: Vec<Provider> = providers
+                    .iter()
+                    .filter(|p| !tried.contains(&p.id))
+                    .cloned()
+                    .collect();
+                let resumed = match continuation {
+                    Some(_) if remaining.is_empty() => {
+                        log::warn!("[{tag}] [RSP-007] 没有可用于续传的其他供应商");
+                        None
+                    }
+                    Some(continuation_body) => {
+                        match forwarder
+                            .forward_with_retry(
+                                &app_type,
+                                endpoint,
+                                continuation_body,
+                                headers.clone(),
+                                remaining,
+                            )
+                            .await
+                        {
+                            Ok(result) if is_sse_response(&result.response) => Some(result),
+                            Ok(result) => {
+                                log::warn!(
+                                    "[{tag}] [RSP-007] Provider {} 的续传响应不是流式响应",
+                                    result.provider.name
+                                );
+                                None
+                            }
+                            Err(e) => {
+                                log::warn!("[{tag}] [RSP-007] 续传请求失败: {}", e.error);
+                                None
+                            }
+                        }
+                    }
+                    None => None,
+                };
+
+                let Some(result) = resumed else {
+                    // 无法续传：原样输出上游错误事件以及缓冲区中尚未成形的内容
+                    let has_error_event = error_event.is_some();
+                    if let Some(event) = error_event {
+                        yield Ok(Bytes::from(event));
+                    }
+                    if !buffer.is_empty() {
+                        yield Ok(Bytes::from(std::mem::take(&mut buffer)));
+                    }
+                    if !has_error_event {
+                        yield Err(std::io::Error::other(reason));
+                    }
+                    break;
+                };
+
+                resumes += 1;
+                log::info!(
+                    "[{tag}] [RSP-006] 已切换到 Provider {} 续传流式响应 ({resumes}/{max_resumes})",
+                    result.provider.name
+                );
+                tried.push(result.provider.id.clone());
+                collector = Some(new_collector(
+                    &result.provider,
+                    result.api_key_id,
+                    result.mapped_model,
+                ));
+                current_provider = result.provider;
+                upstream = result
+                    .response
+                    .bytes_stream()
+                    .map(|chunk| chunk.map_err(|e| std::io::Error::other(e.to_string())))
+                    .boxed();
+                // 中断流中不完整的事件无法拼接，只能丢弃
+                buffer.clear();
+                is_first_chunk = true;
+                upstream_ended = false;
+                continuing = true;
+            }
+
+            if let Some(c) = collector.take() {
+                c.finish().await;
+            }
+        }
+    }
+}
+
+/// 为续传的供应商创建用量收集器：(供应商, Key 标识, 映射后模型)
+pub type ResumeCollectorFactory =
+    Box<dyn Fn(&Provider, Option<String>, Option<String>) -> SseUsageCollector + Send + Sync>;
+
+/// 续传运行时（由响应处理器在接管流式响应时创建）
+pub struct ResumeRuntime {
+    /// 用于发起续传请求（不启用对冲）
+    pub forwarder: RequestForwarder,
+    pub app_type: AppType,
+    pub tag: &'static str,
+    /// 首字节 / 静默期超时（超时视为中断）
+    pub timeout_config: StreamingTimeoutConfig,
+    /// 当前返回流式响应的供应商
+    pub provider: Provider,
+    /// 当前供应商的用量收集器
+    pub collector: SseUsageCollector,
+    pub new_collector: ResumeCollectorFactory,
+}
+
+/// 流拼接状态：跟踪客户端已收到的内容，并把续写流改写成原流的延续
+trait Splice: Send {
+    /// 记录一个已发给客户端的事件
+    fn observe(&mut self, event: &Value);
+    /// 客户端是否已收到结束事件
+    fn is_complete(&self) -> bool;
+    /// 是否为上游错误事件（视为中断）
+    fn is_error(&self, event: &Value) -> bool;
+    /// 构建带预填充的续写请求体并进入续写状态（无法续写时返回 `None`）
+    fn continuation_body(&mut self, body: &Value) -> Option<Value>;
+    /// 把续写流的一个事件改写为发给客户端的事件（可能丢弃或补充事件）
+    fn rewrite(&mut self, event: Value) -> Vec<Value>;
+}
+
+fn event_type(event: &Value) -> &str {
+    event.get("type").and_then(|t| t.as_str()).unwrap_or("")
+}
+
+fn index_of(event: &Value, key: &str) -> Option<usize> {
+    event.get(key).and_then(|i| i.as_u64()).map(|i| i as usize)
+}
+
+// ============================================================================
+// Anthropic Messages
+// ============================================================================
+
+#[derive(Default)]
+struct AnthropicSplice {
+    started: bool,
+    complete: bool,
+    /// 客户端已收到的内容块（按 index）
+    blocks: Vec<ContentBlock>,
+    continuation: Option<AnthropicContinuation>,
+}
+
+struct ContentBlock {
+    kind: String,
+    text: String,
+    closed: bool,
+}
+
+struct AnthropicContinuation {
+    /// 续写流 index + offset = 客户端 index
+    offset: usize,
+    /// 续写的首个文本块合并进客户端 index 为 offset 的未结束文本块
+    merge: bool,
+    /// 预填充去掉了结尾空白（API 不接受），续写开头的空白需要丢弃
+    strip_leading_ws: bool,
+}
+
+impl Splice for AnthropicSplice {
+    fn observe(&mut self, event: &Value) {
+        match event_type(event) {
+            "message_start" => self.started = true,
+            "message_stop" => self.complete = true,
+            // 已收到停止原因即内容已完整，只差 message_stop 时无需续写
+            "message_delta"
+                if event
+                    .pointer("/delta/stop_reason")
+                    .is_some_and(|r| !r.is_null()) =>
+            {
+                self.complete = true
+            }
+            "content_block_start" => {
+                let Some(index) = index_of(event, "index") else {
+                    return;
+                };
+                let block = event.get("content_block");
+                let block = ContentBlock {
+                    kind: block
+                        .and_then(|b| b.get("type"))
+                        .and_then(|t| t.as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                    text: block
+                        .and_then(|b| b.get("text"))
+                        .and_then(|t| t.as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                    closed: false,
+                };
+                if index < self.blocks.len() {
+                    self.blocks[index] = block;
+                } else {
+                    self.blocks.push(block);
+                }
+            }
+            "content_block_delta" => {
+                let text = event.pointer("/delta/text").and_then(|t| t.as_str());
+                if let (Some(index), Some(text)) = (index_of(event, "index"), text) {
+                    if let Some(block) = self.blocks.get_mut(index) {
+                        block.text.push_str(text);
+                    }
+                }
+            }
+            "content_block_stop" => {
+                if let Some(block) = index_of(event, "index").and_then(|i| self.blocks.get_mut(i)) {
+                    block.closed = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    fn is_error(&self, event: &Value) -> bool {
+        event_type(event) == "error"
+    }
+
+    fn continuation_body(&mut self, body: &Value) -> Option<Value> {
+        if self.complete {
+            return None;
+        }
+        // 只能续写文本；thinking 块必须已完整结束（续写请求不再启用 thinking）
+        let resumable = self.blocks.iter().all(|block| match block.kind.as_str() {
+            "text" => true,
+            "thinking" | "redacted_thinking" => block.closed,
+            _ => false,
+        });
+        if !resumable {
+            return None;
+        }
+
+        let prefill: String = self
+            .blocks
+            .iter()
+            .filter(|block| block.kind == "text")
+            .map(|block| block.text.as_str())
+            .collect();
+        let trimmed = prefill.trim_end();
+
+        let mut body = body.clone();
+        let messages = body.get_mut("messages")?.as_array_mut()?;
+        if !trimmed.is_empty() {
+            match messages.last_mut() {
+                // 客户端自己带了预填充：已输出内容接在其后
+                Some(last) if last.get("role").and_then(|r| r.as_str()) == Some("assistant") => {
+                    match last.get_mut("content")? {
+                        Value::String(content) => content.push_str(trimmed),
+                        Value::Array(content) => {
+                            content.push(json!({"type": "text", "text": trimmed}))
+                        }
+                        _ => return None,
+                    }
+                }
+                _ => messages.push(json!({
+                    "role": "assistant",
+                    "content": [{"type": "text", "text": trimmed}],
+                })),
+            }
+        }
+        // 预填充与 thinking 不兼容；thinking 内容已经发给客户端，续写只需补全剩余文本
+        if let Some(obj) = body.as_object_mut() {
+            obj.remove("thinking");
+        }
+
+        let merge = self
+            .blocks
+            .last()
+            .is_some_and(|block| block.kind == "text" && !block.closed);
+        self.continuation = Some(AnthropicContinuation {
+            offset: if merge {
+                self.blocks.len() - 1
+            } else {
+                self.blocks.len()
+            },
+            merge,
+            strip_leading_ws: merge && trimmed.len() < prefill.len() && !trimmed.is_empty(),
+        });
+        Some(body)
+    }
+
+    fn rewrite(&mut self, mut event: Value) -> Vec<Value> {
+        let started = self.started;
+        let Some(cont) = self.continuation.as_mut() else {
+            return vec![event];
+        };
+
+        let mut output = Vec::new();
+        match event_type(&event) {
+            "message_start" if started => return output,
+            "content_block_start" => {
+                let index = index_of(&event, "index").unwrap_or(0);
+                if index == 0 && cont.merge {
+                    let is_text = event
+                        .pointer("/content_block/type")
+                        .and_then(|t| t.as_str())
+                        == Some("text");
+                    if is_text {
+                        // 客户端的文本块仍未结束，续写内容直接追加到该块
+                        return output;
+                    }
+                    // 续写没有接着输出文本：先结束预填充所在的块
+                    output.push(json!({"type": "content_block_stop", "index": cont.offset}));
+                    cont.offset += 1;
+                    cont.merge = false;
+                }
+                event["index"] = json!(cont.offset + index);
+            }
+            "content_block_delta" | "content_block_stop" => {
+                let index = index_of(&event, "index").unwrap_or(0);
+                if cont.strip_leading_ws && index == 0 && cont.merge {
+                    if let Some(text) = event.pointer("/delta/text").and_then(|t| t.as_str()) {
+                        let stripped = text.trim_start().to_string();
+                        if stripped.is_empty() {
+                            return output;
+                        }
+                        cont.strip_leading_ws = false;
+                        event["delta"]["text"] = json!(stripped);
+                    }
+                }
+                event["index"] = json!(cont.offset + index);
+            }
+            "message_delta" => {
+                // 续写没有输出任何内容块：结束预填充所在的块
+                let open = cont.merge
+                    && self
+                        .blocks
+                        .get(cont.offset)
+                        .is_some_and(|block| !block.closed);
+                if open {
+                    output.push(json!({"type": "content_block_stop", "index": cont.offset}));
+                }
+            }
+            _ => {}
+        }
+        output.push(event);
+        output
+    }
+}
+
+// ============================================================================
+// OpenAI Responses
+// ============================================================================
+
+#[derive(Default)]
+struct ResponsesSplice {
+    response_id: Option<String>,
+    complete: bool,
+    /// 客户端最后收到的 sequence_number
+    sequence: Option<u64>,
+    /// 客户端已收到的输出项（按 output_index）
+    items: Vec<OutputItem>,
+    continuation: Option<ResponsesContinuation>,
+}
+
+struct OutputItem {
+    id: String,
+    kind: String,
+    /// 各内容片段的文本与是否已结束
+    parts: Vec<(String, bool)>,
+    /// `response.output_item.done` 中的完整输出项
+    done: Option<Value>,
+}
+
+struct ResponsesContinuation {
+    /// 续写流 output_index + offset = 客户端 output_index
+    offset: usize,
+    /// 续写的首个 message 输出项合并进客户端 output_index 为 offset 的未结束 message
+    merge: bool,
+    /// 合并时续写 content_index + part_offset = 客户端 content_index
+    part_offset: usize,
+    /// 合并时续写的首个内容片段接在客户端未结束的片段之后
+    merge_part: bool,
+}
+
+/// 按客户端已收到的内容构建完整的 message 输出项
+fn message_item(item: &OutputItem) -> Value {
+    let content: Vec<Value> = item
+        .parts
+        .iter()
+        .map(|(text, _)| json!({"type": "output_text", "text": text, "annotations": []}))
+        .collect();
+    json!({
+        "id": item.id,
+        "type": "message",
+        "status": "completed",
+        "role": "assistant",
+        "content": content,
+    })
+}
+
+impl Splice for ResponsesSplice {
+    fn observe(&mut self, event: &Value) {
+        if let Some(sequence) = event.get("sequence_number").and_then(|s| s.as_u64()) {
+            self.sequence = Some(sequence);
+        }
+        let output_index = index_of(event, "output_index");
+        let content_index = index_of(event, "content_index").unwrap_or(0);
+        match event_type(event) {
+            "response.created" | "response.in_progress" => {
+                if let Some(id) = event.pointer("/response/id").and_then(|id| id.as_str()) {
+                    self.response_id = Some(id.to_string());
+                }
+            }
+            "response.completed" | "response.incomplete" => self.complete = true,
+            "response.output_item.added" => {
+                let Some(index) = output_index else {
+                    return;
+                };
+                let item = event.get("item");
+                let field = |key: &str| {
+                    item.and_then(|i| i.get(key))
+                        .and_then(|v| v.as_str())
+                        .unwrap_or_default()
+                        .to_string()
+                };
+                let item = OutputItem {
+                    id: field("id"),
+                    kind: field("type"),
+                    parts: Vec::new(),
+                    done: None,
+                };
+                if index < self.items.len() {
+                    self.items[index] = item;
+                } else {
+                    self.items.push(item);
+                }
+            }
+            "response.content_part.added" => {
+                if let Some(item) = output_index.and_then(|i| self.items.get_mut(i)) {
+                    let text = event
+                        .pointer("/part/text")
+                        .and_then(|t| t.as_str())
+                        .unwrap_or_default()
+                        .to_string();
+                    if content_index < item.parts.len() {
+                        item.parts[content_index] = (text, false);
+                    } else {
+                        item.parts.push((text, false));
+                    }
+                }
+            }
+            "response.output_text.delta" => {
+                let delta = event.get("delta").and_then(|d| d.as_str());
+                let part = output_index
+                    .and_then(|i| self.items.get_mut(i))
+                    .and_then(|item| item.parts.get_mut(content_index));
+                if let (Some((text, _)), Some(delta)) = (part, delta) {
+                    text.push_str(delta);
+                }
+            }
+            "response.content_part.done" => {
+                if let Some((_, done)) = output_index
+                    .and_then(|i| self.items.get_mut(i))
+                    .and_then(|item| item.parts.get_mut(content_index))
+                {
+                    *done = true;
+                }
+            }
+            "response.output_item.done" => {
+                if let Some(item) = output_index.and_then(|i| self.items.get_mut(i)) {
+                    item.done = event.get("item").cloned();
+                    for (_, done) in &mut item.parts {
+                        *done = true;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    fn is_error(&self, event: &Value) -> bool {
+        matches!(event_type(event), "error" | "response.failed")
+    }
+
+    fn continuation_body(&mut self, body: &Value) -> Option<Value> {
+        if self.complete {
+            return None;
+        }
+        // 只能续写文本；reasoning 必须已完整结束
+        let resumable = self.items.iter().all(|item| match item.kind.as_str() {
+            "message" => true,
+            "reasoning" => item.done.is_some(),
+            _ => false,
+        });
+        if !resumable {
+            return None;
+        }
+
+        let prefill: String = self
+            .items
+            .iter()
+            .filter(|item| item.kind == "message")
+            .flat_map(|item| item.parts.iter().map(|(text, _)| text.as_str()))
+            .collect();
+
+        let mut body = body.clone();
+        if !prefill.is_empty() {
+            let assistant = json!({
+                "type": "message",
+                "role": "assistant",
+                "content": [{"type": "output_text", "text": prefill}],
+            });
+            match body.get_mut("input")? {
+                Value::String(text) => {
+                    let user = json!({"role": "user", "content": std::mem::take(text)});
+                    body["input"] = json!([user, assistant]);
+                }
+                Value::Array(input) => input.push(assistant),
+                _ => return None,
+            }
+        }
+
+        let last = self.items.len().checked_sub(1);
+        let merge = last
+            .and_then(|i| self.items.get(i))
+            .is_some_and(|item| item.kind == "message" && item.done.is_none());
+        let (part_offset, merge_part) = match last.and_then(|i| self.items.get(i)) {
+            Some(item) if merge => match item.parts.last() {
+                Some((_, false)) => (item.parts.len() - 1, true),
+                _ => (item.parts.len(), false),
+            },
+            _ => (0, false),
+        };
+        self.continuation = Some(ResponsesContinuation {
+            offset: if merge {
+                self.items.len() - 1
+            } else {
+                self.items.len()
+            },
+            merge,
+            part_offset,
+            merge_part,
+        });
+        Some(body)
+    }
+
+    fn rewrite(&mut self, event: Value) -> Vec<Value> {
+        let Some(mut cont) = self.continuation.take() else {
+            return vec![event];
+        };
+        let output = self.rewrite_continuation(&mut cont, event);
+        self.continuation = Some(cont);
+        output
+    }
+}
+
+impl ResponsesSplice {
+    /// 结束客户端中未结束的 message 输出项（续写没有接着输出文本时）
+    fn close_message(&self, index: usize) -> Vec<Value> {
+        let Some(item) = self.items.get(index) else {
+            return Vec::new();
+        };
+        let mut events = Vec::new();
+        for (content_index, (text, done)) in item.parts.iter().enumerate() {
+            if *done {
+                continue;
+            }
+            events.push(json!({
+                "type": "response.output_text.done",
+                "item_id": item.id,
+                "output_index": index,
+                "content_index": content_index,
+                "text": text,
+            }));
+            events.push(json!({
+                "type": "response.content_part.done",
+                "item_id": item.id,
+                "output_index": index,
+                "content_index": content_index,
+                "part": {"type": "output_text", "text": text, "annotations": []},
+            }));
+        }
+        events.push(json!({
+            "type": "response.output_item.done",
+            "output_index": index,
+            "item": message_item(item),
+        }));
+        events
+    }
+
+    fn rewrite_continuation(
+        &mut self,
+        cont: &mut ResponsesContinuation,
+        mut event: Value,
+    ) -> Vec<Value> {
+        let mut output = Vec::new();
+        let kind = event_type(&event).to_string();
+        if matches!(kind.as_str(), "response.created" | "response.in_progress")
+            && self.response_id.is_some()
+        {
+            return output;
+        }
+
+        if let Some(index) = index_of(&event, "output_index") {
+            if index == 0 && cont.merge && kind == "response.output_item.added" {
+                if event.pointer("/item/type").and_then(|t| t.as_str()) == Some("message") {
+                    // 客户端的 message 仍未结束，续写内容直接追加到该输出项
+                    return output;
+                }
+                // 续写没有接着输出文本：先结束预填充所在的输出项
+                output.extend(self.close_message(cont.offset));
+                cont.offset += 1;
+                cont.merge = false;
+            }
+
+            let merged = index == 0 && cont.merge;
+            event["output_index"] = json!(cont.offset + index);
+            if merged {
+                let item = &self.items[cont.offset];
+                if event.get("item_id").is_some() {
+                    event["item_id"] = json!(item.id);
+                }
+                if let Some(content_index) = index_of(&event, "content_index") {
+                    if content_index == 0
+                        && cont.merge_part
+                        && kind == "response.content_part.added"
+                    {
+                        return output;
+                    }
+                    let mapped = cont.part_offset + content_index;
+                    event["content_index"] = json!(mapped);
+                    // 结束事件中的文本替换为客户端视角的完整文本
+                    let full_text = item.parts.get(mapped).map(|(text, _)| text.clone());
+                    match (kind.as_str(), full_text) {
+                        ("response.output_text.done", Some(text)) => event["text"] = json!(text),
+                        ("response.content_part.done", Some(text)) => {
+                            event["part"]["text"] = json!(text)
+                        }
+                        _ => {}
+                    }
+                }
+                if kind == "response.output_item.done" {
+                    event["item"] = message_item(item);
+                }
+            }
+        } else if event.get("response").is_some_and(|r| r.is_object()) {
+            // 终止事件：续写没有输出任何内容项时结束预填充所在的输出项
+            let open = cont.merge
+                && self
+                    .items
+                    .get(cont.offset)
+                    .is_some_and(|item| item.done.is_none());
+            if open {
+                cont.merge = false;
+                output.extend(self.close_message(cont.offset));
+            }
+        }
+
+        self.finish_rewrite(output, event)
+    }
+
+    /// 对齐 sequence_number、response.id 与最终输出列表
+    fn finish_rewrite(&mut self, mut output: Vec<Value>, event: Value) -> Vec<Value> {
+        output.push(event);
+        let mut sequence = self.sequence;
+        for event in &mut output {
+            if event.get("response").is_some_and(|r| r.is_object()) {
+                if let Some(id) = &self.response_id {
+                    event["response"]["id"] = json!(id);
+                }
+                if event.pointer("/response/output").is_some() {
+                    // 客户端视角的完整输出（本批刚补发结束事件的 message 按已收到的文本构建）
+                    let items: Vec<Value> = self
+                        .items
+                        .iter()
+                        .filter_map(|item| {
+                            item.done
+                                .clone()
+                                .or_else(|| (item.kind == "message").then(|| message_item(item)))
+                        })
+                        .collect();
+                    event["response"]["output"] = json!(items);
+                }
+            }
+            if sequence.is_some() || event.get("sequence_number").is_some() {
+                let next = sequence.map_or(0, |s| s + 1);
+                event["sequence_number"] = json!(next);
+                sequence = Some(next);
+            }
+        }
+        output
+    }
+}
+
+/// 从缓冲区取出一个完整的 SSE 事件（含结尾空行）
+///
+/// 事件以空行结束，行结束符可以是 `\n`、`\r\n` 或 `\r`。缓冲区末尾的 `\r` 可能是被拆到
+/// 下一个数据块的 `\r\n`，需要等待更多数据；上游已关闭（`eof`）时剩余内容整体作为最后一个事件。
+pub(crate) fn take_event(buffer: &mut Vec<u8>, eof: bool) -> Option<Vec<u8>> {
+    let mut pos = 0;
+    let mut line_empty = true;
+    while pos < buffer.len() {
+        let line_break = match buffer[pos] {
+            b'\n' => 1,
+            b'\r' => match buffer.get(pos + 1) {
+                Some(b'\n') => 2,
+                Some(_) => 1,
+                None if eof => 1,
+                None => return None,
+            },
+            _ => {
+                line_empty = false;
+                pos += 1;
+                continue;
+            }
+        };
+        pos += line_break;
+        if line_empty {
+            return Some(buffer.drain(..pos).collect());
+        }
+        line_empty = true;
+    }
+    (eof && !buffer.is_empty()).then(|| std::mem::take(buffer))
+}
+
+/// 解析 SSE 事件的 JSON data（不是 JSON 时返回 `None`）
+fn event_data(event: &[u8]) -> Option<Value> {
+    let text = std::str::from_utf8(event).ok()?;
+    let data: Vec<&str> = text
+        .split(['\r', '\n'])
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|data| data.strip_prefix(' ').unwrap_or(data))
+        .collect();
+    if data.is_empty() {
+        return None;
+    }
+    serde_json::from_str(&data.join("\n")).ok()
+}
+
+/// 把改写后的事件重新编码为 SSE（event 名取 type 字段）
+fn format_event(event: &Value) -> String {
+    format!("event: {}\ndata: {event}\n\n", event_type(event))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use crate::proxy::{
+        failover_switch::FailoverSwitchManager,
+        provider_router::ProviderRouter,
+        types::{ProxyStatus, RectifierConfig},
+    };
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    /// 依次改写续写流事件，并像客户端一样记录改写结果
+    fn splice_continuation(splice: &mut dyn Splice, events: Vec<Value>) -> Vec<Value> {
+        let mut output = Vec::new();
+        for event in events {
+            for rewritten in splice.rewrite(event) {
+                splice.observe(&rewritten);
+                output.push(rewritten);
+            }
+        }
+        output
+    }
+
+    #[test]
+    fn anthropic_continuation_merges_into_open_text_block() {
+        let mut splice = AnthropicSplice::default();
+        for event in [
+            json!({"type": "message_start", "message": {"id": "msg_1"}}),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "thinking", "thinking": ""}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "thinking_delta", "thinking": "..."}}),
+            json!({"type": "content_block_stop", "index": 0}),
+            json!({"type": "content_block_start", "index": 1, "content_block": {"type": "text", "text": ""}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "text_delta", "text": "Hello "}}),
+        ] {
+            splice.observe(&event);
+        }
+        assert!(!splice.is_complete());
+
+        let body = json!({
+            "model": "claude-sonnet-4",
+            "stream": true,
+            "thinking": {"type": "enabled", "budget_tokens": 1024},
+            "messages": [{"role": "user", "content": "hi"}],
+        });
+        let continuation = splice.continuation_body(&body).expect("resumable");
+        assert!(continuation.get("thinking").is_none());
+        assert_eq!(
+            continuation["messages"][1],
+            json!({"role": "assistant", "content": [{"type": "text", "text": "Hello"}]})
+        );
+
+        let output = splice_continuation(
+            &mut splice,
+            vec![
+                json!({"type": "message_start", "message": {"id": "msg_2"}}),
+                json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+                json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": " world"}}),
+                json!({"type": "content_block_stop", "index": 0}),
+                json!({"type": "content_block_start", "index": 1, "content_block": {"type": "text", "text": ""}}),
+                json!({"type": "content_block_stop", "index": 1}),
+                json!({"type": "message_delta", "delta": {"stop_reason": "end_turn"}}),
+                json!({"type": "message_stop"}),
+            ],
+        );
+        assert_eq!(
+            output,
+            vec![
+                json!({"type": "content_block_delta", "index": 1, "delta": {"type": "text_delta", "text": "world"}}),
+                json!({"type": "content_block_stop", "index": 1}),
+                json!({"type": "content_block_start", "index": 2, "content_block": {"type": "text", "text": ""}}),
+                json!({"type": "content_block_stop", "index": 2}),
+                json!({"type": "message_delta", "delta": {"stop_reason": "end_turn"}}),
+                json!({"type": "message_stop"}),
+            ]
+        );
+        assert_eq!(splice.blocks[1].text, "Hello world");
+        assert!(splice.is_complete());
+    }
+
+    #[test]
+    fn anthropic_continuation_closes_prefill_block_before_tool_use() {
+        let mut splice = AnthropicSplice::default();
+        for event in [
+            json!({"type": "message_start", "message": {}}),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": "Let me"}}),
+        ] {
+            splice.observe(&event);
+        }
+        let body = json!({"messages": [{"role": "user", "content": "hi"}, {"role": "assistant", "content": "Sure. "}]});
+        let continuation = splice.continuation_body(&body).expect("resumable");
+        assert_eq!(continuation["messages"][1]["content"], "Sure. Let me");
+
+        let output = splice_continuation(
+            &mut splice,
+            vec![
+                json!({"type": "content_block_start", "index": 0, "content_block": {"type": "tool_use", "id": "t", "name": "ls", "input": {}}}),
+            ],
+        );
+        assert_eq!(output[0], json!({"type": "content_block_stop", "index": 0}));
+        assert_eq!(output[1]["index"], 1);
+    }
+
+    #[test]
+    fn anthropic_refuses_to_resume_tool_use_or_open_thinking() {
+        let body = json!({"messages": [{"role": "user", "content": "hi"}]});
+
+        let mut tool_use = AnthropicSplice::default();
+        tool_use.observe(&json!({"type": "message_start", "message": {}}));
+        tool_use.observe(&json!({"type": "content_block_start", "index": 0, "content_block": {"type": "tool_use", "id": "t", "name": "ls", "input": {}}}));
+        assert!(tool_use.continuation_body(&body).is_none());
+
+        let mut thinking = AnthropicSplice::default();
+        thinking.observe(&json!({"type": "message_start", "message": {}}));
+        thinking.observe(&json!({"type": "content_block_start", "index": 0, "content_block": {"type": "thinking", "thinking": ""}}));
+        assert!(thinking.continuation_body(&body).is_none());
+
+        let mut stopped = AnthropicSplice::default();
+        stopped.observe(&json!({"type": "message_delta", "delta": {"stop_reason": "end_turn"}}));
+        assert!(stopped.is_complete());
+        assert!(stopped.continuation_body(&body).is_none());
+    }
+
+    #[test]
+    fn responses_continuation_keeps_item_ids_and_sequence() {
+        let mut splice = ResponsesSplice::default();
+        for event in [
+            json!({"type": "response.created", "sequence_number": 0, "response": {"id": "resp_1", "output": []}}),
+            json!({"type": "response.output_item.added", "sequence_number": 1, "output_index": 0, "item": {"id": "msg_1", "type": "message", "content": []}}),
+            json!({"type": "response.content_part.added", "sequence_number": 2, "item_id": "msg_1", "output_index": 0, "content_index": 0, "part": {"type": "output_text", "text": ""}}),
+            json!({"type": "response.output_text.delta", "sequence_number": 3, "item_id": "msg_1", "output_index": 0, "content_index": 0, "delta": "Hello"}),
+        ] {
+            splice.observe(&event);
+        }
+
+        let body = json!({"model": "gpt-5", "stream": true, "input": "hi"});
+        let continuation = splice.continuation_body(&body).expect("resumable");
+        assert_eq!(
+            continuation["input"],
+            json!([
+                {"role": "user", "content": "hi"},
+                {"type": "message", "role": "assistant", "content": [{"type": "output_text", "text": "Hello"}]},
+            ])
+        );
+
+        let output = splice_continuation(
+            &mut splice,
+            vec![
+                json!({"type": "response.created", "sequence_number": 0, "response": {"id": "resp_2", "output": []}}),
+                json!({"type": "response.output_item.added", "sequence_number": 1, "output_index": 0, "item": {"id": "msg_2", "type": "message", "content": []}}),
+                json!({"type": "response.content_part.added", "sequence_number": 2, "item_id": "msg_2", "output_index": 0, "content_index": 0, "part": {"type": "output_text", "text": ""}}),
+                json!({"type": "response.output_text.delta", "sequence_number": 3, "item_id": "msg_2", "output_index": 0, "content_index": 0, "delta": " world"}),
+                json!({"type": "response.output_text.done", "sequence_number": 4, "item_id": "msg_2", "output_index": 0, "content_index": 0, "text": " world"}),
+                json!({"type": "response.content_part.done", "sequence_number": 5, "item_id": "msg_2", "output_index": 0, "content_index": 0, "part": {"type": "output_text", "text": " world"}}),
+                json!({"type": "response.output_item.done", "sequence_number": 6, "output_index": 0, "item": {"id": "msg_2", "type": "message", "content": []}}),
+                json!({"type": "response.completed", "sequence_number": 7, "response": {"id": "resp_2", "output": [{"id": "msg_2"}], "usage": {"input_tokens": 3}}}),
+            ],
+        );
+
+        assert_eq!(output.len(), 5);
+        assert_eq!(output[0]["item_id"], "msg_1");
+        assert_eq!(output[1]["text"], "Hello world");
+        assert_eq!(output[2]["part"]["text"], "Hello world");
+        assert_eq!(output[3]["item"]["id"], "msg_1");
+        assert_eq!(output[4]["response"]["id"], "resp_1");
+        assert_eq!(output[4]["response"]["usage"]["input_tokens"], 3);
+        assert_eq!(
+            output[4]["response"]["output"][0]["content"][0]["text"],
+            "Hello world"
+        );
+        let sequence: Vec<u64> = output
+            .iter()
+            .filter_map(|e| e["sequence_number"].as_u64())
+            .collect();
+        assert_eq!(sequence, vec![4, 5, 6, 7, 8]);
+        assert!(splice.is_complete());
+    }
+
+    #[test]
+    fn parses_and_formats_sse_events() {
+        let mut buffer = b"event: ping\ndata: {\"type\":\"ping\"}\n\ndata: {\"ty".to_vec();
+        let event = take_event(&mut buffer, false).expect("complete event");
+        assert_eq!(event_data(&event), Some(json!({"type": "ping"})));
+        assert!(take_event(&mut buffer, false).is_none());
+        assert_eq!(buffer, b"data: {\"ty");
+
+        assert_eq!(
+            format_event(&json!({"type": "message_stop"})),
+            "event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n"
+        );
+    }
+
+    #[test]
+    fn splits_crlf_and_cr_framed_events() {
+        let mut buffer =
+            b"event: ping\r\ndata: {\"type\":\"ping\"}\r\n\r\ndata: {\"a\":1}\r\rdata: {\"b\":"
+                .to_vec();
+        let event = take_event(&mut buffer, false).expect("CRLF framed event");
+        assert_eq!(event, b"event: ping\r\ndata: {\"type\":\"ping\"}\r\n\r\n");
+        assert_eq!(event_data(&event), Some(json!({"type": "ping"})));
+        let event = take_event(&mut buffer, false).expect("CR framed event");
+        assert_eq!(event_data(&event), Some(json!({"a": 1})));
+        assert!(take_event(&mut buffer, false).is_none());
+
+        // 末尾的 \r 可能是被拆开的 \r\n，等待下一个数据块
+        let mut buffer = b"data: {\"c\":2}\r\n\r".to_vec();
+        assert!(take_event(&mut buffer, false).is_none());
+        buffer.extend_from_slice(b"\ndata: {\"d\":3}");
+        let event = take_event(&mut buffer, false).expect("event split across chunks");
+        assert_eq!(event_data(&event), Some(json!({"c": 2})));
+        assert_eq!(buffer, b"data: {\"d\":3}");
+
+        // 上游关闭时剩余内容作为最后一个事件
+        assert!(take_event(&mut buffer, false).is_none());
+        let event = take_event(&mut buffer, true).expect("trailing event at eof");
+        assert_eq!(event_data(&event), Some(json!({"d": 3})));
+        assert!(buffer.is_empty());
+        assert!(take_event(&mut buffer, true).is_none());
+    }
+
+    #[test]
+    fn joins_multiline_data_with_any_line_break() {
+        assert_eq!(
+            event_data(b"data: {\"a\":\r\ndata: 1}\r\n\r\n"),
+            Some(json!({"a": 1}))
+        );
+        assert_eq!(
+            event_data(b"data: {\"a\":\rdata: 2}\r\r"),
+            Some(json!({"a": 2}))
+        );
+        assert_eq!(event_data(b": keep-alive\n\n"), None);
+    }
+
+    /// 按 Anthropic 事件生成 SSE 文本（`line_break` 为行结束符）
+    fn sse(events: &[Value], line_break: &str) -> String {
+        events
+            .iter()
+            .map(|event| {
+                format!(
+                    "event: {}{line_break}data: {event}{line_break}{line_break}",
+                    event_type(event)
+                )
+            })
+            .collect()
+    }
+
+    /// 启动模拟上游：以 `body` 作为 SSE 响应体返回，返回 base URL
+    async fn spawn_upstream(body: String) -> String {
+        let app = axum::Router::new().fallback(move || {
+            let body = body.clone();
+            async move { ([("content-type", "text/event-stream")], body) }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock upstream");
+        let addr = listener.local_addr().expect("mock upstream address");
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        format!("http://{addr}")
+    }
+
+    fn claude_provider(id: &str, base_url: &str) -> Provider {
+        Provider::with_id(
+            id.to_string(),
+            id.to_string(),
+            json!({
+                "env": {
+                    "ANTHROPIC_BASE_URL": base_url,
+                    "ANTHROPIC_AUTH_TOKEN": format!("sk-{id}"),
+                }
+            }),
+            None,
+        )
+    }
+
+    fn stream_resume(providers: Vec<Provider>) -> StreamResume {
+        StreamResume {
+            protocol: ResumeProtocol::Anthropic,
+            max_resumes: 1,
+            endpoint: "/v1/messages",
+            body: json!({
+                "model": "claude-sonnet-4",
+                "stream": true,
+                "messages": [{"role": "user", "content": "hi"}],
+            }),
+            headers: HeaderMap::new(),
+            providers,
+            tried: Vec::new(),
+        }
+    }
+
+    fn resume_runtime(provider: Provider) -> ResumeRuntime {
+        let db = Arc::new(Database::memory().expect("create database"));
+        let forwarder = RequestForwarder::new(
+            Arc::new(ProviderRouter::new(db.clone())),
+            30,
+            Arc::new(RwLock::new(ProxyStatus::default())),
+            Arc::new(RwLock::new(HashMap::new())),
+            Arc::new(FailoverSwitchManager::new(db)),
+            None,
+            String::new(),
+            0,
+            0,
+            RectifierConfig::default(),
+            Vec::new(),
+        );
+        ResumeRuntime {
+            forwarder,
+            app_type: AppType::Claude,
+            tag: "Claude",
+            timeout_config: StreamingTimeoutConfig {
+                first_byte_timeout: 0,
+                idle_timeout: 0,
+            },
+            provider,
+            collector: SseUsageCollector::new(std::time::Instant::now(), |_, _| {}),
+            new_collector: Box::new(|_, _, _| {
+                SseUsageCollector::new(std::time::Instant::now(), |_, _| {})
+            }),
+        }
+    }
+
+    /// 收集续传流的输出：(拼接后的文本, 是否以错误结束)
+    async fn collect_output(
+        stream: impl Stream<Item = Result<Bytes, std::io::Error>> + Send,
+    ) -> (String, bool) {
+        let items: Vec<_> = stream.collect().await;
+        let failed = items.iter().any(|item| item.is_err());
+        let bytes: Vec<u8> = items
+            .into_iter()
+            .filter_map(Result::ok)
+            .flat_map(|bytes| bytes.to_vec())
+            .collect();
+        (String::from_utf8(bytes).expect("utf-8 output"), failed)
+    }
+
+    #[tokio::test]
+    async fn passes_through_crlf_stream_without_trailing_blank_line() {
+        let text = sse(
+            &[
+                json!({"type": "message_start", "message": {"id": "msg_1"}}),
+                json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+                json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hi"}}),
+                json!({"type": "content_block_stop", "index": 0}),
+                json!({"type": "message_stop"}),
+            ],
+            "\r\n",
+        );
+        let text = text.trim_end().to_string();
+        // 按奇数长度切块，让 \r\n 被拆到两个数据块
+        let chunks: Vec<Result<Bytes, std::io::Error>> = text
+            .as_bytes()
+            .chunks(7)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect();
+
+        let provider = claude_provider("only", "http://127.0.0.1:9");
+        let stream = stream_resume(vec![provider.clone()])
+            .into_stream(futures::stream::iter(chunks), resume_runtime(provider));
+        let (output, failed) = collect_output(stream).await;
+        assert!(!failed);
+        assert_eq!(output, text);
+    }
+
+    #[tokio::test]
+    async fn resumes_crlf_stream_on_provider_not_tried_before() {
+        let continuation = |text: &str| {
+            sse(
+                &[
+                    json!({"type": "message_start", "message": {"id": "msg_2"}}),
+                    json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+                    json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": text}}),
+                    json!({"type": "content_block_stop", "index": 0}),
+                    json!({"type": "message_delta", "delta": {"stop_reason": "end_turn"}}),
+                    json!({"type": "message_stop"}),
+                ],
+                "\r\n",
+            )
+        };
+        let failed_before = claude_provider(
+            "failed",
+            &spawn_upstream(continuation(" from failed")).await,
+        );
+        let serving = claude_provider("serving", "http://127.0.0.1:9");
+        let backup = claude_provider("backup", &spawn_upstream(continuation(" world")).await);
+
+        let interrupted = sse(
+            &[
+                json!({"type": "message_start", "message": {"id": "msg_1"}}),
+                json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+                json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hello"}}),
+            ],
+            "\r\n",
+        );
+        let upstream = futures::stream::iter(vec![Ok(Bytes::from(interrupted))]);
+
+        let stream = stream_resume(vec![failed_before, serving.clone(), backup])
+            .with_tried_providers(vec!["failed".to_string(), "serving".to_string()])
+            .into_stream(upstream, resume_runtime(serving));
+        let (output, failed) = collect_output(stream).await;
+        assert!(!failed);
+        assert!(output.contains("\"text\":\" world\""));
+        assert!(!output.contains("from failed"));
+        assert!(!output.contains("msg_2"));
+        assert!(output.ends_with("data: {\"type\":\"message_stop\"}\n\n"));
+    }
+}
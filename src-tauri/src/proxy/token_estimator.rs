@@ -0,0 +1,166 @@
+//! 本地 Token 估算
+//!
+//! 上游没有 `/v1/messages/count_tokens` 等价接口（如 `openai_chat` 格式的供应商）或请求失败时，
+//! 按 Anthropic Messages 请求体在本地粗略估算输入 Token 数。
+//!
+//! 估算规则：
+//! - CJK 字符按 1 个 Token 计，其余字符按 4 个字符 1 个 Token 计
+//! - 每条消息、每个内容块、每个工具定义附加少量结构开销
+//! - 图片按固定值计（无法在不解码图片的情况下得知尺寸）
+
+use serde_json::Value;
+
+/// 每条消息的结构开销
+const MESSAGE_OVERHEAD: u64 = 4;
+
+/// 每个内容块的结构开销
+const BLOCK_OVERHEAD: u64 = 2;
+
+/// 每个工具定义的结构开销
+const TOOL_OVERHEAD: u64 = 8;
+
+/// 单张图片的估算值（约 1092x1092 像素图片的 Token 数）
+const IMAGE_TOKENS: u64 = 1600;
+
+/// 估算 Messages 请求的输入 Token 数
+pub fn estimate_input_tokens(body: &Value) -> u64 {
+    let mut tokens = 0;
+
+    if let Some(system) = body.get("system") {
+        tokens += estimate_content(system);
+    }
+
+    if let Some(messages) = body.get("messages").and_then(|m| m.as_array()) {
+        for message in messages {
+            tokens += MESSAGE_OVERHEAD;
+            if let Some(content) = message.get("content") {
+                tokens += estimate_content(content);
+            }
+        }
+    }
+
+    if let Some(tools) = body.get("tools").and_then(|t| t.as_array()) {
+        for tool in tools {
+            tokens += TOOL_OVERHEAD + estimate_json(tool);
+        }
+    }
+
+    tokens.max(1)
+}
+
+/// 估算消息内容（字符串或内容块数组）
+fn estimate_content(content: &Value) -> u64 {
+    match content {
+        Value::String(text) => estimate_text(text),
+        Value::Array(blocks) => blocks
+            .iter()
+            .map(|block| BLOCK_OVERHEAD + estimate_block(block))
+            .sum(),
+        other => estimate_json(other),
+    }
+}
+
+/// 估算单个内容块
+fn estimate_block(block: &Value) -> u64 {
+    match block.get("type").and_then(|t| t.as_str()) {
+        Some("text") => block
+            .get("text")
+            .and_then(|t| t.as_str())
+            .map_or(0, estimate_text),
+        Some("thinking") => block
+            .get("thinking")
+            .and_then(|t| t.as_str())
+            .map_or(0, estimate_text),
+        // 上游会丢弃 redacted_thinking / 签名内容，不计入
+        Some("redacted_thinking") => 0,
+        Some("image") => IMAGE_TOKENS,
+        Some("tool_use") => {
+            let name = block
+                .get("name")
+                .and_then(|n| n.as_str())
+                .map_or(0, estimate_text);
+            name + block.get("input").map_or(0, estimate_json)
+        }
+        Some("tool_result") => block.get("content").map_or(0, estimate_content),
+        Some("document") => block
+            .get("source")
+            .and_then(|s| s.get("data"))
+            .and_then(|d| d.as_str())
+            // 纯文本文档按文本计，其余（如 base64 PDF）按解码后字节数粗略估算
+            .map_or(0, |data| match block["source"]["type"].as_str() {
+                Some("text") => estimate_text(data),
+                _ => (data.len() as u64 * 3 / 4) / 4,
+            }),
+        _ => estimate_json(block),
+    }
+}
+
+/// 按序列化后的 JSON 文本估算
+fn estimate_json(value: &Value) -> u64 {
+    estimate_text(&value.to_string())
+}
+
+/// 估算文本的 Token 数
+fn estimate_text(text: &str) -> u64 {
+    let (mut cjk, mut other) = (0u64, 0u64);
+    for c in text.chars() {
+        if is_cjk(c) {
+            cjk += 1;
+        } else {
+            other += 1;
+        }
+    }
+    cjk + other.div_ceil(4)
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x3040..=0x30FF     // 平假名、片假名
+            | 0x3400..=0x4DBF // CJK 扩展 A
+            | 0x4E00..=0x9FFF // CJK 统一表意文字
+            | 0xAC00..=0xD7AF // 韩文音节
+            | 0xF900..=0xFAFF // CJK 兼容表意文字
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn text_estimate_counts_cjk_per_character() {
+        assert_eq!(estimate_text("abcdefgh"), 2);
+        assert_eq!(estimate_text("你好世界"), 4);
+        assert_eq!(estimate_text(""), 0);
+    }
+
+    #[test]
+    fn request_estimate_includes_system_messages_and_tools() {
+        let body = json!({
+            "model": "claude-sonnet-4",
+            "system": "abcd",
+            "messages": [
+                { "role": "user", "content": "abcdefgh" },
+                {
+                    "role": "assistant",
+                    "content": [
+                        { "type": "text", "text": "abcd" },
+                        { "type": "image", "source": { "type": "base64", "data": "" } }
+                    ]
+                }
+            ]
+        });
+        let base = 1
+            + (MESSAGE_OVERHEAD + 2)
+            + (MESSAGE_OVERHEAD + BLOCK_OVERHEAD + 1)
+            + BLOCK_OVERHEAD
+            + IMAGE_TOKENS;
+        assert_eq!(estimate_input_tokens(&body), base);
+
+        let mut with_tools = body.clone();
+        with_tools["tools"] = json!([{ "name": "read", "input_schema": {} }]);
+        assert!(estimate_input_tokens(&with_tools) > base + TOOL_OVERHEAD);
+    }
+}
@@ -208,6 +208,43 @@ pub struct RectifierConfig {
     pub request_thinking_signature: bool,
 }
 
+fn default_tracing_endpoint() -> String {
+    "http://127.0.0.1:4318/v1/traces".to_string()
+}
+
+fn default_tracing_service_name() -> String {
+    "cc-switch-proxy".to_string()
+}
+
+/// 链路追踪配置
+///
+/// 存储在 settings 表的 tracing_config 字段中（JSON 格式）。
+/// 启用后每个代理请求导出一条 OTLP trace（HTTP/JSON）到本地 Collector，
+/// 并通过 `traceparent` 请求头把 trace 传递给上游。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TracingConfig {
+    /// 是否启用导出
+    #[serde(default)]
+    pub enabled: bool,
+    /// OTLP/HTTP traces 端点
+    #[serde(default = "default_tracing_endpoint")]
+    pub endpoint: String,
+    /// 上报的 service.name
+    #[serde(default = "default_tracing_service_name")]
+    pub service_name: String,
+}
+
+impl Default for TracingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: default_tracing_endpoint(),
+            service_name: default_tracing_service_name(),
+        }
+    }
+}
+
 fn default_true() -> bool {
     true
 }
@@ -256,6 +293,167 @@ impl LogConfig {
     }
 }
 
+fn default_failback_probe_successes() -> u32 {
+    3
+}
+
+fn default_failback_probe_interval() -> u32 {
+    60
+}
+
+/// 自动切回配置（每个 app 独立）
+///
+/// 存储在 settings 表的 failback_config:{app_type} 字段中（JSON 格式）。
+/// 故障转移到备用供应商后，原主供应商连续通过 N 次健康探测，或冷却时间到期后通过一次健康探测，
+/// 即自动切回；未通过探测时不会切回。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailbackConfig {
+    /// 是否启用自动切回
+    #[serde(default)]
+    pub enabled: bool,
+    /// 需连续通过的健康探测次数（至少 1 次）
+    #[serde(default = "default_failback_probe_successes")]
+    pub probe_successes: u32,
+    /// 健康探测间隔（秒）
+    #[serde(default = "default_failback_probe_interval")]
+    pub probe_interval_seconds: u32,
+    /// 冷却时间（秒），故障转移后超过该时间且主供应商通过一次健康探测即切回（0 表示不使用）
+    #[serde(default)]
+    pub cooldown_seconds: u32,
+}
+
+impl Default for FailbackConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            probe_successes: default_failback_probe_successes(),
+            probe_interval_seconds: default_failback_probe_interval(),
+            cooldown_seconds: 0,
+        }
+    }
+}
+
+impl FailbackConfig {
+    /// 校验配置：至少需要通过 1 次健康探测，避免主供应商仍故障时反复切回
+    pub fn validate(&self) -> Result<(), String> {
+        if self.probe_successes == 0 {
+            return Err("健康探测通过次数至少为 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn default_hedging_delay_ms() -> u32 {
+    3000
+}
+
+/// 请求对冲配置（每个 app 独立）
+///
+/// 存储在 settings 表的 hedging_config:{app_type} 字段中（JSON 格式）。
+/// 流式请求的首选供应商超过 `delay_ms` 仍未返回首字节时，向故障转移链中的下一个供应商
+/// 发送相同请求，采用先返回首字节的一方并取消另一方。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HedgingConfig {
+    /// 是否启用请求对冲
+    #[serde(default)]
+    pub enabled: bool,
+    /// 等待首选供应商首字节的时间（毫秒），超过后发起对冲请求
+    #[serde(default = "default_hedging_delay_ms")]
+    pub delay_ms: u32,
+}
+
+impl Default for HedgingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            delay_ms: default_hedging_delay_ms(),
+        }
+    }
+}
+
+fn default_max_resumes() -> u32 {
+    1
+}
+
+/// 流式断流续传配置（每个 app 独立）
+///
+/// 存储在 settings 表的 stream_resume_config:{app_type} 字段中（JSON 格式）。
+/// Anthropic Messages / OpenAI Responses 流在结束事件之前中断（流错误、静默期超时或上游错误事件）时，
+/// 把已输出的助手内容作为预填充发往故障转移链中的其他供应商，并把续写内容拼接进同一个客户端流。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamResumeConfig {
+    /// 是否启用断流续传
+    #[serde(default)]
+    pub enabled: bool,
+    /// 单个请求最多续传次数
+    #[serde(default = "default_max_resumes")]
+    pub max_resumes: u32,
+}
+
+impl Default for StreamResumeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_resumes: default_max_resumes(),
+        }
+    }
+}
+
+/// 供应商切换类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FailoverTransitionKind {
+    /// 请求失败后切到备用供应商
+    Failover,
+    /// 主供应商恢复后自动切回
+    Failback,
+    /// 手动重置熔断器后切回优先级更高的供应商
+    Recovery,
+}
+
+impl FailoverTransitionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Failover => "failover",
+            Self::Failback => "failback",
+            Self::Recovery => "recovery",
+        }
+    }
+
+    pub fn from_db_str(value: &str) -> Self {
+        match value {
+            "failback" => Self::Failback,
+            "recovery" => Self::Recovery,
+            _ => Self::Failover,
+        }
+    }
+}
+
+impl std::fmt::Display for FailoverTransitionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 故障转移 / 切回历史记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailoverTransition {
+    pub id: i64,
+    pub app_type: String,
+    pub kind: FailoverTransitionKind,
+    pub from_provider_id: Option<String>,
+    pub from_provider_name: Option<String>,
+    pub to_provider_id: String,
+    pub to_provider_name: String,
+    /// 触发原因说明
+    pub detail: Option<String>,
+    pub created_at: i64,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -289,6 +487,23 @@ mod tests {
         assert!(config.request_thinking_signature);
     }
 
+    #[test]
+    fn test_failback_config_rejects_zero_probe_successes() {
+        let config = FailbackConfig {
+            enabled: true,
+            probe_successes: 0,
+            cooldown_seconds: 0,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = FailbackConfig {
+            probe_successes: 1,
+            ..config
+        };
+        assert!(config.validate().is_ok());
+    }
+
     #[test]
     fn test_log_config_default() {
         let config = LogConfig::default();
@@ -4,6 +4,7 @@ use super::calculator::{CostBreakdown, CostCalculator, ModelPricing};
 use super::parser::TokenUsage;
 use crate::database::Database;
 use crate::error::AppError;
+use crate::proxy::metrics::ProxyMetrics;
 use crate::services::usage_stats::find_model_pricing_row;
 use rust_decimal::Decimal;
 use std::{str::FromStr, sync::Arc, time::SystemTime};
@@ -29,20 +30,37 @@ pub struct RequestLog {
     pub is_streaming: bool,
     /// 成本倍数
     pub cost_multiplier: String,
+    /// 使用的 Key 池 Key 标识
+    pub api_key_id: Option<String>,
+    /// 发起请求的客户端访问令牌标识
+    pub client_token_id: Option<String>,
+    /// 请求头路由覆盖（JSON）
+    pub routing_override: Option<String>,
 }
 
 /// 使用量记录器
 pub struct UsageLogger {
     db: Arc<Database>,
+    metrics: Option<Arc<ProxyMetrics>>,
 }
 
 impl UsageLogger {
     pub fn new(db: Arc<Database>) -> Self {
-        Self { db }
+        Self { db, metrics: None }
+    }
+
+    /// 同时把请求记入 Prometheus 指标
+    pub fn with_metrics(mut self, metrics: Arc<ProxyMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
     }
 
     /// 记录成功的请求
     pub async fn log_request(&self, log: RequestLog) -> Result<(), AppError> {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_request(&log);
+        }
+
         let db = self.db.clone();
 
         // Use spawn_blocking to move blocking database I/O off the async runtime
@@ -82,8 +100,9 @@ impl UsageLogger {
                     input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens,
                     input_cost_usd, output_cost_usd, cache_read_cost_usd, cache_creation_cost_usd, total_cost_usd,
                     latency_ms, first_token_ms, status_code, error_message, session_id,
-                    provider_type, is_streaming, cost_multiplier, created_at
-                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23)",
+                    provider_type, is_streaming, cost_multiplier, created_at, api_key_id, client_token_id,
+                    routing_override
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26)",
                 rusqlite::params![
                     log.request_id,
                     log.provider_id,
@@ -108,6 +127,9 @@ impl UsageLogger {
                     log.is_streaming as i64,
                     log.cost_multiplier,
                     created_at,
+                    log.api_key_id,
+                    log.client_token_id,
+                    log.routing_override,
                 ],
             )
             .map(|_| ())
@@ -148,6 +170,9 @@ impl UsageLogger {
             provider_type: None,
             is_streaming: false,
             cost_multiplier: "1.0".to_string(),
+            api_key_id: None,
+            client_token_id: None,
+            routing_override: None,
         };
 
         self.log_request(log).await
@@ -169,6 +194,9 @@ impl UsageLogger {
         is_streaming: bool,
         session_id: Option<String>,
         provider_type: Option<String>,
+        api_key_id: Option<String>,
+        client_token_id: Option<String>,
+        routing_override: Option<String>,
     ) -> Result<(), AppError> {
         let request_model = model.clone();
         let log = RequestLog {
@@ -187,6 +215,9 @@ impl UsageLogger {
             provider_type,
             is_streaming,
             cost_multiplier: "1.0".to_string(),
+            api_key_id,
+            client_token_id,
+            routing_override,
         };
 
         self.log_request(log).await
@@ -313,6 +344,9 @@ impl UsageLogger {
         session_id: Option<String>,
         provider_type: Option<String>,
         is_streaming: bool,
+        api_key_id: Option<String>,
+        client_token_id: Option<String>,
+        routing_override: Option<String>,
     ) -> Result<(), AppError> {
         let pricing = self.get_model_pricing(&pricing_model).await?;
 
@@ -338,6 +372,9 @@ impl UsageLogger {
             provider_type,
             is_streaming,
             cost_multiplier: cost_multiplier.to_string(),
+            api_key_id,
+            client_token_id,
+            routing_override,
         };
 
         self.log_request(log).await
@@ -388,19 +425,25 @@ mod tests {
             None,
             Some("claude".to_string()),
             false,
+            Some("key-1".to_string()),
+            Some("client-1".to_string()),
+            Some(r#"{"noFailover":true}"#.to_string()),
         ).await?;
 
         // 验证记录已插入
         let conn = crate::database::lock_conn!(db.conn);
-        let (count, request_model): (i64, String) = conn
+        let (count, request_model, api_key_id, client_token_id, routing_override): (i64, String, Option<String>, Option<String>, Option<String>) = conn
             .query_row(
-                "SELECT COUNT(*), request_model FROM proxy_request_logs WHERE request_id = 'req-123'",
+                "SELECT COUNT(*), request_model, api_key_id, client_token_id, routing_override FROM proxy_request_logs WHERE request_id = 'req-123'",
                 [],
-                |row| Ok((row.get(0)?, row.get(1)?)),
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
             )
             .unwrap();
         assert_eq!(count, 1);
         assert_eq!(request_model, "req-model");
+        assert_eq!(api_key_id.as_deref(), Some("key-1"));
+        assert_eq!(client_token_id.as_deref(), Some("client-1"));
+        assert_eq!(routing_override.as_deref(), Some(r#"{"noFailover":true}"#));
         Ok(())
     }
 
@@ -484,6 +527,9 @@ mod perf_tests {
                     None,
                     Some("claude".to_string()),
                     false,
+                    None,
+                    None,
+                    None,
                 ).await.unwrap();
             }));
         }
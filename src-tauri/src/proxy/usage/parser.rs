@@ -340,7 +340,10 @@ impl TokenUsage {
     }
 
     /// 从 Gemini API 非流式响应解析
+    ///
+    /// 兼容 Gemini CLI Code Assist（`/v1internal`）包装在 `response` 中的响应
     pub fn from_gemini_response(body: &Value) -> Option<Self> {
+        let body = unwrap_code_assist_envelope(body);
         let usage = body.get("usageMetadata")?;
         // 提取实际使用的模型名称（modelVersion 字段）
         let model = body
@@ -376,6 +379,7 @@ impl TokenUsage {
         let mut model: Option<String> = None;
 
         for chunk in chunks {
+            let chunk = unwrap_code_assist_envelope(chunk);
             if let Some(usage) = chunk.get("usageMetadata") {
                 // 输入 tokens (通常在所有 chunk 中保持不变)
                 total_input = usage
@@ -421,6 +425,14 @@ impl TokenUsage {
     }
 }
 
+/// 取出 Gemini CLI Code Assist 响应中的 `response` 字段（普通 Gemini 响应原样返回）
+fn unwrap_code_assist_envelope(body: &Value) -> &Value {
+    match body.get("response") {
+        Some(inner) if inner.is_object() => inner,
+        _ => body,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -582,6 +594,34 @@ mod tests {
         assert_eq!(usage.model, None);
     }
 
+    #[test]
+    fn test_gemini_cli_code_assist_envelope() {
+        // Gemini CLI（/v1internal）的响应包装在 response 字段中
+        let response = json!({
+            "response": {
+                "modelVersion": "gemini-2.5-pro",
+                "usageMetadata": {
+                    "promptTokenCount": 100,
+                    "totalTokenCount": 130
+                }
+            },
+            "traceId": "abc"
+        });
+        let usage = TokenUsage::from_gemini_response(&response).unwrap();
+        assert_eq!(usage.input_tokens, 100);
+        assert_eq!(usage.output_tokens, 30);
+        assert_eq!(usage.model, Some("gemini-2.5-pro".to_string()));
+
+        let chunks = vec![
+            json!({ "response": { "modelVersion": "gemini-2.5-pro" } }),
+            json!({ "response": { "usageMetadata": { "promptTokenCount": 10, "totalTokenCount": 25 } } }),
+        ];
+        let usage = TokenUsage::from_gemini_stream_chunks(&chunks).unwrap();
+        assert_eq!(usage.input_tokens, 10);
+        assert_eq!(usage.output_tokens, 15);
+        assert_eq!(usage.model, Some("gemini-2.5-pro".to_string()));
+    }
+
     #[test]
     fn test_gemini_response_with_thoughts() {
         // 测试包含 thoughtsTokenCount 的实际响应
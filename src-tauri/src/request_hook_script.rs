@@ -1,7 +1,12 @@
-use rquickjs::{Context, Function, Runtime};
+use rquickjs::Function;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::database::Database;
+use crate::provider::{Provider, RequestHookScript};
+use crate::script_runtime::{ScriptLimits, ScriptRun, ScriptRuntime};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RequestHookProviderInfo {
@@ -87,122 +92,203 @@ pub(crate) fn apply_query_string_map_to_url(
     Ok(parsed.to_string())
 }
 
-pub(crate) fn execute_on_request_script(
-    script_code: &str,
-    context: &RequestHookContext,
-    request: &HookRequest,
-) -> Result<Option<HookRequest>, String> {
-    let runtime = Runtime::new().map_err(|e| format!("创建 JS 运行时失败: {e}"))?;
-    let js_context = Context::full(&runtime).map_err(|e| format!("创建 JS 上下文失败: {e}"))?;
-
-    js_context.with(|ctx| {
-        let config: rquickjs::Object = ctx
-            .eval(script_code)
-            .map_err(|e| format!("解析脚本失败（脚本必须 eval 成一个对象）: {e}"))?;
-
-        let on_request: Option<Function> = config.get("onRequest").ok();
-        let Some(on_request) = on_request else {
-            return Ok(None);
-        };
-
-        let context_json =
-            serde_json::to_string(context).map_err(|e| format!("序列化 context 失败: {e}"))?;
-        let request_json =
-            serde_json::to_string(request).map_err(|e| format!("序列化 request 失败: {e}"))?;
+/// 请求重写脚本的作用范围
+///
+/// onRequest 按 全局 → 应用 → 供应商 的顺序链式执行，每个脚本看到的是上一个脚本的输出；
+/// onResponse / onStreamEvent 按相反顺序执行（供应商脚本最先处理上游响应）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HookScope {
+    Global,
+    App,
+    Provider,
+}
 
-        let context_js: rquickjs::Value = ctx
-            .json_parse(context_json.as_str())
-            .map_err(|e| format!("解析 context JSON 失败: {e}"))?;
-        let request_js: rquickjs::Value = ctx
-            .json_parse(request_json.as_str())
-            .map_err(|e| format!("解析 request JSON 失败: {e}"))?;
+impl HookScope {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            HookScope::Global => "global",
+            HookScope::App => "app",
+            HookScope::Provider => "provider",
+        }
+    }
+}
 
-        let result_js: rquickjs::Value = on_request
-            .call((context_js, request_js))
-            .map_err(|e| format!("执行 onRequest 失败: {e}"))?;
+/// 带作用范围的请求重写脚本
+#[derive(Debug, Clone)]
+pub(crate) struct ScopedHookScript {
+    pub scope: HookScope,
+    pub script: RequestHookScript,
+}
 
-        let result_json = ctx
-            .json_stringify(result_js)
-            .map_err(|e| format!("序列化 onRequest 返回值失败: {e}"))?;
+/// 脚本是否需要执行（已启用、非空且为 javascript）
+fn is_hook_active(script: &RequestHookScript, tag: &str) -> bool {
+    if !script.enabled || script.code.trim().is_empty() {
+        return false;
+    }
+    if !script.language.eq_ignore_ascii_case("javascript") {
+        log::warn!(
+            "[{tag}] [Hook] 不支持的脚本语言: {}（当前仅支持 javascript）",
+            script.language
+        );
+        return false;
+    }
+    true
+}
 
-        let Some(result_json) = result_json else {
-            // undefined: 视为放行（不修改）
-            return Ok(None);
-        };
+/// 读取全局与应用级脚本（供应商级脚本随故障转移变化，由 [`hook_chain`] 追加）
+pub(crate) fn load_shared_hook_scripts(db: &Database, app_type: &str) -> Vec<ScopedHookScript> {
+    [(HookScope::Global, None), (HookScope::App, Some(app_type))]
+        .into_iter()
+        .filter_map(|(scope, app)| match db.get_request_hook_script(app) {
+            Ok(script) => script.map(|script| ScopedHookScript { scope, script }),
+            Err(e) => {
+                log::warn!("[Hook] 读取{}请求重写脚本失败，已忽略: {e}", scope.as_str());
+                None
+            }
+        })
+        .collect()
+}
 
-        let result_str: String = result_json
-            .get()
-            .map_err(|e| format!("获取 onRequest 返回值字符串失败: {e}"))?;
+/// 组合本次请求的脚本链（按 onRequest 的执行顺序）
+pub(crate) fn hook_chain(
+    shared: &[ScopedHookScript],
+    provider: &Provider,
+    tag: &str,
+) -> Vec<ScopedHookScript> {
+    let provider_script = provider
+        .meta
+        .as_ref()
+        .and_then(|m| m.request_hook_script.clone())
+        .map(|script| ScopedHookScript {
+            scope: HookScope::Provider,
+            script,
+        });
+
+    shared
+        .iter()
+        .cloned()
+        .chain(provider_script)
+        .filter(|hook| is_hook_active(&hook.script, tag))
+        .collect()
+}
 
-        if result_str.trim() == "null" {
-            // null: 视为放行（不修改）
-            return Ok(None);
-        }
+/// 请求重写脚本默认执行超时（毫秒）
+const DEFAULT_HOOK_TIMEOUT_MS: u64 = 1000;
 
-        let result_value: Value = serde_json::from_str(&result_str)
-            .map_err(|e| format!("解析 onRequest 返回值 JSON 失败: {e}"))?;
+/// 请求重写脚本的资源限制（`timeout_ms` 为空时使用默认值）
+pub(crate) fn hook_script_limits(timeout_ms: Option<u64>) -> ScriptLimits {
+    let timeout_ms = timeout_ms
+        .unwrap_or(DEFAULT_HOOK_TIMEOUT_MS)
+        .clamp(10, 30_000);
+    ScriptLimits::with_timeout(Duration::from_millis(timeout_ms))
+}
 
-        let merged = merge_hook_request(&result_value, request)?;
-        Ok(Some(merged))
-    })
+pub(crate) fn execute_on_request_script(
+    script_code: &str,
+    timeout_ms: Option<u64>,
+    context: &RequestHookContext,
+    request: &HookRequest,
+) -> ScriptRun<Option<HookRequest>> {
+    let result = call_hook(script_code, timeout_ms, "onRequest", context, request);
+    ScriptRun {
+        result: result.result.and_then(|value| match value {
+            Some(value) => merge_hook_request(&value, request).map(Some),
+            None => Ok(None),
+        }),
+        logs: result.logs,
+    }
 }
 
 pub(crate) fn execute_on_response_script(
     script_code: &str,
+    timeout_ms: Option<u64>,
     context: &RequestHookContext,
     response: &HookResponse,
-) -> Result<Option<HookResponse>, String> {
-    let runtime = Runtime::new().map_err(|e| format!("创建 JS 运行时失败: {e}"))?;
-    let js_context = Context::full(&runtime).map_err(|e| format!("创建 JS 上下文失败: {e}"))?;
+) -> ScriptRun<Option<HookResponse>> {
+    let result = call_hook(script_code, timeout_ms, "onResponse", context, response);
+    ScriptRun {
+        result: result.result.and_then(|value| match value {
+            Some(value) => merge_hook_response(&value, response).map(Some),
+            None => Ok(None),
+        }),
+        logs: result.logs,
+    }
+}
 
-    js_context.with(|ctx| {
-        let config: rquickjs::Object = ctx
-            .eval(script_code)
-            .map_err(|e| format!("解析脚本失败（脚本必须 eval 成一个对象）: {e}"))?;
+/// 调用脚本对象上的钩子函数，返回值为 undefined / null 或钩子不存在时视为放行（不修改）
+fn call_hook<T: Serialize>(
+    script_code: &str,
+    timeout_ms: Option<u64>,
+    hook: &str,
+    context: &RequestHookContext,
+    view: &T,
+) -> ScriptRun<Option<Value>> {
+    let runtime = match ScriptRuntime::new(hook_script_limits(timeout_ms)) {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            return ScriptRun {
+                result: Err(e),
+                logs: Vec::new(),
+            }
+        }
+    };
+
+    let result = runtime.with(|ctx| {
+        let config: rquickjs::Object = ctx.eval(script_code).map_err(|e| {
+            format!(
+                "解析脚本失败（脚本必须 eval 成一个对象）: {}",
+                runtime.describe_error(&ctx, e)
+            )
+        })?;
 
-        let on_response: Option<Function> = config.get("onResponse").ok();
-        let Some(on_response) = on_response else {
+        let hook_fn: Option<Function> = config.get(hook).ok();
+        let Some(hook_fn) = hook_fn else {
             return Ok(None);
         };
 
         let context_json =
             serde_json::to_string(context).map_err(|e| format!("序列化 context 失败: {e}"))?;
-        let response_json =
-            serde_json::to_string(response).map_err(|e| format!("序列化 response 失败: {e}"))?;
+        let view_json =
+            serde_json::to_string(view).map_err(|e| format!("序列化 {hook} 参数失败: {e}"))?;
 
         let context_js: rquickjs::Value = ctx
             .json_parse(context_json.as_str())
             .map_err(|e| format!("解析 context JSON 失败: {e}"))?;
-        let response_js: rquickjs::Value = ctx
-            .json_parse(response_json.as_str())
-            .map_err(|e| format!("解析 response JSON 失败: {e}"))?;
+        let view_js: rquickjs::Value = ctx
+            .json_parse(view_json.as_str())
+            .map_err(|e| format!("解析 {hook} 参数 JSON 失败: {e}"))?;
 
-        let result_js: rquickjs::Value = on_response
-            .call((context_js, response_js))
-            .map_err(|e| format!("执行 onResponse 失败: {e}"))?;
+        let result_js: rquickjs::Value = hook_fn
+            .call((context_js, view_js))
+            .map_err(|e| format!("执行 {hook} 失败: {}", runtime.describe_error(&ctx, e)))?;
 
         let result_json = ctx
             .json_stringify(result_js)
-            .map_err(|e| format!("序列化 onResponse 返回值失败: {e}"))?;
+            .map_err(|e| format!("序列化 {hook} 返回值失败: {e}"))?;
 
         let Some(result_json) = result_json else {
+            // undefined: 视为放行（不修改）
             return Ok(None);
         };
 
         let result_str: String = result_json
             .get()
-            .map_err(|e| format!("获取 onResponse 返回值字符串失败: {e}"))?;
+            .map_err(|e| format!("获取 {hook} 返回值字符串失败: {e}"))?;
 
         if result_str.trim() == "null" {
+            // null: 视为放行（不修改）
             return Ok(None);
         }
 
-        let result_value: Value = serde_json::from_str(&result_str)
-            .map_err(|e| format!("解析 onResponse 返回值 JSON 失败: {e}"))?;
+        serde_json::from_str(&result_str)
+            .map(Some)
+            .map_err(|e| format!("解析 {hook} 返回值 JSON 失败: {e}"))
+    });
 
-        let merged = merge_hook_response(&result_value, response)?;
-        Ok(Some(merged))
-    })
+    ScriptRun {
+        result,
+        logs: runtime.take_logs(),
+    }
 }
 
 fn merge_hook_request(result: &Value, original: &HookRequest) -> Result<HookRequest, String> {
@@ -349,7 +435,8 @@ mod tests {
             queries: HashMap::new(),
             body: json!({"model":"gpt-4.1"}),
         };
-        let out = execute_on_request_script(script, &ctx, &req)
+        let out = execute_on_request_script(script, None, &ctx, &req)
+            .result
             .unwrap()
             .unwrap();
         assert!(!out.headers.contains_key("x-codex-turn-metadata"));
@@ -384,7 +471,9 @@ mod tests {
             queries: HashMap::new(),
             body: json!({"ok":true}),
         };
-        let out = execute_on_request_script(script, &ctx, &req).unwrap();
+        let out = execute_on_request_script(script, None, &ctx, &req)
+            .result
+            .unwrap();
         assert!(out.is_none());
     }
 
@@ -419,7 +508,8 @@ mod tests {
             ]),
             body: json!({"ok":true}),
         };
-        let out = execute_on_request_script(script, &ctx, &req)
+        let out = execute_on_request_script(script, None, &ctx, &req)
+            .result
             .unwrap()
             .unwrap();
         assert_eq!(out.queries.get("foo").unwrap(), "bar");
@@ -427,6 +517,39 @@ mod tests {
         assert!(!out.queries.contains_key("remove_me"));
     }
 
+    #[test]
+    fn on_request_timeout_is_enforced_and_console_captured() {
+        let script = r#"
+({
+  onRequest: function(context, request) {
+    console.log("start", request.body.model);
+    while (true) {}
+  }
+})
+"#;
+        let ctx = RequestHookContext {
+            app: "codex".to_string(),
+            method: "POST".to_string(),
+            path: "/v1/responses".to_string(),
+            endpoint: "/v1/responses".to_string(),
+            url: "https://api.openai.com/v1/responses".to_string(),
+            provider: RequestHookProviderInfo {
+                id: "p1".to_string(),
+                name: "Provider".to_string(),
+            },
+            incoming_headers: HashMap::new(),
+        };
+        let req = HookRequest {
+            headers: HashMap::new(),
+            queries: HashMap::new(),
+            body: json!({"model":"gpt-4.1"}),
+        };
+        let run = execute_on_request_script(script, Some(50), &ctx, &req);
+        let err = run.result.unwrap_err();
+        assert!(err.contains("超时"), "{err}");
+        assert_eq!(run.logs, vec!["start gpt-4.1"]);
+    }
+
     #[test]
     fn on_response_can_modify_status_headers_and_body() {
         let script = r#"
@@ -456,7 +579,8 @@ mod tests {
             headers: HashMap::from([("content-type".to_string(), "application/json".to_string())]),
             body: json!({"ok":true}),
         };
-        let out = execute_on_response_script(script, &ctx, &resp)
+        let out = execute_on_response_script(script, None, &ctx, &resp)
+            .result
             .unwrap()
             .unwrap();
         assert_eq!(out.code, 404);
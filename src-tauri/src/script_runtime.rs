@@ -0,0 +1,466 @@
+//! 用户 JS 脚本的共享运行时
+//!
+//! 用量查询脚本与请求重写脚本都通过这里创建 QuickJS 运行时，统一提供：
+//! - 执行超时（中断处理器）与内存上限
+//! - `console.log` 等输出捕获（测试命令会返回给前端）
+//! - 可选的 `fetch`：仅允许访问通过策略校验的 URL，并限制单次执行的请求数
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rquickjs::function::{Opt, Rest};
+use rquickjs::{Context, Ctx, Exception, Function, Object, Runtime, Value};
+
+/// 默认内存上限
+pub const DEFAULT_MEMORY_LIMIT: usize = 32 * 1024 * 1024;
+
+/// JS 调用栈上限
+const MAX_STACK_SIZE: usize = 1024 * 1024;
+
+/// 捕获的控制台输出行数上限
+const MAX_LOG_LINES: usize = 200;
+
+/// 单行控制台输出长度上限（字符）
+const MAX_LOG_LINE_CHARS: usize = 2000;
+
+/// 脚本资源限制
+#[derive(Debug, Clone, Copy)]
+pub struct ScriptLimits {
+    pub timeout: Duration,
+    pub memory_limit: usize,
+}
+
+impl ScriptLimits {
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            memory_limit: DEFAULT_MEMORY_LIMIT,
+        }
+    }
+}
+
+/// 脚本执行结果，无论成功与否都附带控制台输出
+#[derive(Debug)]
+pub struct ScriptRun<T, E = String> {
+    pub result: Result<T, E>,
+    pub logs: Vec<String>,
+}
+
+impl<T, E> ScriptRun<T, E> {
+    /// 将控制台输出写入应用日志（线上执行时使用）
+    pub fn log_console(&self, prefix: &str) {
+        for line in &self.logs {
+            log::debug!("{prefix} console: {line}");
+        }
+    }
+}
+
+/// URL 校验函数（返回错误信息即拒绝）
+pub type UrlPolicy = Arc<dyn Fn(&str) -> Result<(), String> + Send + Sync>;
+
+/// `fetch` 的访问策略
+#[derive(Clone)]
+pub struct FetchPolicy {
+    pub allow: UrlPolicy,
+    /// 单次执行允许的最大请求数
+    pub max_requests: usize,
+    /// 单个请求的超时（同时受脚本剩余执行时间约束）
+    pub request_timeout: Duration,
+}
+
+/// `fetch` 在 JS 侧的包装：返回 Promise 与类 Response 对象
+const FETCH_PRELUDE: &str = r#"
+globalThis.fetch = async function (url, init) {
+  const raw = JSON.parse(__ccs_fetch(String(url), JSON.stringify(init || {})));
+  return {
+    ok: raw.status >= 200 && raw.status < 300,
+    status: raw.status,
+    headers: raw.headers,
+    text: async () => raw.body,
+    json: async () => JSON.parse(raw.body),
+  };
+};
+"#;
+
+/// 带资源限制的 QuickJS 运行时
+pub struct ScriptRuntime {
+    // 字段顺序保证 Context 先于 Runtime 释放
+    context: Context,
+    _runtime: Runtime,
+    logs: Rc<RefCell<Vec<String>>>,
+    deadline: Rc<Cell<Instant>>,
+    timeout: Cell<Duration>,
+    memory_limit: usize,
+}
+
+impl ScriptRuntime {
+    /// 创建运行时并注册 `console`，超时从创建时开始计算
+    pub fn new(limits: ScriptLimits) -> Result<Self, String> {
+        let runtime = Runtime::new().map_err(|e| format!("创建 JS 运行时失败: {e}"))?;
+        runtime.set_memory_limit(limits.memory_limit);
+        runtime.set_max_stack_size(MAX_STACK_SIZE);
+
+        let deadline = Rc::new(Cell::new(Instant::now() + limits.timeout));
+        let interrupt_deadline = deadline.clone();
+        runtime.set_interrupt_handler(Some(Box::new(move || {
+            Instant::now() >= interrupt_deadline.get()
+        })));
+
+        let context = Context::full(&runtime).map_err(|e| format!("创建 JS 上下文失败: {e}"))?;
+        let logs = Rc::new(RefCell::new(Vec::new()));
+
+        context
+            .with(|ctx| install_console(&ctx, logs.clone()))
+            .map_err(|e| format!("初始化 console 失败: {e}"))?;
+
+        Ok(Self {
+            context,
+            _runtime: runtime,
+            logs,
+            deadline,
+            timeout: Cell::new(limits.timeout),
+            memory_limit: limits.memory_limit,
+        })
+    }
+
+    /// 启用 `fetch`（请求在当前线程同步发出，调用方需处于阻塞线程中）
+    pub fn enable_fetch(&self, policy: FetchPolicy) -> Result<(), String> {
+        let client = fetch_client(policy.allow.clone())?;
+        let deadline = self.deadline.clone();
+        self.context
+            .with(|ctx| {
+                let count = Rc::new(RefCell::new(0usize));
+                let fetch = Function::new(
+                    ctx.clone(),
+                    move |ctx: Ctx<'_>,
+                          url: String,
+                          init: Opt<String>|
+                          -> rquickjs::Result<String> {
+                        {
+                            let mut count = count.borrow_mut();
+                            if *count >= policy.max_requests {
+                                return Err(Exception::throw_message(
+                                    &ctx,
+                                    &format!("fetch 请求数超过上限 {}", policy.max_requests),
+                                ));
+                            }
+                            *count += 1;
+                        }
+                        (policy.allow)(&url).map_err(|e| Exception::throw_message(&ctx, &e))?;
+
+                        let remaining = deadline.get().saturating_duration_since(Instant::now());
+                        if remaining.is_zero() {
+                            return Err(Exception::throw_message(&ctx, "脚本执行超时"));
+                        }
+                        let timeout = policy.request_timeout.min(remaining);
+                        let init = init.0.unwrap_or_default();
+                        tauri::async_runtime::block_on(send_fetch(&client, &url, &init, timeout))
+                            .map_err(|e| Exception::throw_message(&ctx, &e))
+                    },
+                )?;
+                ctx.globals().set("__ccs_fetch", fetch)?;
+                ctx.eval::<(), _>(FETCH_PRELUDE)
+            })
+            .map_err(|e| format!("初始化 fetch 失败: {e}"))
+    }
+
+    /// 重新开始计时（同一运行时多次调用脚本时，为每次调用单独限时）
+    pub fn reset_deadline(&self, timeout: Duration) {
+        self.timeout.set(timeout);
+        self.deadline.set(Instant::now() + timeout);
+    }
+
+    /// 在脚本上下文中执行闭包
+    pub fn with<F, R>(&self, f: F) -> R
+    where
+        F: for<'js> FnOnce(Ctx<'js>) -> R,
+    {
+        self.context.with(f)
+    }
+
+    /// 将 rquickjs 错误转换为可读信息（包含 JS 异常消息，超时与内存不足单独提示）
+    pub fn describe_error(&self, ctx: &Ctx<'_>, error: rquickjs::Error) -> String {
+        if Instant::now() >= self.deadline.get() {
+            return format!("脚本执行超时（{} ms）", self.timeout.get().as_millis());
+        }
+        let message = match error {
+            rquickjs::Error::Exception => {
+                let thrown = ctx.catch();
+                match thrown.as_exception() {
+                    Some(exception) => exception.message().unwrap_or_default(),
+                    None => stringify(ctx, thrown),
+                }
+            }
+            other => other.to_string(),
+        };
+        if message.contains("out of memory") {
+            format!("脚本内存超出上限（{} MB）", self.memory_limit / 1024 / 1024)
+        } else {
+            message
+        }
+    }
+
+    /// 若值为 Promise，驱动任务队列直到完成
+    pub fn resolve<'js>(&self, ctx: &Ctx<'js>, value: Value<'js>) -> Result<Value<'js>, String> {
+        match value.as_promise() {
+            Some(promise) => promise
+                .finish::<Value>()
+                .map_err(|e| self.describe_error(ctx, e)),
+            None => Ok(value),
+        }
+    }
+
+    /// 取出已捕获的控制台输出
+    pub fn take_logs(&self) -> Vec<String> {
+        std::mem::take(&mut self.logs.borrow_mut())
+    }
+}
+
+fn install_console<'js>(ctx: &Ctx<'js>, logs: Rc<RefCell<Vec<String>>>) -> rquickjs::Result<()> {
+    let console = Object::new(ctx.clone())?;
+    for level in ["log", "info", "warn", "error", "debug"] {
+        let logs = logs.clone();
+        let func = Function::new(ctx.clone(), move |args: Rest<Value<'_>>| {
+            let mut logs = logs.borrow_mut();
+            if logs.len() >= MAX_LOG_LINES {
+                return;
+            }
+            let line = args
+                .0
+                .into_iter()
+                .map(|arg| {
+                    let ctx = arg.ctx().clone();
+                    stringify(&ctx, arg)
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            let line: String = line.chars().take(MAX_LOG_LINE_CHARS).collect();
+            logs.push(if level == "log" {
+                line
+            } else {
+                format!("[{level}] {line}")
+            });
+        })?;
+        console.set(level, func)?;
+    }
+    ctx.globals().set("console", console)
+}
+
+/// 字符串原样输出，其余值按 JSON 序列化（无法序列化时退回 String()）
+fn stringify<'js>(ctx: &Ctx<'js>, value: Value<'js>) -> String {
+    if let Some(s) = value.as_string() {
+        return s.to_string().unwrap_or_default();
+    }
+    if let Some(exception) = value.as_exception() {
+        return exception.message().unwrap_or_default();
+    }
+    match ctx.json_stringify(value.clone()) {
+        Ok(Some(json)) => json.to_string().unwrap_or_default(),
+        _ => ctx
+            .globals()
+            .get::<_, Function>("String")
+            .and_then(|f| f.call::<_, String>((value,)))
+            .unwrap_or_default(),
+    }
+}
+
+/// `fetch` 的 init 参数（兼容浏览器 fetch 的常用字段）
+#[derive(Debug, Default, serde::Deserialize)]
+struct FetchInit {
+    #[serde(default)]
+    method: Option<String>,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    #[serde(default)]
+    body: Option<String>,
+}
+
+/// 单个 fetch 请求允许跟随的最大重定向次数
+const MAX_FETCH_REDIRECTS: usize = 10;
+
+/// 构建 `fetch` 专用客户端：每一跳重定向目标都需通过策略校验
+fn fetch_client(allow: UrlPolicy) -> Result<reqwest::Client, String> {
+    let redirect = reqwest::redirect::Policy::custom(move |attempt| {
+        if attempt.previous().len() >= MAX_FETCH_REDIRECTS {
+            return attempt.error(format!("重定向次数超过上限 {MAX_FETCH_REDIRECTS}"));
+        }
+        match allow(attempt.url().as_str()) {
+            Ok(()) => attempt.follow(),
+            Err(e) => attempt.error(format!("重定向被拒绝: {e}")),
+        }
+    });
+    crate::proxy::http_client::build_with_redirect_policy(redirect)
+}
+
+async fn send_fetch(
+    client: &reqwest::Client,
+    url: &str,
+    init: &str,
+    timeout: Duration,
+) -> Result<String, String> {
+    let init: FetchInit = if init.trim().is_empty() {
+        FetchInit::default()
+    } else {
+        serde_json::from_str(init).map_err(|e| format!("fetch 参数无效: {e}"))?
+    };
+    let method: reqwest::Method = init
+        .method
+        .as_deref()
+        .unwrap_or("GET")
+        .to_ascii_uppercase()
+        .parse()
+        .map_err(|_| format!("不支持的 HTTP 方法: {:?}", init.method))?;
+
+    let mut req = client.request(method, url).timeout(timeout);
+    for (k, v) in &init.headers {
+        req = req.header(k, v);
+    }
+    if let Some(body) = init.body {
+        req = req.body(body);
+    }
+
+    let resp = req.send().await.map_err(|e| {
+        // 重定向被策略拒绝时，reqwest 的错误信息不含具体原因，取其来源
+        match std::error::Error::source(&e) {
+            Some(source) if e.is_redirect() => format!("请求失败: {source}"),
+            _ => format!("请求失败: {e}"),
+        }
+    })?;
+    let status = resp.status().as_u16();
+    let headers: HashMap<String, String> = resp
+        .headers()
+        .iter()
+        .filter_map(|(k, v)| Some((k.as_str().to_string(), v.to_str().ok()?.to_string())))
+        .collect();
+    let body = resp
+        .text()
+        .await
+        .map_err(|e| format!("读取响应失败: {e}"))?;
+
+    serde_json::to_string(&serde_json::json!({
+        "status": status,
+        "headers": headers,
+        "body": body,
+    }))
+    .map_err(|e| format!("序列化响应失败: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runtime(timeout_ms: u64) -> ScriptRuntime {
+        ScriptRuntime::new(ScriptLimits::with_timeout(Duration::from_millis(
+            timeout_ms,
+        )))
+        .expect("create runtime")
+    }
+
+    #[test]
+    fn console_output_is_captured() {
+        let rt = runtime(1000);
+        rt.with(|ctx| {
+            ctx.eval::<(), _>(r#"console.log("hello", { a: 1 }); console.warn(42);"#)
+                .expect("eval");
+        });
+        assert_eq!(rt.take_logs(), vec!["hello {\"a\":1}", "[warn] 42"]);
+    }
+
+    #[test]
+    fn infinite_loop_is_interrupted() {
+        let rt = runtime(50);
+        let err = rt.with(|ctx| {
+            let err = ctx
+                .eval::<(), _>("while (true) {}")
+                .expect_err("must time out");
+            rt.describe_error(&ctx, err)
+        });
+        assert!(err.contains("超时"), "{err}");
+    }
+
+    #[test]
+    fn memory_limit_is_enforced() {
+        let rt = ScriptRuntime::new(ScriptLimits {
+            timeout: Duration::from_secs(5),
+            memory_limit: 4 * 1024 * 1024,
+        })
+        .expect("create runtime");
+        let err = rt.with(|ctx| {
+            let err = ctx
+                .eval::<(), _>("const a = []; while (true) { a.push('x'.repeat(1024)); }")
+                .expect_err("must run out of memory");
+            rt.describe_error(&ctx, err)
+        });
+        assert!(err.contains("内存"), "{err}");
+    }
+
+    #[test]
+    fn fetch_rejects_urls_outside_policy() {
+        let rt = runtime(1000);
+        rt.enable_fetch(FetchPolicy {
+            allow: Arc::new(|url| {
+                if url.starts_with("https://api.example.com/") {
+                    Ok(())
+                } else {
+                    Err(format!("不允许访问 {url}"))
+                }
+            }),
+            max_requests: 1,
+            request_timeout: Duration::from_secs(1),
+        })
+        .expect("enable fetch");
+
+        let err = rt.with(|ctx| {
+            let value = ctx
+                .eval::<Value, _>("fetch('https://evil.example.net/')")
+                .expect("eval");
+            rt.resolve(&ctx, value).expect_err("must be rejected")
+        });
+        assert!(err.contains("不允许访问"), "{err}");
+    }
+
+    #[test]
+    fn fetch_rejects_redirects_outside_policy() {
+        use std::io::{Read, Write};
+
+        // 允许访问的本地地址返回 302，跳转到策略之外的主机
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+        let origin = format!("http://{}/", listener.local_addr().expect("addr"));
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("accept");
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf);
+            let _ = stream.write_all(
+                b"HTTP/1.1 302 Found\r\nLocation: http://169.254.169.254/latest/\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            );
+        });
+
+        let rt = runtime(5000);
+        let allowed = origin.clone();
+        rt.enable_fetch(FetchPolicy {
+            allow: Arc::new(move |url| {
+                if url.starts_with(&allowed) {
+                    Ok(())
+                } else {
+                    Err(format!("不允许访问 {url}"))
+                }
+            }),
+            max_requests: 1,
+            request_timeout: Duration::from_secs(5),
+        })
+        .expect("enable fetch");
+
+        let err = rt.with(|ctx| {
+            let value = ctx
+                .eval::<Value, _>(format!("fetch('{origin}')"))
+                .expect("eval");
+            rt.resolve(&ctx, value).expect_err("must be rejected")
+        });
+        server.join().expect("server thread");
+        assert!(err.contains("重定向被拒绝"), "{err}");
+        assert!(err.contains("169.254.169.254"), "{err}");
+    }
+}
@@ -0,0 +1,518 @@
+//! Live 配置漂移检测
+//!
+//! 用户或 CLI 自身可能绕过 cc-switch 直接修改 Live 配置文件。
+//! 此模块将 Live 配置与当前供应商存储的 `settings_config`（以及代理接管状态）对比，
+//! 生成结构化差异，并支持"采纳到供应商"、"还原为存储配置"或"忽略"。
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+
+use crate::app_config::AppType;
+use crate::error::AppError;
+use crate::provider::{OpenCodeProviderConfig, Provider};
+use crate::services::provider::{
+    detect_gemini_auth_type, import_opencode_providers_from_live, read_live_settings,
+    remove_opencode_provider_from_live, sanitize_claude_settings_for_live, write_live_snapshot,
+    GeminiAuthType,
+};
+use crate::services::McpService;
+use crate::store::AppState;
+
+/// 被忽略漂移的 Live 指纹（settings 表 key 前缀）
+const IGNORED_FINGERPRINT_KEY_PREFIX: &str = "config_drift_ignored_";
+
+/// 差异类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DriftChangeKind {
+    /// Live 中新增的字段
+    Added,
+    /// Live 中被删除的字段
+    Removed,
+    /// 值被修改的字段
+    Changed,
+}
+
+/// 单个字段的差异
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DriftChange {
+    /// 字段路径（以 `.` 分隔，如 `env.ANTHROPIC_BASE_URL`）
+    pub path: String,
+    pub kind: DriftChangeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stored: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub live: Option<Value>,
+}
+
+/// 某个应用的配置漂移
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigDrift {
+    #[serde(rename = "appType")]
+    pub app_type: String,
+    /// 对比的当前供应商（OpenCode 为累加模式，无当前供应商）
+    #[serde(rename = "providerId", skip_serializing_if = "Option::is_none")]
+    pub provider_id: Option<String>,
+    #[serde(rename = "providerName", skip_serializing_if = "Option::is_none")]
+    pub provider_name: Option<String>,
+    /// 代理接管期间 Live 配置被改写，接管占位符已丢失
+    #[serde(rename = "takeoverLost")]
+    pub takeover_lost: bool,
+    pub changes: Vec<DriftChange>,
+    /// Live 配置指纹，用于忽略与去重
+    pub fingerprint: String,
+    #[serde(rename = "detectedAt")]
+    pub detected_at: i64,
+}
+
+/// 漂移处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DriftResolution {
+    /// 将 Live 配置采纳到供应商
+    Adopt,
+    /// 用存储的供应商配置覆盖 Live
+    Revert,
+    /// 忽略本次修改（Live 再次变化后重新提示）
+    Ignore,
+}
+
+/// Live 配置漂移相关业务
+pub struct ConfigDriftService;
+
+impl ConfigDriftService {
+    /// 检测应用的配置漂移，已忽略的漂移返回 `None`
+    pub async fn detect(
+        state: &AppState,
+        app_type: &AppType,
+    ) -> Result<Option<ConfigDrift>, AppError> {
+        let Some(drift) = Self::detect_unfiltered(state, app_type).await? else {
+            return Ok(None);
+        };
+
+        let ignored = state.db.get_setting(&Self::ignored_key(app_type))?;
+        if ignored.as_deref() == Some(drift.fingerprint.as_str()) {
+            return Ok(None);
+        }
+        Ok(Some(drift))
+    }
+
+    /// 处理漂移
+    pub async fn resolve(
+        state: &AppState,
+        app_type: &AppType,
+        resolution: DriftResolution,
+    ) -> Result<(), AppError> {
+        let Some(drift) = Self::detect_unfiltered(state, app_type).await? else {
+            return Ok(());
+        };
+
+        match resolution {
+            DriftResolution::Ignore => {
+                state
+                    .db
+                    .set_setting(&Self::ignored_key(app_type), &drift.fingerprint)?;
+                log::info!("已忽略 {} 的 Live 配置漂移", app_type.as_str());
+                return Ok(());
+            }
+            DriftResolution::Adopt => Self::adopt(state, app_type, &drift).await?,
+            DriftResolution::Revert => Self::revert(state, app_type, &drift).await?,
+        }
+
+        state.db.set_setting(&Self::ignored_key(app_type), "")?;
+        log::info!(
+            "已处理 {} 的 Live 配置漂移: {resolution:?}",
+            app_type.as_str()
+        );
+        Ok(())
+    }
+
+    fn ignored_key(app_type: &AppType) -> String {
+        format!("{IGNORED_FINGERPRINT_KEY_PREFIX}{}", app_type.as_str())
+    }
+
+    async fn detect_unfiltered(
+        state: &AppState,
+        app_type: &AppType,
+    ) -> Result<Option<ConfigDrift>, AppError> {
+        if app_type.is_additive_mode() {
+            return Self::detect_opencode(state);
+        }
+
+        let Some(provider_id) =
+            crate::settings::get_effective_current_provider(&state.db, app_type)?
+        else {
+            return Ok(None);
+        };
+        let Some(provider) = state
+            .db
+            .get_provider_by_id(&provider_id, app_type.as_str())?
+        else {
+            return Ok(None);
+        };
+        // Live 文件不存在时没有可对比的内容
+        let Ok(live) = read_live_settings(app_type.clone()) else {
+            return Ok(None);
+        };
+
+        let takeover = state
+            .db
+            .get_proxy_config_for_app(app_type.as_str())
+            .await?
+            .enabled;
+        if takeover {
+            // 接管期间 Live 指向本地代理，只需确认接管占位符仍在
+            if state
+                .proxy_service
+                .detect_takeover_in_live_config_for_app(app_type)
+            {
+                return Ok(None);
+            }
+            return Ok(Some(ConfigDrift {
+                app_type: app_type.as_str().to_string(),
+                provider_id: Some(provider.id.clone()),
+                provider_name: Some(provider.name.clone()),
+                takeover_lost: true,
+                changes: Vec::new(),
+                fingerprint: fingerprint(&live),
+                detected_at: chrono::Utc::now().timestamp(),
+            }));
+        }
+
+        let (stored_view, live_view) = comparable_views(app_type, &provider, &live);
+        let mut changes = Vec::new();
+        diff_values("", Some(&stored_view), Some(&live_view), &mut changes);
+        if changes.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(ConfigDrift {
+            app_type: app_type.as_str().to_string(),
+            provider_id: Some(provider.id.clone()),
+            provider_name: Some(provider.name.clone()),
+            takeover_lost: false,
+            changes,
+            fingerprint: fingerprint(&live_view),
+            detected_at: chrono::Utc::now().timestamp(),
+        }))
+    }
+
+    /// OpenCode 为累加模式：逐个对比数据库中的供应商与 `opencode.json` 中的同名条目
+    fn detect_opencode(state: &AppState) -> Result<Option<ConfigDrift>, AppError> {
+        if !crate::opencode_config::get_opencode_config_path().exists() {
+            return Ok(None);
+        }
+        let live_providers = crate::opencode_config::get_providers()?;
+        let providers = state.db.get_all_providers(AppType::OpenCode.as_str())?;
+
+        let mut changes = Vec::new();
+        for (id, provider) in &providers {
+            let stored = normalize_opencode_fragment(opencode_fragment(provider));
+            let live = live_providers
+                .get(id)
+                .cloned()
+                .map(normalize_opencode_fragment);
+            diff_values(
+                &format!("provider.{id}"),
+                Some(&stored),
+                live.as_ref(),
+                &mut changes,
+            );
+        }
+        for (id, live) in &live_providers {
+            if !providers.contains_key(id) {
+                diff_values(&format!("provider.{id}"), None, Some(live), &mut changes);
+            }
+        }
+
+        if changes.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(ConfigDrift {
+            app_type: AppType::OpenCode.as_str().to_string(),
+            provider_id: None,
+            provider_name: None,
+            takeover_lost: false,
+            changes,
+            fingerprint: fingerprint(&Value::Object(live_providers)),
+            detected_at: chrono::Utc::now().timestamp(),
+        }))
+    }
+
+    async fn adopt(
+        state: &AppState,
+        app_type: &AppType,
+        drift: &ConfigDrift,
+    ) -> Result<(), AppError> {
+        if drift.takeover_lost {
+            // 将改写后的 Live 作为新的备份，再重新接管
+            return state
+                .proxy_service
+                .retake_live_config_for_app(app_type, true)
+                .await
+                .map_err(AppError::Message);
+        }
+
+        if app_type.is_additive_mode() {
+            // 已存在的供应商用 Live 条目覆盖，新增条目导入为供应商；
+            // Live 中被删除的条目不会删除数据库中的供应商
+            let live_providers = crate::opencode_config::get_providers()?;
+            for (id, mut provider) in state.db.get_all_providers(app_type.as_str())? {
+                if let Some(live) = live_providers.get(&id) {
+                    if normalize_opencode_fragment(opencode_fragment(&provider))
+                        != normalize_opencode_fragment(live.clone())
+                    {
+                        provider.settings_config = live.clone();
+                        state.db.save_provider(app_type.as_str(), &provider)?;
+                    }
+                }
+            }
+            import_opencode_providers_from_live(state)?;
+            return Ok(());
+        }
+
+        let Some(provider_id) = drift.provider_id.as_deref() else {
+            return Ok(());
+        };
+        let Some(mut provider) = state
+            .db
+            .get_provider_by_id(provider_id, app_type.as_str())?
+        else {
+            return Err(AppError::Message(format!("供应商 {provider_id} 不存在")));
+        };
+        provider.settings_config = read_live_settings(app_type.clone())?;
+        state.db.save_provider(app_type.as_str(), &provider)?;
+        state
+            .proxy_service
+            .invalidate_provider_cache(app_type.as_str())
+            .await;
+        Ok(())
+    }
+
+    async fn revert(
+        state: &AppState,
+        app_type: &AppType,
+        drift: &ConfigDrift,
+    ) -> Result<(), AppError> {
+        if drift.takeover_lost {
+            return state
+                .proxy_service
+                .retake_live_config_for_app(app_type, false)
+                .await
+                .map_err(AppError::Message);
+        }
+
+        if app_type.is_additive_mode() {
+            let providers = state.db.get_all_providers(app_type.as_str())?;
+            for provider in providers.values() {
+                write_live_snapshot(app_type, provider)?;
+            }
+            for id in crate::opencode_config::get_providers()?.keys() {
+                if !providers.contains_key(id) {
+                    remove_opencode_provider_from_live(id)?;
+                }
+            }
+            return Ok(());
+        }
+
+        let Some(provider_id) = drift.provider_id.as_deref() else {
+            return Ok(());
+        };
+        let Some(provider) = state
+            .db
+            .get_provider_by_id(provider_id, app_type.as_str())?
+        else {
+            return Err(AppError::Message(format!("供应商 {provider_id} 不存在")));
+        };
+        write_live_snapshot(app_type, &provider)?;
+        // Codex/Gemini 的 MCP 配置与供应商配置同文件，覆盖后需重新同步
+        McpService::sync_all_enabled(state)?;
+        Ok(())
+    }
+}
+
+/// 构造可直接对比的存储配置与 Live 配置
+///
+/// 只保留 cc-switch 会写入的部分：Codex 忽略 `mcp_servers`，
+/// Gemini 只对比 `.env` 与供应商配置中声明的 settings.json 字段。
+fn comparable_views(app_type: &AppType, provider: &Provider, live: &Value) -> (Value, Value) {
+    let stored = &provider.settings_config;
+    match app_type {
+        AppType::Claude => (sanitize_claude_settings_for_live(stored), live.clone()),
+        AppType::Codex => (codex_view(stored), codex_view(live)),
+        AppType::Gemini => {
+            let stored_env = match detect_gemini_auth_type(provider) {
+                // Google 官方使用 OAuth，写入 Live 时会清空 .env
+                GeminiAuthType::GoogleOfficial => json!({}),
+                _ => stored.get("env").cloned().unwrap_or_else(|| json!({})),
+            };
+            let live_env = live.get("env").cloned().unwrap_or_else(|| json!({}));
+
+            let mut stored_config = Map::new();
+            let mut live_config = Map::new();
+            if let Some(config) = stored.get("config").and_then(|v| v.as_object()) {
+                for (key, value) in config {
+                    stored_config.insert(key.clone(), value.clone());
+                    if let Some(live_value) = live.get("config").and_then(|c| c.get(key)) {
+                        live_config.insert(key.clone(), live_value.clone());
+                    }
+                }
+            }
+
+            (
+                json!({ "env": stored_env, "config": stored_config }),
+                json!({ "env": live_env, "config": live_config }),
+            )
+        }
+        AppType::OpenCode => (stored.clone(), live.clone()),
+    }
+}
+
+/// Codex 配置视图：auth 原样保留，config.toml 解析为结构化数据并去掉 MCP 段
+fn codex_view(settings: &Value) -> Value {
+    let auth = settings.get("auth").cloned().unwrap_or_else(|| json!({}));
+    let config_text = settings
+        .get("config")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    let config = match toml::from_str::<toml::Table>(config_text) {
+        Ok(mut table) => {
+            table.remove("mcp_servers");
+            serde_json::to_value(table).unwrap_or_else(|_| json!(config_text))
+        }
+        // 无法解析时按原文对比
+        Err(_) => json!(config_text),
+    };
+    json!({ "auth": auth, "config": config })
+}
+
+/// 提取 OpenCode 供应商片段（兼容存储了完整配置结构的旧数据）
+fn opencode_fragment(provider: &Provider) -> Value {
+    let settings = &provider.settings_config;
+    match settings.as_object() {
+        Some(obj) if obj.contains_key("$schema") || obj.contains_key("provider") => obj
+            .get("provider")
+            .and_then(|p| p.get(&provider.id))
+            .cloned()
+            .unwrap_or_else(|| settings.clone()),
+        _ => settings.clone(),
+    }
+}
+
+/// 经 `OpenCodeProviderConfig` 往返序列化，抹平字段顺序与默认值差异
+fn normalize_opencode_fragment(value: Value) -> Value {
+    serde_json::from_value::<OpenCodeProviderConfig>(value.clone())
+        .ok()
+        .and_then(|config| serde_json::to_value(config).ok())
+        .unwrap_or(value)
+}
+
+fn fingerprint(value: &Value) -> String {
+    let serialized = serde_json::to_string(value).unwrap_or_default();
+    format!("{:x}", Sha256::digest(serialized.as_bytes()))
+}
+
+/// 递归对比两个 JSON 值，对象逐字段展开，其他类型整体比较
+fn diff_values(
+    path: &str,
+    stored: Option<&Value>,
+    live: Option<&Value>,
+    out: &mut Vec<DriftChange>,
+) {
+    let child_path = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{path}.{key}")
+        }
+    };
+
+    match (stored, live) {
+        (Some(Value::Object(stored_obj)), Some(Value::Object(live_obj))) => {
+            for (key, stored_value) in stored_obj {
+                diff_values(&child_path(key), Some(stored_value), live_obj.get(key), out);
+            }
+            for (key, live_value) in live_obj {
+                if !stored_obj.contains_key(key) {
+                    diff_values(&child_path(key), None, Some(live_value), out);
+                }
+            }
+        }
+        (Some(stored_value), Some(live_value)) => {
+            if stored_value != live_value {
+                out.push(DriftChange {
+                    path: path.to_string(),
+                    kind: DriftChangeKind::Changed,
+                    stored: Some(stored_value.clone()),
+                    live: Some(live_value.clone()),
+                });
+            }
+        }
+        (Some(stored_value), None) => out.push(DriftChange {
+            path: path.to_string(),
+            kind: DriftChangeKind::Removed,
+            stored: Some(stored_value.clone()),
+            live: None,
+        }),
+        (None, Some(live_value)) => out.push(DriftChange {
+            path: path.to_string(),
+            kind: DriftChangeKind::Added,
+            stored: None,
+            live: Some(live_value.clone()),
+        }),
+        (None, None) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_reports_nested_changes_by_path() {
+        let stored = json!({
+            "env": { "ANTHROPIC_BASE_URL": "https://a", "ANTHROPIC_AUTH_TOKEN": "sk-1" },
+            "model": "opus"
+        });
+        let live = json!({
+            "env": { "ANTHROPIC_BASE_URL": "https://b", "ANTHROPIC_AUTH_TOKEN": "sk-1" },
+            "permissions": { "allow": [] }
+        });
+
+        let mut changes = Vec::new();
+        diff_values("", Some(&stored), Some(&live), &mut changes);
+
+        let summary: Vec<(&str, DriftChangeKind)> =
+            changes.iter().map(|c| (c.path.as_str(), c.kind)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("env.ANTHROPIC_BASE_URL", DriftChangeKind::Changed),
+                ("model", DriftChangeKind::Removed),
+                ("permissions", DriftChangeKind::Added),
+            ]
+        );
+    }
+
+    #[test]
+    fn codex_view_ignores_mcp_servers_and_formatting() {
+        let stored = json!({
+            "auth": { "OPENAI_API_KEY": "sk" },
+            "config": "model = \"gpt-5\"\nmodel_provider = \"p\"\n"
+        });
+        let live = json!({
+            "auth": { "OPENAI_API_KEY": "sk" },
+            "config": "model_provider = \"p\"\nmodel = \"gpt-5\"\n\n[mcp_servers.fs]\ncommand = \"npx\"\n"
+        });
+
+        let mut changes = Vec::new();
+        diff_values(
+            "",
+            Some(&codex_view(&stored)),
+            Some(&codex_view(&live)),
+            &mut changes,
+        );
+        assert!(changes.is_empty(), "unexpected drift: {changes:?}");
+    }
+}
@@ -1,17 +1,26 @@
 pub mod config;
+pub mod config_drift;
 pub mod env_checker;
 pub mod env_manager;
 pub mod mcp;
+pub mod profile;
+pub mod project;
 pub mod prompt;
 pub mod provider;
 pub mod proxy;
 pub mod skill;
+pub mod skill_source;
+pub mod skill_update;
 pub mod speedtest;
 pub mod stream_check;
+pub mod switch_rule;
+pub mod usage_history;
 pub mod usage_stats;
 
 pub use config::ConfigService;
 pub use mcp::McpService;
+pub use profile::ProfileService;
+pub use project::ProjectService;
 pub use prompt::PromptService;
 pub use provider::{ProviderService, ProviderSortUpdate};
 pub use proxy::ProxyService;
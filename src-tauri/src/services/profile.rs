@@ -0,0 +1,252 @@
+use std::collections::HashSet;
+
+use crate::app_config::AppType;
+use crate::error::AppError;
+use crate::profile::{Profile, ProfileAppConfig, ProfileApps};
+use crate::services::{McpService, PromptService, ProviderService, SkillService};
+use crate::store::AppState;
+
+/// 配置档案相关业务
+pub struct ProfileService;
+
+impl ProfileService {
+    pub fn list(state: &AppState) -> Result<Vec<Profile>, AppError> {
+        state.db.get_profiles()
+    }
+
+    /// 新增或更新配置档案
+    ///
+    /// 保存前校验档案引用的供应商、MCP 服务器、提示词与 Skills 均存在。
+    pub fn upsert(state: &AppState, mut profile: Profile) -> Result<Profile, AppError> {
+        profile.name = profile.name.trim().to_string();
+        if profile.name.is_empty() {
+            return Err(AppError::InvalidInput("档案名称不能为空".to_string()));
+        }
+        if profile.id.trim().is_empty() {
+            profile.id = uuid::Uuid::new_v4().to_string();
+        }
+        if profile.created_at.is_none() {
+            profile.created_at = match state.db.get_profile(&profile.id)? {
+                Some(existing) => existing.created_at,
+                None => Some(chrono::Utc::now().timestamp()),
+            };
+        }
+
+        Self::validate(state, &profile.apps)?;
+        state.db.save_profile(&profile)?;
+        Ok(profile)
+    }
+
+    pub fn delete(state: &AppState, id: &str) -> Result<bool, AppError> {
+        state.db.delete_profile(id)
+    }
+
+    /// 获取最近一次成功应用的档案 ID
+    pub fn active_id(state: &AppState) -> Result<Option<String>, AppError> {
+        state.db.get_active_profile_id()
+    }
+
+    /// 以当前各应用的状态创建档案（"保存当前配置为档案"）
+    pub fn capture_current(state: &AppState, name: &str) -> Result<Profile, AppError> {
+        let profile = Profile {
+            id: String::new(),
+            name: name.to_string(),
+            description: None,
+            apps: Self::snapshot(state)?,
+            sort_index: None,
+            created_at: None,
+        };
+        Self::upsert(state, profile)
+    }
+
+    /// 读取当前各应用的供应商、MCP、全局提示词与 Skills 启用状态
+    pub fn snapshot(state: &AppState) -> Result<ProfileApps, AppError> {
+        let servers = McpService::get_all_servers(state)?;
+        let skills = state.db.get_all_installed_skills()?;
+        let mut apps = ProfileApps::default();
+
+        for app in AppType::all() {
+            let prompt_id = PromptService::get_prompts(state, app.clone(), None)?
+                .values()
+                .find(|p| p.enabled)
+                .map(|p| p.id.clone())
+                .unwrap_or_default();
+
+            let config = ProfileAppConfig {
+                provider_id: crate::settings::get_effective_current_provider(&state.db, &app)?,
+                mcp_servers: Some(
+                    servers
+                        .values()
+                        .filter(|s| s.apps.is_enabled_for(&app))
+                        .map(|s| s.id.clone())
+                        .collect(),
+                ),
+                prompt_id: Some(prompt_id),
+                skills: Some(
+                    skills
+                        .values()
+                        .filter(|s| s.apps.is_enabled_for(&app))
+                        .map(|s| s.id.clone())
+                        .collect(),
+                ),
+            };
+            apps.set(&app, Some(config));
+        }
+
+        Ok(apps)
+    }
+
+    /// 应用配置档案
+    ///
+    /// 先校验档案中的所有引用，再依次切换各应用；任一步骤失败时
+    /// 按应用前的快照回滚已改动的部分，保证不会停留在半切换状态。
+    pub fn apply(state: &AppState, id: &str) -> Result<Profile, AppError> {
+        let profile = state
+            .db
+            .get_profile(id)?
+            .ok_or_else(|| AppError::InvalidInput(format!("档案 {id} 不存在")))?;
+
+        Self::validate(state, &profile.apps)?;
+        let before = Self::snapshot(state)?;
+
+        if let Err(err) = Self::apply_apps(state, &profile.apps) {
+            log::error!("应用档案 {} 失败，正在回滚: {err}", profile.name);
+            let rollback = Self::restrict_to(&before, &profile.apps.configured_apps());
+            if let Err(rollback_err) = Self::apply_apps(state, &rollback) {
+                log::error!("回滚档案 {} 失败: {rollback_err}", profile.name);
+            }
+            return Err(err);
+        }
+
+        state.db.set_active_profile_id(Some(&profile.id))?;
+        log::info!("已应用档案: {}", profile.name);
+        Ok(profile)
+    }
+
+    /// 仅保留指定应用的配置
+    fn restrict_to(apps: &ProfileApps, targets: &[AppType]) -> ProfileApps {
+        let mut restricted = ProfileApps::default();
+        for app in targets {
+            restricted.set(app, apps.get(app).cloned());
+        }
+        restricted
+    }
+
+    fn apply_apps(state: &AppState, apps: &ProfileApps) -> Result<(), AppError> {
+        for app in apps.configured_apps() {
+            if let Some(config) = apps.get(&app) {
+                Self::apply_app(state, &app, config)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_app(
+        state: &AppState,
+        app: &AppType,
+        config: &ProfileAppConfig,
+    ) -> Result<(), AppError> {
+        if let Some(provider_id) = &config.provider_id {
+            let current = crate::settings::get_effective_current_provider(&state.db, app)?;
+            if current.as_deref() != Some(provider_id.as_str()) {
+                ProviderService::switch(state, app.clone(), provider_id)?;
+            }
+        }
+
+        if let Some(wanted) = &config.mcp_servers {
+            let wanted: HashSet<&str> = wanted.iter().map(String::as_str).collect();
+            for server in McpService::get_all_servers(state)?.values() {
+                let enabled = wanted.contains(server.id.as_str());
+                if server.apps.is_enabled_for(app) != enabled {
+                    McpService::toggle_app(state, &server.id, app.clone(), enabled)?;
+                }
+            }
+        }
+
+        if let Some(prompt_id) = &config.prompt_id {
+            let prompts = PromptService::get_prompts(state, app.clone(), None)?;
+            if prompt_id.is_empty() {
+                for prompt in prompts.values().filter(|p| p.enabled) {
+                    let mut prompt = prompt.clone();
+                    prompt.enabled = false;
+                    let id = prompt.id.clone();
+                    PromptService::upsert_prompt(state, app.clone(), &id, prompt)?;
+                }
+            } else if !prompts.get(prompt_id).is_some_and(|p| p.enabled) {
+                PromptService::enable_prompt(state, app.clone(), prompt_id)?;
+            }
+        }
+
+        if let Some(wanted) = &config.skills {
+            let wanted: HashSet<&str> = wanted.iter().map(String::as_str).collect();
+            for skill in state.db.get_all_installed_skills()?.values() {
+                let enabled = wanted.contains(skill.id.as_str());
+                if skill.apps.is_enabled_for(app) != enabled {
+                    SkillService::toggle_app(&state.db, &skill.id, app, enabled)
+                        .map_err(|e| AppError::Message(e.to_string()))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 校验档案引用的对象均存在
+    fn validate(state: &AppState, apps: &ProfileApps) -> Result<(), AppError> {
+        let servers = McpService::get_all_servers(state)?;
+        let skills = state.db.get_all_installed_skills()?;
+
+        for app in apps.configured_apps() {
+            let Some(config) = apps.get(&app) else {
+                continue;
+            };
+
+            if let Some(provider_id) = &config.provider_id {
+                if state
+                    .db
+                    .get_provider_by_id(provider_id, app.as_str())?
+                    .is_none()
+                {
+                    return Err(AppError::InvalidInput(format!(
+                        "档案引用的供应商 {provider_id} ({}) 不存在",
+                        app.as_str()
+                    )));
+                }
+            }
+
+            if let Some(missing) = config
+                .mcp_servers
+                .iter()
+                .flatten()
+                .find(|id| !servers.contains_key(id.as_str()))
+            {
+                return Err(AppError::InvalidInput(format!(
+                    "档案引用的 MCP 服务器 {missing} 不存在"
+                )));
+            }
+
+            if let Some(prompt_id) = config.prompt_id.as_deref().filter(|id| !id.is_empty()) {
+                let prompt = state.db.get_prompt(app.as_str(), prompt_id)?;
+                if prompt.is_none_or(|p| p.project_id.is_some()) {
+                    return Err(AppError::InvalidInput(format!(
+                        "档案引用的全局提示词 {prompt_id} ({}) 不存在",
+                        app.as_str()
+                    )));
+                }
+            }
+
+            if let Some(missing) = config
+                .skills
+                .iter()
+                .flatten()
+                .find(|id| !skills.contains_key(id.as_str()))
+            {
+                return Err(AppError::InvalidInput(format!(
+                    "档案引用的 Skill {missing} 不存在"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
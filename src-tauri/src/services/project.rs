@@ -0,0 +1,249 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::app_config::AppType;
+use crate::error::AppError;
+use crate::project::{Project, ProjectProvider, ProjectProviderOverride};
+use crate::project_config::{
+    project_provider_config_path, remove_project_provider, write_project_provider,
+};
+use crate::store::AppState;
+
+/// 项目目录登记相关业务
+pub struct ProjectService;
+
+impl ProjectService {
+    pub fn list(state: &AppState) -> Result<Vec<Project>, AppError> {
+        state.db.get_projects()
+    }
+
+    /// 登记项目根目录
+    ///
+    /// 路径必须是已存在目录的绝对路径；重复登记返回已有项目。
+    pub fn add(state: &AppState, path: &str, name: Option<String>) -> Result<Project, AppError> {
+        let root = Self::normalize_root(path)?;
+        let root_str = root.to_string_lossy().to_string();
+
+        if let Some(existing) = state
+            .db
+            .get_projects()?
+            .into_iter()
+            .find(|p| p.path == root_str)
+        {
+            return Ok(existing);
+        }
+
+        let name = name
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty())
+            .or_else(|| root.file_name().map(|n| n.to_string_lossy().to_string()))
+            .unwrap_or_else(|| root_str.clone());
+
+        let project = Project {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            path: root_str,
+            created_at: Some(chrono::Utc::now().timestamp()),
+        };
+        state.db.save_project(&project)?;
+        log::info!("已登记项目: {} ({})", project.name, project.path);
+        Ok(project)
+    }
+
+    /// 取消登记项目
+    ///
+    /// 删除 cc-switch 中的记录与项目级提示词；项目目录中仅清理供应商覆盖写入的键，
+    /// 提示词文件保持不变。
+    pub fn remove(state: &AppState, id: &str) -> Result<(), AppError> {
+        if let Some(project) = state.db.get_project(id)? {
+            let root = Path::new(&project.path);
+            for binding in state.db.get_project_providers(Some(id))? {
+                let cleaned = AppType::from_str(&binding.app_type)
+                    .and_then(|app| remove_project_provider(&app, root, &binding.managed_keys));
+                if let Err(e) = cleaned {
+                    log::warn!(
+                        "清理项目 {} 的 {} 供应商配置失败: {e}",
+                        project.name,
+                        binding.app_type
+                    );
+                }
+            }
+        }
+        state.db.delete_project(id)
+    }
+
+    /// 获取项目根目录，项目不存在或目录已被移除时报错
+    pub fn project_root(state: &AppState, id: &str) -> Result<PathBuf, AppError> {
+        let project = state
+            .db
+            .get_project(id)?
+            .ok_or_else(|| AppError::InvalidInput(format!("项目 {id} 不存在")))?;
+        let root = PathBuf::from(&project.path);
+        if !root.is_dir() {
+            return Err(AppError::localized(
+                "project_dir_missing",
+                format!("项目目录不存在: {}", project.path),
+                format!("Project directory not found: {}", project.path),
+            ));
+        }
+        Ok(root)
+    }
+
+    /// 列出项目覆盖的供应商
+    ///
+    /// `project_id` 为 `None` 时列出所有项目。
+    pub fn list_provider_overrides(
+        state: &AppState,
+        project_id: Option<&str>,
+    ) -> Result<Vec<ProjectProviderOverride>, AppError> {
+        let projects = state.db.get_projects()?;
+        let mut overrides = Vec::new();
+
+        for binding in state.db.get_project_providers(project_id)? {
+            let Some(project) = projects.iter().find(|p| p.id == binding.project_id) else {
+                continue;
+            };
+            overrides.push(Self::to_override(state, project, &binding)?);
+        }
+
+        Ok(overrides)
+    }
+
+    /// 为项目指定（或取消）某个应用的供应商
+    ///
+    /// 指定后写入项目目录作用域的配置文件，全局 Live 配置不受影响；
+    /// `provider_id` 为 `None` 时清理 cc-switch 写入的键并解除绑定。
+    pub fn set_provider(
+        state: &AppState,
+        project_id: &str,
+        app: AppType,
+        provider_id: Option<&str>,
+    ) -> Result<Option<ProjectProviderOverride>, AppError> {
+        let root = Self::project_root(state, project_id)?;
+        // 提前拒绝不支持的应用
+        project_provider_config_path(&app, &root)?;
+        let existing = state.db.get_project_provider(project_id, app.as_str())?;
+
+        let Some(provider_id) = provider_id else {
+            if let Some(existing) = existing {
+                remove_project_provider(&app, &root, &existing.managed_keys)?;
+                state.db.delete_project_provider(project_id, app.as_str())?;
+                log::info!("已取消项目 {project_id} 的 {} 供应商覆盖", app.as_str());
+            }
+            return Ok(None);
+        };
+
+        let provider = state
+            .db
+            .get_provider_by_id(provider_id, app.as_str())?
+            .ok_or_else(|| AppError::InvalidInput(format!("供应商 {provider_id} 不存在")))?;
+        let previous_keys = existing.map(|b| b.managed_keys).unwrap_or_default();
+        let managed_keys = write_project_provider(&app, &root, &provider, &previous_keys)?;
+
+        let binding = ProjectProvider {
+            project_id: project_id.to_string(),
+            app_type: app.as_str().to_string(),
+            provider_id: provider.id.clone(),
+            managed_keys,
+            updated_at: Some(chrono::Utc::now().timestamp()),
+        };
+        state.db.save_project_provider(&binding)?;
+        log::info!(
+            "项目 {project_id} 的 {} 供应商已设为 {}",
+            app.as_str(),
+            provider.name
+        );
+
+        let project = state
+            .db
+            .get_project(project_id)?
+            .ok_or_else(|| AppError::InvalidInput(format!("项目 {project_id} 不存在")))?;
+        Self::to_override(state, &project, &binding).map(Some)
+    }
+
+    /// 供应商配置更新后，重写所有引用它的项目配置
+    ///
+    /// 单个项目失败（如目录已被移除）只记录日志，不影响其他项目。
+    pub fn sync_provider_overrides(state: &AppState, app: &AppType, provider_id: &str) {
+        let bindings = match state.db.get_project_providers(None) {
+            Ok(bindings) => bindings,
+            Err(e) => {
+                log::warn!("读取项目供应商绑定失败: {e}");
+                return;
+            }
+        };
+
+        for binding in bindings
+            .into_iter()
+            .filter(|b| b.app_type == app.as_str() && b.provider_id == provider_id)
+        {
+            if let Err(e) =
+                Self::set_provider(state, &binding.project_id, app.clone(), Some(provider_id))
+            {
+                log::warn!(
+                    "同步项目 {} 的 {} 供应商配置失败: {e}",
+                    binding.project_id,
+                    app.as_str()
+                );
+            }
+        }
+    }
+
+    /// 返回引用该供应商的项目名称
+    pub fn projects_using_provider(
+        state: &AppState,
+        app: &AppType,
+        provider_id: &str,
+    ) -> Result<Vec<String>, AppError> {
+        Ok(Self::list_provider_overrides(state, None)?
+            .into_iter()
+            .filter(|o| o.app_type == app.as_str() && o.provider_id == provider_id)
+            .map(|o| o.project_name)
+            .collect())
+    }
+
+    fn to_override(
+        state: &AppState,
+        project: &Project,
+        binding: &ProjectProvider,
+    ) -> Result<ProjectProviderOverride, AppError> {
+        let app = AppType::from_str(&binding.app_type)?;
+        let provider_name = state
+            .db
+            .get_provider_by_id(&binding.provider_id, &binding.app_type)?
+            .map(|p| p.name);
+        let config_path = project_provider_config_path(&app, Path::new(&project.path))?;
+
+        Ok(ProjectProviderOverride {
+            project_id: project.id.clone(),
+            project_name: project.name.clone(),
+            project_path: project.path.clone(),
+            app_type: binding.app_type.clone(),
+            provider_id: binding.provider_id.clone(),
+            provider_name,
+            config_path: config_path.to_string_lossy().to_string(),
+        })
+    }
+
+    fn normalize_root(path: &str) -> Result<PathBuf, AppError> {
+        let trimmed = path.trim();
+        if trimmed.is_empty() {
+            return Err(AppError::InvalidInput("项目路径不能为空".to_string()));
+        }
+        let raw = Path::new(trimmed);
+        if !raw.is_absolute() {
+            return Err(AppError::InvalidInput(format!(
+                "项目路径必须是绝对路径: {trimmed}"
+            )));
+        }
+        if !raw.is_dir() {
+            return Err(AppError::localized(
+                "project_dir_missing",
+                format!("项目目录不存在: {trimmed}"),
+                format!("Project directory not found: {trimmed}"),
+            ));
+        }
+        // 去掉末尾分隔符与 `.` 组件，便于去重
+        Ok(raw.components().collect())
+    }
+}
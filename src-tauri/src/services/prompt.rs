@@ -1,10 +1,20 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
 use indexmap::IndexMap;
+use similar::{ChangeTag, TextDiff};
 
 use crate::app_config::AppType;
 use crate::config::write_text_file;
 use crate::error::AppError;
-use crate::prompt::Prompt;
-use crate::prompt_files::prompt_file_path;
+use crate::prompt::{
+    Prompt, PromptDiffLine, PromptRevision, PromptRevisionDiff, PromptRevisionSource,
+};
+use crate::prompt_files::{project_prompt_file_path, prompt_file_path};
+use crate::prompt_template::{
+    render_prompt_template, IncludedPrompt, RenderedPrompt, UnknownVariables,
+};
+use crate::services::{ProjectService, ProviderService};
 use crate::store::AppState;
 
 /// 安全地获取当前 Unix 时间戳
@@ -18,11 +28,39 @@ fn get_unix_timestamp() -> Result<i64, AppError> {
 pub struct PromptService;
 
 impl PromptService {
+    /// 获取提示词列表
+    ///
+    /// `project_id` 为 `None` 时返回全局提示词，否则返回该项目的提示词。
     pub fn get_prompts(
         state: &AppState,
         app: AppType,
+        project_id: Option<&str>,
     ) -> Result<IndexMap<String, Prompt>, AppError> {
-        state.db.get_prompts(app.as_str())
+        state.db.get_prompts_in_scope(app.as_str(), project_id)
+    }
+
+    /// 解析提示词作用域对应的 live 文件路径
+    ///
+    /// 全局提示词写入应用配置目录，项目提示词写入 `<project>/CLAUDE.md` 等。
+    fn prompt_path(
+        state: &AppState,
+        app: &AppType,
+        project_id: Option<&str>,
+    ) -> Result<PathBuf, AppError> {
+        match project_id {
+            None => prompt_file_path(app),
+            Some(project_id) => {
+                let root = ProjectService::project_root(state, project_id)?;
+                Ok(project_prompt_file_path(app, &root))
+            }
+        }
+    }
+
+    fn require_prompt(state: &AppState, app: &AppType, id: &str) -> Result<Prompt, AppError> {
+        state
+            .db
+            .get_prompt(app.as_str(), id)?
+            .ok_or_else(|| AppError::InvalidInput(format!("提示词 {id} 不存在")))
     }
 
     pub fn upsert_prompt(
@@ -33,23 +71,35 @@ impl PromptService {
     ) -> Result<(), AppError> {
         // 检查是否为已启用的提示词
         let is_enabled = prompt.enabled;
+        let project_id = prompt.project_id.clone();
+        let target_path = Self::prompt_path(state, &app, project_id.as_deref())?;
+
+        if is_enabled {
+            // 先校验模板可渲染，避免数据库与 live 文件不一致
+            Self::render(state, &app, &prompt, UnknownVariables::Keep)?;
+            // 覆盖 live 文件前先回填外部修改，使其进入修订历史
+            Self::backfill_from_live(state, &app, project_id.as_deref(), &target_path)?;
+        }
 
         state.db.save_prompt(app.as_str(), &prompt)?;
 
         if is_enabled {
-            // 启用提示词：写入内容到文件
-            let target_path = prompt_file_path(&app)?;
-            write_text_file(&target_path, &prompt.content)?;
+            // 启用提示词：渲染模板后写入文件
+            Self::write_live(state, &app, &prompt, &target_path)?;
         } else {
-            // 禁用提示词：检查是否还有其他已启用的提示词
-            let prompts = state.db.get_prompts(app.as_str())?;
+            // 禁用提示词：检查同一作用域内是否还有其他已启用的提示词
+            let prompts = state
+                .db
+                .get_prompts_in_scope(app.as_str(), project_id.as_deref())?;
             let any_enabled = prompts.values().any(|p| p.enabled);
 
             if !any_enabled {
                 // 所有提示词都已禁用，清空文件
-                let target_path = prompt_file_path(&app)?;
                 if target_path.exists() {
                     write_text_file(&target_path, "")?;
+                    state
+                        .db
+                        .set_prompt_live_snapshot(app.as_str(), project_id.as_deref(), "")?;
                 }
             }
         }
@@ -58,9 +108,7 @@ impl PromptService {
     }
 
     pub fn delete_prompt(state: &AppState, app: AppType, id: &str) -> Result<(), AppError> {
-        let prompts = state.db.get_prompts(app.as_str())?;
-
-        if let Some(prompt) = prompts.get(id) {
+        if let Some(prompt) = state.db.get_prompt(app.as_str(), id)? {
             if prompt.enabled {
                 return Err(AppError::InvalidInput("无法删除已启用的提示词".to_string()));
             }
@@ -70,58 +118,19 @@ impl PromptService {
         Ok(())
     }
 
+    /// 启用提示词（同一作用域内的其他提示词会被禁用）
     pub fn enable_prompt(state: &AppState, app: AppType, id: &str) -> Result<(), AppError> {
-        // 回填当前 live 文件内容到已启用的提示词，或创建备份
-        let target_path = prompt_file_path(&app)?;
-        if target_path.exists() {
-            if let Ok(live_content) = std::fs::read_to_string(&target_path) {
-                if !live_content.trim().is_empty() {
-                    let mut prompts = state.db.get_prompts(app.as_str())?;
-
-                    // 尝试回填到当前已启用的提示词
-                    if let Some((enabled_id, enabled_prompt)) = prompts
-                        .iter_mut()
-                        .find(|(_, p)| p.enabled)
-                        .map(|(id, p)| (id.clone(), p))
-                    {
-                        let timestamp = get_unix_timestamp()?;
-                        enabled_prompt.content = live_content.clone();
-                        enabled_prompt.updated_at = Some(timestamp);
-                        log::info!("回填 live 提示词内容到已启用项: {enabled_id}");
-                        state.db.save_prompt(app.as_str(), enabled_prompt)?;
-                    } else {
-                        // 没有已启用的提示词，则创建一次备份（避免重复备份）
-                        let content_exists = prompts
-                            .values()
-                            .any(|p| p.content.trim() == live_content.trim());
-                        if !content_exists {
-                            let timestamp = std::time::SystemTime::now()
-                                .duration_since(std::time::UNIX_EPOCH)
-                                .unwrap_or_default()
-                                .as_secs() as i64;
-                            let backup_id = format!("backup-{timestamp}");
-                            let backup_prompt = Prompt {
-                                id: backup_id.clone(),
-                                name: format!(
-                                    "原始提示词 {}",
-                                    chrono::Local::now().format("%Y-%m-%d %H:%M")
-                                ),
-                                content: live_content,
-                                description: Some("自动备份的原始提示词".to_string()),
-                                enabled: false,
-                                created_at: Some(timestamp),
-                                updated_at: Some(timestamp),
-                            };
-                            log::info!("回填 live 提示词内容，创建备份: {backup_id}");
-                            state.db.save_prompt(app.as_str(), &backup_prompt)?;
-                        }
-                    }
-                }
-            }
-        }
+        let target = Self::require_prompt(state, &app, id)?;
+        // 先校验模板可渲染，避免回填或切换后才失败
+        Self::render(state, &app, &target, UnknownVariables::Keep)?;
+        let project_id = target.project_id;
+        let target_path = Self::prompt_path(state, &app, project_id.as_deref())?;
+        Self::backfill_from_live(state, &app, project_id.as_deref(), &target_path)?;
 
         // 启用目标提示词并写入文件
-        let mut prompts = state.db.get_prompts(app.as_str())?;
+        let mut prompts = state
+            .db
+            .get_prompts_in_scope(app.as_str(), project_id.as_deref())?;
 
         for prompt in prompts.values_mut() {
             prompt.enabled = false;
@@ -129,7 +138,7 @@ impl PromptService {
 
         if let Some(prompt) = prompts.get_mut(id) {
             prompt.enabled = true;
-            write_text_file(&target_path, &prompt.content)?; // 原子写入
+            Self::write_live(state, &app, prompt, &target_path)?; // 渲染后原子写入
             state.db.save_prompt(app.as_str(), prompt)?;
         } else {
             return Err(AppError::InvalidInput(format!("提示词 {id} 不存在")));
@@ -143,8 +152,324 @@ impl PromptService {
         Ok(())
     }
 
-    pub fn import_from_file(state: &AppState, app: AppType) -> Result<String, AppError> {
-        let file_path = prompt_file_path(&app)?;
+    /// 切换供应商后重新渲染各作用域已启用的提示词，使 `{{provider_name}}` 等变量保持最新
+    ///
+    /// live 文件被外部修改过的作用域会跳过，留待下次写入前回填。
+    pub fn refresh_live(state: &AppState, app: &AppType) -> Result<(), AppError> {
+        let scopes = std::iter::once(None).chain(
+            state
+                .db
+                .get_projects()?
+                .into_iter()
+                .map(|project| Some(project.id)),
+        );
+
+        for project_id in scopes {
+            let project_id = project_id.as_deref();
+            let Some(prompt) = state
+                .db
+                .get_prompts_in_scope(app.as_str(), project_id)?
+                .into_values()
+                .find(|p| p.enabled)
+            else {
+                continue;
+            };
+            let target_path = match Self::prompt_path(state, app, project_id) {
+                Ok(path) => path,
+                Err(e) => {
+                    log::debug!("跳过刷新提示词 {}: {e}", prompt.id);
+                    continue;
+                }
+            };
+            let Ok(live_content) = std::fs::read_to_string(&target_path) else {
+                continue;
+            };
+            if !state
+                .db
+                .get_prompt_live_snapshot(app.as_str(), project_id)?
+                .is_some_and(|snapshot| snapshot == live_content)
+            {
+                continue;
+            }
+
+            let refreshed =
+                Self::render(state, app, &prompt, UnknownVariables::Keep).and_then(|rendered| {
+                    if rendered.content == live_content {
+                        return Ok(());
+                    }
+                    Self::write_live(state, app, &prompt, &target_path)
+                });
+            if let Err(e) = refreshed {
+                log::warn!("刷新提示词 {} 失败: {e}", prompt.id);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 预览提示词渲染结果（不写入文件）
+    ///
+    /// 使用传入的内容与作用域渲染，便于在编辑器中预览尚未保存的修改。
+    /// 预览时未知变量会报错，写入 live 文件时则原样保留。
+    pub fn preview_prompt(
+        state: &AppState,
+        app: AppType,
+        prompt: &Prompt,
+    ) -> Result<RenderedPrompt, AppError> {
+        Self::render(state, &app, prompt, UnknownVariables::Reject)
+    }
+
+    /// 渲染提示词模板（变量替换与引入）
+    fn render(
+        state: &AppState,
+        app: &AppType,
+        prompt: &Prompt,
+        unknown: UnknownVariables,
+    ) -> Result<RenderedPrompt, AppError> {
+        let project_id = prompt.project_id.as_deref();
+        let variables = Self::template_variables(state, app, project_id)?;
+
+        // 可引入同一作用域的提示词；项目级提示词还可引入全局提示词
+        let mut candidates: Vec<Prompt> = state
+            .db
+            .get_prompts_in_scope(app.as_str(), project_id)?
+            .into_values()
+            .collect();
+        if project_id.is_some() {
+            candidates.extend(state.db.get_prompts(app.as_str())?.into_values());
+        }
+
+        render_prompt_template(
+            &prompt.id,
+            &prompt.content,
+            &variables,
+            unknown,
+            |reference| {
+                candidates
+                    .iter()
+                    .find(|p| p.id == reference)
+                    .or_else(|| candidates.iter().find(|p| p.name == reference))
+                    .map(|p| IncludedPrompt {
+                        id: p.id.clone(),
+                        content: p.content.clone(),
+                    })
+            },
+        )
+    }
+
+    /// 构建模板变量
+    fn template_variables(
+        state: &AppState,
+        app: &AppType,
+        project_id: Option<&str>,
+    ) -> Result<HashMap<String, String>, AppError> {
+        let mut variables = HashMap::new();
+        variables.insert(
+            "date".to_string(),
+            chrono::Local::now().format("%Y-%m-%d").to_string(),
+        );
+        variables.insert("os".to_string(), std::env::consts::OS.to_string());
+        variables.insert("app".to_string(), app.as_str().to_string());
+
+        let current_id = ProviderService::current(state, app.clone())?;
+        let provider_name = if current_id.is_empty() {
+            String::new()
+        } else {
+            state
+                .db
+                .get_provider_by_id(&current_id, app.as_str())?
+                .map(|p| p.name)
+                .unwrap_or_default()
+        };
+        variables.insert("provider_name".to_string(), provider_name);
+
+        if let Some(project_id) = project_id {
+            let project = state
+                .db
+                .get_project(project_id)?
+                .ok_or_else(|| AppError::InvalidInput(format!("项目 {project_id} 不存在")))?;
+            variables.insert("project_name".to_string(), project.name);
+            variables.insert("project_path".to_string(), project.path);
+        }
+
+        Ok(variables)
+    }
+
+    /// 渲染并写入 live 文件，同时记录写入内容
+    fn write_live(
+        state: &AppState,
+        app: &AppType,
+        prompt: &Prompt,
+        target_path: &Path,
+    ) -> Result<(), AppError> {
+        let rendered = Self::render(state, app, prompt, UnknownVariables::Keep)?;
+        write_text_file(target_path, &rendered.content)?;
+        state.db.set_prompt_live_snapshot(
+            app.as_str(),
+            prompt.project_id.as_deref(),
+            &rendered.content,
+        )
+    }
+
+    /// 回填当前 live 文件内容到已启用的提示词，或创建备份
+    ///
+    /// 回填会记录一条 `backfill` 修订，被覆盖的旧内容仍可从历史中恢复。
+    fn backfill_from_live(
+        state: &AppState,
+        app: &AppType,
+        project_id: Option<&str>,
+        target_path: &Path,
+    ) -> Result<(), AppError> {
+        if !target_path.exists() {
+            return Ok(());
+        }
+        let Ok(live_content) = std::fs::read_to_string(target_path) else {
+            return Ok(());
+        };
+        if live_content.trim().is_empty() {
+            return Ok(());
+        }
+
+        // 文件内容与上次写入一致，说明未被外部修改（模板渲染结果不应回填覆盖模板源）
+        if state
+            .db
+            .get_prompt_live_snapshot(app.as_str(), project_id)?
+            .is_some_and(|snapshot| snapshot == live_content)
+        {
+            return Ok(());
+        }
+
+        let mut prompts = state.db.get_prompts_in_scope(app.as_str(), project_id)?;
+
+        // 尝试回填到当前已启用的提示词
+        if let Some((enabled_id, enabled_prompt)) = prompts
+            .iter_mut()
+            .find(|(_, p)| p.enabled)
+            .map(|(id, p)| (id.clone(), p))
+        {
+            if enabled_prompt.content == live_content {
+                return Ok(());
+            }
+            let timestamp = get_unix_timestamp()?;
+            enabled_prompt.content = live_content;
+            enabled_prompt.updated_at = Some(timestamp);
+            log::info!("回填 live 提示词内容到已启用项: {enabled_id}");
+            state.db.save_prompt_with_source(
+                app.as_str(),
+                enabled_prompt,
+                PromptRevisionSource::Backfill,
+            )?;
+        } else {
+            // 没有已启用的提示词，则创建一次备份（避免重复备份）
+            let content_exists = prompts
+                .values()
+                .any(|p| p.content.trim() == live_content.trim());
+            if !content_exists {
+                let timestamp = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs() as i64;
+                let backup_id = format!("backup-{timestamp}");
+                let backup_prompt = Prompt {
+                    id: backup_id.clone(),
+                    name: format!(
+                        "原始提示词 {}",
+                        chrono::Local::now().format("%Y-%m-%d %H:%M")
+                    ),
+                    content: live_content,
+                    description: Some("自动备份的原始提示词".to_string()),
+                    enabled: false,
+                    created_at: Some(timestamp),
+                    updated_at: Some(timestamp),
+                    project_id: project_id.map(str::to_string),
+                };
+                log::info!("回填 live 提示词内容，创建备份: {backup_id}");
+                state.db.save_prompt_with_source(
+                    app.as_str(),
+                    &backup_prompt,
+                    PromptRevisionSource::Backfill,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 获取提示词的历史修订（最新在前）
+    pub fn get_revisions(
+        state: &AppState,
+        app: AppType,
+        id: &str,
+    ) -> Result<Vec<PromptRevision>, AppError> {
+        state.db.get_prompt_revisions(app.as_str(), id)
+    }
+
+    /// 对比同一提示词的两个修订（from → to）
+    pub fn diff_revisions(
+        state: &AppState,
+        app: AppType,
+        id: &str,
+        from_revision: i64,
+        to_revision: i64,
+    ) -> Result<PromptRevisionDiff, AppError> {
+        let from = Self::require_revision(state, &app, id, from_revision)?;
+        let to = Self::require_revision(state, &app, id, to_revision)?;
+        Ok(build_revision_diff(&from, &to))
+    }
+
+    /// 将提示词恢复到指定修订
+    ///
+    /// 恢复本身也会记录为一条 `restore` 修订；若该提示词已启用，
+    /// 先回填 live 文件（避免外部修改丢失），再把恢复后的内容写回文件。
+    pub fn restore_revision(
+        state: &AppState,
+        app: AppType,
+        id: &str,
+        revision_id: i64,
+    ) -> Result<(), AppError> {
+        let revision = Self::require_revision(state, &app, id, revision_id)?;
+
+        let existing = Self::require_prompt(state, &app, id)?;
+        let target_path = Self::prompt_path(state, &app, existing.project_id.as_deref())?;
+        if existing.enabled {
+            Self::backfill_from_live(state, &app, existing.project_id.as_deref(), &target_path)?;
+        }
+
+        let mut prompt = Self::require_prompt(state, &app, id)?;
+        prompt.content = revision.content;
+        prompt.updated_at = Some(get_unix_timestamp()?);
+        state
+            .db
+            .save_prompt_with_source(app.as_str(), &prompt, PromptRevisionSource::Restore)?;
+
+        if prompt.enabled {
+            Self::write_live(state, &app, &prompt, &target_path)?;
+        }
+
+        log::info!("提示词 {id} 已恢复到修订 #{revision_id}");
+        Ok(())
+    }
+
+    fn require_revision(
+        state: &AppState,
+        app: &AppType,
+        id: &str,
+        revision_id: i64,
+    ) -> Result<PromptRevision, AppError> {
+        state
+            .db
+            .get_prompt_revision(app.as_str(), id, revision_id)?
+            .ok_or_else(|| {
+                AppError::InvalidInput(format!("提示词 {id} 的修订 #{revision_id} 不存在"))
+            })
+    }
+
+    pub fn import_from_file(
+        state: &AppState,
+        app: AppType,
+        project_id: Option<&str>,
+    ) -> Result<String, AppError> {
+        let file_path = Self::prompt_path(state, &app, project_id)?;
 
         if !file_path.exists() {
             return Err(AppError::Message("提示词文件不存在".to_string()));
@@ -166,14 +491,19 @@ impl PromptService {
             enabled: false,
             created_at: Some(timestamp),
             updated_at: Some(timestamp),
+            project_id: project_id.map(str::to_string),
         };
 
         Self::upsert_prompt(state, app, &id, prompt)?;
         Ok(id)
     }
 
-    pub fn get_current_file_content(app: AppType) -> Result<Option<String>, AppError> {
-        let file_path = prompt_file_path(&app)?;
+    pub fn get_current_file_content(
+        state: &AppState,
+        app: AppType,
+        project_id: Option<&str>,
+    ) -> Result<Option<String>, AppError> {
+        let file_path = Self::prompt_path(state, &app, project_id)?;
         if !file_path.exists() {
             return Ok(None);
         }
@@ -231,6 +561,7 @@ impl PromptService {
             enabled: true, // 首次导入时自动启用
             created_at: Some(timestamp),
             updated_at: Some(timestamp),
+            project_id: None,
         };
 
         // 保存到数据库
@@ -240,3 +571,100 @@ impl PromptService {
         Ok(1)
     }
 }
+
+/// 生成两个修订之间的逐行对比
+fn build_revision_diff(from: &PromptRevision, to: &PromptRevision) -> PromptRevisionDiff {
+    let diff = TextDiff::from_lines(&from.content, &to.content);
+
+    let mut additions = 0;
+    let mut deletions = 0;
+    let lines = diff
+        .iter_all_changes()
+        .map(|change| {
+            let tag = match change.tag() {
+                ChangeTag::Equal => "equal",
+                ChangeTag::Insert => {
+                    additions += 1;
+                    "insert"
+                }
+                ChangeTag::Delete => {
+                    deletions += 1;
+                    "delete"
+                }
+            };
+            PromptDiffLine {
+                tag: tag.to_string(),
+                content: change.value().trim_end_matches(['\r', '\n']).to_string(),
+                old_line: change.old_index().map(|i| i + 1),
+                new_line: change.new_index().map(|i| i + 1),
+            }
+        })
+        .collect();
+
+    let unified = diff
+        .unified_diff()
+        .context_radius(3)
+        .header(
+            &format!("revision #{}", from.id),
+            &format!("revision #{}", to.id),
+        )
+        .to_string();
+
+    PromptRevisionDiff {
+        from_revision: from.id,
+        to_revision: to.id,
+        additions,
+        deletions,
+        lines,
+        unified,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn revision(id: i64, content: &str) -> PromptRevision {
+        PromptRevision {
+            id,
+            prompt_id: "p1".to_string(),
+            content: content.to_string(),
+            source: PromptRevisionSource::Edit,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn diff_counts_added_and_removed_lines() {
+        let from = revision(1, "# Rules\nuse tabs\nbe brief\n");
+        let to = revision(2, "# Rules\nuse spaces\nbe brief\nadd tests\n");
+
+        let diff = build_revision_diff(&from, &to);
+
+        assert_eq!(diff.additions, 2);
+        assert_eq!(diff.deletions, 1);
+        assert_eq!(diff.from_revision, 1);
+        assert_eq!(diff.to_revision, 2);
+        let removed: Vec<_> = diff
+            .lines
+            .iter()
+            .filter(|l| l.tag == "delete")
+            .map(|l| (l.content.as_str(), l.old_line))
+            .collect();
+        assert_eq!(removed, vec![("use tabs", Some(2))]);
+        assert!(diff.unified.contains("-use tabs"));
+        assert!(diff.unified.contains("+use spaces"));
+    }
+
+    #[test]
+    fn diff_of_identical_revisions_is_all_equal() {
+        let from = revision(1, "same\n");
+        let to = revision(2, "same\n");
+
+        let diff = build_revision_diff(&from, &to);
+
+        assert_eq!(diff.additions, 0);
+        assert_eq!(diff.deletions, 0);
+        assert!(diff.lines.iter().all(|l| l.tag == "equal"));
+    }
+}
@@ -26,11 +26,13 @@ pub use live::{
 };
 
 // Internal re-exports (pub(crate))
+pub(crate) use gemini_auth::{detect_gemini_auth_type, GeminiAuthType};
+pub(crate) use live::remove_opencode_provider_from_live;
 pub(crate) use live::sanitize_claude_settings_for_live;
 pub(crate) use live::write_live_snapshot;
 
 // Internal re-exports
-use live::{remove_opencode_provider_from_live, write_gemini_live};
+use live::write_gemini_live;
 use usage::validate_usage_script;
 
 /// Provider business logic service
@@ -196,6 +198,9 @@ impl ProviderService {
         // Save to database
         state.db.save_provider(app_type.as_str(), &provider)?;
 
+        // Rewrite project-scoped configs that reference this provider
+        crate::services::ProjectService::sync_provider_overrides(state, &app_type, &provider.id);
+
         // OpenCode uses additive mode - always update in live config
         if matches!(app_type, AppType::OpenCode) {
             write_live_snapshot(&app_type, &provider)?;
@@ -261,6 +266,15 @@ impl ProviderService {
             ));
         }
 
+        let projects =
+            crate::services::ProjectService::projects_using_provider(state, &app_type, id)?;
+        if !projects.is_empty() {
+            return Err(AppError::Message(format!(
+                "无法删除被项目使用的供应商: {}",
+                projects.join(", ")
+            )));
+        }
+
         state.db.delete_provider(app_type.as_str(), id)?;
         Self::invalidate_cache(state, &app_type);
         Ok(())
@@ -359,6 +373,7 @@ impl ProviderService {
 
             // Note: No Live config write, no MCP sync
             // The proxy server will route requests to the new provider via is_current
+            Self::refresh_prompts(state, &app_type);
             Self::invalidate_cache(state, &app_type);
             return Ok(());
         }
@@ -414,10 +429,21 @@ impl ProviderService {
         // Sync MCP
         McpService::sync_all_enabled(state)?;
 
+        Self::refresh_prompts(state, &app_type);
         Self::invalidate_cache(state, &app_type);
         Ok(())
     }
 
+    /// 切换后重新渲染已启用的提示词（`{{provider_name}}` 等变量），失败不影响切换结果
+    fn refresh_prompts(state: &AppState, app_type: &AppType) {
+        if let Err(e) = crate::services::PromptService::refresh_live(state, app_type) {
+            log::warn!(
+                "刷新 {} 提示词失败（不影响切换结果）: {e}",
+                app_type.as_str()
+            );
+        }
+    }
+
     /// Sync current provider to live configuration (re-export)
     pub fn sync_current_to_live(state: &AppState) -> Result<(), AppError> {
         sync_current_to_live(state)
@@ -695,6 +721,16 @@ impl ProviderService {
         usage::query_usage(state, app_type, provider_id).await
     }
 
+    /// Query provider usage with snapshot cache (re-export)
+    pub async fn query_usage_cached(
+        state: &AppState,
+        app_type: AppType,
+        provider_id: &str,
+        max_age: std::time::Duration,
+    ) -> Result<UsageResult, AppError> {
+        usage::query_usage_cached(state, app_type, provider_id, max_age).await
+    }
+
     /// Test usage script (re-export)
     #[allow(clippy::too_many_arguments)]
     pub async fn test_usage_script(
@@ -2,6 +2,8 @@
 //!
 //! Handles executing and formatting usage query results.
 
+use std::time::Duration;
+
 use crate::app_config::AppType;
 use crate::error::AppError;
 use crate::provider::{UsageData, UsageResult, UsageScript};
@@ -19,7 +21,7 @@ pub(crate) async fn execute_and_format_usage_result(
     user_id: Option<&str>,
     template_type: Option<&str>,
 ) -> Result<UsageResult, AppError> {
-    match usage_script::execute_usage_script(
+    let run = usage_script::execute_usage_script(
         script_code,
         api_key,
         base_url,
@@ -28,8 +30,10 @@ pub(crate) async fn execute_and_format_usage_result(
         user_id,
         template_type,
     )
-    .await
-    {
+    .await;
+    let logs = run.logs;
+
+    match run.result {
         Ok(data) => {
             let usage_list: Vec<UsageData> = if data.is_array() {
                 serde_json::from_value(data).map_err(|e| {
@@ -54,6 +58,7 @@ pub(crate) async fn execute_and_format_usage_result(
                 success: true,
                 data: Some(usage_list),
                 error: None,
+                logs,
             })
         }
         Err(err) => {
@@ -76,6 +81,7 @@ pub(crate) async fn execute_and_format_usage_result(
                 success: false,
                 data: None,
                 error: Some(msg),
+                logs,
             })
         }
     }
@@ -170,7 +176,7 @@ pub async fn query_usage(
         )
     };
 
-    execute_and_format_usage_result(
+    let result = execute_and_format_usage_result(
         &script_code,
         &api_key,
         &base_url,
@@ -179,7 +185,59 @@ pub async fn query_usage(
         user_id.as_deref(),
         template_type.as_deref(),
     )
-    .await
+    .await?;
+
+    // 记录用量快照，供趋势、预测与低余额提醒使用
+    if let (true, Some(data)) = (result.success, result.data.as_deref()) {
+        if let Err(e) = state.db.insert_usage_snapshots(
+            app_type.as_str(),
+            provider_id,
+            data,
+            chrono::Utc::now().timestamp(),
+        ) {
+            log::warn!("记录用量快照失败 ({provider_id}): {e}");
+        }
+    }
+
+    Ok(result)
+}
+
+/// Query provider usage, reusing the latest snapshot if it is newer than `max_age`
+pub async fn query_usage_cached(
+    state: &AppState,
+    app_type: AppType,
+    provider_id: &str,
+    max_age: Duration,
+) -> Result<UsageResult, AppError> {
+    let latest = state
+        .db
+        .get_latest_usage_snapshots(app_type.as_str(), provider_id)?;
+    let fresh = latest.first().is_some_and(|snapshot| {
+        chrono::Utc::now().timestamp() - snapshot.captured_at < max_age.as_secs() as i64
+    });
+    if !fresh {
+        return query_usage(state, app_type, provider_id).await;
+    }
+
+    let data = latest
+        .into_iter()
+        .map(|snapshot| UsageData {
+            plan_name: Some(snapshot.plan_name).filter(|name| !name.is_empty()),
+            extra: None,
+            is_valid: snapshot.is_valid,
+            invalid_message: None,
+            total: snapshot.total,
+            used: snapshot.used,
+            remaining: snapshot.remaining,
+            unit: snapshot.unit,
+        })
+        .collect();
+    Ok(UsageResult {
+        success: true,
+        data: Some(data),
+        error: None,
+        logs: Vec::new(),
+    })
 }
 
 /// Test usage script (using temporary script content, not saved)
@@ -1108,6 +1108,22 @@ impl ProxyService {
         Ok(())
     }
 
+    /// 接管期间 Live 配置被外部改写（占位符丢失）后重新接管
+    ///
+    /// `adopt` 为 true 时将当前 Live 作为新的备份并同步 Token 到供应商；
+    /// 否则沿用原备份，仅重新写入接管配置。
+    pub async fn retake_live_config_for_app(
+        &self,
+        app_type: &AppType,
+        adopt: bool,
+    ) -> Result<(), String> {
+        if adopt {
+            self.backup_live_config_strict(app_type).await?;
+            self.sync_live_to_provider(app_type).await?;
+        }
+        self.takeover_live_config_strict(app_type).await
+    }
+
     /// 恢复原始 Live 配置
     async fn restore_live_configs(&self) -> Result<(), String> {
         let mut errors = Vec::new();
@@ -1872,6 +1888,23 @@ impl ProxyService {
         Ok(())
     }
 
+    /// 获取 Key 池中指定 Key 的熔断器状态（代理未运行时返回 None）
+    pub async fn get_key_circuit_breaker_stats(
+        &self,
+        provider_id: &str,
+        app_type: &str,
+        key_id: &str,
+    ) -> Option<crate::proxy::CircuitBreakerStats> {
+        match self.server.read().await.as_ref() {
+            Some(server) => {
+                server
+                    .get_key_circuit_breaker_stats(provider_id, app_type, key_id)
+                    .await
+            }
+            None => None,
+        }
+    }
+
     /// 使 ProviderRouter 缓存失效
     ///
     /// 如果代理服务器正在运行，通知 ProviderRouter 清除指定应用的缓存
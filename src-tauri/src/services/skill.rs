@@ -18,6 +18,8 @@ use crate::app_config::{AppType, InstalledSkill, SkillApps, UnmanagedSkill};
 use crate::config::get_app_config_dir;
 use crate::database::Database;
 use crate::error::format_skill_error;
+use crate::services::skill_source::{self, RepoLocation, SkillRepoSource};
+use crate::services::skill_update::{self, SkillUpdateInfo};
 
 // ========== 数据结构 ==========
 
@@ -89,7 +91,7 @@ pub struct Skill {
 /// 仓库配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SkillRepo {
-    /// GitHub 用户/组织名
+    /// 用户/组织名（GitLab 可包含子组，如 "group/subgroup"）
     pub owner: String,
     /// 仓库名称
     pub name: String,
@@ -97,6 +99,47 @@ pub struct SkillRepo {
     pub branch: String,
     /// 是否启用
     pub enabled: bool,
+    /// 仓库来源（默认 GitHub）
+    #[serde(default)]
+    pub source: SkillRepoSource,
+    /// 来源地址：GitHub/GitLab/Gitea 为实例根地址，ZIP 为下载地址，Local 为本地目录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// 访问令牌（私有仓库），只接收不返回，避免明文下发到前端
+    #[serde(default, skip_serializing)]
+    pub token: Option<String>,
+}
+
+impl SkillRepo {
+    /// 创建 GitHub 仓库配置
+    pub fn github(owner: &str, name: &str, branch: &str) -> Self {
+        Self {
+            owner: owner.to_string(),
+            name: name.to_string(),
+            branch: branch.to_string(),
+            enabled: true,
+            source: SkillRepoSource::Github,
+            url: None,
+            token: None,
+        }
+    }
+}
+
+/// 已获取到本地的仓库内容
+struct RepoCheckout {
+    root: PathBuf,
+    /// 是否为下载解压出的临时目录（本地来源不可删除）
+    temporary: bool,
+    /// 归档对应的上游提交（ZIP / 本地来源可能无法识别）
+    commit: Option<String>,
+}
+
+impl RepoCheckout {
+    fn cleanup(&self) {
+        if self.temporary {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
 }
 
 /// 技能安装状态（旧版兼容）
@@ -123,30 +166,10 @@ impl Default for SkillStore {
         SkillStore {
             skills: HashMap::new(),
             repos: vec![
-                SkillRepo {
-                    owner: "anthropics".to_string(),
-                    name: "skills".to_string(),
-                    branch: "main".to_string(),
-                    enabled: true,
-                },
-                SkillRepo {
-                    owner: "ComposioHQ".to_string(),
-                    name: "awesome-claude-skills".to_string(),
-                    branch: "master".to_string(),
-                    enabled: true,
-                },
-                SkillRepo {
-                    owner: "cexll".to_string(),
-                    name: "myclaude".to_string(),
-                    branch: "master".to_string(),
-                    enabled: true,
-                },
-                SkillRepo {
-                    owner: "JimLiu".to_string(),
-                    name: "baoyu-skills".to_string(),
-                    branch: "main".to_string(),
-                    enabled: true,
-                },
+                SkillRepo::github("anthropics", "skills", "main"),
+                SkillRepo::github("ComposioHQ", "awesome-claude-skills", "master"),
+                SkillRepo::github("cexll", "myclaude", "master"),
+                SkillRepo::github("JimLiu", "baoyu-skills", "main"),
             ],
         }
     }
@@ -299,36 +322,18 @@ impl SkillService {
         let dest = ssot_dir.join(&install_name);
 
         // 如果已存在则跳过下载
+        let mut source_ref = None;
         if !dest.exists() {
-            let repo = SkillRepo {
-                owner: skill.repo_owner.clone(),
-                name: skill.repo_name.clone(),
-                branch: skill.repo_branch.clone(),
-                enabled: true,
-            };
+            let repo =
+                Self::configured_repo(db, &skill.repo_owner, &skill.repo_name, &skill.repo_branch)?;
 
             // 下载仓库
-            let temp_dir = timeout(
-                std::time::Duration::from_secs(60),
-                self.download_repo(&repo),
-            )
-            .await
-            .map_err(|_| {
-                anyhow!(format_skill_error(
-                    "DOWNLOAD_TIMEOUT",
-                    &[
-                        ("owner", &repo.owner),
-                        ("name", &repo.name),
-                        ("timeout", "60")
-                    ],
-                    Some("checkNetwork"),
-                ))
-            })??;
+            let checkout = self.checkout_repo(&repo, None).await?;
 
             // 复制到 SSOT
-            let source = temp_dir.join(&skill.directory);
+            let source = Self::skill_source_dir(&checkout.root, &skill.directory, &repo);
             if !source.exists() {
-                let _ = fs::remove_dir_all(&temp_dir);
+                checkout.cleanup();
                 return Err(anyhow!(format_skill_error(
                     "SKILL_DIR_NOT_FOUND",
                     &[("path", &source.display().to_string())],
@@ -336,8 +341,10 @@ impl SkillService {
                 )));
             }
 
-            Self::copy_dir_recursive(&source, &dest)?;
-            let _ = fs::remove_dir_all(&temp_dir);
+            let copied = Self::copy_dir_recursive(&source, &dest);
+            checkout.cleanup();
+            copied?;
+            source_ref = checkout.commit;
         }
 
         // 创建 InstalledSkill 记录
@@ -356,6 +363,9 @@ impl SkillService {
             readme_url: skill.readme_url.clone(),
             apps: SkillApps::only(current_app),
             installed_at: chrono::Utc::now().timestamp(),
+            source_ref,
+            content_hash: skill_update::compute_content_hash(&dest).ok(),
+            pinned_ref: None,
         };
 
         // 保存到数据库
@@ -609,6 +619,9 @@ impl SkillService {
                 readme_url: None,
                 apps,
                 installed_at: chrono::Utc::now().timestamp(),
+                source_ref: None,
+                content_hash: None,
+                pinned_ref: None,
             };
 
             // 保存到数据库
@@ -760,6 +773,208 @@ impl SkillService {
         Ok(())
     }
 
+    // ========== 更新检测 ==========
+
+    /// 已配置仓库的来源与令牌，未配置时按 GitHub 处理
+    fn configured_repo(
+        db: &Arc<Database>,
+        owner: &str,
+        name: &str,
+        branch: &str,
+    ) -> Result<SkillRepo> {
+        let mut repo = db
+            .get_skill_repos()?
+            .into_iter()
+            .find(|r| r.owner == owner && r.name == name)
+            .unwrap_or_else(|| SkillRepo::github(owner, name, branch));
+        if !branch.is_empty() {
+            repo.branch = branch.to_string();
+        }
+        Ok(repo)
+    }
+
+    /// 已安装 Skill 在仓库中的相对目录（从 "owner/repo:directory" 格式的 ID 中解析）
+    fn repo_directory(skill: &InstalledSkill) -> &str {
+        skill
+            .id
+            .split_once(':')
+            .map(|(_, directory)| directory)
+            .filter(|directory| !directory.is_empty())
+            .unwrap_or(&skill.directory)
+    }
+
+    /// 检查已安装 Skills 的可用更新
+    ///
+    /// 按仓库（及固定版本）分组下载，上游内容哈希与安装时记录的不同时，
+    /// 比较 SSOT 中的内容与上游内容，仅返回存在差异的 Skill。
+    pub async fn check_updates(&self, db: &Arc<Database>) -> Result<Vec<SkillUpdateInfo>> {
+        let ssot_dir = Self::get_ssot_dir()?;
+
+        let mut groups: HashMap<(String, String, String, Option<String>), Vec<InstalledSkill>> =
+            HashMap::new();
+        for skill in db.get_all_installed_skills()?.into_values() {
+            let (Some(owner), Some(name)) = (skill.repo_owner.clone(), skill.repo_name.clone())
+            else {
+                continue;
+            };
+            let branch = skill.repo_branch.clone().unwrap_or_default();
+            groups
+                .entry((owner, name, branch, skill.pinned_ref.clone()))
+                .or_default()
+                .push(skill);
+        }
+
+        let mut updates = Vec::new();
+        for ((owner, name, branch, pinned_ref), skills) in groups {
+            let repo = Self::configured_repo(db, &owner, &name, &branch)?;
+            let checkout = match self.checkout_repo(&repo, pinned_ref.as_deref()).await {
+                Ok(checkout) => checkout,
+                Err(e) => {
+                    log::warn!("检查仓库 {owner}/{name} 更新失败: {e}");
+                    continue;
+                }
+            };
+
+            for skill in skills {
+                let upstream =
+                    Self::skill_source_dir(&checkout.root, Self::repo_directory(&skill), &repo);
+                if !upstream.exists() {
+                    log::warn!("上游仓库中已不存在 Skill 目录: {}", skill.id);
+                    continue;
+                }
+
+                let changes = match skill_update::detect_update(
+                    skill.content_hash.as_deref(),
+                    &ssot_dir.join(&skill.directory),
+                    &upstream,
+                ) {
+                    Ok(changes) => changes,
+                    Err(e) => {
+                        log::warn!("比较 Skill {} 差异失败: {e}", skill.id);
+                        continue;
+                    }
+                };
+                if changes.is_empty() {
+                    continue;
+                }
+
+                updates.push(SkillUpdateInfo {
+                    id: skill.id,
+                    name: skill.name,
+                    directory: skill.directory,
+                    current_ref: skill.source_ref,
+                    latest_ref: checkout.commit.clone(),
+                    pinned_ref: skill.pinned_ref,
+                    changes,
+                });
+            }
+
+            checkout.cleanup();
+        }
+
+        updates.sort_by_key(|a| a.name.to_lowercase());
+        Ok(updates)
+    }
+
+    /// 更新 Skill 到上游最新内容（已固定版本时更新到固定的 tag / commit）
+    ///
+    /// 新内容先复制到临时目录，成功后再替换 SSOT 中的旧内容，并重新同步到已启用的应用。
+    pub async fn update_skill(&self, db: &Arc<Database>, id: &str) -> Result<InstalledSkill> {
+        let mut skill = db.get_installed_skill(id)?.ok_or_else(|| {
+            anyhow!(format_skill_error(
+                "SKILL_NOT_FOUND",
+                &[("directory", id)],
+                None
+            ))
+        })?;
+        let (Some(owner), Some(name)) = (skill.repo_owner.clone(), skill.repo_name.clone()) else {
+            return Err(anyhow!(format_skill_error("MISSING_REPO_INFO", &[], None)));
+        };
+
+        let branch = skill.repo_branch.clone().unwrap_or_default();
+        let repo = Self::configured_repo(db, &owner, &name, &branch)?;
+        let checkout = self
+            .checkout_repo(&repo, skill.pinned_ref.as_deref())
+            .await?;
+
+        let upstream = Self::skill_source_dir(&checkout.root, Self::repo_directory(&skill), &repo);
+        if !upstream.exists() {
+            checkout.cleanup();
+            return Err(anyhow!(format_skill_error(
+                "SKILL_DIR_NOT_FOUND",
+                &[("path", &upstream.display().to_string())],
+                Some("checkRepoUrl"),
+            )));
+        }
+
+        let ssot_dir = Self::get_ssot_dir()?;
+        let dest = ssot_dir.join(&skill.directory);
+        let staging = ssot_dir.join(format!(".{}.updating", skill.directory));
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+
+        let copied = Self::copy_dir_recursive(&upstream, &staging);
+        checkout.cleanup();
+        if let Err(e) = copied {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+
+        if dest.exists() {
+            fs::remove_dir_all(&dest)?;
+        }
+        fs::rename(&staging, &dest)?;
+
+        skill.source_ref = checkout.commit;
+        skill.content_hash = Some(skill_update::compute_content_hash(&dest)?);
+        db.save_skill(&skill)?;
+
+        for app in skill.apps.enabled_apps() {
+            Self::sync_to_app_dir(&skill.directory, &app)?;
+        }
+
+        log::info!(
+            "Skill {} 已更新到 {}",
+            skill.name,
+            skill.source_ref.as_deref().unwrap_or("最新内容")
+        );
+        Ok(skill)
+    }
+
+    /// 固定 Skill 到指定 tag / commit（`None` 取消固定并跟随分支），并立即更新到对应版本
+    ///
+    /// 更新失败时恢复原有的固定设置。
+    pub async fn pin_skill(
+        &self,
+        db: &Arc<Database>,
+        id: &str,
+        git_ref: Option<String>,
+    ) -> Result<InstalledSkill> {
+        let mut skill = db.get_installed_skill(id)?.ok_or_else(|| {
+            anyhow!(format_skill_error(
+                "SKILL_NOT_FOUND",
+                &[("directory", id)],
+                None
+            ))
+        })?;
+
+        let previous = skill.pinned_ref.clone();
+        skill.pinned_ref = git_ref
+            .map(|r| r.trim().to_string())
+            .filter(|r| !r.is_empty());
+        db.save_skill(&skill)?;
+
+        match self.update_skill(db, id).await {
+            Ok(updated) => Ok(updated),
+            Err(e) => {
+                skill.pinned_ref = previous;
+                db.save_skill(&skill)?;
+                Err(e)
+            }
+        }
+    }
+
     // ========== 发现功能（保留原有逻辑）==========
 
     /// 列出所有可发现的技能（从仓库获取）
@@ -862,30 +1077,28 @@ impl SkillService {
 
     /// 从仓库获取技能列表
     async fn fetch_repo_skills(&self, repo: &SkillRepo) -> Result<Vec<DiscoverableSkill>> {
-        let temp_dir = timeout(std::time::Duration::from_secs(60), self.download_repo(repo))
-            .await
-            .map_err(|_| {
-                anyhow!(format_skill_error(
-                    "DOWNLOAD_TIMEOUT",
-                    &[
-                        ("owner", &repo.owner),
-                        ("name", &repo.name),
-                        ("timeout", "60")
-                    ],
-                    Some("checkNetwork"),
-                ))
-            })??;
+        let checkout = self.checkout_repo(repo, None).await?;
 
         let mut skills = Vec::new();
-        let scan_dir = temp_dir.clone();
-
-        self.scan_dir_recursive(&scan_dir, &scan_dir, repo, &mut skills)?;
-
-        let _ = fs::remove_dir_all(&temp_dir);
+        let result = self.scan_dir_recursive(&checkout.root, &checkout.root, repo, &mut skills);
+        checkout.cleanup();
+        result?;
 
         Ok(skills)
     }
 
+    /// 技能在仓库内容中的实际目录
+    ///
+    /// 仓库根目录本身即为技能时，`directory` 记录为仓库名，此时返回根目录。
+    fn skill_source_dir(root: &Path, directory: &str, repo: &SkillRepo) -> PathBuf {
+        let nested = root.join(directory);
+        if !nested.exists() && directory == repo.name && root.join("SKILL.md").exists() {
+            root.to_path_buf()
+        } else {
+            nested
+        }
+    }
+
     /// 递归扫描目录查找 SKILL.md
     fn scan_dir_recursive(
         &self,
@@ -918,7 +1131,8 @@ impl SkillService {
             let entry = entry?;
             let path = entry.path();
 
-            if path.is_dir() {
+            // 本地来源可能是完整的 git 工作区，跳过版本库元数据
+            if path.is_dir() && entry.file_name() != ".git" {
                 self.scan_dir_recursive(&path, base_dir, repo, skills)?;
             }
         }
@@ -940,10 +1154,7 @@ impl SkillService {
             name: meta.name.unwrap_or_else(|| directory.to_string()),
             description: meta.description.unwrap_or_default(),
             directory: directory.to_string(),
-            readme_url: Some(format!(
-                "https://github.com/{}/{}/tree/{}/{}",
-                repo.owner, repo.name, repo.branch, directory
-            )),
+            readme_url: skill_source::tree_url(repo, directory),
             repo_owner: repo.owner.clone(),
             repo_name: repo.name.clone(),
             repo_branch: repo.branch.clone(),
@@ -993,28 +1204,62 @@ impl SkillService {
         });
     }
 
-    /// 下载仓库
-    async fn download_repo(&self, repo: &SkillRepo) -> Result<PathBuf> {
+    /// 获取仓库内容（60 秒超时）
+    async fn checkout_repo(&self, repo: &SkillRepo, git_ref: Option<&str>) -> Result<RepoCheckout> {
+        timeout(
+            std::time::Duration::from_secs(60),
+            self.download_repo(repo, git_ref),
+        )
+        .await
+        .map_err(|_| {
+            anyhow!(format_skill_error(
+                "DOWNLOAD_TIMEOUT",
+                &[
+                    ("owner", &repo.owner),
+                    ("name", &repo.name),
+                    ("timeout", "60")
+                ],
+                Some("checkNetwork"),
+            ))
+        })?
+    }
+
+    /// 获取仓库内容（下载归档或直接使用本地目录）
+    async fn download_repo(&self, repo: &SkillRepo, git_ref: Option<&str>) -> Result<RepoCheckout> {
+        let requests = match skill_source::resolve_location(repo, git_ref)? {
+            RepoLocation::Directory(root) => {
+                if !root.is_dir() {
+                    return Err(anyhow!(format_skill_error(
+                        "LOCAL_REPO_NOT_FOUND",
+                        &[("path", &root.display().to_string())],
+                        Some("checkRepoUrl"),
+                    )));
+                }
+                return Ok(RepoCheckout {
+                    root,
+                    temporary: false,
+                    commit: None,
+                });
+            }
+            RepoLocation::Archives(requests) => requests,
+        };
+
         let temp_dir = tempfile::tempdir()?;
         let temp_path = temp_dir.path().to_path_buf();
         let _ = temp_dir.keep();
 
-        let branches = if repo.branch.is_empty() {
-            vec!["main", "master"]
-        } else {
-            vec![repo.branch.as_str(), "main", "master"]
-        };
-
         let mut last_error = None;
-        for branch in branches {
-            let url = format!(
-                "https://github.com/{}/{}/archive/refs/heads/{}.zip",
-                repo.owner, repo.name, branch
-            );
-
-            match self.download_and_extract(&url, &temp_path).await {
-                Ok(_) => {
-                    return Ok(temp_path);
+        for request in requests {
+            match self
+                .download_and_extract(&request.url, &request.headers, &temp_path)
+                .await
+            {
+                Ok(commit) => {
+                    return Ok(RepoCheckout {
+                        root: temp_path,
+                        temporary: true,
+                        commit,
+                    });
                 }
                 Err(e) => {
                     last_error = Some(e);
@@ -1023,20 +1268,32 @@ impl SkillService {
             }
         }
 
+        let _ = fs::remove_dir_all(&temp_path);
         Err(last_error.unwrap_or_else(|| anyhow::anyhow!("所有分支下载失败")))
     }
 
-    /// 下载并解压 ZIP
-    async fn download_and_extract(&self, url: &str, dest: &Path) -> Result<()> {
+    /// 下载并解压 ZIP，返回归档对应的提交
+    ///
+    /// 所有条目位于同一顶层目录时（托管平台归档的常见结构）剥离该目录，否则按原样解压。
+    async fn download_and_extract(
+        &self,
+        url: &str,
+        headers: &[(&'static str, String)],
+        dest: &Path,
+    ) -> Result<Option<String>> {
         let client = crate::proxy::http_client::get();
-        let response = client.get(url).send().await?;
+        let mut request = client.get(url);
+        for (name, value) in headers {
+            request = request.header(*name, value);
+        }
+        let response = request.send().await?;
         if !response.status().is_success() {
             let status = response.status().as_u16().to_string();
             return Err(anyhow::anyhow!(format_skill_error(
                 "DOWNLOAD_FAILED",
                 &[("status", &status)],
                 match status.as_str() {
-                    "403" => Some("http403"),
+                    "401" | "403" => Some("http403"),
                     "404" => Some("http404"),
                     "429" => Some("http429"),
                     _ => Some("checkNetwork"),
@@ -1048,30 +1305,32 @@ impl SkillService {
         let cursor = std::io::Cursor::new(bytes);
         let mut archive = zip::ZipArchive::new(cursor)?;
 
-        let root_name = if !archive.is_empty() {
-            let first_file = archive.by_index(0)?;
-            let name = first_file.name();
-            name.split('/').next().unwrap_or("").to_string()
-        } else {
+        if archive.is_empty() {
             return Err(anyhow::anyhow!(format_skill_error(
                 "EMPTY_ARCHIVE",
                 &[],
                 Some("checkRepoUrl"),
             )));
-        };
+        }
+
+        let root_prefix = Self::archive_root_prefix(&mut archive);
+        let commit = Self::archive_commit(archive.comment());
 
         for i in 0..archive.len() {
             let mut file = archive.by_index(i)?;
-            let file_path = file.name();
+            let Some(file_path) = file.enclosed_name() else {
+                continue;
+            };
 
-            let relative_path =
-                if let Some(stripped) = file_path.strip_prefix(&format!("{root_name}/")) {
-                    stripped
-                } else {
-                    continue;
-                };
+            let relative_path = match &root_prefix {
+                Some(root) => match file_path.strip_prefix(root) {
+                    Ok(stripped) => stripped.to_path_buf(),
+                    Err(_) => continue,
+                },
+                None => file_path,
+            };
 
-            if relative_path.is_empty() {
+            if relative_path.as_os_str().is_empty() {
                 continue;
             }
 
@@ -1088,7 +1347,37 @@ impl SkillService {
             }
         }
 
-        Ok(())
+        Ok(commit)
+    }
+
+    /// 从归档注释中读取提交 ID
+    ///
+    /// `git archive` 生成的 ZIP（GitHub / GitLab / Gitea 均如此）会把提交 ID 写入归档注释。
+    fn archive_commit(comment: &[u8]) -> Option<String> {
+        let comment = std::str::from_utf8(comment).ok()?.trim();
+        let is_object_id =
+            matches!(comment.len(), 40 | 64) && comment.chars().all(|c| c.is_ascii_hexdigit());
+        is_object_id.then(|| comment.to_ascii_lowercase())
+    }
+
+    /// 所有条目共享的顶层目录（仅当每个条目都位于该目录下时返回）
+    fn archive_root_prefix<R: std::io::Read + std::io::Seek>(
+        archive: &mut zip::ZipArchive<R>,
+    ) -> Option<PathBuf> {
+        let mut root: Option<String> = None;
+        for name in archive.file_names() {
+            // 顶层的普通文件说明不存在统一的根目录
+            let (first, _) = name.split_once('/')?;
+            if first.is_empty() {
+                return None;
+            }
+            match &root {
+                Some(existing) if existing != first => return None,
+                Some(_) => {}
+                None => root = Some(first.to_string()),
+            }
+        }
+        root.map(PathBuf::from)
     }
 
     /// 递归复制目录
@@ -1197,6 +1486,9 @@ impl SkillService {
                 readme_url: None,
                 apps: SkillApps::only(current_app),
                 installed_at: chrono::Utc::now().timestamp(),
+                source_ref: None,
+                content_hash: None,
+                pinned_ref: None,
             };
 
             // 保存到数据库
@@ -1412,6 +1704,9 @@ pub fn migrate_skills_to_ssot(db: &Arc<Database>) -> Result<usize> {
             readme_url: None,
             apps,
             installed_at: chrono::Utc::now().timestamp(),
+            source_ref: None,
+            content_hash: None,
+            pinned_ref: None,
         };
 
         db.save_skill(&skill)?;
@@ -0,0 +1,442 @@
+//! Skill 仓库来源
+//!
+//! 将不同托管平台的仓库统一为两种获取方式：下载 ZIP 归档，或直接读取本地目录。
+//! - GitHub：`{base}/{owner}/{name}/archive/refs/heads/{branch}.zip`；配置令牌时改用 API zipball（支持私有仓库）
+//! - GitLab：`{base}/api/v4/projects/{owner%2Fname}/repository/archive.zip?sha={branch}`
+//! - Gitea / Forgejo：`{base}/{owner}/{name}/archive/{branch}.zip`
+//! - ZIP：任意可直接下载的 ZIP 地址
+//! - Local：本地目录，不做下载
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::error::format_skill_error;
+use crate::services::skill::SkillRepo;
+
+const GITHUB_BASE_URL: &str = "https://github.com";
+const GITHUB_API_BASE_URL: &str = "https://api.github.com";
+
+/// Skill 仓库来源类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SkillRepoSource {
+    /// GitHub（含 GitHub Enterprise）
+    #[default]
+    Github,
+    /// GitLab（含自建实例）
+    Gitlab,
+    /// Gitea / Forgejo
+    Gitea,
+    /// 任意 ZIP 下载地址
+    Zip,
+    /// 本地目录
+    Local,
+}
+
+impl SkillRepoSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Github => "github",
+            Self::Gitlab => "gitlab",
+            Self::Gitea => "gitea",
+            Self::Zip => "zip",
+            Self::Local => "local",
+        }
+    }
+
+    /// 从数据库字段解析，未知值按 GitHub 处理
+    pub fn from_db_str(value: &str) -> Self {
+        match value {
+            "gitlab" => Self::Gitlab,
+            "gitea" | "forgejo" => Self::Gitea,
+            "zip" => Self::Zip,
+            "local" => Self::Local,
+            _ => Self::Github,
+        }
+    }
+}
+
+impl std::fmt::Display for SkillRepoSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 一次归档下载请求
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveRequest {
+    pub url: String,
+    pub headers: Vec<(&'static str, String)>,
+}
+
+/// 仓库内容的获取方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepoLocation {
+    /// 依次尝试下载的归档（按候选分支排列）
+    Archives(Vec<ArchiveRequest>),
+    /// 本地目录
+    Directory(PathBuf),
+}
+
+/// 校验仓库来源配置
+pub fn validate_repo(repo: &SkillRepo) -> Result<()> {
+    if repo.owner.trim().is_empty() || repo.name.trim().is_empty() {
+        return Err(anyhow!(format_skill_error(
+            "MISSING_REPO_INFO",
+            &[],
+            Some("checkRepoUrl"),
+        )));
+    }
+
+    match repo.source {
+        SkillRepoSource::Github => Ok(()),
+        SkillRepoSource::Gitlab | SkillRepoSource::Gitea | SkillRepoSource::Zip => {
+            source_url(repo).map(|_| ())
+        }
+        SkillRepoSource::Local => {
+            let path = PathBuf::from(source_url(repo)?);
+            if path.is_dir() {
+                Ok(())
+            } else {
+                Err(anyhow!(format_skill_error(
+                    "LOCAL_REPO_NOT_FOUND",
+                    &[("path", &path.display().to_string())],
+                    Some("checkRepoUrl"),
+                )))
+            }
+        }
+    }
+}
+
+/// 解析仓库内容的获取方式
+///
+/// `git_ref` 为固定的 tag / commit 时只下载该版本，否则按分支候选依次尝试。
+pub fn resolve_location(repo: &SkillRepo, git_ref: Option<&str>) -> Result<RepoLocation> {
+    let token = repo
+        .token
+        .as_deref()
+        .map(str::trim)
+        .filter(|t| !t.is_empty());
+
+    let pinned = git_ref.map(str::trim).filter(|r| !r.is_empty());
+    // ZIP 归档与本地目录没有版本概念，无法固定到 tag / commit
+    if pinned.is_some() && matches!(repo.source, SkillRepoSource::Zip | SkillRepoSource::Local) {
+        return Err(anyhow!(format_skill_error(
+            "PIN_NOT_SUPPORTED",
+            &[("source", repo.source.as_str())],
+            None,
+        )));
+    }
+
+    let refs = match pinned {
+        Some(git_ref) => vec![git_ref],
+        None => branch_candidates(&repo.branch),
+    };
+
+    let requests = match repo.source {
+        SkillRepoSource::Local => {
+            return Ok(RepoLocation::Directory(PathBuf::from(source_url(repo)?)));
+        }
+        SkillRepoSource::Zip => {
+            let mut headers = Vec::new();
+            if let Some(token) = token {
+                headers.push(("Authorization", format!("Bearer {token}")));
+            }
+            vec![ArchiveRequest {
+                url: source_url(repo)?.to_string(),
+                headers,
+            }]
+        }
+        SkillRepoSource::Github => {
+            let base = base_url(repo, GITHUB_BASE_URL);
+            refs.into_iter()
+                .map(|reference| match token {
+                    Some(token) => {
+                        let api_base = if base == GITHUB_BASE_URL {
+                            GITHUB_API_BASE_URL.to_string()
+                        } else {
+                            format!("{base}/api/v3")
+                        };
+                        ArchiveRequest {
+                            url: format!(
+                                "{api_base}/repos/{}/{}/zipball/{reference}",
+                                repo.owner, repo.name
+                            ),
+                            headers: vec![
+                                ("Authorization", format!("Bearer {token}")),
+                                ("Accept", "application/vnd.github+json".to_string()),
+                            ],
+                        }
+                    }
+                    // 固定版本可能是 tag 或 commit，使用不限定 refs/heads 的归档地址
+                    None if git_ref.is_some() => ArchiveRequest {
+                        url: format!(
+                            "{base}/{}/{}/archive/{reference}.zip",
+                            repo.owner, repo.name
+                        ),
+                        headers: Vec::new(),
+                    },
+                    None => ArchiveRequest {
+                        url: format!(
+                            "{base}/{}/{}/archive/refs/heads/{reference}.zip",
+                            repo.owner, repo.name
+                        ),
+                        headers: Vec::new(),
+                    },
+                })
+                .collect()
+        }
+        SkillRepoSource::Gitlab => {
+            let base = source_url(repo)?.trim_end_matches('/');
+            let project: String = url::form_urlencoded::byte_serialize(
+                format!("{}/{}", repo.owner, repo.name).as_bytes(),
+            )
+            .collect();
+            refs.into_iter()
+                .map(|reference| {
+                    let sha: String =
+                        url::form_urlencoded::byte_serialize(reference.as_bytes()).collect();
+                    ArchiveRequest {
+                        url: format!(
+                            "{base}/api/v4/projects/{project}/repository/archive.zip?sha={sha}"
+                        ),
+                        headers: token
+                            .map(|t| vec![("PRIVATE-TOKEN", t.to_string())])
+                            .unwrap_or_default(),
+                    }
+                })
+                .collect()
+        }
+        SkillRepoSource::Gitea => {
+            let base = source_url(repo)?.trim_end_matches('/');
+            refs.into_iter()
+                .map(|reference| ArchiveRequest {
+                    url: format!(
+                        "{base}/{}/{}/archive/{reference}.zip",
+                        repo.owner, repo.name
+                    ),
+                    headers: token
+                        .map(|t| vec![("Authorization", format!("token {t}"))])
+                        .unwrap_or_default(),
+                })
+                .collect()
+        }
+    };
+
+    Ok(RepoLocation::Archives(requests))
+}
+
+/// 技能目录在托管平台上的浏览地址（ZIP / 本地来源没有浏览地址）
+pub fn tree_url(repo: &SkillRepo, directory: &str) -> Option<String> {
+    let branch = if repo.branch.is_empty() {
+        "main"
+    } else {
+        repo.branch.as_str()
+    };
+
+    match repo.source {
+        SkillRepoSource::Github => Some(format!(
+            "{}/{}/{}/tree/{branch}/{directory}",
+            base_url(repo, GITHUB_BASE_URL),
+            repo.owner,
+            repo.name
+        )),
+        SkillRepoSource::Gitlab => Some(format!(
+            "{}/{}/{}/-/tree/{branch}/{directory}",
+            source_url(repo).ok()?.trim_end_matches('/'),
+            repo.owner,
+            repo.name
+        )),
+        SkillRepoSource::Gitea => Some(format!(
+            "{}/{}/{}/src/branch/{branch}/{directory}",
+            source_url(repo).ok()?.trim_end_matches('/'),
+            repo.owner,
+            repo.name
+        )),
+        SkillRepoSource::Zip | SkillRepoSource::Local => None,
+    }
+}
+
+/// 候选分支：优先配置的分支，再回退到 main / master
+fn branch_candidates(branch: &str) -> Vec<&str> {
+    let mut branches = Vec::with_capacity(3);
+    if !branch.is_empty() {
+        branches.push(branch);
+    }
+    for fallback in ["main", "master"] {
+        if !branches.contains(&fallback) {
+            branches.push(fallback);
+        }
+    }
+    branches
+}
+
+fn base_url<'a>(repo: &'a SkillRepo, default: &'a str) -> &'a str {
+    repo.url
+        .as_deref()
+        .map(str::trim)
+        .filter(|u| !u.is_empty())
+        .unwrap_or(default)
+        .trim_end_matches('/')
+}
+
+fn source_url(repo: &SkillRepo) -> Result<&str> {
+    repo.url
+        .as_deref()
+        .map(str::trim)
+        .filter(|u| !u.is_empty())
+        .ok_or_else(|| {
+            anyhow!(format_skill_error(
+                "REPO_URL_REQUIRED",
+                &[("source", repo.source.as_str())],
+                Some("checkRepoUrl"),
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo(source: SkillRepoSource, url: Option<&str>, token: Option<&str>) -> SkillRepo {
+        SkillRepo {
+            owner: "team/tools".to_string(),
+            name: "skills".to_string(),
+            branch: "dev".to_string(),
+            enabled: true,
+            source,
+            url: url.map(str::to_string),
+            token: token.map(str::to_string),
+        }
+    }
+
+    fn archives(repo: &SkillRepo) -> Vec<ArchiveRequest> {
+        match resolve_location(repo, None).expect("resolve") {
+            RepoLocation::Archives(requests) => requests,
+            RepoLocation::Directory(path) => panic!("unexpected directory {path:?}"),
+        }
+    }
+
+    #[test]
+    fn github_uses_archive_urls_with_branch_fallback() {
+        let mut repo = repo(SkillRepoSource::Github, None, None);
+        repo.owner = "anthropics".to_string();
+        let urls: Vec<String> = archives(&repo).into_iter().map(|r| r.url).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://github.com/anthropics/skills/archive/refs/heads/dev.zip",
+                "https://github.com/anthropics/skills/archive/refs/heads/main.zip",
+                "https://github.com/anthropics/skills/archive/refs/heads/master.zip",
+            ]
+        );
+    }
+
+    #[test]
+    fn pinned_ref_downloads_single_archive() {
+        let mut repo = repo(SkillRepoSource::Github, None, None);
+        repo.owner = "acme".to_string();
+        let requests = match resolve_location(&repo, Some("v1.2.0")).expect("resolve") {
+            RepoLocation::Archives(requests) => requests,
+            RepoLocation::Directory(path) => panic!("unexpected directory {path:?}"),
+        };
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].url,
+            "https://github.com/acme/skills/archive/v1.2.0.zip"
+        );
+    }
+
+    #[test]
+    fn token_is_accepted_but_never_serialized() {
+        let parsed: SkillRepo = serde_json::from_value(serde_json::json!({
+            "owner": "acme",
+            "name": "skills",
+            "branch": "main",
+            "enabled": true,
+            "source": "gitlab",
+            "token": "glpat",
+        }))
+        .expect("deserialize");
+        assert_eq!(parsed.token.as_deref(), Some("glpat"));
+
+        let value = serde_json::to_value(&parsed).expect("serialize");
+        assert!(value.get("token").is_none(), "{value}");
+    }
+
+    #[test]
+    fn github_token_switches_to_api_zipball() {
+        let mut repo = repo(SkillRepoSource::Github, None, Some("ghp_x"));
+        repo.owner = "acme".to_string();
+        let first = archives(&repo).remove(0);
+        assert_eq!(
+            first.url,
+            "https://api.github.com/repos/acme/skills/zipball/dev"
+        );
+        assert!(first
+            .headers
+            .contains(&("Authorization", "Bearer ghp_x".to_string())));
+    }
+
+    #[test]
+    fn gitlab_encodes_project_path_and_sends_private_token() {
+        let repo = repo(
+            SkillRepoSource::Gitlab,
+            Some("https://gitlab.example.com/"),
+            Some("glpat"),
+        );
+        let first = archives(&repo).remove(0);
+        assert_eq!(
+            first.url,
+            "https://gitlab.example.com/api/v4/projects/team%2Ftools%2Fskills/repository/archive.zip?sha=dev"
+        );
+        assert_eq!(first.headers, vec![("PRIVATE-TOKEN", "glpat".to_string())]);
+        assert_eq!(
+            tree_url(&repo, "pdf").as_deref(),
+            Some("https://gitlab.example.com/team/tools/skills/-/tree/dev/pdf")
+        );
+    }
+
+    #[test]
+    fn gitea_uses_archive_endpoint() {
+        let mut repo = repo(SkillRepoSource::Gitea, Some("https://codeberg.org"), None);
+        repo.owner = "alice".to_string();
+        let first = archives(&repo).remove(0);
+        assert_eq!(
+            first.url,
+            "https://codeberg.org/alice/skills/archive/dev.zip"
+        );
+        assert!(first.headers.is_empty());
+        assert_eq!(
+            tree_url(&repo, "pdf").as_deref(),
+            Some("https://codeberg.org/alice/skills/src/branch/dev/pdf")
+        );
+    }
+
+    #[test]
+    fn zip_and_local_sources_require_url() {
+        let zip = repo(
+            SkillRepoSource::Zip,
+            Some("https://example.com/s.zip"),
+            None,
+        );
+        assert_eq!(archives(&zip).len(), 1);
+        assert!(tree_url(&zip, "pdf").is_none());
+
+        let missing = repo(SkillRepoSource::Zip, None, None);
+        let err = resolve_location(&missing, None).expect_err("url required");
+        assert!(err.to_string().contains("REPO_URL_REQUIRED"));
+
+        let local = repo(SkillRepoSource::Local, Some("/tmp/skills"), None);
+        assert_eq!(
+            resolve_location(&local, None).expect("resolve"),
+            RepoLocation::Directory(PathBuf::from("/tmp/skills"))
+        );
+
+        for source in [zip, local] {
+            let err = resolve_location(&source, Some("v1.2.0")).expect_err("pin unsupported");
+            assert!(err.to_string().contains("PIN_NOT_SUPPORTED"));
+        }
+    }
+}
@@ -0,0 +1,253 @@
+//! Skill 更新检测
+//!
+//! 通过内容哈希判断已安装技能与上游是否一致，并生成文件级差异供前端展示。
+
+use anyhow::Result;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 文件变更类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SkillFileStatus {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// 单个文件的变更
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillFileChange {
+    /// 相对技能目录的路径（使用 `/` 分隔）
+    pub path: String,
+    pub status: SkillFileStatus,
+    /// unified diff 文本（二进制文件为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+}
+
+/// 可用更新
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillUpdateInfo {
+    pub id: String,
+    pub name: String,
+    pub directory: String,
+    /// 当前安装的上游提交
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_ref: Option<String>,
+    /// 上游最新提交（无法从归档中识别时为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latest_ref: Option<String>,
+    /// 固定的 tag / commit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pinned_ref: Option<String>,
+    /// 从当前安装内容到上游内容的文件变更
+    pub changes: Vec<SkillFileChange>,
+}
+
+/// 计算技能目录的内容哈希（SHA-256，按相对路径排序，忽略 `.git`）
+pub fn compute_content_hash(dir: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    for (relative, path) in collect_files(dir)? {
+        let content = fs::read(&path)?;
+        hasher.update(relative.as_bytes());
+        hasher.update([0u8]);
+        hasher.update((content.len() as u64).to_le_bytes());
+        hasher.update(&content);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// 检测上游更新，返回从当前安装内容到上游内容的文件变更（没有更新时为空）
+///
+/// 上游内容哈希与安装时记录的 `installed_hash` 一致时视为没有更新（本地改动不算更新）；
+/// 未记录哈希（旧版本安装的技能）时直接比较两个目录。
+pub fn detect_update(
+    installed_hash: Option<&str>,
+    current: &Path,
+    latest: &Path,
+) -> Result<Vec<SkillFileChange>> {
+    if let Some(hash) = installed_hash {
+        if compute_content_hash(latest)? == hash {
+            return Ok(Vec::new());
+        }
+    }
+    diff_dirs(current, latest)
+}
+
+/// 比较两个技能目录，返回从 `current` 到 `latest` 的文件变更
+pub fn diff_dirs(current: &Path, latest: &Path) -> Result<Vec<SkillFileChange>> {
+    let current_files = collect_files(current)?;
+    let latest_files = collect_files(latest)?;
+
+    let mut paths: Vec<&String> = current_files.keys().chain(latest_files.keys()).collect();
+    paths.sort();
+    paths.dedup();
+
+    let mut changes = Vec::new();
+    for path in paths {
+        let old = current_files.get(path).map(fs::read).transpose()?;
+        let new = latest_files.get(path).map(fs::read).transpose()?;
+
+        let status = match (&old, &new) {
+            (None, Some(_)) => SkillFileStatus::Added,
+            (Some(_), None) => SkillFileStatus::Removed,
+            (Some(a), Some(b)) if a != b => SkillFileStatus::Modified,
+            _ => continue,
+        };
+
+        changes.push(SkillFileChange {
+            path: path.clone(),
+            status,
+            diff: unified_diff(path, old.as_deref(), new.as_deref()),
+        });
+    }
+
+    Ok(changes)
+}
+
+/// 生成文本文件的 unified diff，任一侧不是 UTF-8 时返回 `None`
+fn unified_diff(path: &str, old: Option<&[u8]>, new: Option<&[u8]>) -> Option<String> {
+    let old = std::str::from_utf8(old.unwrap_or_default()).ok()?;
+    let new = std::str::from_utf8(new.unwrap_or_default()).ok()?;
+    Some(
+        similar::TextDiff::from_lines(old, new)
+            .unified_diff()
+            .context_radius(3)
+            .header(&format!("a/{path}"), &format!("b/{path}"))
+            .to_string(),
+    )
+}
+
+/// 收集目录下所有文件（相对路径 -> 绝对路径）
+fn collect_files(dir: &Path) -> Result<BTreeMap<String, PathBuf>> {
+    let mut files = BTreeMap::new();
+    if dir.is_dir() {
+        collect_files_recursive(dir, dir, &mut files)?;
+    }
+    Ok(files)
+}
+
+fn collect_files_recursive(
+    base: &Path,
+    current: &Path,
+    files: &mut BTreeMap<String, PathBuf>,
+) -> Result<()> {
+    for entry in fs::read_dir(current)? {
+        let entry = entry?;
+        if entry.file_name() == ".git" {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files_recursive(base, &path, files)?;
+        } else {
+            let relative = path
+                .strip_prefix(base)
+                .unwrap_or(&path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.insert(relative, path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, relative: &str, content: &[u8]) {
+        let path = dir.join(relative);
+        fs::create_dir_all(path.parent().expect("file has parent dir")).expect("create parent dir");
+        fs::write(path, content).expect("write file");
+    }
+
+    #[test]
+    fn content_hash_ignores_git_metadata_and_tracks_content() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        write(dir.path(), "SKILL.md", b"---\nname: demo\n---\n");
+        let hash = compute_content_hash(dir.path()).expect("hash skill dir");
+
+        write(dir.path(), ".git/HEAD", b"ref: refs/heads/main");
+        assert_eq!(
+            compute_content_hash(dir.path()).expect("hash skill dir"),
+            hash
+        );
+
+        write(dir.path(), "scripts/run.sh", b"echo hi");
+        assert_ne!(
+            compute_content_hash(dir.path()).expect("hash skill dir"),
+            hash
+        );
+    }
+
+    #[test]
+    fn diff_dirs_reports_file_level_changes() {
+        let current = tempfile::tempdir().expect("create temp dir");
+        let latest = tempfile::tempdir().expect("create temp dir");
+        write(current.path(), "SKILL.md", b"line one\nline two\n");
+        write(current.path(), "old.txt", b"gone");
+        write(current.path(), "same.txt", b"same");
+        write(latest.path(), "SKILL.md", b"line one\nline 2\n");
+        write(latest.path(), "same.txt", b"same");
+        write(latest.path(), "bin/data", &[0xff, 0xfe]);
+
+        let changes = diff_dirs(current.path(), latest.path()).expect("diff skill dirs");
+        let summary: Vec<(&str, SkillFileStatus)> = changes
+            .iter()
+            .map(|c| (c.path.as_str(), c.status))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("SKILL.md", SkillFileStatus::Modified),
+                ("bin/data", SkillFileStatus::Added),
+                ("old.txt", SkillFileStatus::Removed),
+            ]
+        );
+
+        let diff = changes[0].diff.as_deref().expect("text diff");
+        assert!(diff.contains("-line two"));
+        assert!(diff.contains("+line 2"));
+        assert!(changes[1].diff.is_none());
+    }
+
+    #[test]
+    fn detect_update_compares_upstream_with_installed_hash() {
+        let current = tempfile::tempdir().expect("create temp dir");
+        let latest = tempfile::tempdir().expect("create temp dir");
+        write(current.path(), "SKILL.md", b"v1");
+        write(latest.path(), "SKILL.md", b"v1");
+        let installed_hash = compute_content_hash(latest.path()).expect("hash skill dir");
+
+        // 本地改动但上游未变：没有更新
+        write(current.path(), "SKILL.md", b"v1 local");
+        assert!(
+            detect_update(Some(&installed_hash), current.path(), latest.path())
+                .expect("detect update")
+                .is_empty()
+        );
+        // 未记录哈希时按目录比较
+        assert_eq!(
+            detect_update(None, current.path(), latest.path())
+                .expect("detect update")
+                .len(),
+            1
+        );
+
+        // 上游变化：返回相对本地内容的变更
+        write(latest.path(), "SKILL.md", b"v2");
+        let changes = detect_update(Some(&installed_hash), current.path(), latest.path())
+            .expect("detect update");
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].status, SkillFileStatus::Modified);
+    }
+}
@@ -0,0 +1,515 @@
+//! 自动切换规则
+//!
+//! 按时间窗口、用量限额或用量脚本剩余额度自动切换供应商或调整故障转移队列，
+//! 每次触发都会写入规则触发记录。
+
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::app_config::AppType;
+use crate::error::AppError;
+use crate::services::{ProviderService, ProviderSortUpdate};
+use crate::store::AppState;
+use crate::switch_rule::{
+    parse_hhmm, SwitchRule, SwitchRuleAction, SwitchRuleCondition, SwitchRuleFiring,
+};
+
+/// 规则评估间隔
+const RULE_EVAL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 用量脚本结果缓存时间，避免每分钟都请求上游
+const USAGE_CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// 规则引擎运行时状态（进程内，重启后重置）
+#[derive(Default)]
+pub struct SwitchRuleRuntime {
+    /// 条件满足且动作已生效的规则 - key: rule id
+    ///
+    /// 条件持续满足期间不再重复执行，避免覆盖用户的手动切换；
+    /// 条件不再满足时移除，下次满足时重新执行。
+    applied: HashSet<String>,
+}
+
+impl SwitchRuleRuntime {
+    /// 条件满足时是否需要执行动作（启动时已处于满足状态的规则同样会执行）
+    fn should_apply(&self, rule_id: &str, matched: bool) -> bool {
+        matched && !self.applied.contains(rule_id)
+    }
+
+    /// 记录规则的条件状态；仅在动作执行成功或目标已达成后标记为已生效
+    fn record(&mut self, rule_id: &str, applied: bool) {
+        if applied {
+            self.applied.insert(rule_id.to_string());
+        } else {
+            self.applied.remove(rule_id);
+        }
+    }
+}
+
+/// 自动切换规则相关业务
+pub struct SwitchRuleService;
+
+impl SwitchRuleService {
+    pub fn list(state: &AppState, app_type: Option<&str>) -> Result<Vec<SwitchRule>, AppError> {
+        state.db.get_switch_rules(app_type)
+    }
+
+    /// 新增或更新规则（保存前校验时间格式与引用的供应商）
+    pub fn upsert(state: &AppState, mut rule: SwitchRule) -> Result<SwitchRule, AppError> {
+        rule.name = rule.name.trim().to_string();
+        if rule.name.is_empty() {
+            return Err(AppError::InvalidInput("规则名称不能为空".to_string()));
+        }
+        let app_type = AppType::from_str(&rule.app_type)?;
+        if app_type.is_additive_mode() {
+            return Err(AppError::InvalidInput(format!(
+                "{} 不支持自动切换规则",
+                app_type.as_str()
+            )));
+        }
+
+        match &rule.condition {
+            SwitchRuleCondition::TimeWindow { days, start, end } => {
+                let start = parse_hhmm(start).map_err(AppError::InvalidInput)?;
+                let end = parse_hhmm(end).map_err(AppError::InvalidInput)?;
+                if start == end {
+                    return Err(AppError::InvalidInput(
+                        "时间窗口的开始与结束时间不能相同".to_string(),
+                    ));
+                }
+                if days.iter().any(|d| *d > 6) {
+                    return Err(AppError::InvalidInput(
+                        "星期取值应为 0（周日）到 6（周六）".to_string(),
+                    ));
+                }
+            }
+            SwitchRuleCondition::UsageRemainingBelow { threshold, .. }
+                if !threshold.is_finite() =>
+            {
+                return Err(AppError::InvalidInput("剩余额度阈值无效".to_string()));
+            }
+            _ => {}
+        }
+
+        let referenced = rule
+            .condition
+            .provider_id()
+            .into_iter()
+            .chain(std::iter::once(rule.action.provider_id()));
+        for provider_id in referenced {
+            if state
+                .db
+                .get_provider_by_id(provider_id, app_type.as_str())?
+                .is_none()
+            {
+                return Err(AppError::InvalidInput(format!(
+                    "规则引用的供应商 {provider_id} 不存在"
+                )));
+            }
+        }
+
+        if rule.id.trim().is_empty() {
+            rule.id = uuid::Uuid::new_v4().to_string();
+        }
+        if rule.created_at.is_none() {
+            rule.created_at = Some(chrono::Utc::now().timestamp());
+        }
+        state.db.save_switch_rule(&rule)?;
+        Ok(rule)
+    }
+
+    pub fn delete(state: &AppState, id: &str) -> Result<bool, AppError> {
+        state.db.delete_switch_rule(id)
+    }
+
+    pub fn get_log(
+        state: &AppState,
+        app_type: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<SwitchRuleFiring>, AppError> {
+        state.db.get_switch_rule_log(app_type, limit)
+    }
+
+    /// 评估所有启用的规则并执行触发的动作，返回本次的触发记录
+    ///
+    /// 条件满足且动作尚未生效时执行（包括启动时已处于满足状态的规则），
+    /// 执行失败的规则会在下次评估时重试。动作目标已达成（如已是当前供应商）时不记录。
+    pub fn evaluate(state: &AppState, runtime: &mut SwitchRuleRuntime) -> Vec<SwitchRuleFiring> {
+        let rules = match state.db.get_switch_rules(None) {
+            Ok(rules) => rules,
+            Err(e) => {
+                log::warn!("[SwitchRule] 读取自动切换规则失败: {e}");
+                return Vec::new();
+            }
+        };
+        runtime
+            .applied
+            .retain(|id| rules.iter().any(|r| r.enabled && &r.id == id));
+
+        let mut firings = Vec::new();
+        for rule in rules.iter().filter(|r| r.enabled) {
+            let Ok(app_type) = AppType::from_str(&rule.app_type) else {
+                continue;
+            };
+
+            let matched = match Self::condition_matches(state, &app_type, &rule.condition) {
+                Ok(matched) => matched,
+                Err(e) => {
+                    log::warn!("[SwitchRule] 评估规则 {} 失败: {e}", rule.name);
+                    continue;
+                }
+            };
+            if !matched {
+                runtime.record(&rule.id, false);
+                continue;
+            }
+            if !runtime.should_apply(&rule.id, matched) {
+                continue;
+            }
+
+            let (success, from_provider_id, message) =
+                match Self::execute(state, &app_type, &rule.action) {
+                    Ok(None) => {
+                        runtime.record(&rule.id, true);
+                        continue;
+                    }
+                    Ok(Some(from)) => {
+                        runtime.record(&rule.id, true);
+                        (true, from, Some(Self::describe(&rule.condition)))
+                    }
+                    Err(e) => (false, None, Some(e.to_string())),
+                };
+
+            let mut firing = SwitchRuleFiring {
+                id: 0,
+                rule_id: rule.id.clone(),
+                rule_name: rule.name.clone(),
+                app_type: rule.app_type.clone(),
+                action: rule.action.as_str().to_string(),
+                from_provider_id,
+                to_provider_id: rule.action.provider_id().to_string(),
+                success,
+                message,
+                fired_at: chrono::Utc::now().timestamp(),
+            };
+            match state.db.insert_switch_rule_firing(&firing) {
+                Ok(id) => firing.id = id,
+                Err(e) => log::warn!("[SwitchRule] 记录规则触发失败: {e}"),
+            }
+            if success {
+                log::info!(
+                    "[SwitchRule] 规则 {} 已触发: {} -> {}",
+                    rule.name,
+                    rule.action.as_str(),
+                    firing.to_provider_id
+                );
+            } else {
+                log::warn!(
+                    "[SwitchRule] 规则 {} 执行失败: {}",
+                    rule.name,
+                    firing.message.as_deref().unwrap_or_default()
+                );
+            }
+            firings.push(firing);
+        }
+
+        firings
+    }
+
+    fn condition_matches(
+        state: &AppState,
+        app_type: &AppType,
+        condition: &SwitchRuleCondition,
+    ) -> Result<bool, AppError> {
+        if let Some(matched) = condition.matches_time(&chrono::Local::now()) {
+            return Ok(matched);
+        }
+
+        let provider_id = match condition.provider_id() {
+            Some(id) => id.to_string(),
+            None => match crate::settings::get_effective_current_provider(&state.db, app_type)? {
+                Some(id) => id,
+                None => return Ok(false),
+            },
+        };
+
+        match condition {
+            SwitchRuleCondition::DailyLimitExceeded { .. } => Ok(state
+                .db
+                .check_provider_limits(&provider_id, app_type.as_str())?
+                .daily_exceeded),
+            SwitchRuleCondition::MonthlyLimitExceeded { .. } => Ok(state
+                .db
+                .check_provider_limits(&provider_id, app_type.as_str())?
+                .monthly_exceeded),
+            SwitchRuleCondition::UsageRemainingBelow { threshold, .. } => {
+                let remaining = Self::cached_remaining(state, app_type, &provider_id);
+                Ok(remaining.is_some_and(|remaining| remaining < *threshold))
+            }
+            SwitchRuleCondition::TimeWindow { .. } => Ok(false),
+        }
+    }
+
+    /// 查询用量脚本报告的最小剩余额度（复用用量快照缓存，查询失败视为未知）
+    fn cached_remaining(state: &AppState, app_type: &AppType, provider_id: &str) -> Option<f64> {
+        match tauri::async_runtime::block_on(ProviderService::query_usage_cached(
+            state,
+            app_type.clone(),
+            provider_id,
+            USAGE_CACHE_TTL,
+        )) {
+            Ok(result) if result.success => result
+                .data
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|plan| plan.remaining)
+                .reduce(f64::min),
+            Ok(result) => {
+                log::debug!(
+                    "[SwitchRule] 用量查询失败 {provider_id}: {}",
+                    result.error.unwrap_or_default()
+                );
+                None
+            }
+            Err(e) => {
+                log::debug!("[SwitchRule] 用量查询失败 {provider_id}: {e}");
+                None
+            }
+        }
+    }
+
+    /// 执行规则动作
+    ///
+    /// 返回 `Ok(None)` 表示目标已达成无需执行，否则返回执行前的供应商。
+    fn execute(
+        state: &AppState,
+        app_type: &AppType,
+        action: &SwitchRuleAction,
+    ) -> Result<Option<Option<String>>, AppError> {
+        match action {
+            SwitchRuleAction::SwitchProvider { provider_id } => {
+                let current = crate::settings::get_effective_current_provider(&state.db, app_type)?;
+                if current.as_deref() == Some(provider_id.as_str()) {
+                    return Ok(None);
+                }
+                ProviderService::switch(state, app_type.clone(), provider_id)?;
+                Ok(Some(current))
+            }
+            SwitchRuleAction::PromoteInFailoverQueue { provider_id } => {
+                let mut queue = state.db.get_failover_queue(app_type.as_str())?;
+                let previous_first = queue.first().map(|item| item.provider_id.clone());
+                if previous_first.as_deref() == Some(provider_id.as_str()) {
+                    return Ok(None);
+                }
+                if !queue.iter().any(|item| &item.provider_id == provider_id) {
+                    state
+                        .db
+                        .add_to_failover_queue(app_type.as_str(), provider_id)?;
+                    queue = state.db.get_failover_queue(app_type.as_str())?;
+                }
+
+                // 复用队列成员原有的排序值，只调整它们之间的相对顺序
+                let mut slots: Vec<usize> = queue
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| item.sort_index.unwrap_or(i))
+                    .collect();
+                slots.sort_unstable();
+                slots.dedup();
+                if slots.len() != queue.len() {
+                    slots = (0..queue.len()).collect();
+                }
+
+                let ordered = std::iter::once(provider_id.clone()).chain(
+                    queue
+                        .iter()
+                        .map(|item| item.provider_id.clone())
+                        .filter(|id| id != provider_id),
+                );
+                let updates = ordered
+                    .zip(slots)
+                    .map(|(id, sort_index)| ProviderSortUpdate { id, sort_index })
+                    .collect();
+                ProviderService::update_sort_order(state, app_type.clone(), updates)?;
+                Ok(Some(previous_first))
+            }
+        }
+    }
+
+    fn describe(condition: &SwitchRuleCondition) -> String {
+        match condition {
+            SwitchRuleCondition::TimeWindow { start, end, .. } => {
+                format!("进入时间窗口 {start}-{end}")
+            }
+            SwitchRuleCondition::DailyLimitExceeded { .. } => "当日用量超出限额".to_string(),
+            SwitchRuleCondition::MonthlyLimitExceeded { .. } => "当月用量超出限额".to_string(),
+            SwitchRuleCondition::UsageRemainingBelow { threshold, .. } => {
+                format!("剩余额度低于 {threshold}")
+            }
+        }
+    }
+
+    /// 启动规则调度（每分钟评估一次）
+    pub fn start_scheduler(app: AppHandle) {
+        tauri::async_runtime::spawn(async move {
+            let mut runtime = SwitchRuleRuntime::default();
+            let mut ticker = tokio::time::interval(RULE_EVAL_INTERVAL);
+            loop {
+                ticker.tick().await;
+
+                // 切换供应商与用量查询均为阻塞调用，放到阻塞线程池执行
+                let handle = app.clone();
+                let evaluated = tauri::async_runtime::spawn_blocking(move || {
+                    let firings = match handle.try_state::<AppState>() {
+                        Some(state) => Self::evaluate(state.inner(), &mut runtime),
+                        None => Vec::new(),
+                    };
+                    (runtime, firings)
+                })
+                .await;
+
+                let firings = match evaluated {
+                    Ok((returned, firings)) => {
+                        runtime = returned;
+                        firings
+                    }
+                    Err(e) => {
+                        log::error!("[SwitchRule] 规则评估任务异常: {e}");
+                        runtime = SwitchRuleRuntime::default();
+                        continue;
+                    }
+                };
+                Self::notify(&app, &firings);
+            }
+        });
+    }
+
+    fn notify(app: &AppHandle, firings: &[SwitchRuleFiring]) {
+        if firings.is_empty() {
+            return;
+        }
+        for firing in firings {
+            if let Err(e) = app.emit("switch-rule-fired", firing) {
+                log::error!("发射 switch-rule-fired 事件失败: {e}");
+            }
+            if firing.success && firing.action == "switch_provider" {
+                let event_data = serde_json::json!({
+                    "appType": firing.app_type,
+                    "providerId": firing.to_provider_id
+                });
+                if let Err(e) = app.emit("provider-switched", event_data) {
+                    log::error!("发射 provider-switched 事件失败: {e}");
+                }
+            }
+        }
+        if let Some(state) = app.try_state::<AppState>() {
+            crate::tray::refresh_tray_menu(app, state.inner());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use crate::provider::Provider;
+    use std::sync::Arc;
+
+    fn time_window(start: &str, end: &str) -> SwitchRuleCondition {
+        SwitchRuleCondition::TimeWindow {
+            days: vec![],
+            start: start.to_string(),
+            end: end.to_string(),
+        }
+    }
+
+    #[test]
+    fn rule_applies_when_starting_inside_an_active_window() {
+        let state = AppState::new(Arc::new(Database::memory().expect("create memory db")));
+        for (sort_index, id) in ["main", "cheap"].into_iter().enumerate() {
+            let mut provider =
+                Provider::with_id(id.to_string(), id.to_string(), serde_json::json!({}), None);
+            provider.sort_index = Some(sort_index);
+            state
+                .db
+                .save_provider("claude", &provider)
+                .expect("save provider");
+            state
+                .db
+                .add_to_failover_queue("claude", id)
+                .expect("queue provider");
+        }
+
+        // 窗口覆盖当前时刻（跨午夜时由 matches_time 处理）
+        let now = chrono::Local::now();
+        let rule = SwitchRule {
+            id: "night".to_string(),
+            name: "Night".to_string(),
+            app_type: "claude".to_string(),
+            enabled: true,
+            condition: time_window(
+                &(now - chrono::Duration::hours(1))
+                    .format("%H:%M")
+                    .to_string(),
+                &(now + chrono::Duration::hours(1))
+                    .format("%H:%M")
+                    .to_string(),
+            ),
+            action: SwitchRuleAction::PromoteInFailoverQueue {
+                provider_id: "cheap".to_string(),
+            },
+            sort_index: None,
+            created_at: Some(1),
+        };
+        state.db.save_switch_rule(&rule).expect("save rule");
+
+        let mut runtime = SwitchRuleRuntime::default();
+        let firings = SwitchRuleService::evaluate(&state, &mut runtime);
+        assert_eq!(firings.len(), 1);
+        assert!(firings[0].success);
+        let queue = state.db.get_failover_queue("claude").expect("queue");
+        assert_eq!(queue[0].provider_id, "cheap");
+
+        // 条件持续满足期间不再重复执行
+        assert!(SwitchRuleService::evaluate(&state, &mut runtime).is_empty());
+    }
+
+    #[test]
+    fn failed_actions_are_retried_while_condition_holds() {
+        let mut runtime = SwitchRuleRuntime::default();
+
+        // 动作失败时不标记为已生效，下次评估重试
+        assert!(runtime.should_apply("night", true));
+        assert!(runtime.should_apply("night", true));
+
+        runtime.record("night", true);
+        assert!(!runtime.should_apply("night", true));
+
+        // 离开窗口后再次进入重新执行
+        runtime.record("night", false);
+        assert!(runtime.should_apply("night", true));
+    }
+
+    #[test]
+    fn upsert_rejects_empty_time_window() {
+        let state = AppState::new(Arc::new(Database::memory().expect("create memory db")));
+        let rule = SwitchRule {
+            id: String::new(),
+            name: "Never".to_string(),
+            app_type: "claude".to_string(),
+            enabled: true,
+            condition: time_window("09:00", "09:00"),
+            action: SwitchRuleAction::SwitchProvider {
+                provider_id: "cheap".to_string(),
+            },
+            sort_index: None,
+            created_at: None,
+        };
+
+        let err = SwitchRuleService::upsert(&state, rule).expect_err("must be rejected");
+        assert!(err.to_string().contains("不能相同"), "{err}");
+    }
+}
@@ -0,0 +1,311 @@
+//! 用量历史与后台轮询
+//!
+//! 按供应商用量脚本的 `autoQueryInterval` 在后台定期查询用量并保存快照，
+//! 基于快照计算消耗趋势与耗尽预测，余额低于阈值时通知前端并在托盘标记。
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::app_config::AppType;
+use crate::error::AppError;
+use crate::provider::{Provider, UsageSnapshot};
+use crate::services::ProviderService;
+use crate::store::AppState;
+
+/// 轮询检查间隔（实际查询频率由各供应商的 `autoQueryInterval` 决定）
+const POLL_TICK: Duration = Duration::from_secs(60);
+
+/// 预测所需的最短观测跨度，避免少量快照导致预测剧烈波动
+const MIN_FORECAST_SPAN_SECS: i64 = 3600;
+
+/// 单个套餐的耗尽预测
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageForecast {
+    pub plan_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    pub remaining: f64,
+    /// 平均每日消耗（观测期内余额未下降时为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub daily_burn: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub days_until_exhausted: Option<f64>,
+    /// 预计耗尽时间（Unix 秒）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exhausted_at: Option<i64>,
+}
+
+/// 用量趋势：观测期内的快照及各套餐预测
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageTrend {
+    pub snapshots: Vec<UsageSnapshot>,
+    pub forecasts: Vec<UsageForecast>,
+}
+
+/// 低余额事件（`usage-low-balance`）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LowBalanceEvent {
+    pub app_type: String,
+    pub provider_id: String,
+    pub provider_name: String,
+    pub remaining: f64,
+    pub threshold: f64,
+}
+
+pub struct UsageHistoryService;
+
+impl UsageHistoryService {
+    /// 获取最近 `days` 天的用量趋势与耗尽预测
+    pub fn trend(
+        state: &AppState,
+        app_type: &AppType,
+        provider_id: &str,
+        days: u32,
+    ) -> Result<UsageTrend, AppError> {
+        let since = chrono::Utc::now().timestamp() - i64::from(days.max(1)) * 86400;
+        let snapshots = state
+            .db
+            .get_usage_snapshots(app_type.as_str(), provider_id, since)?;
+
+        // 按套餐分组，保持首次出现的顺序
+        let mut plans: Vec<(&str, Vec<&UsageSnapshot>)> = Vec::new();
+        for snapshot in &snapshots {
+            match plans
+                .iter_mut()
+                .find(|(name, _)| *name == snapshot.plan_name)
+            {
+                Some((_, points)) => points.push(snapshot),
+                None => plans.push((&snapshot.plan_name, vec![snapshot])),
+            }
+        }
+
+        let forecasts = plans
+            .into_iter()
+            .filter_map(|(plan_name, points)| {
+                let series: Vec<(i64, f64)> = points
+                    .iter()
+                    .filter_map(|s| s.remaining.map(|r| (s.captured_at, r)))
+                    .collect();
+                let (captured_at, remaining) = *series.last()?;
+                let daily_burn = daily_burn(&series);
+                let days_until_exhausted = daily_burn.map(|burn| (remaining / burn).max(0.0));
+                Some(UsageForecast {
+                    plan_name: plan_name.to_string(),
+                    unit: points.last().and_then(|s| s.unit.clone()),
+                    remaining,
+                    daily_burn,
+                    days_until_exhausted,
+                    exhausted_at: days_until_exhausted
+                        .map(|days| captured_at + (days * 86400.0) as i64),
+                })
+            })
+            .collect();
+
+        Ok(UsageTrend {
+            snapshots,
+            forecasts,
+        })
+    }
+
+    /// 供应商当前是否处于低余额状态，返回 (最小剩余额度, 阈值)
+    pub fn low_balance(provider: &Provider, remaining: Option<f64>) -> Option<(f64, f64)> {
+        let threshold = provider
+            .meta
+            .as_ref()
+            .and_then(|m| m.usage_script.as_ref())
+            .filter(|script| script.enabled)
+            .and_then(|script| script.low_balance_threshold)?;
+        let remaining = remaining?;
+        (remaining < threshold).then_some((remaining, threshold))
+    }
+
+    /// 启动后台用量轮询
+    pub fn start_scheduler(app: AppHandle) {
+        tauri::async_runtime::spawn(async move {
+            // 上次尝试查询的时间（含失败），避免失败的脚本每个周期都被重试
+            let mut last_attempt: HashMap<String, i64> = HashMap::new();
+            let mut low: HashSet<String> = HashSet::new();
+            let mut ticker = tokio::time::interval(POLL_TICK);
+            loop {
+                ticker.tick().await;
+                let Some(state) = app.try_state::<AppState>() else {
+                    continue;
+                };
+                Self::poll(state.inner(), &mut last_attempt).await;
+                Self::check_low_balance(&app, state.inner(), &mut low);
+            }
+        });
+    }
+
+    /// 查询到期的供应商用量（前端手动查询产生的快照同样计入间隔）
+    async fn poll(state: &AppState, last_attempt: &mut HashMap<String, i64>) {
+        for app_type in AppType::all() {
+            let providers = match state.db.get_all_providers(app_type.as_str()) {
+                Ok(providers) => providers,
+                Err(e) => {
+                    log::warn!("[UsagePoll] 读取 {} 供应商失败: {e}", app_type.as_str());
+                    continue;
+                }
+            };
+
+            for (id, provider) in providers {
+                let interval = provider
+                    .meta
+                    .as_ref()
+                    .and_then(|m| m.usage_script.as_ref())
+                    .filter(|script| script.enabled)
+                    .and_then(|script| script.auto_query_interval)
+                    .unwrap_or(0);
+                if interval == 0 {
+                    continue;
+                }
+                let interval_secs = interval as i64 * 60;
+                let now = chrono::Utc::now().timestamp();
+
+                let key = format!("{}:{id}", app_type.as_str());
+                let last_snapshot = state
+                    .db
+                    .get_latest_usage_snapshots(app_type.as_str(), &id)
+                    .ok()
+                    .and_then(|snapshots| snapshots.first().map(|s| s.captured_at));
+                let last = last_snapshot
+                    .into_iter()
+                    .chain(last_attempt.get(&key).copied())
+                    .max();
+                if last.is_some_and(|last| now - last < interval_secs) {
+                    continue;
+                }
+                last_attempt.insert(key, now);
+
+                match ProviderService::query_usage(state, app_type.clone(), &id).await {
+                    Ok(result) if !result.success => log::debug!(
+                        "[UsagePoll] {} 用量查询失败: {}",
+                        provider.name,
+                        result.error.unwrap_or_default()
+                    ),
+                    Ok(_) => {}
+                    Err(e) => log::debug!("[UsagePoll] {} 用量查询失败: {e}", provider.name),
+                }
+            }
+        }
+    }
+
+    /// 检查低余额状态，新进入低余额时发射事件，状态变化时刷新托盘
+    fn check_low_balance(app: &AppHandle, state: &AppState, low: &mut HashSet<String>) {
+        let mut current = HashSet::new();
+        let mut events = Vec::new();
+
+        for app_type in AppType::all() {
+            let (Ok(providers), Ok(remaining)) = (
+                state.db.get_all_providers(app_type.as_str()),
+                state.db.get_latest_usage_remaining(app_type.as_str()),
+            ) else {
+                continue;
+            };
+            for (id, provider) in providers {
+                let Some((remaining, threshold)) =
+                    Self::low_balance(&provider, remaining.get(&id).copied())
+                else {
+                    continue;
+                };
+                let key = format!("{}:{id}", app_type.as_str());
+                if !low.contains(&key) {
+                    events.push(LowBalanceEvent {
+                        app_type: app_type.as_str().to_string(),
+                        provider_id: id,
+                        provider_name: provider.name,
+                        remaining,
+                        threshold,
+                    });
+                }
+                current.insert(key);
+            }
+        }
+
+        for event in &events {
+            log::info!(
+                "[UsagePoll] {} 余额低于阈值: {} < {}",
+                event.provider_name,
+                event.remaining,
+                event.threshold
+            );
+            if let Err(e) = app.emit("usage-low-balance", event) {
+                log::error!("发射 usage-low-balance 事件失败: {e}");
+            }
+        }
+
+        if current != *low {
+            *low = current;
+            crate::tray::refresh_tray_menu(app, state);
+        }
+    }
+}
+
+/// 根据余额序列估算每日消耗
+///
+/// 只使用最近一次充值（余额上升）之后的数据，对其做最小二乘线性拟合。
+fn daily_burn(series: &[(i64, f64)]) -> Option<f64> {
+    let start = series
+        .windows(2)
+        .rposition(|pair| pair[1].1 > pair[0].1)
+        .map_or(0, |idx| idx + 1);
+    let segment = &series[start..];
+    let (first, last) = (segment.first()?, segment.last()?);
+    if last.0 - first.0 < MIN_FORECAST_SPAN_SECS {
+        return None;
+    }
+
+    let n = segment.len() as f64;
+    let mean_t = segment
+        .iter()
+        .map(|(t, _)| (t - first.0) as f64)
+        .sum::<f64>()
+        / n;
+    let mean_v = segment.iter().map(|(_, v)| v).sum::<f64>() / n;
+    let (mut cov, mut var) = (0.0, 0.0);
+    for (t, v) in segment {
+        let dt = (t - first.0) as f64 - mean_t;
+        cov += dt * (v - mean_v);
+        var += dt * dt;
+    }
+    if var == 0.0 {
+        return None;
+    }
+
+    let burn = -(cov / var) * 86400.0;
+    (burn > 0.0).then_some(burn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 86400;
+
+    #[test]
+    fn steady_consumption_is_forecast() {
+        let series = [(0, 100.0), (DAY, 90.0), (2 * DAY, 80.0)];
+        let burn = daily_burn(&series).expect("burn");
+        assert!((burn - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn only_data_after_last_top_up_is_used() {
+        let series = [(0, 50.0), (DAY, 10.0), (2 * DAY, 100.0), (3 * DAY, 95.0)];
+        let burn = daily_burn(&series).expect("burn");
+        assert!((burn - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn no_forecast_without_consumption_or_enough_span() {
+        assert_eq!(daily_burn(&[(0, 10.0), (DAY, 10.0)]), None);
+        assert_eq!(daily_burn(&[(0, 10.0), (60, 9.0)]), None);
+        assert_eq!(daily_burn(&[(0, 10.0)]), None);
+    }
+}
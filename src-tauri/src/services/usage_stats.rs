@@ -4,6 +4,7 @@
 
 use crate::database::{lock_conn, Database};
 use crate::error::AppError;
+use crate::proxy::routing_override::RoutingOverrides;
 use chrono::{Local, TimeZone};
 use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
@@ -51,6 +52,18 @@ pub struct ProviderStats {
     pub avg_latency_ms: u64,
 }
 
+/// Key 池中单个 Key 的用量统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyStats {
+    pub key_id: String,
+    pub request_count: u64,
+    pub total_tokens: u64,
+    pub total_cost: String,
+    pub success_rate: f32,
+    pub last_used_at: Option<i64>,
+}
+
 /// 模型统计
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -113,6 +126,20 @@ pub struct RequestLogDetail {
     pub status_code: u16,
     pub error_message: Option<String>,
     pub created_at: i64,
+    /// 使用的 Key 池 Key 标识
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key_id: Option<String>,
+    /// 发起请求的客户端访问令牌标识
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_token_id: Option<String>,
+    /// 请求头路由覆盖（指定供应商 / 模型、禁用故障转移）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub routing_override: Option<RoutingOverrides>,
+}
+
+/// 解析请求日志中的路由覆盖 JSON（格式无效时忽略）
+fn parse_routing_override(value: Option<String>) -> Option<RoutingOverrides> {
+    value.and_then(|v| serde_json::from_str(&v).ok())
 }
 
 impl Database {
@@ -342,6 +369,54 @@ impl Database {
         Ok(stats)
     }
 
+    /// 获取供应商 Key 池中各 Key 的用量统计
+    pub fn get_api_key_stats(
+        &self,
+        provider_id: &str,
+        app_type: &str,
+    ) -> Result<Vec<ApiKeyStats>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let sql = "SELECT
+                api_key_id,
+                COUNT(*) as request_count,
+                COALESCE(SUM(input_tokens + output_tokens), 0) as total_tokens,
+                COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0) as total_cost,
+                COALESCE(SUM(CASE WHEN status_code >= 200 AND status_code < 300 THEN 1 ELSE 0 END), 0) as success_count,
+                MAX(created_at) as last_used_at
+             FROM proxy_request_logs
+             WHERE provider_id = ?1 AND app_type = ?2 AND api_key_id IS NOT NULL
+             GROUP BY api_key_id
+             ORDER BY request_count DESC";
+
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map(params![provider_id, app_type], |row| {
+            let request_count: i64 = row.get(1)?;
+            let success_count: i64 = row.get(4)?;
+            let success_rate = if request_count > 0 {
+                (success_count as f32 / request_count as f32) * 100.0
+            } else {
+                0.0
+            };
+
+            Ok(ApiKeyStats {
+                key_id: row.get(0)?,
+                request_count: request_count as u64,
+                total_tokens: row.get::<_, i64>(2)? as u64,
+                total_cost: format!("{:.6}", row.get::<_, f64>(3)?),
+                success_rate,
+                last_used_at: row.get(5)?,
+            })
+        })?;
+
+        let mut stats = Vec::new();
+        for row in rows {
+            stats.push(row?);
+        }
+
+        Ok(stats)
+    }
+
     /// 获取模型统计
     pub fn get_model_stats(&self) -> Result<Vec<ModelStats>, AppError> {
         let conn = lock_conn!(self.conn);
@@ -447,7 +522,8 @@ impl Database {
                     l.input_tokens, l.output_tokens, l.cache_read_tokens, l.cache_creation_tokens,
                     l.input_cost_usd, l.output_cost_usd, l.cache_read_cost_usd, l.cache_creation_cost_usd, l.total_cost_usd,
                     l.is_streaming, l.latency_ms, l.first_token_ms, l.duration_ms,
-                    l.status_code, l.error_message, l.created_at
+                    l.status_code, l.error_message, l.created_at, l.api_key_id,
+                    l.client_token_id, l.routing_override
              FROM proxy_request_logs l
              LEFT JOIN providers p ON l.provider_id = p.id AND l.app_type = p.app_type
              {where_clause}
@@ -484,6 +560,9 @@ impl Database {
                 status_code: row.get::<_, i64>(20)? as u16,
                 error_message: row.get(21)?,
                 created_at: row.get(22)?,
+                api_key_id: row.get(23)?,
+                client_token_id: row.get(24)?,
+                routing_override: parse_routing_override(row.get(25)?),
             })
         })?;
 
@@ -523,7 +602,8 @@ impl Database {
                     input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens,
                     input_cost_usd, output_cost_usd, cache_read_cost_usd, cache_creation_cost_usd, total_cost_usd,
                     is_streaming, latency_ms, first_token_ms, duration_ms,
-                    status_code, error_message, created_at
+                    status_code, error_message, created_at, l.api_key_id, l.client_token_id,
+                    l.routing_override
              FROM proxy_request_logs l
              LEFT JOIN providers p ON l.provider_id = p.id AND l.app_type = p.app_type
              WHERE l.request_id = ?",
@@ -553,6 +633,9 @@ impl Database {
                     status_code: row.get::<_, i64>(20)? as u16,
                     error_message: row.get(21)?,
                     created_at: row.get(22)?,
+                    api_key_id: row.get(23)?,
+                    client_token_id: row.get(24)?,
+                    routing_override: parse_routing_override(row.get(25)?),
                 })
             },
         );
@@ -0,0 +1,216 @@
+use chrono::{Datelike, NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
+
+/// 自动切换规则的触发条件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SwitchRuleCondition {
+    /// 本地时间位于时间窗口内（如夜间低价时段）
+    TimeWindow {
+        /// 生效的星期（0 = 周日 … 6 = 周六），为空表示每天
+        #[serde(default)]
+        days: Vec<u8>,
+        /// 开始时间 "HH:MM"
+        start: String,
+        /// 结束时间 "HH:MM"（早于开始时间表示跨越午夜）
+        end: String,
+    },
+    /// 供应商当日用量超过 `limitDailyUsd`（即 `check_provider_limits` 的 daily_exceeded）
+    DailyLimitExceeded {
+        /// 检查的供应商，为空表示当前供应商
+        #[serde(
+            rename = "providerId",
+            default,
+            skip_serializing_if = "Option::is_none"
+        )]
+        provider_id: Option<String>,
+    },
+    /// 供应商当月用量超过 `limitMonthlyUsd`
+    MonthlyLimitExceeded {
+        #[serde(
+            rename = "providerId",
+            default,
+            skip_serializing_if = "Option::is_none"
+        )]
+        provider_id: Option<String>,
+    },
+    /// 用量脚本报告的剩余额度低于阈值（任一套餐满足即触发）
+    UsageRemainingBelow {
+        #[serde(
+            rename = "providerId",
+            default,
+            skip_serializing_if = "Option::is_none"
+        )]
+        provider_id: Option<String>,
+        threshold: f64,
+    },
+}
+
+impl SwitchRuleCondition {
+    /// 判断本地时间是否位于时间窗口内；非时间窗口条件返回 `None`
+    pub fn matches_time<T: Datelike + Timelike>(&self, now: &T) -> Option<bool> {
+        let SwitchRuleCondition::TimeWindow { days, start, end } = self else {
+            return None;
+        };
+        let (Ok(start), Ok(end)) = (parse_hhmm(start), parse_hhmm(end)) else {
+            return Some(false);
+        };
+        let time = NaiveTime::from_hms_opt(now.hour(), now.minute(), 0)?;
+        let weekday = now.weekday().num_days_from_sunday() as u8;
+
+        let in_window = if start <= end {
+            start <= time && time < end
+        } else {
+            // 跨午夜：凌晨部分属于前一天的窗口
+            time >= start || time < end
+        };
+        if !in_window {
+            return Some(false);
+        }
+        if days.is_empty() {
+            return Some(true);
+        }
+        let window_day = if start > end && time < end {
+            (weekday + 6) % 7
+        } else {
+            weekday
+        };
+        Some(days.contains(&window_day))
+    }
+
+    /// 条件检查的供应商（时间窗口条件不涉及供应商）
+    pub fn provider_id(&self) -> Option<&str> {
+        match self {
+            SwitchRuleCondition::TimeWindow { .. } => None,
+            SwitchRuleCondition::DailyLimitExceeded { provider_id }
+            | SwitchRuleCondition::MonthlyLimitExceeded { provider_id }
+            | SwitchRuleCondition::UsageRemainingBelow { provider_id, .. } => {
+                provider_id.as_deref()
+            }
+        }
+    }
+}
+
+pub fn parse_hhmm(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M")
+        .map_err(|_| format!("时间格式无效（应为 HH:MM）: {value}"))
+}
+
+/// 规则触发后执行的动作
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SwitchRuleAction {
+    /// 切换到指定供应商（等同于手动切换）
+    SwitchProvider {
+        #[serde(rename = "providerId")]
+        provider_id: String,
+    },
+    /// 将供应商移到故障转移队列首位（不在队列中时先加入）
+    PromoteInFailoverQueue {
+        #[serde(rename = "providerId")]
+        provider_id: String,
+    },
+}
+
+impl SwitchRuleAction {
+    pub fn provider_id(&self) -> &str {
+        match self {
+            SwitchRuleAction::SwitchProvider { provider_id }
+            | SwitchRuleAction::PromoteInFailoverQueue { provider_id } => provider_id,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SwitchRuleAction::SwitchProvider { .. } => "switch_provider",
+            SwitchRuleAction::PromoteInFailoverQueue { .. } => "promote_in_failover_queue",
+        }
+    }
+}
+
+/// 自动切换规则
+///
+/// 条件由不满足变为满足时触发一次（边沿触发），避免覆盖用户在条件持续期间的手动切换。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwitchRule {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "appType")]
+    pub app_type: String,
+    #[serde(default)]
+    pub enabled: bool,
+    pub condition: SwitchRuleCondition,
+    pub action: SwitchRuleAction,
+    #[serde(rename = "sortIndex", skip_serializing_if = "Option::is_none")]
+    pub sort_index: Option<i64>,
+    #[serde(rename = "createdAt", skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<i64>,
+}
+
+/// 规则触发记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwitchRuleFiring {
+    pub id: i64,
+    #[serde(rename = "ruleId")]
+    pub rule_id: String,
+    #[serde(rename = "ruleName")]
+    pub rule_name: String,
+    #[serde(rename = "appType")]
+    pub app_type: String,
+    pub action: String,
+    #[serde(rename = "fromProviderId", skip_serializing_if = "Option::is_none")]
+    pub from_provider_id: Option<String>,
+    #[serde(rename = "toProviderId")]
+    pub to_provider_id: String,
+    pub success: bool,
+    /// 触发原因或失败信息
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(rename = "firedAt")]
+    pub fired_at: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn window(days: Vec<u8>, start: &str, end: &str) -> SwitchRuleCondition {
+        SwitchRuleCondition::TimeWindow {
+            days,
+            start: start.to_string(),
+            end: end.to_string(),
+        }
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> chrono::NaiveDateTime {
+        // 2026-03-01 为周日
+        NaiveDate::from_ymd_opt(2026, 3, day)
+            .and_then(|d| d.and_hms_opt(hour, minute, 0))
+            .expect("valid datetime")
+    }
+
+    #[test]
+    fn time_window_within_a_day() {
+        let rule = window(vec![], "09:00", "18:00");
+        assert_eq!(rule.matches_time(&at(2, 9, 0)), Some(true));
+        assert_eq!(rule.matches_time(&at(2, 17, 59)), Some(true));
+        assert_eq!(rule.matches_time(&at(2, 18, 0)), Some(false));
+    }
+
+    #[test]
+    fn overnight_window_belongs_to_start_day() {
+        // 仅周五夜间生效：周六凌晨仍属于周五的窗口
+        let rule = window(vec![5], "22:00", "06:00");
+        assert_eq!(rule.matches_time(&at(6, 23, 0)), Some(true));
+        assert_eq!(rule.matches_time(&at(7, 5, 0)), Some(true));
+        assert_eq!(rule.matches_time(&at(7, 23, 0)), Some(false));
+        assert_eq!(rule.matches_time(&at(6, 5, 0)), Some(false));
+    }
+
+    #[test]
+    fn non_time_conditions_are_not_time_based() {
+        let rule = SwitchRuleCondition::DailyLimitExceeded { provider_id: None };
+        assert_eq!(rule.matches_time(&at(2, 9, 0)), None);
+    }
+}
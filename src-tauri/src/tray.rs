@@ -16,6 +16,7 @@ pub struct TrayTexts {
     pub no_provider_hint: &'static str,
     pub quit: &'static str,
     pub auto_label: &'static str,
+    pub profiles_label: &'static str,
 }
 
 impl TrayTexts {
@@ -26,6 +27,7 @@ impl TrayTexts {
                 no_provider_hint: "  (No providers yet, please add them from the main window)",
                 quit: "Quit",
                 auto_label: "Auto (Failover)",
+                profiles_label: "Profiles",
             },
             "ja" => Self {
                 show_main: "メインウィンドウを開く",
@@ -33,12 +35,14 @@ impl TrayTexts {
                     "  (プロバイダーがまだありません。メイン画面から追加してください)",
                 quit: "終了",
                 auto_label: "自動 (フェイルオーバー)",
+                profiles_label: "プロファイル",
             },
             _ => Self {
                 show_main: "打开主界面",
                 no_provider_hint: "  (无供应商，请在主界面添加)",
                 quit: "退出",
                 auto_label: "自动 (故障转移)",
+                profiles_label: "配置档案",
             },
         }
     }
@@ -57,6 +61,9 @@ pub struct TrayAppSection {
 /// Auto 菜单项后缀
 pub const AUTO_SUFFIX: &str = "auto";
 
+/// 配置档案菜单项前缀
+pub const PROFILE_PREFIX: &str = "profile_";
+
 pub const TRAY_SECTIONS: [TrayAppSection; 3] = [
     TrayAppSection {
         app_type: AppType::Claude,
@@ -155,13 +162,26 @@ fn append_provider_section<'a>(
         a.name.cmp(&b.name)
     });
 
+    // 最近一次用量查询的剩余额度，用于标记低余额供应商
+    let usage_remaining = app_state
+        .db
+        .get_latest_usage_remaining(section.app_type.as_str())
+        .unwrap_or_default();
+
     for (id, provider) in sorted_providers {
         // Auto 模式下所有供应商都不选中
         let is_current = !auto_mode && manager.current == *id;
+        let label = match crate::services::usage_history::UsageHistoryService::low_balance(
+            provider,
+            usage_remaining.get(id).copied(),
+        ) {
+            Some((remaining, _)) => format!("{} ⚠ {remaining:.2}", provider.name),
+            None => provider.name.clone(),
+        };
         let item = CheckMenuItem::with_id(
             app,
             format!("{}{}", section.prefix, id),
-            &provider.name,
+            &label,
             true,
             is_current,
             None::<&str>,
@@ -206,6 +226,113 @@ pub fn handle_provider_tray_event(app: &tauri::AppHandle, event_id: &str) -> boo
     false
 }
 
+/// 添加配置档案分区到菜单（无档案时不显示）
+fn append_profile_section<'a>(
+    app: &'a tauri::AppHandle,
+    mut menu_builder: MenuBuilder<'a, tauri::Wry, tauri::AppHandle<tauri::Wry>>,
+    tray_texts: &TrayTexts,
+    app_state: &AppState,
+) -> Result<MenuBuilder<'a, tauri::Wry, tauri::AppHandle<tauri::Wry>>, AppError> {
+    let profiles = app_state.db.get_profiles()?;
+    if profiles.is_empty() {
+        return Ok(menu_builder);
+    }
+    let active_id = app_state.db.get_active_profile_id()?;
+
+    let header = MenuItem::with_id(
+        app,
+        "profile_header",
+        tray_texts.profiles_label,
+        false,
+        None::<&str>,
+    )
+    .map_err(|e| AppError::Message(format!("创建配置档案标题失败: {e}")))?;
+    menu_builder = menu_builder.item(&header);
+
+    for profile in profiles {
+        let is_active = active_id.as_deref() == Some(profile.id.as_str());
+        let item = CheckMenuItem::with_id(
+            app,
+            format!("{PROFILE_PREFIX}{}", profile.id),
+            &profile.name,
+            true,
+            is_active,
+            None::<&str>,
+        )
+        .map_err(|e| AppError::Message(format!("创建配置档案菜单项失败: {e}")))?;
+        menu_builder = menu_builder.item(&item);
+    }
+
+    Ok(menu_builder.separator())
+}
+
+/// 处理配置档案菜单点击
+pub fn handle_profile_tray_event(app: &tauri::AppHandle, event_id: &str) -> bool {
+    let Some(profile_id) = event_id.strip_prefix(PROFILE_PREFIX) else {
+        return false;
+    };
+    if profile_id == "header" {
+        return true;
+    }
+
+    log::info!("切换到配置档案: {profile_id}");
+    let app_handle = app.clone();
+    let profile_id = profile_id.to_string();
+    tauri::async_runtime::spawn_blocking(move || {
+        let Some(app_state) = app_handle.try_state::<AppState>() else {
+            return;
+        };
+        match crate::services::ProfileService::apply(app_state.inner(), &profile_id) {
+            Ok(profile) => notify_profile_applied(&app_handle, app_state.inner(), &profile),
+            Err(e) => {
+                log::error!("切换配置档案失败: {e}");
+                // 刷新菜单以恢复勾选状态
+                refresh_tray_menu(&app_handle, app_state.inner());
+            }
+        }
+    });
+    true
+}
+
+/// 重建托盘菜单
+pub fn refresh_tray_menu(app: &tauri::AppHandle, app_state: &AppState) {
+    if let Ok(new_menu) = create_tray_menu(app, app_state) {
+        if let Some(tray) = app.tray_by_id("main") {
+            let _ = tray.set_menu(Some(new_menu));
+        }
+    }
+}
+
+/// 配置档案应用成功后更新托盘并通知前端
+pub fn notify_profile_applied(
+    app: &tauri::AppHandle,
+    app_state: &AppState,
+    profile: &crate::profile::Profile,
+) {
+    refresh_tray_menu(app, app_state);
+
+    if let Err(e) = app.emit("profile-applied", profile) {
+        log::error!("发射 profile-applied 事件失败: {e}");
+    }
+    // 档案可能切换了供应商，发射 provider-switched 以便前端刷新
+    for app_type in profile.apps.configured_apps() {
+        let provider_id = profile
+            .apps
+            .get(&app_type)
+            .and_then(|config| config.provider_id.clone());
+        let Some(provider_id) = provider_id else {
+            continue;
+        };
+        let event_data = serde_json::json!({
+            "appType": app_type.as_str(),
+            "providerId": provider_id
+        });
+        if let Err(e) = app.emit("provider-switched", event_data) {
+            log::error!("发射 provider-switched 事件失败: {e}");
+        }
+    }
+}
+
 /// 处理 Auto 点击：启用 proxy 和 auto_failover
 fn handle_auto_click(app: &tauri::AppHandle, app_type: &AppType) -> Result<(), AppError> {
     if let Some(app_state) = app.try_state::<AppState>() {
@@ -362,6 +489,9 @@ pub fn create_tray_menu(
             .map_err(|e| AppError::Message(format!("创建打开主界面菜单失败: {e}")))?;
     menu_builder = menu_builder.item(&show_main_item).separator();
 
+    // 配置档案（一键切换整套配置）
+    menu_builder = append_profile_section(app, menu_builder, &tray_texts, app_state)?;
+
     // 直接添加所有供应商到主菜单（扁平化结构，更简单可靠）
     // Only add visible app sections
     for section in TRAY_SECTIONS.iter() {
@@ -451,6 +581,9 @@ pub fn handle_tray_menu_event(app: &tauri::AppHandle, event_id: &str) {
             app.exit(0);
         }
         _ => {
+            if handle_profile_tray_event(app, event_id) {
+                return;
+            }
             if handle_provider_tray_event(app, event_id) {
                 return;
             }
@@ -1,11 +1,24 @@
-use rquickjs::{Context, Function, Runtime};
+use rquickjs::Function;
 use serde_json::Value;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 use url::{Host, Url};
 
 use crate::error::AppError;
+use crate::script_runtime::{FetchPolicy, ScriptLimits, ScriptRun, ScriptRuntime};
+
+/// 单次脚本执行允许的 fetch 请求数
+const MAX_FETCH_REQUESTS: usize = 10;
 
 /// 执行用量查询脚本
+///
+/// 脚本需要 eval 成一个对象，支持两种写法：
+/// - `{ request, extractor }`：由后端发送 `request` 描述的请求，再以响应调用 `extractor`
+/// - `{ query }`：`query` 可为 async 函数，内部可多次调用 `fetch`（如先登录再查询余额），
+///   返回用量数据
+///
+/// 两种写法的请求 URL 都需要通过同源 / SSRF 校验。
 pub async fn execute_usage_script(
     script_code: &str,
     api_key: &str,
@@ -14,7 +27,7 @@ pub async fn execute_usage_script(
     access_token: Option<&str>,
     user_id: Option<&str>,
     template_type: Option<&str>,
-) -> Result<Value, AppError> {
+) -> ScriptRun<Value, AppError> {
     // 检测是否为自定义模板模式
     // 优先使用前端传递的 template_type
     let is_custom_template = template_type.map(|t| t == "custom").unwrap_or(false);
@@ -26,187 +39,213 @@ pub async fn execute_usage_script(
     // 2. 验证 base_url 的安全性（仅当提供了 base_url 时）
     // 自定义模板模式下，用户可能不使用模板变量，而是直接在脚本中写完整 URL
     if !base_url.is_empty() {
-        validate_base_url(base_url)?;
+        if let Err(e) = validate_base_url(base_url) {
+            return ScriptRun {
+                result: Err(e),
+                logs: Vec::new(),
+            };
+        }
     }
 
-    // 3. 在独立作用域中提取 request 配置（确保 Runtime/Context 在 await 前释放）
-    let request_config = {
-        let runtime = Runtime::new().map_err(|e| {
-            AppError::localized(
-                "usage_script.runtime_create_failed",
-                format!("创建 JS 运行时失败: {e}"),
-                format!("Failed to create JS runtime: {e}"),
-            )
-        })?;
-        let context = Context::full(&runtime).map_err(|e| {
-            AppError::localized(
-                "usage_script.context_create_failed",
-                format!("创建 JS 上下文失败: {e}"),
-                format!("Failed to create JS context: {e}"),
-            )
-        })?;
-
-        context.with(|ctx| {
-            // 执行用户代码，获取配置对象
-            let config: rquickjs::Object = ctx.eval(script_with_vars.clone()).map_err(|e| {
-                AppError::localized(
-                    "usage_script.config_parse_failed",
-                    format!("解析配置失败: {e}"),
-                    format!("Failed to parse config: {e}"),
-                )
-            })?;
-
-            // 提取 request 配置
-            let request: rquickjs::Object = config.get("request").map_err(|e| {
-                AppError::localized(
-                    "usage_script.request_missing",
-                    format!("缺少 request 配置: {e}"),
-                    format!("Missing request config: {e}"),
-                )
-            })?;
+    // 3. 脚本与其中的 fetch 均为阻塞执行，放到阻塞线程池中运行
+    // 约束超时范围，防止异常配置导致长时间阻塞（最小 2 秒，最大 30 秒）
+    let timeout = Duration::from_secs(timeout_secs.clamp(2, 30));
+    let base_url = base_url.to_string();
+    let joined = tauri::async_runtime::spawn_blocking(move || {
+        run_usage_script(&script_with_vars, &base_url, timeout, is_custom_template)
+    })
+    .await;
+
+    joined.unwrap_or_else(|e| ScriptRun {
+        result: Err(AppError::localized(
+            "usage_script.exec_task_failed",
+            format!("脚本执行任务异常: {e}"),
+            format!("Script task failed: {e}"),
+        )),
+        logs: Vec::new(),
+    })
+}
 
-            // 将 request 转换为 JSON 字符串
-            let request_json: String = ctx
-                .json_stringify(request)
-                .map_err(|e| {
-                    AppError::localized(
-                        "usage_script.request_serialize_failed",
-                        format!("序列化 request 失败: {e}"),
-                        format!("Failed to serialize request: {e}"),
-                    )
-                })?
-                .ok_or_else(|| {
-                    AppError::localized(
-                        "usage_script.serialize_none",
-                        "序列化返回 None",
-                        "Serialization returned None",
-                    )
-                })?
-                .get()
-                .map_err(|e| {
-                    AppError::localized(
-                        "usage_script.get_string_failed",
-                        format!("获取字符串失败: {e}"),
-                        format!("Failed to get string: {e}"),
+fn run_usage_script(
+    script: &str,
+    base_url: &str,
+    timeout: Duration,
+    is_custom_template: bool,
+) -> ScriptRun<Value, AppError> {
+    let runtime = match ScriptRuntime::new(ScriptLimits::with_timeout(timeout)) {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            return ScriptRun {
+                result: Err(AppError::localized(
+                    "usage_script.runtime_create_failed",
+                    e.clone(),
+                    e,
+                )),
+                logs: Vec::new(),
+            }
+        }
+    };
+
+    let fetch_base_url = base_url.to_string();
+    let policy = FetchPolicy {
+        allow: Arc::new(move |url| {
+            validate_request_url(url, &fetch_base_url, is_custom_template)
+                .map_err(|e| e.to_string())
+        }),
+        max_requests: MAX_FETCH_REQUESTS,
+        request_timeout: timeout,
+    };
+
+    let result = runtime
+        .enable_fetch(policy)
+        .map_err(|e| AppError::localized("usage_script.context_create_failed", e.clone(), e))
+        .and_then(|_| {
+            runtime.with(|ctx| {
+                let js_error = |key: &'static str, zh: &str, en: &str, e: rquickjs::Error| {
+                    let detail = runtime.describe_error(&ctx, e);
+                    AppError::localized(key, format!("{zh}: {detail}"), format!("{en}: {detail}"))
+                };
+
+                // 执行用户代码，获取配置对象
+                let config: rquickjs::Object = ctx.eval(script).map_err(|e| {
+                    js_error(
+                        "usage_script.config_parse_failed",
+                        "解析配置失败",
+                        "Failed to parse config",
+                        e,
                     )
                 })?;
 
-            Ok::<_, AppError>(request_json)
-        })?
-    }; // Runtime 和 Context 在这里被 drop
-
-    // 4. 解析 request 配置
-    let request: RequestConfig = serde_json::from_str(&request_config).map_err(|e| {
-        AppError::localized(
-            "usage_script.request_format_invalid",
-            format!("request 配置格式错误: {e}"),
-            format!("Invalid request config format: {e}"),
-        )
-    })?;
-
-    // 5. 验证请求 URL 是否安全（防止 SSRF）
-    // 如果提供了 base_url，则验证同源；否则只做基本安全检查
-    validate_request_url(&request.url, base_url, is_custom_template)?;
+                let result_js = if let Ok(query) = config.get::<_, Function>("query") {
+                    let value: rquickjs::Value = query.call(()).map_err(|e| {
+                        js_error(
+                            "usage_script.query_exec_failed",
+                            "执行 query 失败",
+                            "Failed to execute query",
+                            e,
+                        )
+                    })?;
+                    runtime.resolve(&ctx, value).map_err(|e| {
+                        AppError::localized(
+                            "usage_script.query_exec_failed",
+                            format!("执行 query 失败: {e}"),
+                            format!("Failed to execute query: {e}"),
+                        )
+                    })?
+                } else {
+                    // 提取 request 配置
+                    let request: rquickjs::Object = config.get("request").map_err(|e| {
+                        AppError::localized(
+                            "usage_script.request_missing",
+                            format!("缺少 request 配置或 query 函数: {e}"),
+                            format!("Missing request config or query function: {e}"),
+                        )
+                    })?;
+                    let request_json = stringify_js(&ctx, request.into_value())?;
+
+                    // 解析 request 配置
+                    let request: RequestConfig =
+                        serde_json::from_str(&request_json).map_err(|e| {
+                            AppError::localized(
+                                "usage_script.request_format_invalid",
+                                format!("request 配置格式错误: {e}"),
+                                format!("Invalid request config format: {e}"),
+                            )
+                        })?;
+
+                    // 验证请求 URL 是否安全（防止 SSRF）
+                    // 如果提供了 base_url，则验证同源；否则只做基本安全检查
+                    validate_request_url(&request.url, base_url, is_custom_template)?;
+
+                    // 发送 HTTP 请求
+                    let response_data =
+                        tauri::async_runtime::block_on(send_http_request(&request, timeout))?;
+
+                    // 提取 extractor 函数
+                    let extractor: Function = config.get("extractor").map_err(|e| {
+                        AppError::localized(
+                            "usage_script.extractor_missing",
+                            format!("缺少 extractor 函数: {e}"),
+                            format!("Missing extractor function: {e}"),
+                        )
+                    })?;
+
+                    // 将响应数据转换为 JS 值
+                    let response_js: rquickjs::Value =
+                        ctx.json_parse(response_data.as_str()).map_err(|e| {
+                            AppError::localized(
+                                "usage_script.response_parse_failed",
+                                format!("解析响应 JSON 失败: {e}"),
+                                format!("Failed to parse response JSON: {e}"),
+                            )
+                        })?;
+
+                    // 调用 extractor(response)
+                    let value: rquickjs::Value = extractor.call((response_js,)).map_err(|e| {
+                        js_error(
+                            "usage_script.extractor_exec_failed",
+                            "执行 extractor 失败",
+                            "Failed to execute extractor",
+                            e,
+                        )
+                    })?;
+                    runtime.resolve(&ctx, value).map_err(|e| {
+                        AppError::localized(
+                            "usage_script.extractor_exec_failed",
+                            format!("执行 extractor 失败: {e}"),
+                            format!("Failed to execute extractor: {e}"),
+                        )
+                    })?
+                };
+
+                // 解析为 serde_json::Value
+                let result_json = stringify_js(&ctx, result_js)?;
+                serde_json::from_str::<Value>(&result_json).map_err(|e| {
+                    AppError::localized(
+                        "usage_script.json_parse_failed",
+                        format!("JSON 解析失败: {e}"),
+                        format!("JSON parse failed: {e}"),
+                    )
+                })
+            })
+        })
+        // 验证返回值格式
+        .and_then(|result| validate_result(&result).map(|_| result));
 
-    // 6. 发送 HTTP 请求
-    let response_data = send_http_request(&request, timeout_secs).await?;
+    ScriptRun {
+        result,
+        logs: runtime.take_logs(),
+    }
+}
 
-    // 7. 在独立作用域中执行 extractor（确保 Runtime/Context 在函数结束前释放）
-    let result: Value = {
-        let runtime = Runtime::new().map_err(|e| {
+/// 将 JS 值序列化为 JSON 字符串
+fn stringify_js<'js>(
+    ctx: &rquickjs::Ctx<'js>,
+    value: rquickjs::Value<'js>,
+) -> Result<String, AppError> {
+    ctx.json_stringify(value)
+        .map_err(|e| {
             AppError::localized(
-                "usage_script.runtime_create_failed",
-                format!("创建 JS 运行时失败: {e}"),
-                format!("Failed to create JS runtime: {e}"),
+                "usage_script.result_serialize_failed",
+                format!("序列化结果失败: {e}"),
+                format!("Failed to serialize result: {e}"),
             )
-        })?;
-        let context = Context::full(&runtime).map_err(|e| {
+        })?
+        .ok_or_else(|| {
             AppError::localized(
-                "usage_script.context_create_failed",
-                format!("创建 JS 上下文失败: {e}"),
-                format!("Failed to create JS context: {e}"),
+                "usage_script.serialize_none",
+                "序列化返回 None",
+                "Serialization returned None",
             )
-        })?;
-
-        context.with(|ctx| {
-            // 重新 eval 获取配置对象
-            let config: rquickjs::Object = ctx.eval(script_with_vars.clone()).map_err(|e| {
-                AppError::localized(
-                    "usage_script.config_reparse_failed",
-                    format!("重新解析配置失败: {e}"),
-                    format!("Failed to re-parse config: {e}"),
-                )
-            })?;
-
-            // 提取 extractor 函数
-            let extractor: Function = config.get("extractor").map_err(|e| {
-                AppError::localized(
-                    "usage_script.extractor_missing",
-                    format!("缺少 extractor 函数: {e}"),
-                    format!("Missing extractor function: {e}"),
-                )
-            })?;
-
-            // 将响应数据转换为 JS 值
-            let response_js: rquickjs::Value =
-                ctx.json_parse(response_data.as_str()).map_err(|e| {
-                    AppError::localized(
-                        "usage_script.response_parse_failed",
-                        format!("解析响应 JSON 失败: {e}"),
-                        format!("Failed to parse response JSON: {e}"),
-                    )
-                })?;
-
-            // 调用 extractor(response)
-            let result_js: rquickjs::Value = extractor.call((response_js,)).map_err(|e| {
-                AppError::localized(
-                    "usage_script.extractor_exec_failed",
-                    format!("执行 extractor 失败: {e}"),
-                    format!("Failed to execute extractor: {e}"),
-                )
-            })?;
-
-            // 转换为 JSON 字符串
-            let result_json: String = ctx
-                .json_stringify(result_js)
-                .map_err(|e| {
-                    AppError::localized(
-                        "usage_script.result_serialize_failed",
-                        format!("序列化结果失败: {e}"),
-                        format!("Failed to serialize result: {e}"),
-                    )
-                })?
-                .ok_or_else(|| {
-                    AppError::localized(
-                        "usage_script.serialize_none",
-                        "序列化返回 None",
-                        "Serialization returned None",
-                    )
-                })?
-                .get()
-                .map_err(|e| {
-                    AppError::localized(
-                        "usage_script.get_string_failed",
-                        format!("获取字符串失败: {e}"),
-                        format!("Failed to get string: {e}"),
-                    )
-                })?;
-
-            // 解析为 serde_json::Value
-            serde_json::from_str(&result_json).map_err(|e| {
-                AppError::localized(
-                    "usage_script.json_parse_failed",
-                    format!("JSON 解析失败: {e}"),
-                    format!("JSON parse failed: {e}"),
-                )
-            })
         })?
-    }; // Runtime 和 Context 在这里被 drop
-
-    // 8. 验证返回值格式
-    validate_result(&result)?;
-
-    Ok(result)
+        .get()
+        .map_err(|e| {
+            AppError::localized(
+                "usage_script.get_string_failed",
+                format!("获取字符串失败: {e}"),
+                format!("Failed to get string: {e}"),
+            )
+        })
 }
 
 /// 请求配置结构
@@ -221,11 +260,12 @@ struct RequestConfig {
 }
 
 /// 发送 HTTP 请求
-async fn send_http_request(config: &RequestConfig, timeout_secs: u64) -> Result<String, AppError> {
+async fn send_http_request(
+    config: &RequestConfig,
+    request_timeout: Duration,
+) -> Result<String, AppError> {
     // 使用全局 HTTP 客户端（已包含代理配置）
     let client = crate::proxy::http_client::get();
-    // 约束超时范围，防止异常配置导致长时间阻塞（最小 2 秒，最大 30 秒）
-    let request_timeout = std::time::Duration::from_secs(timeout_secs.clamp(2, 30));
 
     // 严格校验 HTTP 方法，非法值不回退为 GET
     let method: reqwest::Method = config.method.parse().map_err(|_| {
@@ -832,6 +872,49 @@ mod tests {
         assert!(!is_private_ip_addr(ipv6_public));
     }
 
+    #[test]
+    fn query_function_result_and_console_are_returned() {
+        let script = r#"({
+  query: async function () {
+    console.log("checking balance");
+    return { planName: "pro", remaining: 5, unit: "USD" };
+  }
+})"#;
+        let run = tauri::async_runtime::block_on(execute_usage_script(
+            script,
+            "",
+            "",
+            5,
+            None,
+            None,
+            Some("custom"),
+        ));
+        let result = run.result.expect("script succeeds");
+        assert_eq!(result["remaining"], 5);
+        assert_eq!(run.logs, vec!["checking balance"]);
+    }
+
+    #[test]
+    fn fetch_to_private_address_is_blocked() {
+        let script = r#"({
+  query: async function () {
+    const resp = await fetch("http://10.0.0.1/balance");
+    return await resp.json();
+  }
+})"#;
+        let run = tauri::async_runtime::block_on(execute_usage_script(
+            script,
+            "",
+            "",
+            5,
+            None,
+            None,
+            Some("custom"),
+        ));
+        let err = run.result.expect_err("private address must be rejected");
+        assert!(err.to_string().contains("私有 IP"), "{err}");
+    }
+
     #[test]
     fn test_port_comparison() {
         // 测试端口比较逻辑是否正确处理默认端口和显式端口
@@ -2,7 +2,7 @@ use serde_json::json;
 
 use cc_switch_lib::{
     get_claude_settings_path, read_json_file, write_codex_live_atomic, AppError, AppType, McpApps,
-    McpServer, MultiAppConfig, Provider, ProviderMeta, ProviderService,
+    McpServer, MultiAppConfig, PromptService, Provider, ProviderMeta, ProviderService,
 };
 
 #[path = "support.rs"]
@@ -420,6 +420,63 @@ fn provider_service_switch_claude_updates_live_and_state() {
     );
 }
 
+#[test]
+fn provider_service_switch_rerenders_enabled_prompt_variables() {
+    let _guard = test_mutex().lock().expect("acquire test mutex");
+    reset_test_fs();
+    let _home = ensure_test_home();
+
+    let mut config = MultiAppConfig::default();
+    {
+        let manager = config
+            .get_manager_mut(&AppType::Claude)
+            .expect("claude manager");
+        manager.current = "old-provider".to_string();
+        for (id, name) in [
+            ("old-provider", "Legacy Claude"),
+            ("new-provider", "Fresh Claude"),
+        ] {
+            manager.providers.insert(
+                id.to_string(),
+                Provider::with_id(
+                    id.to_string(),
+                    name.to_string(),
+                    json!({ "env": { "ANTHROPIC_API_KEY": format!("{id}-key") } }),
+                    None,
+                ),
+            );
+        }
+    }
+
+    let state = create_test_state_with_config(&config).expect("create test state");
+
+    let prompt = serde_json::from_value(json!({
+        "id": "rules",
+        "name": "Rules",
+        "content": "Provider: {{provider_name}}",
+        "enabled": true
+    }))
+    .expect("parse prompt");
+    PromptService::upsert_prompt(&state, AppType::Claude, "rules", prompt)
+        .expect("save enabled prompt");
+
+    let prompt_path = get_claude_settings_path()
+        .parent()
+        .expect("claude dir")
+        .join("CLAUDE.md");
+    let read_prompt = || std::fs::read_to_string(&prompt_path).expect("read live prompt");
+    assert_eq!(read_prompt(), "Provider: Legacy Claude");
+
+    ProviderService::switch(&state, AppType::Claude, "new-provider")
+        .expect("switch provider should succeed");
+
+    assert_eq!(
+        read_prompt(),
+        "Provider: Fresh Claude",
+        "live prompt should be re-rendered with the new provider name"
+    );
+}
+
 #[test]
 fn provider_service_switch_missing_provider_returns_error() {
     let _guard = test_mutex().lock().expect("acquire test mutex");
@@ -291,6 +291,7 @@ const UsageScriptModal: React.FC<UsageScriptModalProps> = ({
           })
           .join(", ");
         toast.success(`${t("usageScript.testSuccess")}${summary}`, {
+          description: result.logs?.join("\n"),
           duration: 3000,
           closeButton: true,
         });
@@ -301,6 +302,7 @@ const UsageScriptModal: React.FC<UsageScriptModalProps> = ({
         toast.error(
           `${t("usageScript.testFailed")}: ${result.error || t("endpointTest.noResult")}`,
           {
+            description: result.logs?.join("\n"),
             duration: 5000,
           },
         );
@@ -219,10 +219,12 @@ export function ProviderForm({
       return initialData?.meta?.requestBodyRewriter ?? defaultRequestBodyRewriterConfig;
     });
 
-  // 请求重写脚本（onRequest）配置状态
+  // 请求重写脚本（onRequest）配置状态（经代理转发的应用可用）
+  const supportsRequestHook =
+    appId === "claude" || appId === "codex" || appId === "gemini";
   const [requestHookScript, setRequestHookScript] =
     useState<RequestHookScriptConfigType>(() => {
-      if (appId !== "codex") return defaultRequestHookScriptConfig;
+      if (!supportsRequestHook) return defaultRequestHookScriptConfig;
       return initialData?.meta?.requestHookScript ?? defaultRequestHookScriptConfig;
     });
 
@@ -990,9 +992,9 @@ export function ProviderForm({
         appId === "codex" && requestBodyRewriter.enabled
           ? requestBodyRewriter
           : undefined,
-      // 请求重写脚本（onRequest）（仅 Codex 供应商使用）
+      // 请求重写脚本（onRequest）
       requestHookScript:
-        appId === "codex" && requestHookScript.enabled
+        supportsRequestHook && requestHookScript.enabled
           ? requestHookScript
           : undefined,
     };
@@ -1564,8 +1566,8 @@ export function ProviderForm({
           />
         )}
 
-        {/* 请求重写脚本（onRequest，仅 Codex） */}
-        {appId === "codex" && (
+        {/* 请求重写脚本（onRequest/onResponse/onStreamEvent） */}
+        {supportsRequestHook && (
           <RequestHookScriptConfig
             appId={appId}
             providerId={providerId}
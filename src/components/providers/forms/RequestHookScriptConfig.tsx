@@ -7,12 +7,13 @@ import { Switch } from "@/components/ui/switch";
 import { Button } from "@/components/ui/button";
 import { providersApi } from "@/lib/api/providers";
 
-/** 请求/响应重写脚本（onRequest/onResponse）配置 */
+/** 请求/响应重写脚本（onRequest/onResponse/onStreamEvent）配置 */
 export interface RequestHookScriptConfig {
   enabled: boolean;
   language: "javascript";
   code: string;
   timeoutMs?: number;
+  streamEventBudgetMs?: number;
 }
 
 export const defaultRequestHookScriptConfig: RequestHookScriptConfig = {
@@ -33,6 +34,11 @@ const TEMPLATE_DELETE_CODEX_METADATA = `({
   onResponse: function (context, response) {
     // 示例：透传响应（可选）
     return response;
+  },
+
+  onStreamEvent: function (event, context) {
+    // 流式响应逐事件调用：返回 undefined 透传，返回 [] 丢弃，返回数组可插入多个事件
+    return event;
   }
 })
 `;
@@ -77,7 +83,7 @@ export function RequestHookScriptConfig({
   const [testResultBody, setTestResultBody] = useState<string>("");
   const [testResultUrl, setTestResultUrl] = useState<string>("");
 
-  const canTest = Boolean(providerId) && appId === "codex";
+  const canTest = Boolean(providerId) && appId !== "opencode";
 
   const handleInsertTemplate = () => {
     onConfigChange({
@@ -148,6 +154,7 @@ export function RequestHookScriptConfig({
         headersObj,
         bodyObj,
         "/v1/responses",
+        config.timeoutMs,
       );
       setTestResultUrl(result.url || "");
       setTestResultHeaders(JSON.stringify(result.headers ?? {}, null, 2));
@@ -1,5 +1,6 @@
 // 配置相关 API
 import { invoke } from "@tauri-apps/api/core";
+import type { AppId } from "./types";
 
 export type AppType = "claude" | "codex" | "gemini";
 
@@ -75,3 +76,46 @@ export async function extractCommonConfigSnippet(
 
   return invoke<string>("extract_common_config_snippet", args);
 }
+
+export type DriftChangeKind = "added" | "removed" | "changed";
+
+export interface DriftChange {
+  /** 字段路径，如 env.ANTHROPIC_BASE_URL */
+  path: string;
+  kind: DriftChangeKind;
+  stored?: unknown;
+  live?: unknown;
+}
+
+/** Live 配置与当前供应商存储配置之间的漂移（`config-drift-detected` 事件负载） */
+export interface ConfigDrift {
+  appType: AppId;
+  providerId?: string;
+  providerName?: string;
+  /** 代理接管期间 Live 配置被改写，接管占位符已丢失 */
+  takeoverLost: boolean;
+  changes: DriftChange[];
+  fingerprint: string;
+  detectedAt: number;
+}
+
+export type DriftResolution = "adopt" | "revert" | "ignore";
+
+/**
+ * 检测 Live 配置漂移
+ * @returns 无漂移或已忽略时返回 null
+ */
+export async function getConfigDrift(app: AppId): Promise<ConfigDrift | null> {
+  return invoke<ConfigDrift | null>("get_config_drift", { app });
+}
+
+/**
+ * 处理 Live 配置漂移
+ * @param resolution - adopt 采纳到供应商 / revert 还原为存储配置 / ignore 忽略本次修改
+ */
+export async function resolveConfigDrift(
+  app: AppId,
+  resolution: DriftResolution,
+): Promise<void> {
+  return invoke("resolve_config_drift", { app, resolution });
+}
@@ -4,6 +4,11 @@ import type {
   CircuitBreakerConfig,
   CircuitBreakerStats,
   FailoverQueueItem,
+  FailbackConfig,
+  FailoverTransition,
+  HedgingConfig,
+  RoutingRule,
+  StreamResumeConfig,
 } from "@/types/proxy";
 
 export interface Provider {
@@ -59,6 +64,19 @@ export const failoverApi = {
     return invoke("get_circuit_breaker_stats", { providerId, appType });
   },
 
+  // 获取 Key 池中指定 Key 的熔断器统计信息
+  async getKeyCircuitBreakerStats(
+    providerId: string,
+    appType: string,
+    keyId: string,
+  ): Promise<CircuitBreakerStats | null> {
+    return invoke("get_key_circuit_breaker_stats", {
+      providerId,
+      appType,
+      keyId,
+    });
+  },
+
   // ========== 故障转移队列 API（新） ==========
 
   // 获取故障转移队列
@@ -96,4 +114,69 @@ export const failoverApi = {
   ): Promise<void> {
     return invoke("set_auto_failover_enabled", { appType, enabled });
   },
+
+  // ========== 自动切回 API ==========
+
+  // 获取指定应用的自动切回配置
+  async getFailbackConfig(appType: string): Promise<FailbackConfig> {
+    return invoke("get_failback_config", { appType });
+  },
+
+  // 设置指定应用的自动切回配置
+  async setFailbackConfig(
+    appType: string,
+    config: FailbackConfig,
+  ): Promise<void> {
+    return invoke("set_failback_config", { appType, config });
+  },
+
+  // ========== 请求对冲 API ==========
+
+  // 获取指定应用的请求对冲配置
+  async getHedgingConfig(appType: string): Promise<HedgingConfig> {
+    return invoke("get_hedging_config", { appType });
+  },
+
+  // 设置指定应用的请求对冲配置
+  async setHedgingConfig(
+    appType: string,
+    config: HedgingConfig,
+  ): Promise<void> {
+    return invoke("set_hedging_config", { appType, config });
+  },
+
+  // ========== 断流续传 API ==========
+
+  // 获取指定应用的断流续传配置
+  async getStreamResumeConfig(appType: string): Promise<StreamResumeConfig> {
+    return invoke("get_stream_resume_config", { appType });
+  },
+
+  // 设置指定应用的断流续传配置
+  async setStreamResumeConfig(
+    appType: string,
+    config: StreamResumeConfig,
+  ): Promise<void> {
+    return invoke("set_stream_resume_config", { appType, config });
+  },
+
+  // ========== 路由规则 API ==========
+
+  // 获取指定应用的路由规则
+  async getRoutingRules(appType: string): Promise<RoutingRule[]> {
+    return invoke("get_routing_rules", { appType });
+  },
+
+  // 整体替换指定应用的路由规则（数组顺序即评估顺序）
+  async setRoutingRules(appType: string, rules: RoutingRule[]): Promise<void> {
+    return invoke("set_routing_rules", { appType, rules });
+  },
+
+  // 获取故障转移 / 自动切回历史
+  async getFailoverHistory(
+    appType: string,
+    limit?: number,
+  ): Promise<FailoverTransition[]> {
+    return invoke("get_failover_history", { appType, limit });
+  },
 };
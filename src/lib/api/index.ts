@@ -3,7 +3,10 @@ export { providersApi, universalProvidersApi } from "./providers";
 export { settingsApi } from "./settings";
 export { mcpApi } from "./mcp";
 export { promptsApi } from "./prompts";
+export { projectsApi } from "./projects";
+export { profilesApi } from "./profiles";
 export { skillsApi } from "./skills";
+export { switchRulesApi } from "./switchRules";
 export { usageApi } from "./usage";
 export { vscodeApi } from "./vscode";
 export { proxyApi } from "./proxy";
@@ -11,3 +14,6 @@ export { sessionsApi } from "./sessions";
 export * as configApi from "./config";
 export type { ProviderSwitchEvent } from "./providers";
 export type { Prompt } from "./prompts";
+export type { Project, ProjectProviderOverride } from "./projects";
+export type { Profile, ProfileApps, ProfileAppConfig } from "./profiles";
+export type { SwitchRule, SwitchRuleFiring } from "./switchRules";
@@ -0,0 +1,56 @@
+import { invoke } from "@tauri-apps/api/core";
+
+/** 单个应用的档案配置，未设置的字段在应用档案时保持不变 */
+export interface ProfileAppConfig {
+  providerId?: string;
+  mcpServers?: string[];
+  /** 空字符串表示禁用所有全局提示词 */
+  promptId?: string;
+  skills?: string[];
+}
+
+export interface ProfileApps {
+  claude?: ProfileAppConfig;
+  codex?: ProfileAppConfig;
+  gemini?: ProfileAppConfig;
+  opencode?: ProfileAppConfig;
+}
+
+export interface Profile {
+  id: string;
+  name: string;
+  description?: string;
+  apps: ProfileApps;
+  sortIndex?: number;
+  createdAt?: number;
+}
+
+export const profilesApi = {
+  async getProfiles(): Promise<Profile[]> {
+    return await invoke("get_profiles");
+  },
+
+  async getActiveProfileId(): Promise<string | null> {
+    return await invoke("get_active_profile_id");
+  },
+
+  async saveProfile(profile: Profile): Promise<Profile> {
+    return await invoke("save_profile", { profile });
+  },
+
+  async deleteProfile(id: string): Promise<boolean> {
+    return await invoke("delete_profile", { id });
+  },
+
+  async getCurrentSnapshot(): Promise<ProfileApps> {
+    return await invoke("get_current_profile_snapshot");
+  },
+
+  async captureCurrent(name: string): Promise<Profile> {
+    return await invoke("capture_current_profile", { name });
+  },
+
+  async applyProfile(id: string): Promise<Profile> {
+    return await invoke("apply_profile", { id });
+  },
+};
@@ -0,0 +1,50 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { AppId } from "./types";
+
+export interface Project {
+  id: string;
+  name: string;
+  path: string;
+  createdAt?: number;
+}
+
+/** 项目覆盖的供应商（写入项目目录作用域的配置文件） */
+export interface ProjectProviderOverride {
+  projectId: string;
+  projectName: string;
+  projectPath: string;
+  appType: AppId;
+  providerId: string;
+  /** 供应商已被删除时为空 */
+  providerName?: string;
+  configPath: string;
+}
+
+export const projectsApi = {
+  async getProjects(): Promise<Project[]> {
+    return await invoke("get_projects");
+  },
+
+  async addProject(path: string, name?: string): Promise<Project> {
+    return await invoke("add_project", { path, name });
+  },
+
+  async removeProject(id: string): Promise<void> {
+    return await invoke("remove_project", { id });
+  },
+
+  async getProjectProviders(
+    projectId?: string,
+  ): Promise<ProjectProviderOverride[]> {
+    return await invoke("get_project_providers", { projectId });
+  },
+
+  /** providerId 为空时取消该项目的供应商覆盖 */
+  async setProjectProvider(
+    projectId: string,
+    app: AppId,
+    providerId?: string,
+  ): Promise<ProjectProviderOverride | null> {
+    return await invoke("set_project_provider", { projectId, app, providerId });
+  },
+};
@@ -9,11 +9,48 @@ export interface Prompt {
   enabled: boolean;
   createdAt?: number;
   updatedAt?: number;
+  /** 所属项目 ID，缺省表示全局提示词 */
+  projectId?: string;
+}
+
+export type PromptRevisionSource = "baseline" | "edit" | "backfill" | "restore";
+
+export interface PromptRevision {
+  id: number;
+  promptId: string;
+  content: string;
+  source: PromptRevisionSource;
+  createdAt: number;
+}
+
+export interface PromptDiffLine {
+  tag: "equal" | "insert" | "delete";
+  content: string;
+  oldLine?: number;
+  newLine?: number;
+}
+
+export interface PromptRevisionDiff {
+  fromRevision: number;
+  toRevision: number;
+  additions: number;
+  deletions: number;
+  lines: PromptDiffLine[];
+  unified: string;
+}
+
+export interface RenderedPrompt {
+  content: string;
+  /** 被引入的提示词 ID */
+  includes: string[];
 }
 
 export const promptsApi = {
-  async getPrompts(app: AppId): Promise<Record<string, Prompt>> {
-    return await invoke("get_prompts", { app });
+  async getPrompts(
+    app: AppId,
+    projectId?: string,
+  ): Promise<Record<string, Prompt>> {
+    return await invoke("get_prompts", { app, projectId });
   },
 
   async upsertPrompt(app: AppId, id: string, prompt: Prompt): Promise<void> {
@@ -28,11 +65,44 @@ export const promptsApi = {
     return await invoke("enable_prompt", { app, id });
   },
 
-  async importFromFile(app: AppId): Promise<string> {
-    return await invoke("import_prompt_from_file", { app });
+  async importFromFile(app: AppId, projectId?: string): Promise<string> {
+    return await invoke("import_prompt_from_file", { app, projectId });
+  },
+
+  async getCurrentFileContent(
+    app: AppId,
+    projectId?: string,
+  ): Promise<string | null> {
+    return await invoke("get_current_prompt_file_content", { app, projectId });
+  },
+
+  async getRevisions(app: AppId, id: string): Promise<PromptRevision[]> {
+    return await invoke("get_prompt_revisions", { app, id });
+  },
+
+  async diffRevisions(
+    app: AppId,
+    id: string,
+    fromRevision: number,
+    toRevision: number,
+  ): Promise<PromptRevisionDiff> {
+    return await invoke("diff_prompt_revisions", {
+      app,
+      id,
+      fromRevision,
+      toRevision,
+    });
+  },
+
+  async restoreRevision(
+    app: AppId,
+    id: string,
+    revisionId: number,
+  ): Promise<void> {
+    return await invoke("restore_prompt_revision", { app, id, revisionId });
   },
 
-  async getCurrentFileContent(app: AppId): Promise<string | null> {
-    return await invoke("get_current_prompt_file_content", { app });
+  async previewPrompt(app: AppId, prompt: Prompt): Promise<RenderedPrompt> {
+    return await invoke("preview_prompt", { app, prompt });
   },
 };
@@ -21,6 +21,8 @@ export interface RequestHookScriptTestResult {
   url: string;
   headers: Record<string, string>;
   body: any;
+  /** 脚本 console 输出 */
+  logs: string[];
 }
 
 export const providersApi = {
@@ -78,6 +80,7 @@ export const providersApi = {
     headers: Record<string, string>,
     body: any,
     endpoint?: string,
+    timeoutMs?: number,
   ): Promise<RequestHookScriptTestResult> {
     return await invoke("testRequestHookScript", {
       providerId,
@@ -86,6 +89,7 @@ export const providersApi = {
       headers,
       body,
       endpoint,
+      timeoutMs,
     });
   },
 
@@ -6,6 +6,8 @@ import type {
   ProxyTakeoverStatus,
   GlobalProxyConfig,
   AppProxyConfig,
+  ProxyClientToken,
+  CreatedProxyClientToken,
 } from "@/types/proxy";
 
 export const proxyApi = {
@@ -117,4 +119,34 @@ export const proxyApi = {
   async setPricingModelSource(appType: string, value: string): Promise<void> {
     return invoke("set_pricing_model_source", { appType, value });
   },
+
+  // ========== 客户端访问令牌 API ==========
+
+  // 获取客户端访问令牌列表（不含明文）
+  async getClientTokens(): Promise<ProxyClientToken[]> {
+    return invoke("get_proxy_client_tokens");
+  },
+
+  // 创建客户端访问令牌（明文令牌只返回这一次）
+  async createClientToken(
+    name: string,
+    allowedApps?: string[],
+    allowedProviders?: string[],
+  ): Promise<CreatedProxyClientToken> {
+    return invoke("create_proxy_client_token", {
+      name,
+      allowedApps,
+      allowedProviders,
+    });
+  },
+
+  // 更新客户端访问令牌的名称、访问限制与启用状态
+  async updateClientToken(token: ProxyClientToken): Promise<boolean> {
+    return invoke("update_proxy_client_token", { token });
+  },
+
+  // 删除客户端访问令牌
+  async deleteClientToken(id: string): Promise<boolean> {
+    return invoke("delete_proxy_client_token", { id });
+  },
 };
@@ -1,5 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
-import type { Settings } from "@/types";
+import type { RequestHookScript, Settings } from "@/types";
 import type { AppId } from "./types";
 
 export interface ConfigTransferResult {
@@ -166,6 +166,29 @@ export const settingsApi = {
     return await invoke("set_rectifier_config", { config });
   },
 
+  async getTracingConfig(): Promise<TracingConfig> {
+    return await invoke("get_tracing_config");
+  },
+
+  async setTracingConfig(config: TracingConfig): Promise<boolean> {
+    return await invoke("set_tracing_config", { config });
+  },
+
+  /** 全局（app 为空）或应用级请求重写脚本 */
+  async getRequestHookScript(app?: AppId): Promise<RequestHookScript | null> {
+    return await invoke("get_request_hook_script", { app: app ?? null });
+  },
+
+  async setRequestHookScript(
+    script: RequestHookScript | null,
+    app?: AppId,
+  ): Promise<boolean> {
+    return await invoke("set_request_hook_script", {
+      app: app ?? null,
+      script,
+    });
+  },
+
   async getLogConfig(): Promise<LogConfig> {
     return await invoke("get_log_config");
   },
@@ -180,6 +203,14 @@ export interface RectifierConfig {
   requestThinkingSignature: boolean;
 }
 
+/** OTLP 链路追踪导出配置 */
+export interface TracingConfig {
+  enabled: boolean;
+  /** OTLP/HTTP traces 端点，如 http://127.0.0.1:4318/v1/traces */
+  endpoint: string;
+  serviceName: string;
+}
+
 export interface LogConfig {
   enabled: boolean;
   level: "error" | "warn" | "info" | "debug" | "trace";
@@ -24,6 +24,31 @@ export interface InstalledSkill {
   readmeUrl?: string;
   apps: SkillApps;
   installedAt: number;
+  /** 安装内容对应的上游提交 */
+  sourceRef?: string;
+  /** 安装内容哈希（SHA-256） */
+  contentHash?: string;
+  /** 固定的 tag / commit */
+  pinnedRef?: string;
+}
+
+/** 文件变更 */
+export interface SkillFileChange {
+  path: string;
+  status: "added" | "removed" | "modified";
+  /** unified diff（二进制文件为空） */
+  diff?: string;
+}
+
+/** 可用更新 */
+export interface SkillUpdateInfo {
+  id: string;
+  name: string;
+  directory: string;
+  currentRef?: string;
+  latestRef?: string;
+  pinnedRef?: string;
+  changes: SkillFileChange[];
 }
 
 /** 可发现的 Skill（来自仓库） */
@@ -59,12 +84,21 @@ export interface Skill {
   repoBranch?: string;
 }
 
+/** 仓库来源类型 */
+export type SkillRepoSource = "github" | "gitlab" | "gitea" | "zip" | "local";
+
 /** 仓库配置 */
 export interface SkillRepo {
   owner: string;
   name: string;
   branch: string;
   enabled: boolean;
+  /** 默认 github */
+  source?: SkillRepoSource;
+  /** GitHub/GitLab/Gitea 为实例根地址，zip 为下载地址，local 为本地目录 */
+  url?: string;
+  /** 私有仓库访问令牌（仅在添加时提交，后端不会返回） */
+  token?: string;
 }
 
 // ========== API ==========
@@ -114,6 +148,21 @@ export const skillsApi = {
     return await invoke("discover_available_skills");
   },
 
+  /** 检查已安装 Skills 的可用更新 */
+  async checkUpdates(): Promise<SkillUpdateInfo[]> {
+    return await invoke("check_skill_updates");
+  },
+
+  /** 更新 Skill 到上游最新内容（或固定的版本） */
+  async update(id: string): Promise<InstalledSkill> {
+    return await invoke("update_skill", { id });
+  },
+
+  /** 固定 Skill 到指定 tag / commit，传 null 取消固定 */
+  async pin(id: string, gitRef: string | null): Promise<InstalledSkill> {
+    return await invoke("pin_skill", { id, gitRef });
+  },
+
   // ========== 兼容旧 API ==========
 
   /** 获取技能列表（兼容旧 API） */
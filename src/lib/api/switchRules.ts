@@ -0,0 +1,62 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { AppId } from "./types";
+
+/** 规则触发条件 */
+export type SwitchRuleCondition =
+  | {
+      type: "time_window";
+      /** 0 = 周日 … 6 = 周六，为空表示每天 */
+      days?: number[];
+      /** "HH:MM"，结束早于开始表示跨越午夜 */
+      start: string;
+      end: string;
+    }
+  | { type: "daily_limit_exceeded"; providerId?: string }
+  | { type: "monthly_limit_exceeded"; providerId?: string }
+  | { type: "usage_remaining_below"; providerId?: string; threshold: number };
+
+export type SwitchRuleAction =
+  | { type: "switch_provider"; providerId: string }
+  | { type: "promote_in_failover_queue"; providerId: string };
+
+export interface SwitchRule {
+  id: string;
+  name: string;
+  appType: AppId;
+  enabled: boolean;
+  condition: SwitchRuleCondition;
+  action: SwitchRuleAction;
+  sortIndex?: number;
+  createdAt?: number;
+}
+
+export interface SwitchRuleFiring {
+  id: number;
+  ruleId: string;
+  ruleName: string;
+  appType: AppId;
+  action: SwitchRuleAction["type"];
+  fromProviderId?: string;
+  toProviderId: string;
+  success: boolean;
+  message?: string;
+  firedAt: number;
+}
+
+export const switchRulesApi = {
+  async getRules(app?: AppId): Promise<SwitchRule[]> {
+    return await invoke("get_switch_rules", { app });
+  },
+
+  async saveRule(rule: SwitchRule): Promise<SwitchRule> {
+    return await invoke("save_switch_rule", { rule });
+  },
+
+  async deleteRule(id: string): Promise<boolean> {
+    return await invoke("delete_switch_rule", { id });
+  },
+
+  async getLog(app?: AppId, limit?: number): Promise<SwitchRuleFiring[]> {
+    return await invoke("get_switch_rule_log", { app, limit });
+  },
+};
@@ -3,6 +3,7 @@ import type {
   UsageSummary,
   DailyStats,
   ProviderStats,
+  ApiKeyStats,
   ModelStats,
   RequestLog,
   LogFilters,
@@ -10,7 +11,7 @@ import type {
   ProviderLimitStatus,
   PaginatedLogs,
 } from "@/types/usage";
-import type { UsageResult } from "@/types";
+import type { UsageResult, UsageScriptTrend } from "@/types";
 import type { AppId } from "./types";
 
 export const usageApi = {
@@ -43,6 +44,15 @@ export const usageApi = {
     });
   },
 
+  /** 用量脚本历史快照与耗尽预测（默认最近 30 天） */
+  getScriptTrend: async (
+    providerId: string,
+    appId: AppId,
+    days?: number,
+  ): Promise<UsageScriptTrend> => {
+    return invoke("get_usage_script_trend", { providerId, app: appId, days });
+  },
+
   // Proxy usage statistics methods
   getUsageSummary: async (
     startDate?: number,
@@ -62,6 +72,13 @@ export const usageApi = {
     return invoke("get_provider_stats");
   },
 
+  getApiKeyStats: async (
+    providerId: string,
+    appType: string,
+  ): Promise<ApiKeyStats[]> => {
+    return invoke("get_api_key_stats", { providerId, appType });
+  },
+
   getModelStats: async (): Promise<ModelStats[]> => {
     return invoke("get_model_stats");
   },
@@ -39,6 +39,9 @@ function getErrorI18nKey(code: string): string {
     EMPTY_ARCHIVE: "skills.error.emptyArchive",
     GET_HOME_DIR_FAILED: "skills.error.getHomeDirFailed",
     NO_SKILLS_IN_ZIP: "skills.error.noSkillsInZip",
+    REPO_URL_REQUIRED: "skills.error.repoUrlRequired",
+    LOCAL_REPO_NOT_FOUND: "skills.error.localRepoNotFound",
+    PIN_NOT_SUPPORTED: "skills.error.pinNotSupported",
   };
 
   return mapping[code] || "skills.error.unknownError";
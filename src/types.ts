@@ -59,6 +59,7 @@ export interface UsageScript {
   userId?: string; // 用户ID（NewAPI 模板使用）
   autoQueryInterval?: number; // 自动查询间隔（单位：分钟，0 表示禁用）
   autoIntervalMinutes?: number; // 自动查询间隔（分钟）- 别名字段
+  lowBalanceThreshold?: number; // 低余额提醒阈值（任一套餐剩余额度低于该值时提醒）
   request?: {
     // 请求配置
     url?: string; // 请求 URL
@@ -74,6 +75,7 @@ export interface RequestHookScript {
   language: "javascript";
   code: string;
   timeoutMs?: number;
+  streamEventBudgetMs?: number; // onStreamEvent 单事件执行预算（毫秒，默认 50）
 }
 // 单个套餐用量数据
 export interface UsageData {
@@ -87,11 +89,47 @@ export interface UsageData {
   unit?: string; // 单位（可选）
 }
 
+// 用量快照（后台轮询或手动查询的历史结果）
+export interface UsageSnapshot {
+  planName: string;
+  total?: number;
+  used?: number;
+  remaining?: number;
+  unit?: string;
+  isValid?: boolean;
+  capturedAt: number; // Unix 秒
+}
+
+// 单个套餐的耗尽预测
+export interface UsageForecast {
+  planName: string;
+  unit?: string;
+  remaining: number;
+  dailyBurn?: number; // 平均每日消耗，观测期内余额未下降时为空
+  daysUntilExhausted?: number;
+  exhaustedAt?: number; // 预计耗尽时间（Unix 秒）
+}
+
+export interface UsageScriptTrend {
+  snapshots: UsageSnapshot[];
+  forecasts: UsageForecast[];
+}
+
+// usage-low-balance 事件
+export interface LowBalanceEvent {
+  appType: string;
+  providerId: string;
+  providerName: string;
+  remaining: number;
+  threshold: number;
+}
+
 // 用量查询结果（支持多套餐）
 export interface UsageResult {
   success: boolean;
   data?: UsageData[]; // 改为数组，支持返回多个套餐
   error?: string;
+  logs?: string[]; // 脚本 console 输出
 }
 
 // 供应商单独的模型测试配置
@@ -126,6 +164,25 @@ export interface ProviderProxyConfig {
   proxyPassword?: string;
 }
 
+// API Key 轮换策略
+export type ApiKeyRotationStrategy = "round_robin" | "least_used" | "failover";
+
+// Key 池中的单个 API Key
+export interface ApiKeyEntry {
+  // Key 标识（用于熔断与用量归因）
+  id: string;
+  key: string;
+  label?: string;
+  enabled: boolean;
+}
+
+// 供应商 API Key 池（启用后覆盖 settingsConfig 中的 Key，全部不可用时回退）
+export interface ApiKeyPool {
+  enabled: boolean;
+  strategy: ApiKeyRotationStrategy;
+  keys: ApiKeyEntry[];
+}
+
 // 供应商元数据（字段名与后端一致，保持 snake_case）
 export interface ProviderMeta {
   // 自定义端点：以 URL 为键，值为端点信息
@@ -163,6 +220,8 @@ export interface ProviderMeta {
   };
   // 请求/响应重写脚本（onRequest/onResponse）（仅 Codex 供应商使用）
   requestHookScript?: RequestHookScript;
+  // API Key 池（多 Key 轮换）
+  apiKeyPool?: ApiKeyPool;
 }
 
 // Skill 同步方式
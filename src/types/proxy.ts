@@ -111,6 +111,62 @@ export interface FailoverQueueItem {
   sortIndex?: number;
 }
 
+// 自动切回配置（每个 app 独立）
+export interface FailbackConfig {
+  enabled: boolean;
+  // 需连续通过的健康探测次数（至少 1 次）
+  probeSuccesses: number;
+  probeIntervalSeconds: number;
+  // 冷却时间（秒），到期后主供应商通过一次健康探测即切回，0 表示不使用
+  cooldownSeconds: number;
+}
+
+// 请求对冲：流式请求的首选供应商超过 delayMs 仍未返回首字节时，向下一个供应商发送相同请求
+export interface HedgingConfig {
+  enabled: boolean;
+  // 100-60000 毫秒
+  delayMs: number;
+}
+
+// 断流续传配置（Anthropic Messages / OpenAI Responses 流式响应在结束前中断时，换供应商续写）
+export interface StreamResumeConfig {
+  enabled: boolean;
+  // 单个请求最多续传次数（1-5）
+  maxResumes: number;
+}
+
+// 路由规则（按数组顺序评估，首条命中的规则决定供应商链；未设置的条件不参与匹配）
+export interface RoutingRule {
+  id: string;
+  name: string;
+  enabled: boolean;
+  // 模型 glob（* / ?，不区分大小写），如 "*haiku*"
+  modelPattern?: string;
+  // 要求启用（true）或未启用（false）thinking
+  thinking?: boolean;
+  minBodyBytes?: number;
+  maxBodyBytes?: number;
+  // 端点 glob（不含查询参数），如 "/v1/messages"
+  endpointPattern?: string;
+  // 命中后按顺序尝试的供应商 ID
+  providerIds: string[];
+}
+
+export type FailoverTransitionKind = "failover" | "failback" | "recovery";
+
+// 故障转移 / 自动切回历史
+export interface FailoverTransition {
+  id: number;
+  appType: string;
+  kind: FailoverTransitionKind;
+  fromProviderId?: string;
+  fromProviderName?: string;
+  toProviderId: string;
+  toProviderName: string;
+  detail?: string;
+  createdAt: number;
+}
+
 // 全局代理配置（统一字段，三行镜像）
 export interface GlobalProxyConfig {
   proxyEnabled: boolean;
@@ -119,6 +175,25 @@ export interface GlobalProxyConfig {
   enableLogging: boolean;
 }
 
+// 代理客户端访问令牌（不含明文）
+export interface ProxyClientToken {
+  id: string;
+  name: string;
+  tokenPrefix: string;
+  // 为空表示不限制
+  allowedApps?: string[];
+  allowedProviders?: string[];
+  enabled: boolean;
+  createdAt: number;
+  lastUsedAt?: number;
+}
+
+// 新建令牌的结果（token 为明文，只返回一次）
+export interface CreatedProxyClientToken {
+  token: string;
+  info: ProxyClientToken;
+}
+
 // 应用级代理配置（每个 app 独立）
 export interface AppProxyConfig {
   appType: string;
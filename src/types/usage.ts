@@ -31,6 +31,16 @@ export interface RequestLog {
   statusCode: number;
   errorMessage?: string;
   createdAt: number;
+  apiKeyId?: string;
+  clientTokenId?: string;
+  routingOverride?: RoutingOverride;
+}
+
+/** 请求头路由覆盖（x-ccswitch-provider / x-ccswitch-model / x-ccswitch-no-failover） */
+export interface RoutingOverride {
+  provider?: string;
+  model?: string;
+  noFailover?: boolean;
 }
 
 export interface PaginatedLogs {
@@ -80,6 +90,15 @@ export interface ProviderStats {
   avgLatencyMs: number;
 }
 
+export interface ApiKeyStats {
+  keyId: string;
+  requestCount: number;
+  totalTokens: number;
+  totalCost: string;
+  successRate: number;
+  lastUsedAt?: number;
+}
+
 export interface ModelStats {
   model: string;
   requestCount: number;